@@ -1,16 +1,22 @@
+// Shader functions are only used through `wgsl!`.
+#![allow(dead_code)]
+
 use std::ops::Add;
 
-use rsshader::shader_item;
+use rsshader::{shader_item, wgsl};
 
 fn main() {
-    println!("Hello, world!");
+    println!("{}", wgsl!(fs_main));
 }
 
 #[shader_item(vector)]
+#[derive(Clone, Copy)]
 struct Vector<const N: usize, T>([T; N]);
 
+type Vec4 = Vector<4, f32>;
+
 #[shader_item]
-impl<const N: usize, T> Vector<N, T> {
+impl<const N: usize, T: Copy> Vector<N, T> {
     #[builtin(new_vec)]
     pub fn new(array: [T; N]) -> Self {
         Self(array)
@@ -28,6 +34,20 @@ impl<const N: usize, T: Add> Add for Vector<N, T> {
 
     #[builtin(add)]
     fn add(self, rhs: Self) -> Self::Output {
-        Vector::new(self.0.into_iter().zip(rhs.0.into_iter()))
+        let mut rhs = rhs.0.into_iter();
+        Vector(self.0.map(|x| x + rhs.next().unwrap()))
     }
 }
+
+#[shader_item(fragment)]
+#[derive(Clone, Copy)]
+struct Fragment {
+    #[position]
+    position: Vec4,
+    brightness: f32,
+}
+
+#[shader_item(fragment)]
+fn fs_main(frag: Fragment) -> Vec4 {
+    Vec4::splat(frag.brightness) + Vec4::new([0.1, 0.2, 0.3, 1.0])
+}
@@ -1,3 +1,4 @@
+use proc_macro2::Span;
 use quote::quote;
 use syn::{
     Ident, Path, Token,
@@ -18,13 +19,27 @@ pub fn shader(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
             }
         }
 
+        let arithmetic = match input.parse::<Option<Token![;]>>()? {
+            Some(_) => {
+                let key = input.parse::<Ident>()?;
+                if key != "arithmetic" {
+                    return Err(syn::Error::new(key.span(), "expected `arithmetic`"));
+                }
+
+                let _ = input.parse::<Token![=]>()?;
+                input.parse::<Ident>()?
+            }
+
+            None => Ident::new("Native", Span::call_site()),
+        };
+
         let _ = input.parse::<Token![=>]>()?;
         let fmt_fn = input.parse::<Path>()?;
 
-        Ok((entry_points, fmt_fn))
+        Ok((entry_points, arithmetic, fmt_fn))
     };
 
-    let (entry_points, fmt_fn) = match parse_fn.parse2(input.into()) {
+    let (entry_points, arithmetic, fmt_fn) = match parse_fn.parse2(input.into()) {
         Ok(result) => result,
         Err(e) => return e.to_compile_error().into(),
     };
@@ -32,16 +47,18 @@ pub fn shader(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
     quote! {{
         const RSSHADER_STR: &str = {
             const RSSHADER_IR: rsshader::ir::ShaderIr = rsshader::ir::ShaderIr {
-                entry_points: &[#(&<#entry_points as rsshader::reflection::ShaderFn>::IR),*],
+                entry_points: &[#(&<#entry_points as rsshader::reflection::EntryPoint>::IR),*],
+                arithmetic: rsshader::ir::Arithmetic::#arithmetic,
             };
 
-            const RSSHADER_LINKED_IR_BUF: rsshader::ir::LinkedShaderIrBuffer<128, 128> = rsshader::ir::LinkedShaderIrBuffer::link(&RSSHADER_IR);
+            const RSSHADER_LINKER: &rsshader::ir::linker::Linker<16, 16, 16, 128, 16, 256, 64> =
+                &rsshader::ir::linker::Linker::new(&RSSHADER_IR);
 
-            const RSSHADER_LINKED_IR: rsshader::ir::LinkedShaderIr = RSSHADER_LINKED_IR_BUF.as_ref();
+            const RSSHADER_LINKED_IR: rsshader::ir::LinkedShaderIr = RSSHADER_LINKER.view();
 
             const RSSHADER_LEN: usize = {
                 let mut f = rsshader::lang::Formatter::without_output();
-                rsshader::lang::fmt_wgsl(&mut f, &RSSHADER_LINKED_IR);
+                #fmt_fn(&mut f, &RSSHADER_LINKED_IR);
                 f.output_len()
             };
 
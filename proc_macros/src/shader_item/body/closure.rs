@@ -0,0 +1,843 @@
+use std::{
+    collections::{HashMap, HashSet},
+    mem,
+    rc::Rc,
+};
+
+use proc_macro2::{Span, TokenStream, TokenTree};
+use quote::{ToTokens, quote};
+use syn::{
+    ConstParam, ExprCall, ExprClosure, ExprGroup, ExprParen, ExprPath, ExprTuple, GenericParam,
+    Generics, Ident, Pat, PatIdent, PatReference, PatTuple, PatType, Path, QSelf, ReturnType, Type,
+    TypeParam, TypePath,
+    spanned::Spanned,
+    visit::{self, Visit},
+};
+
+use crate::shader_item::{
+    body::{
+        Body, Expr, Local,
+        expr::{Hint, auto_deref},
+    },
+    r#fn::where_predicates,
+    util::{check_ty, mentions, referent, ty_ir},
+};
+
+/// A closure, which is inlined wherever it is called since WGSL has none. Where
+/// it is used as a value instead, it is lowered to a function of its own.
+#[derive(Clone)]
+pub(super) struct Closure {
+    pub func: Rc<ExprClosure>,
+    /// The bindings in scope where the closure is bound to a name. The body is
+    /// lowered with these rather than with the bindings where it is called, so
+    /// that bindings declared in between cannot shadow what it captures.
+    scopes: Option<Rc<Vec<HashMap<String, Local>>>>,
+}
+
+/// A closure that is used as a value, such as an argument of a function. It is
+/// lowered to the value of a type generated for it, which holds what it
+/// captures and implements `rsshader::reflection::Closure`.
+pub(super) struct Lifted {
+    /// The name of the generated type.
+    ty: Ident,
+    captures: Vec<TokenStream>,
+    /// The generic parameters that stand for the types of parameters without a
+    /// type annotation.
+    args: Vec<Ident>,
+    params: Vec<TokenStream>,
+    ret: TokenStream,
+    stmts: Vec<TokenStream>,
+    span: Span,
+}
+
+/// An argument or result of a closure. Shaders have no tuples, so tuples are
+/// kept apart until a pattern destructures them.
+#[derive(Debug, Clone)]
+pub(super) enum Item {
+    Value(Expr),
+    Tuple(Vec<Item>),
+}
+
+impl Body<'_> {
+    /// Binds `ident` to a closure, which can then be called or passed to
+    /// iterator adapters.
+    pub(super) fn declare_closure(
+        &mut self,
+        ident: &Ident,
+        func: &ExprClosure,
+        stmts: &mut Vec<TokenStream>,
+    ) {
+        let mut scopes = self.scopes.clone();
+
+        // A `move` closure owns copies of what it captures, so mutating the
+        // originals afterwards must not change them.
+        if func.capture.is_some() {
+            let mut names = HashSet::new();
+            collect_idents(func.body.to_token_stream(), &mut names);
+
+            for name in names {
+                let Some(local) = self.find(&name).cloned() else {
+                    continue;
+                };
+                if local.place.is_none() {
+                    continue;
+                }
+
+                let copy = self.declare_var(&local.ty, stmts);
+                let place = &copy.place;
+                let value = &local.ir;
+
+                stmts.push(quote! {
+                    rsshader::ir::StmtIr::Assignment {
+                        left: #place,
+                        right: #value,
+                    }
+                });
+
+                scopes.last_mut().unwrap().insert(name, copy);
+            }
+        }
+
+        self.closures.push(Closure {
+            func: Rc::new(func.clone()),
+            scopes: Some(Rc::new(scopes)),
+        });
+
+        // Closures are not values, so `lower_path` never uses `ir` and `ty`.
+        self.declare(
+            ident.to_string(),
+            Local {
+                ir: quote! { unreachable!() },
+                ty: quote! { () },
+                place: None,
+                closure: Some(self.closures.len() - 1),
+            },
+        );
+    }
+
+    /// The closure `expr` evaluates to, if it is a closure expression or a
+    /// binding to one.
+    pub(super) fn closure_arg(&self, expr: &syn::Expr) -> Option<Closure> {
+        match expr {
+            syn::Expr::Paren(ExprParen { expr, .. }) | syn::Expr::Group(ExprGroup { expr, .. }) => {
+                self.closure_arg(expr)
+            }
+
+            syn::Expr::Closure(func) => Some(Closure {
+                func: Rc::new(func.clone()),
+                scopes: None,
+            }),
+
+            syn::Expr::Path(path) if path.qself.is_none() => {
+                let ident = path.path.get_ident()?;
+                let idx = self.find(&ident.to_string())?.closure?;
+
+                Some(self.closures[idx].clone())
+            }
+
+            _ => None,
+        }
+    }
+
+    /// Lowers a call to a closure, which `closure_arg` has to accept.
+    pub(super) fn lower_closure_call(
+        &mut self,
+        expr: &ExprCall,
+        stmts: &mut Vec<TokenStream>,
+        hint: Hint,
+    ) -> Expr {
+        let closure = self.closure_arg(&expr.func).unwrap();
+        let args = self.lower_closure_args(&closure, expr, stmts);
+
+        let value = self.lower_closure(&closure, args, stmts, hint);
+        self.item_value(value, expr)
+    }
+
+    /// Lowers a call to a closure whose value is discarded, which allows its
+    /// body to be a statement such as an assignment.
+    pub(super) fn lower_closure_call_stmt(
+        &mut self,
+        expr: &ExprCall,
+        stmts: &mut Vec<TokenStream>,
+    ) {
+        let closure = self.closure_arg(&expr.func).unwrap();
+        let args = self.lower_closure_args(&closure, expr, stmts);
+
+        self.in_closure(&closure, args, stmts, |this, stmts| {
+            this.lower_expr_stmt(&closure.func.body, stmts)
+        });
+    }
+
+    /// Lowers the arguments of a closure call, before the closure is entered
+    /// since they are evaluated where it is called.
+    fn lower_closure_args(
+        &mut self,
+        closure: &Closure,
+        expr: &ExprCall,
+        stmts: &mut Vec<TokenStream>,
+    ) -> Vec<Item> {
+        let mut params = closure.func.inputs.iter();
+
+        expr.args
+            .iter()
+            .map(|arg| {
+                let ty = match params.next() {
+                    Some(Pat::Type(PatType { ty, .. })) => Some(ty.to_token_stream()),
+                    _ => None,
+                };
+                let hint = match &ty {
+                    Some(ty) => Hint::Ty(ty),
+                    None => Hint::None,
+                };
+
+                self.lower_item(arg, stmts, hint)
+            })
+            .collect()
+    }
+
+    /// Inlines a call to `closure` with `args`. Values that the closure
+    /// computes are evaluated once, since the result may be used more than
+    /// once.
+    pub(super) fn lower_closure(
+        &mut self,
+        closure: &Closure,
+        args: Vec<Item>,
+        stmts: &mut Vec<TokenStream>,
+        hint: Hint,
+    ) -> Item {
+        self.in_closure(closure, args, stmts, |this, stmts| {
+            this.lower_item(&closure.func.body, stmts, hint)
+        })
+    }
+
+    /// Runs `lower` with the parameters of `closure` bound to `args`, in the
+    /// scope the closure captures from.
+    pub(super) fn in_closure<R>(
+        &mut self,
+        closure: &Closure,
+        args: Vec<Item>,
+        stmts: &mut Vec<TokenStream>,
+        lower: impl FnOnce(&mut Self, &mut Vec<TokenStream>) -> R,
+    ) -> R {
+        let outer = closure
+            .scopes
+            .as_ref()
+            .map(|scopes| mem::replace(&mut self.scopes, Vec::clone(scopes)));
+
+        self.push_scope();
+        self.closure_depth += 1;
+
+        for (pat, arg) in closure.func.inputs.iter().zip(args) {
+            self.bind_item(pat, arg, stmts);
+        }
+
+        let value = lower(self, stmts);
+
+        self.closure_depth -= 1;
+        self.pop_scope();
+
+        if let Some(outer) = outer {
+            self.scopes = outer;
+        }
+
+        value
+    }
+
+    /// Lowers an expression that produces an item, which is a tuple only if it
+    /// is written as one.
+    pub(super) fn lower_item(
+        &mut self,
+        expr: &syn::Expr,
+        stmts: &mut Vec<TokenStream>,
+        hint: Hint,
+    ) -> Item {
+        match expr {
+            syn::Expr::Paren(ExprParen { expr, .. }) | syn::Expr::Group(ExprGroup { expr, .. }) => {
+                self.lower_item(expr, stmts, hint)
+            }
+
+            syn::Expr::Tuple(ExprTuple { elems, .. }) => Item::Tuple(
+                elems
+                    .iter()
+                    .map(|elem| self.lower_item(elem, stmts, Hint::None))
+                    .collect(),
+            ),
+
+            // Bindings and literals can be used again without evaluating them
+            // again.
+            syn::Expr::Path(_) | syn::Expr::Lit(_) => {
+                Item::Value(self.lower_expr(expr, stmts, hint))
+            }
+
+            _ => {
+                let value = self.lower_expr(expr, stmts, hint);
+                Item::Value(local_value(self.declare_let(value, stmts)))
+            }
+        }
+    }
+
+    /// Binds the parameter pattern of a closure or a `for` loop to an item.
+    pub(super) fn bind_item(&mut self, pat: &Pat, item: Item, stmts: &mut Vec<TokenStream>) {
+        match (pat, item) {
+            (Pat::Wild(_), _) => {}
+
+            (Pat::Paren(pat), item) => self.bind_item(&pat.pat, item, stmts),
+
+            (Pat::Type(PatType { pat, ty, .. }), item) => {
+                if !check_ty(ty, self.errors) {
+                    return;
+                }
+
+                if let Item::Value(value) = &item {
+                    self.unify(&quote! { #ty }, &value.ty);
+                }
+
+                self.bind_item(pat, item, stmts);
+            }
+
+            (
+                Pat::Ident(PatIdent {
+                    by_ref: None,
+                    mutability,
+                    ident,
+                    subpat: None,
+                    ..
+                }),
+                Item::Value(value),
+            ) => self.bind(ident, mutability.is_some(), value, stmts),
+
+            (Pat::Reference(PatReference { pat, .. }), item) => {
+                self.bind_item(pat, item.deref(), stmts)
+            }
+
+            (Pat::Tuple(PatTuple { elems, .. }), Item::Tuple(items))
+                if elems.len() == items.len() =>
+            {
+                for (pat, item) in elems.iter().zip(items) {
+                    self.bind_item(pat, item, stmts);
+                }
+            }
+
+            (Pat::Ident(_) | Pat::Tuple(_), Item::Tuple(_)) => {
+                self.error(pat, "tuples must be destructured in shaders");
+            }
+
+            _ => {
+                self.error(
+                    pat,
+                    "only identifier, tuple and reference patterns are supported for closure \
+                     parameters in shaders",
+                );
+            }
+        }
+    }
+
+    /// Lowers `closure` to a value. Its body becomes a function that takes the
+    /// captured values as its first parameter, so that the function it is
+    /// passed to can call it.
+    pub(super) fn lift_closure(&mut self, closure: &Closure) -> Expr {
+        let func = Rc::clone(&closure.func);
+
+        let mut param_names = HashSet::new();
+        for input in &func.inputs {
+            collect_idents(input.to_token_stream(), &mut param_names);
+        }
+
+        let mut names = HashSet::new();
+        collect_idents(func.body.to_token_stream(), &mut names);
+
+        // What the closure captures is looked up where it is bound, like when
+        // it is inlined.
+        let outer = closure
+            .scopes
+            .as_ref()
+            .map(|scopes| mem::replace(&mut self.scopes, Vec::clone(scopes)));
+
+        let mut names = names
+            .into_iter()
+            .filter(|name| !param_names.contains(name) && self.find(name).is_some())
+            .collect::<Vec<_>>();
+        names.sort();
+
+        let mut captures = Vec::new();
+        for name in names {
+            let local = self.find(&name).unwrap().clone();
+
+            // Closures that are captured are passed along as values too.
+            let value = match local.closure {
+                Some(idx) => {
+                    let closure = self.closures[idx].clone();
+                    self.lift_closure(&closure)
+                }
+                None => local_value(local),
+            };
+
+            if referent(&value.ty).is_some() {
+                self.error(
+                    &func,
+                    format!(
+                        "closures that are passed as values cannot capture the reference `{name}` \
+                         in shaders, copy what it refers to into a binding first"
+                    ),
+                );
+                continue;
+            }
+
+            captures.push((name, value));
+        }
+
+        if let Some(outer) = outer {
+            self.scopes = outer;
+        }
+
+        let ty = Ident::new(
+            &format!("__rsshader_closure{}", self.next_var_id()),
+            Span::call_site(),
+        );
+        let generic_args = generic_args(self.generics);
+        let closure_ty = quote! { #ty<#(#generic_args),*> };
+
+        let value = if captures.is_empty() {
+            quote! { rsshader::ir::ExprIr::Literal(rsshader::ir::Literal::U32(0)) }
+        } else {
+            let fields = captures.iter().enumerate().map(|(idx, (_, value))| {
+                let value = &value.ir;
+                quote! { rsshader::ir::FieldInitIr { idx: #idx, value: #value } }
+            });
+
+            quote! {
+                rsshader::ir::ExprIr::StructConstructor {
+                    ty: &<#closure_ty as rsshader::reflection::Ty>::IR,
+                    fields: &[#(#fields),*],
+                    base: None,
+                }
+            }
+        };
+
+        // The body is lowered like the body of a function, with nothing of
+        // the enclosing function in scope but the captured values.
+        let scopes = mem::replace(&mut self.scopes, vec![HashMap::new()]);
+        let loops = mem::take(&mut self.loops);
+        self.closure_depth += 1;
+
+        let env = quote! {
+            rsshader::ir::ExprIr::Param {
+                idx: 0,
+                ty: &<#closure_ty as rsshader::reflection::Ty>::IR,
+            }
+        };
+
+        for (idx, (name, value)) in captures.iter().enumerate() {
+            self.declare(
+                name.clone(),
+                Local {
+                    ir: quote! {
+                        rsshader::ir::ExprIr::StructField {
+                            idx: #idx,
+                            base: &#env,
+                        }
+                    },
+                    ty: value.ty.clone(),
+                    place: None,
+                    closure: None,
+                },
+            );
+        }
+
+        let mut stmts = Vec::new();
+        let mut args = Vec::new();
+        let mut params = Vec::new();
+
+        for (idx, input) in func.inputs.iter().enumerate() {
+            let (pat, ty) = match input {
+                Pat::Type(PatType { pat, ty, .. }) => {
+                    check_ty(ty, self.errors);
+                    (&**pat, ty.to_token_stream())
+                }
+
+                pat => {
+                    let arg = Ident::new(&format!("__Arg{idx}"), Span::call_site());
+                    args.push(arg.clone());
+                    (pat, arg.into_token_stream())
+                }
+            };
+
+            match pat {
+                Pat::Ident(PatIdent {
+                    by_ref: None,
+                    mutability,
+                    ident,
+                    subpat: None,
+                    ..
+                }) => {
+                    let idx = idx + 1;
+                    let ty_ir = ty_ir(&ty);
+                    let param = Expr {
+                        ir: quote! {
+                            rsshader::ir::ExprIr::Param {
+                                idx: #idx,
+                                ty: &#ty_ir,
+                            }
+                        },
+                        ty: ty.clone(),
+                    };
+
+                    self.bind(ident, mutability.is_some(), param, &mut stmts);
+                }
+
+                Pat::Wild(_) => {}
+
+                _ => {
+                    self.error(
+                        pat,
+                        "only identifier patterns are supported for the parameters of closures \
+                         that are passed as values in shaders",
+                    );
+                }
+            }
+
+            params.push(ty);
+        }
+
+        let ret = match &func.output {
+            ReturnType::Default => None,
+            ReturnType::Type(_, ty) => Some(ty.to_token_stream()),
+        };
+        let hint = match &ret {
+            Some(ret) => Hint::Ty(ret),
+            None => Hint::None,
+        };
+
+        let value_ir = self.lower_expr(&func.body, &mut stmts, hint);
+        if let Some(ret) = &ret {
+            self.unify(ret, &value_ir.ty);
+        }
+
+        let ret = ret.unwrap_or_else(|| value_ir.ty.clone());
+        let value_ir = value_ir.ir;
+        stmts.push(quote! {
+            rsshader::ir::StmtIr::Return {
+                value: Some(#value_ir),
+            }
+        });
+
+        self.closure_depth -= 1;
+        self.loops = loops;
+        self.scopes = scopes;
+
+        self.lifted.push(Lifted {
+            ty,
+            captures: captures.into_iter().map(|(_, value)| value.ty).collect(),
+            args,
+            params,
+            ret,
+            stmts,
+            span: func.span(),
+        });
+
+        Expr {
+            ir: value,
+            ty: closure_ty,
+        }
+    }
+
+    /// The types generated for the closures that are used as values, and
+    /// their impls.
+    ///
+    /// Parameters without a type annotation are generic, and the traits the
+    /// body uses them with become bounds. The types are only known once the
+    /// function the closure is passed to is instantiated.
+    pub(super) fn lifted_items(&mut self) -> Vec<TokenStream> {
+        let generics = self.generics;
+        let generic_args = generic_args(generics);
+        let generic_params = generics
+            .params
+            .iter()
+            .filter_map(|param| match param {
+                GenericParam::Type(TypeParam { ident, .. }) => Some(ident.to_token_stream()),
+                GenericParam::Const(ConstParam { ident, ty, .. }) => {
+                    Some(quote! { const #ident: #ty })
+                }
+                GenericParam::Lifetime(_) => None,
+            })
+            .collect::<Vec<_>>();
+        let type_params = generics
+            .type_params()
+            .map(|param| &param.ident)
+            .collect::<Vec<_>>();
+
+        mem::take(&mut self.lifted)
+            .into_iter()
+            .map(|lifted| {
+                let Lifted {
+                    ty,
+                    captures,
+                    args,
+                    params,
+                    ret,
+                    stmts,
+                    span,
+                } = lifted;
+
+                let captures = captures
+                    .into_iter()
+                    .map(|ty| self.resolve_infer(ty))
+                    .collect::<Vec<_>>();
+                let params = params
+                    .into_iter()
+                    .map(|ty| self.resolve_infer(ty))
+                    .collect::<Vec<_>>();
+                let ret = self.resolve_infer(ret);
+                let stmts = stmts
+                    .into_iter()
+                    .map(|stmt| self.resolve_infer(stmt))
+                    .collect::<Vec<_>>();
+
+                let closure_ty = quote! { #ty<#(#generic_args),*> };
+                let fn_marker = Ident::new(&format!("{ty}_fn"), Span::call_site());
+                let fn_ty = quote! { #fn_marker<#(#generic_args,)* #(#args),*> };
+
+                let mut predicates = where_predicates(generics);
+                predicates.extend(
+                    captures
+                        .iter()
+                        .map(|ty| quote! { #ty: rsshader::reflection::Ty }),
+                );
+
+                if has_infer(ret.clone()) {
+                    self.errors.push(syn::Error::new(
+                        span,
+                        "closures that are passed as values need a return type annotation in \
+                         shaders if they call methods, as in `|x: f32| -> f32 { x.rem_euclid(2.0) }`",
+                    ));
+                }
+
+                let mut arg_predicates = args
+                    .iter()
+                    .map(|arg| quote! { #arg: rsshader::reflection::Ty })
+                    .collect::<Vec<_>>();
+                arg_predicates.push(quote! { #ret: rsshader::reflection::Ty });
+
+                for predicate in qualified_bounds(&stmts, &params, &ret) {
+                    if !args.iter().any(|arg| mentions(predicate.clone(), arg)) {
+                        continue;
+                    }
+
+                    if has_infer(predicate.clone()) {
+                        self.errors.push(syn::Error::new(
+                            span,
+                            "the parameters of closures that are passed as values need type \
+                             annotations in shaders, since the functions and methods they are \
+                             used with are looked up by type",
+                        ));
+                        break;
+                    }
+
+                    if !arg_predicates
+                        .iter()
+                        .any(|other| other.to_string() == predicate.to_string())
+                    {
+                        arg_predicates.push(predicate);
+                    }
+                }
+
+                // WGSL has no empty structs, so closures that capture nothing
+                // are passed as a `u32`.
+                let closure_ty_ir = if captures.is_empty() {
+                    quote! {
+                        rsshader::ir::TypeIr::Primitive(rsshader::ir::Primitive::U32)
+                    }
+                } else {
+                    quote! {
+                        rsshader::ir::TypeIr::Struct {
+                            fields: &[#(<#captures as rsshader::reflection::Ty>::IR),*],
+                        }
+                    }
+                };
+
+                let param_irs = params.iter().map(ty_ir);
+
+                quote! {
+                    #[allow(non_camel_case_types)]
+                    struct #ty<#(#generic_params),*>(
+                        #(#captures,)*
+                        core::marker::PhantomData<fn() -> (#(#type_params,)*)>,
+                    )
+                    where
+                        #(#predicates,)*;
+
+                    impl<#(#generic_params),*> Clone for #closure_ty where #(#predicates,)* {
+                        fn clone(&self) -> Self {
+                            *self
+                        }
+                    }
+
+                    impl<#(#generic_params),*> Copy for #closure_ty where #(#predicates,)* {}
+
+                    impl<#(#generic_params),*> rsshader::reflection::Ty for #closure_ty
+                    where
+                        #(#predicates,)*
+                    {
+                        const IR: rsshader::ir::TypeIr = #closure_ty_ir;
+                    }
+
+                    #[allow(non_camel_case_types)]
+                    struct #fn_marker<#(#generic_params,)* #(#args),*>(
+                        core::marker::PhantomData<fn() -> (#(#type_params,)* #(#args,)*)>,
+                    );
+
+                    impl<#(#generic_params,)* #(#args),*>
+                        rsshader::reflection::Closure<(#(#params,)*)> for #closure_ty
+                    where
+                        #(#predicates,)*
+                        #(#arg_predicates,)*
+                    {
+                        type Fn = #fn_ty;
+                    }
+
+                    impl<#(#generic_params,)* #(#args),*> rsshader::reflection::Fn for #fn_ty
+                    where
+                        #(#predicates,)*
+                        #(#arg_predicates,)*
+                    {
+                        type Output = #ret;
+
+                        const IR: rsshader::ir::FnIr = rsshader::ir::FnIr::UserDefined {
+                            param_types: &[
+                                &<#closure_ty as rsshader::reflection::Ty>::IR,
+                                #(&#param_irs),*
+                            ],
+                            ret_type: Some(&<#ret as rsshader::reflection::Ty>::IR),
+                            body: rsshader::ir::BodyIr {
+                                stmts: &[#(#stmts),*],
+                            },
+                        };
+                    }
+                }
+            })
+            .collect()
+    }
+
+    pub(super) fn item_value(&mut self, item: Item, spanned: impl Spanned) -> Expr {
+        match item {
+            Item::Value(value) => value,
+            Item::Tuple(_) => self.error(spanned, "tuples cannot be used as values in shaders"),
+        }
+    }
+}
+
+impl Item {
+    /// The item as the argument of a closure that takes a reference to it.
+    pub(super) fn by_ref(self) -> Self {
+        match self {
+            Item::Value(Expr { ir, ty }) => Item::Value(Expr {
+                ir,
+                ty: quote! { &#ty },
+            }),
+            Item::Tuple(items) => Item::Tuple(items.into_iter().map(Item::by_ref).collect()),
+        }
+    }
+
+    /// The item behind a reference pattern.
+    fn deref(self) -> Self {
+        match self {
+            Item::Value(value) => Item::Value(auto_deref(value)),
+            Item::Tuple(items) => Item::Tuple(items.into_iter().map(Item::deref).collect()),
+        }
+    }
+}
+
+pub(super) fn local_value(local: Local) -> Expr {
+    Expr {
+        ir: local.ir,
+        ty: local.ty,
+    }
+}
+
+/// Collects every identifier in `tokens`, which is a superset of the bindings
+/// a closure body captures.
+fn collect_idents(tokens: TokenStream, idents: &mut HashSet<String>) {
+    for token in tokens {
+        match token {
+            TokenTree::Ident(ident) => {
+                idents.insert(ident.to_string());
+            }
+            TokenTree::Group(group) => collect_idents(group.stream(), idents),
+            TokenTree::Punct(_) | TokenTree::Literal(_) => {}
+        }
+    }
+}
+
+/// The generic parameters in scope as arguments, which the types generated for
+/// closures are instantiated with.
+fn generic_args(generics: &Generics) -> Vec<&Ident> {
+    generics
+        .params
+        .iter()
+        .filter_map(|param| match param {
+            GenericParam::Type(TypeParam { ident, .. })
+            | GenericParam::Const(ConstParam { ident, .. }) => Some(ident),
+            GenericParam::Lifetime(_) => None,
+        })
+        .collect()
+}
+
+/// The bounds that the qualified paths in a lowered function rely on, like
+/// `T: Mul<f32>` for `<T as Mul<f32>>::IR`.
+fn qualified_bounds(
+    stmts: &[TokenStream],
+    params: &[TokenStream],
+    ret: &TokenStream,
+) -> Vec<TokenStream> {
+    struct Bounds(Vec<TokenStream>);
+
+    impl Bounds {
+        fn add(&mut self, qself: &Option<QSelf>, path: &Path) {
+            if let Some(QSelf { ty, position, .. }) = qself
+                && *position > 0
+            {
+                let leading_colon = &path.leading_colon;
+                let segments = path.segments.iter().take(*position);
+
+                self.0.push(quote! { #ty: #leading_colon #(#segments)::* });
+            }
+        }
+    }
+
+    impl Visit<'_> for Bounds {
+        fn visit_expr_path(&mut self, expr: &ExprPath) {
+            self.add(&expr.qself, &expr.path);
+            visit::visit_expr_path(self, expr);
+        }
+
+        fn visit_type_path(&mut self, ty: &TypePath) {
+            self.add(&ty.qself, &ty.path);
+            visit::visit_type_path(self, ty);
+        }
+    }
+
+    let mut bounds = Bounds(Vec::new());
+
+    for stmt in stmts {
+        if let Ok(stmt) = syn::parse2::<syn::Expr>(stmt.clone()) {
+            bounds.visit_expr(&stmt);
+        }
+    }
+
+    for ty in params.iter().chain([ret]) {
+        if let Ok(ty) = syn::parse2::<Type>(ty.clone()) {
+            bounds.visit_type(&ty);
+        }
+    }
+
+    bounds.0
+}
+
+/// Whether `tokens` contain a `_`, which cannot be part of a bound.
+fn has_infer(tokens: TokenStream) -> bool {
+    tokens.into_iter().any(|token| match token {
+        TokenTree::Ident(ident) => ident == "_",
+        TokenTree::Group(group) => has_infer(group.stream()),
+        _ => false,
+    })
+}
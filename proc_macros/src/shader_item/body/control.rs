@@ -0,0 +1,740 @@
+use std::mem;
+
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{
+    ExprBlock, ExprBreak, ExprContinue, ExprForLoop, ExprGroup, ExprIf, ExprLet, ExprLoop,
+    ExprMethodCall, ExprParen, ExprRange, ExprWhile, Label, Lifetime, Pat, PatIdent, RangeLimits,
+    Stmt, parse_quote, spanned::Spanned,
+};
+
+use crate::shader_item::body::{Body, Expr, expr::Hint, stmt::Tail};
+
+impl Body<'_> {
+    /// Lowers an `if` expression whose branches send their values to `tail`.
+    pub(super) fn lower_if(
+        &mut self,
+        expr: &ExprIf,
+        stmts: &mut Vec<TokenStream>,
+        tail: &mut Tail,
+    ) {
+        let ExprIf {
+            cond,
+            then_branch,
+            else_branch,
+            ..
+        } = expr;
+
+        if let syn::Expr::Let(_) = &**cond {
+            self.lower_if_let(expr, stmts, tail);
+            return;
+        }
+
+        let cond = self.lower_expr(cond, stmts, Hint::Ty(&quote! { bool })).ir;
+
+        let mut then = Vec::new();
+        self.lower_block_tail(then_branch, &mut then, tail);
+
+        let mut otherwise = Vec::new();
+        if let Some((_, else_branch)) = else_branch {
+            self.lower_tail(else_branch, &mut otherwise, tail);
+        }
+
+        stmts.push(quote! {
+            rsshader::ir::StmtIr::If {
+                cond: #cond,
+                then: rsshader::ir::BodyIr {
+                    stmts: &[#(#then),*],
+                },
+                otherwise: rsshader::ir::BodyIr {
+                    stmts: &[#(#otherwise),*],
+                },
+            }
+        });
+    }
+
+    /// Lowers a `loop` or `while` loop whose `break` values are sent to
+    /// `tail`.
+    pub(super) fn lower_loop(
+        &mut self,
+        expr: &syn::Expr,
+        stmts: &mut Vec<TokenStream>,
+        tail: &mut Tail,
+    ) {
+        let (label, cond, block) = match expr {
+            syn::Expr::Loop(ExprLoop { label, body, .. }) => (label, None, body),
+
+            syn::Expr::While(ExprWhile {
+                label, cond, body, ..
+            }) => {
+                // `while let` is a loop around a match that breaks when the
+                // pattern does not match.
+                if let syn::Expr::Let(ExprLet { pat, expr, .. }) = &**cond {
+                    let expr = parse_quote! {
+                        #label loop {
+                            match (#expr) {
+                                #pat => #body,
+                                _ => break,
+                            }
+                        }
+                    };
+
+                    self.lower_loop(&expr, stmts, tail);
+                    return;
+                }
+
+                (label, Some(cond), body)
+            }
+
+            _ => unreachable!(),
+        };
+
+        let (decls, body) =
+            self.lower_loop_body(LoopKind::Loop, label.as_ref(), tail, |this, body| {
+                // The condition is evaluated at the start of every iteration, so
+                // any statements it depends on are part of the body.
+                if let Some(cond) = cond {
+                    let cond = this.lower_expr(cond, body, Hint::Ty(&quote! { bool })).ir;
+
+                    body.push(quote! {
+                        rsshader::ir::StmtIr::If {
+                            cond: rsshader::ir::ExprIr::Call {
+                                func: &<bool as rsshader::reflection::Not>::IR,
+                                args: &[#cond],
+                            },
+                            then: rsshader::ir::BodyIr {
+                                stmts: &[rsshader::ir::StmtIr::Break],
+                            },
+                            otherwise: rsshader::ir::BodyIr {
+                                stmts: &[],
+                            },
+                        }
+                    });
+                }
+
+                this.lower_block_tail(block, body, &mut Tail::Discard);
+            });
+
+        stmts.extend(decls);
+        stmts.push(quote! {
+            rsshader::ir::StmtIr::Loop {
+                body: rsshader::ir::BodyIr {
+                    stmts: &[#(#body),*],
+                },
+                continuing: rsshader::ir::BodyIr {
+                    stmts: &[],
+                },
+                break_if: None,
+            }
+        });
+
+        self.lower_loop_exit_checks(stmts);
+    }
+
+    /// Lowers a `for` loop over an integer range, which can be reversed with
+    /// `rev` and stepped with `step_by`.
+    pub(super) fn lower_for(&mut self, expr: &ExprForLoop, stmts: &mut Vec<TokenStream>) {
+        let ExprForLoop {
+            label,
+            pat,
+            expr: iter,
+            body: block,
+            ..
+        } = expr;
+
+        // Anything but a range is an array or an iterator chain over arrays.
+        if !is_range(iter) {
+            return self.lower_for_iter(expr, stmts);
+        }
+
+        let Some(ForRange { range, rev, step }) = self.parse_for_range(iter) else {
+            return;
+        };
+
+        let (Some(start), Some(end)) = (&range.start, &range.end) else {
+            self.error(range, "only bounded ranges can be iterated over in shaders");
+            return;
+        };
+
+        let inclusive = matches!(range.limits, RangeLimits::Closed(_));
+
+        // The bounds and the step are evaluated once, before the loop.
+        let start = self.lower_expr(start, stmts, Hint::None);
+        let end = self.lower_expr(end, stmts, Hint::Ty(&start.ty));
+        self.unify(&start.ty, &end.ty);
+
+        let ty = start.ty.clone();
+        let start = self.declare_let(start, stmts).ir;
+        let end = self.declare_let(end, stmts).ir;
+
+        let step = match step {
+            Some(step) => {
+                let step = self.lower_expr(step, stmts, Hint::Ty(&ty));
+                self.unify(&ty, &step.ty);
+
+                self.declare_let(step, stmts).ir
+            }
+
+            None => quote! {
+                rsshader::ir::ExprIr::Literal(rsshader::ir::Literal::int(
+                    &<#ty as rsshader::reflection::Ty>::IR,
+                    1,
+                ))
+            },
+        };
+
+        let op = |op_trait: TokenStream,
+                  op_const: TokenStream,
+                  left: &TokenStream,
+                  right: &TokenStream| {
+            quote! {
+                rsshader::ir::ExprIr::Call {
+                    func: &<#ty as rsshader::reflection::#op_trait>::#op_const,
+                    args: &[#left, #right],
+                }
+            }
+        };
+
+        let counter_id = self.next_var_id();
+        let counter = quote! {
+            rsshader::ir::ExprIr::Variable {
+                id: #counter_id,
+                ty: &<#ty as rsshader::reflection::Ty>::IR,
+            }
+        };
+        let counter_place = quote! {
+            rsshader::ir::PlaceIr::Variable {
+                id: #counter_id,
+                ty: &<#ty as rsshader::reflection::Ty>::IR,
+            }
+        };
+
+        let is_not_empty = match inclusive {
+            false => op(quote! { PartialOrd }, quote! { LT }, &start, &end),
+            true => op(quote! { PartialOrd }, quote! { LE }, &start, &end),
+        };
+
+        let first = match (rev, inclusive) {
+            (false, _) => start.clone(),
+            (true, false) => {
+                let one = quote! {
+                    rsshader::ir::ExprIr::Literal(rsshader::ir::Literal::int(
+                        &<#ty as rsshader::reflection::Ty>::IR,
+                        1,
+                    ))
+                };
+
+                op(quote! { Sub }, quote! { IR }, &end, &one)
+            }
+            (true, true) => end.clone(),
+        };
+
+        // The loop ends once the distance to the last value is less than the
+        // step, which unlike comparing the next value to the bounds cannot
+        // overflow.
+        let remaining_id = self.next_var_id();
+        let remaining = quote! {
+            rsshader::ir::ExprIr::Variable {
+                id: #remaining_id,
+                ty: &<#ty as rsshader::reflection::Ty>::IR,
+            }
+        };
+
+        let (remaining_value, next, break_if) = match (rev, inclusive) {
+            (false, false) => (
+                op(quote! { Sub }, quote! { IR }, &end, &counter),
+                op(quote! { Add }, quote! { IR }, &counter, &step),
+                op(quote! { PartialOrd }, quote! { LE }, &remaining, &step),
+            ),
+            (false, true) => (
+                op(quote! { Sub }, quote! { IR }, &end, &counter),
+                op(quote! { Add }, quote! { IR }, &counter, &step),
+                op(quote! { PartialOrd }, quote! { LT }, &remaining, &step),
+            ),
+            (true, _) => (
+                op(quote! { Sub }, quote! { IR }, &counter, &start),
+                op(quote! { Sub }, quote! { IR }, &counter, &step),
+                op(quote! { PartialOrd }, quote! { LT }, &remaining, &step),
+            ),
+        };
+
+        let (decls, body) = self.lower_loop_body(
+            LoopKind::Loop,
+            label.as_ref(),
+            &mut Tail::Discard,
+            |this, body| {
+                this.push_scope();
+                this.declare_for_binding(pat, &counter, &ty, body);
+                this.lower_block_tail(block, body, &mut Tail::Discard);
+                this.pop_scope();
+            },
+        );
+
+        let mut then = decls;
+        then.push(quote! {
+            rsshader::ir::StmtIr::VariableDecl {
+                id: #counter_id,
+                ty: &<#ty as rsshader::reflection::Ty>::IR,
+            }
+        });
+        then.push(quote! {
+            rsshader::ir::StmtIr::Assignment {
+                left: #counter_place,
+                right: #first,
+            }
+        });
+        then.push(quote! {
+            rsshader::ir::StmtIr::Loop {
+                body: rsshader::ir::BodyIr {
+                    stmts: &[#(#body),*],
+                },
+                continuing: rsshader::ir::BodyIr {
+                    stmts: &[
+                        rsshader::ir::StmtIr::LetDecl {
+                            id: #remaining_id,
+                            value: #remaining_value,
+                        },
+                        rsshader::ir::StmtIr::Assignment {
+                            left: #counter_place,
+                            right: #next,
+                        },
+                    ],
+                },
+                break_if: Some(#break_if),
+            }
+        });
+        self.lower_loop_exit_checks(&mut then);
+
+        stmts.push(quote! {
+            rsshader::ir::StmtIr::If {
+                cond: #is_not_empty,
+                then: rsshader::ir::BodyIr {
+                    stmts: &[#(#then),*],
+                },
+                otherwise: rsshader::ir::BodyIr {
+                    stmts: &[],
+                },
+            }
+        });
+    }
+
+    /// Binds the pattern of a `for` loop to the current value of the counter.
+    fn declare_for_binding(
+        &mut self,
+        pat: &Pat,
+        counter: &TokenStream,
+        ty: &TokenStream,
+        body: &mut Vec<TokenStream>,
+    ) {
+        match pat {
+            Pat::Wild(_) => {}
+
+            Pat::Ident(PatIdent {
+                by_ref: None,
+                mutability,
+                ident,
+                subpat: None,
+                ..
+            }) => {
+                let counter = Expr {
+                    ir: counter.clone(),
+                    ty: ty.clone(),
+                };
+
+                self.bind(ident, mutability.is_some(), counter, body);
+            }
+
+            _ => {
+                self.error(
+                    pat,
+                    "only identifier patterns are supported for `for` loops",
+                );
+            }
+        }
+    }
+
+    /// Matches `a..b`, `(a..b).rev()`, `(a..b).step_by(k)` and
+    /// `(a..b).rev().step_by(k)`.
+    fn parse_for_range<'a>(&mut self, expr: &'a syn::Expr) -> Option<ForRange<'a>> {
+        match expr {
+            syn::Expr::Paren(ExprParen { expr, .. }) | syn::Expr::Group(ExprGroup { expr, .. }) => {
+                self.parse_for_range(expr)
+            }
+
+            syn::Expr::Range(range) => Some(ForRange {
+                range,
+                rev: false,
+                step: None,
+            }),
+
+            syn::Expr::MethodCall(ExprMethodCall {
+                receiver,
+                method,
+                turbofish: None,
+                args,
+                ..
+            }) if method == "rev" && args.is_empty() => {
+                let range = self.parse_for_range(receiver)?;
+                if range.rev || range.step.is_some() {
+                    self.error(
+                        method,
+                        "only `rev` followed by `step_by` is supported in shaders",
+                    );
+                    return None;
+                }
+
+                Some(ForRange { rev: true, ..range })
+            }
+
+            syn::Expr::MethodCall(ExprMethodCall {
+                receiver,
+                method,
+                turbofish: None,
+                args,
+                ..
+            }) if method == "step_by" && args.len() == 1 => {
+                let range = self.parse_for_range(receiver)?;
+                if range.step.is_some() {
+                    self.error(method, "`step_by` can only be used once in shaders");
+                    return None;
+                }
+
+                Some(ForRange {
+                    step: args.first(),
+                    ..range
+                })
+            }
+
+            _ => {
+                self.error(
+                    expr,
+                    "only integer ranges can be iterated over in shader functions",
+                );
+                None
+            }
+        }
+    }
+
+    /// Lowers a labeled block whose value and `break` values are sent to
+    /// `tail`. WGSL has no labeled blocks, so it is lowered to a loop that
+    /// breaks at the end of its first iteration.
+    pub(super) fn lower_labeled_block(
+        &mut self,
+        expr: &ExprBlock,
+        stmts: &mut Vec<TokenStream>,
+        tail: &mut Tail,
+    ) {
+        let ExprBlock { label, block, .. } = expr;
+
+        let (decls, body) =
+            self.lower_loop_body(LoopKind::Block, label.as_ref(), tail, |this, body| {
+                let loop_idx = this.loops.len() - 1;
+                this.push_scope();
+
+                // The tail expression is treated like a `break` with a value.
+                for (stmt_idx, stmt) in block.stmts.iter().enumerate() {
+                    match stmt {
+                        Stmt::Expr(expr, None) if stmt_idx + 1 == block.stmts.len() => {
+                            this.lower_break_value(loop_idx, expr, body);
+                        }
+
+                        _ => this.lower_stmt(stmt, body),
+                    }
+                }
+
+                this.pop_scope();
+                body.push(quote! { rsshader::ir::StmtIr::Break });
+            });
+
+        stmts.extend(decls);
+        stmts.push(quote! {
+            rsshader::ir::StmtIr::Loop {
+                body: rsshader::ir::BodyIr {
+                    stmts: &[#(#body),*],
+                },
+                continuing: rsshader::ir::BodyIr {
+                    stmts: &[],
+                },
+                break_if: None,
+            }
+        });
+
+        self.lower_loop_exit_checks(stmts);
+    }
+
+    /// Lowers the body of a loop with `lower`, returning the statements that
+    /// need to come before the loop and the body itself.
+    pub(super) fn lower_loop_body(
+        &mut self,
+        kind: LoopKind,
+        label: Option<&Label>,
+        tail: &mut Tail,
+        lower: impl FnOnce(&mut Self, &mut Vec<TokenStream>),
+    ) -> (Vec<TokenStream>, Vec<TokenStream>) {
+        self.loops.push(Loop {
+            kind,
+            label: label.map(|label| label.name.ident.to_string()),
+            tail: mem::replace(tail, Tail::Discard),
+            decls: Vec::new(),
+            break_flag: None,
+            continue_flag: None,
+        });
+
+        let mut body = Vec::new();
+        lower(self, &mut body);
+
+        let Loop {
+            tail: loop_tail,
+            decls,
+            ..
+        } = self.loops.pop().unwrap();
+        *tail = loop_tail;
+
+        (decls, body)
+    }
+
+    /// Lowers the cases of a `switch` with `lower`. A `break` inside a WGSL
+    /// `switch` exits the `switch` itself, so it is treated like an unlabeled
+    /// loop that `break` cannot target directly.
+    pub(super) fn lower_switch_body(&mut self, lower: impl FnOnce(&mut Self)) {
+        self.loops.push(Loop {
+            kind: LoopKind::Switch,
+            label: None,
+            tail: Tail::Discard,
+            decls: Vec::new(),
+            break_flag: None,
+            continue_flag: None,
+        });
+
+        lower(self);
+
+        self.loops.pop();
+    }
+
+    /// Propagates `break` and `continue` to an outer loop after an inner loop
+    /// has been exited through its flags, since WGSL has no labeled loops.
+    pub(super) fn lower_loop_exit_checks(&mut self, stmts: &mut Vec<TokenStream>) {
+        let loop_count = self.loops.len();
+
+        for (loop_idx, target) in self.loops.iter().enumerate() {
+            let is_innermost = loop_idx + 1 == loop_count;
+
+            if let Some(flag) = target.break_flag {
+                stmts.push(flag_check(flag, quote! { rsshader::ir::StmtIr::Break }));
+            }
+
+            if let Some(flag) = target.continue_flag {
+                let exit = if is_innermost {
+                    let reset = flag_set(flag, false);
+
+                    quote! { #reset, rsshader::ir::StmtIr::Continue }
+                } else {
+                    quote! { rsshader::ir::StmtIr::Break }
+                };
+
+                stmts.push(flag_check(flag, exit));
+            }
+        }
+    }
+
+    pub(super) fn lower_break(&mut self, expr: &ExprBreak, stmts: &mut Vec<TokenStream>) {
+        let ExprBreak {
+            label, expr: value, ..
+        } = expr;
+
+        let Some(loop_idx) = self.find_loop(expr, label.as_ref()) else {
+            return;
+        };
+
+        // The value has already left the function if it is returned.
+        if let Some(value) = value
+            && self.lower_break_value(loop_idx, value, stmts)
+        {
+            return;
+        }
+
+        if loop_idx + 1 != self.loops.len() {
+            let flag = self.loop_flag(loop_idx, LoopFlag::Break);
+            stmts.push(flag_set(flag, true));
+        }
+
+        stmts.push(quote! { rsshader::ir::StmtIr::Break });
+    }
+
+    /// Sends the value of a `break` to the loop at `loop_idx`, returning
+    /// whether it was returned from the function.
+    fn lower_break_value(
+        &mut self,
+        loop_idx: usize,
+        value: &syn::Expr,
+        stmts: &mut Vec<TokenStream>,
+    ) -> bool {
+        let mut tail = mem::replace(&mut self.loops[loop_idx].tail, Tail::Discard);
+        self.lower_tail(value, stmts, &mut tail);
+
+        let returns = matches!(tail, Tail::Return);
+        self.loops[loop_idx].tail = tail;
+
+        returns
+    }
+
+    pub(super) fn lower_continue(&mut self, expr: &ExprContinue, stmts: &mut Vec<TokenStream>) {
+        let Some(loop_idx) = self.find_loop(expr, expr.label.as_ref()) else {
+            return;
+        };
+
+        // Unlike `break`, `continue` is not affected by a `switch`.
+        if self.loops[loop_idx + 1..]
+            .iter()
+            .all(|target| matches!(target.kind, LoopKind::Switch))
+        {
+            stmts.push(quote! { rsshader::ir::StmtIr::Continue });
+        } else {
+            let flag = self.loop_flag(loop_idx, LoopFlag::Continue);
+            stmts.push(flag_set(flag, true));
+            stmts.push(quote! { rsshader::ir::StmtIr::Break });
+        }
+    }
+
+    fn find_loop(&mut self, spanned: impl Spanned, label: Option<&Lifetime>) -> Option<usize> {
+        let loop_idx = match label {
+            Some(label) => {
+                let name = label.ident.to_string();
+
+                self.loops
+                    .iter()
+                    .rposition(|target| target.label.as_ref() == Some(&name))
+            }
+
+            None => self
+                .loops
+                .iter()
+                .rposition(|target| matches!(target.kind, LoopKind::Loop)),
+        };
+
+        if loop_idx.is_none() {
+            self.error(spanned, "this can only be used inside a loop");
+        }
+
+        loop_idx
+    }
+
+    /// Returns the id of a flag variable of the loop at `loop_idx`, declaring
+    /// it before the loop if needed.
+    fn loop_flag(&mut self, loop_idx: usize, kind: LoopFlag) -> usize {
+        let target = &self.loops[loop_idx];
+        let flag = match kind {
+            LoopFlag::Break => target.break_flag,
+            LoopFlag::Continue => target.continue_flag,
+        };
+
+        if let Some(flag) = flag {
+            return flag;
+        }
+
+        let flag = self.next_var_id();
+        let target = &mut self.loops[loop_idx];
+
+        target.decls.push(quote! {
+            rsshader::ir::StmtIr::VariableDecl {
+                id: #flag,
+                ty: &<bool as rsshader::reflection::Ty>::IR,
+            }
+        });
+        target.decls.push(flag_set(flag, false));
+
+        match kind {
+            LoopFlag::Break => target.break_flag = Some(flag),
+            LoopFlag::Continue => target.continue_flag = Some(flag),
+        }
+
+        flag
+    }
+}
+
+/// Whether `expr` is a range, or a range that `parse_for_range` accepts
+/// adapters of.
+fn is_range(expr: &syn::Expr) -> bool {
+    match expr {
+        syn::Expr::Paren(ExprParen { expr, .. }) | syn::Expr::Group(ExprGroup { expr, .. }) => {
+            is_range(expr)
+        }
+
+        syn::Expr::Range(_) => true,
+
+        syn::Expr::MethodCall(ExprMethodCall {
+            receiver, method, ..
+        }) if method == "rev" || method == "step_by" => is_range(receiver),
+
+        _ => false,
+    }
+}
+
+/// The iterator of a `for` loop.
+struct ForRange<'a> {
+    range: &'a ExprRange,
+    rev: bool,
+    step: Option<&'a syn::Expr>,
+}
+
+/// A loop, `switch` or labeled block that is being lowered.
+#[derive(Debug)]
+pub(super) struct Loop {
+    kind: LoopKind,
+    label: Option<String>,
+    /// Where the values of `break` expressions go.
+    tail: Tail,
+    /// Statements that need to come before the loop.
+    decls: Vec<TokenStream>,
+    /// A variable that is set when an inner loop breaks out of this loop.
+    break_flag: Option<usize>,
+    /// A variable that is set when an inner loop continues this loop.
+    continue_flag: Option<usize>,
+}
+
+/// What a [`Loop`] was lowered from. Every kind is a construct that a WGSL
+/// `break` exits, but only loops are targeted by an unlabeled `break` or
+/// `continue` in Rust.
+#[derive(Debug, Clone, Copy)]
+pub(super) enum LoopKind {
+    Loop,
+    /// A `match`, which `continue` passes through.
+    Switch,
+    /// A labeled block, lowered to a loop that runs once.
+    Block,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum LoopFlag {
+    Break,
+    Continue,
+}
+
+fn flag_set(flag: usize, value: bool) -> TokenStream {
+    quote! {
+        rsshader::ir::StmtIr::Assignment {
+            left: rsshader::ir::PlaceIr::Variable {
+                id: #flag,
+                ty: &<bool as rsshader::reflection::Ty>::IR,
+            },
+            right: rsshader::ir::ExprIr::Literal(rsshader::ir::Literal::Bool(#value)),
+        }
+    }
+}
+
+fn flag_check(flag: usize, exit: TokenStream) -> TokenStream {
+    quote! {
+        rsshader::ir::StmtIr::If {
+            cond: rsshader::ir::ExprIr::Variable {
+                id: #flag,
+                ty: &<bool as rsshader::reflection::Ty>::IR,
+            },
+            then: rsshader::ir::BodyIr {
+                stmts: &[#exit],
+            },
+            otherwise: rsshader::ir::BodyIr {
+                stmts: &[],
+            },
+        }
+    }
+}
@@ -0,0 +1,239 @@
+use proc_macro2::{Ident, TokenStream};
+use quote::quote;
+use syn::{ExprCall, ExprGroup, ExprParen, ExprPath, ExprStruct, FieldValue, Member, Path};
+
+use crate::shader_item::{
+    body::{Body, Expr, expr::Hint, infer::generic_args},
+    util::variant_field_name,
+};
+
+impl Body<'_> {
+    /// Lowers `Some(value)`, whose type is known from the value.
+    pub(super) fn lower_some(
+        &mut self,
+        expr: &ExprCall,
+        stmts: &mut Vec<TokenStream>,
+        hint: Hint,
+    ) -> Expr {
+        let [value] = &expr.args.iter().collect::<Vec<_>>()[..] else {
+            return self.error(expr, "`Some` takes a single value");
+        };
+
+        let inner_hint = match hint {
+            Hint::Ty(ty) => match generic_args(ty) {
+                Some((name, args)) if name == "Option" && args.len() == 1 => Some(args),
+                _ => None,
+            },
+            _ => None,
+        };
+        let hint = match &inner_hint {
+            Some(args) => Hint::Ty(&args[0]),
+            None => Hint::None,
+        };
+
+        let value = self.lower_expr(value, stmts, hint);
+        let value_ty = &value.ty;
+        let ty = quote! { Option<#value_ty> };
+
+        let some = match &*expr.func {
+            syn::Expr::Path(ExprPath { path, .. }) => path.get_ident().unwrap(),
+            _ => unreachable!("not a call to `Some`"),
+        };
+        let field = variant_field_name(some, &Member::Unnamed(0.into()));
+
+        variant_expr(&ty, some, &[(field, value)])
+    }
+
+    /// Lowers `None`, whose type has to be known from its context or from how
+    /// it is used later.
+    pub(super) fn lower_none(&mut self, expr: &ExprPath, hint: Hint) -> Expr {
+        let none = expr.path.get_ident().unwrap();
+
+        let ty = match hint {
+            Hint::Ty(ty) => ty.clone(),
+            Hint::None => {
+                let inner = self.infer_ty();
+                quote! { Option<#inner> }
+            }
+
+            // The Rust type is unknown here, but it is only ever used directly
+            // as an argument. The tag is the same for every `Option`.
+            Hint::Param { func, idx } => {
+                let tag = variant_tag(&quote! { Option<u32> }, none);
+
+                return Expr {
+                    ir: quote! {
+                        rsshader::ir::ExprIr::StructConstructor {
+                            ty: &<#func as rsshader::reflection::Fn>::IR.param_type(#idx),
+                            fields: &[rsshader::ir::FieldInitIr {
+                                idx: 0,
+                                value: rsshader::ir::ExprIr::Literal(rsshader::ir::Literal::U32(
+                                    #tag as u32,
+                                )),
+                            }],
+                            base: None,
+                        }
+                    },
+                    ty: quote! { _ },
+                };
+            }
+        };
+
+        variant_expr(&ty, none, &[])
+    }
+
+    /// Lowers `Enum::Variant { .. }`, which constructs a struct variant.
+    pub(super) fn lower_variant_struct(
+        &mut self,
+        expr: &ExprStruct,
+        ty: &Path,
+        variant: &Ident,
+        stmts: &mut Vec<TokenStream>,
+    ) -> Expr {
+        if let Some(rest) = &expr.rest {
+            return self.error(
+                rest,
+                "enum variants cannot be constructed from a base in shaders",
+            );
+        }
+
+        let ty = quote! { #ty };
+
+        let fields = expr
+            .fields
+            .iter()
+            .map(|FieldValue { member, expr, .. }| {
+                let name = variant_field_name(variant, member);
+                let (_, field_ty) = variant_field(&ty, &name);
+
+                let value = self.lower_expr(expr, stmts, Hint::Ty(&field_ty));
+                self.unify(&field_ty, &value.ty);
+
+                (name, value)
+            })
+            .collect::<Vec<_>>();
+
+        variant_expr(&ty, variant, &fields)
+    }
+}
+
+/// A value of the enum `ty` that is the variant `variant` with `fields`, named
+/// like `Some.0`. Fields of other variants are zero.
+fn variant_expr(ty: &TokenStream, variant: &Ident, fields: &[(String, Expr)]) -> Expr {
+    let tag = variant_tag(ty, variant);
+
+    let field_inits = fields.iter().map(|(name, value)| {
+        let (idx, _) = variant_field(ty, name);
+        let value = &value.ir;
+
+        quote! {
+            rsshader::ir::FieldInitIr {
+                idx: #idx,
+                value: #value,
+            }
+        }
+    });
+
+    Expr {
+        ir: quote! {
+            rsshader::ir::ExprIr::StructConstructor {
+                ty: &<#ty as rsshader::reflection::Ty>::IR,
+                fields: &[
+                    rsshader::ir::FieldInitIr {
+                        idx: 0,
+                        value: rsshader::ir::ExprIr::Literal(rsshader::ir::Literal::int(
+                            &<<#ty as rsshader::reflection::Enum>::Tag
+                                as rsshader::reflection::Ty>::IR,
+                            #tag,
+                        )),
+                    },
+                    #(#field_inits),*
+                ],
+                base: None,
+            }
+        },
+        ty: ty.clone(),
+    }
+}
+
+/// The tag of the variant `variant` of the enum `ty`, as an `i128`.
+pub(super) fn variant_tag(ty: &TokenStream, variant: &Ident) -> TokenStream {
+    let name = variant.to_string();
+
+    quote! {
+        <#ty as rsshader::reflection::Variant<{ rsshader::reflection::name_id(#name) }>>::TAG
+    }
+}
+
+/// The index and the type of the variant field `name` of the enum `ty`.
+fn variant_field(ty: &TokenStream, name: &str) -> (TokenStream, TokenStream) {
+    let field = quote! { rsshader::reflection::Field<{ rsshader::reflection::name_id(#name) }> };
+
+    (
+        quote! { <#ty as #field>::IDX },
+        quote! { <#ty as #field>::Ty },
+    )
+}
+
+/// Reads the variant field `name` of `value`, which is a tagged enum.
+pub(super) fn read_variant_field(value: &Expr, name: &str) -> Expr {
+    let Expr { ir, ty } = value;
+    let (idx, ty) = variant_field(ty, name);
+
+    Expr {
+        ir: quote! {
+            rsshader::ir::ExprIr::StructField {
+                idx: #idx,
+                base: &#ir,
+            }
+        },
+        ty,
+    }
+}
+
+/// Splits `Enum::Variant` into the enum and the variant, if the path names
+/// one. Enums are told apart from modules by their capitalization.
+pub(super) fn split_variant_path(path: &Path) -> Option<(Path, &Ident)> {
+    let [.., ty, variant] = path.segments.iter().collect::<Vec<_>>()[..] else {
+        return None;
+    };
+
+    if !ty.ident.to_string().starts_with(char::is_uppercase) {
+        return None;
+    }
+
+    let ty = Path {
+        leading_colon: path.leading_colon,
+        segments: path
+            .segments
+            .iter()
+            .take(path.segments.len() - 1)
+            .cloned()
+            .collect(),
+    };
+
+    Some((ty, &variant.ident))
+}
+
+/// Whether `expr` is `None`, whose type is taken from its context.
+pub(super) fn is_none(expr: &syn::Expr) -> bool {
+    match expr {
+        syn::Expr::Path(ExprPath {
+            qself: None, path, ..
+        }) => path.is_ident("None"),
+
+        syn::Expr::Paren(ExprParen { expr, .. }) | syn::Expr::Group(ExprGroup { expr, .. }) => {
+            is_none(expr)
+        }
+
+        _ => false,
+    }
+}
+
+/// Whether `expr` calls `Some`.
+pub(super) fn is_some_call(expr: &ExprCall) -> bool {
+    matches!(
+        &*expr.func,
+        syn::Expr::Path(ExprPath { qself: None, path, .. }) if path.is_ident("Some")
+    )
+}
@@ -0,0 +1,1284 @@
+use proc_macro2::{Ident, TokenStream};
+use quote::{ToTokens, quote, quote_spanned};
+use syn::{
+    BinOp, ExprArray, ExprBinary, ExprCall, ExprCast, ExprField, ExprGroup, ExprIf, ExprIndex,
+    ExprLit, ExprMacro, ExprMethodCall, ExprParen, ExprPath, ExprReference, ExprRepeat, ExprStruct,
+    ExprUnary, FieldValue, Index, Lit, Member, Path, PathSegment, QSelf, Type, TypeArray, TypePath,
+    UnOp, spanned::Spanned,
+};
+
+use crate::shader_item::{
+    body::{
+        Body, Expr,
+        r#enum::{is_none, is_some_call, split_variant_path},
+        iter::{is_array_from_fn, is_iter_consumer},
+        place::Place,
+        reported,
+        stmt::has_calls,
+    },
+    util::{check_ty, referent, std_ty_message, ty_ir},
+};
+
+/// The type an expression is expected to have, used to infer the type of
+/// unsuffixed integer literals.
+#[derive(Debug, Clone, Copy)]
+pub(super) enum Hint<'a> {
+    None,
+    Ty(&'a TokenStream),
+    Param { func: &'a TokenStream, idx: usize },
+}
+
+/// A lowered call to a user function, kept apart so it can either be used as
+/// an expression or as a statement.
+pub(super) struct Call {
+    pub func: TokenStream,
+    pub args: Vec<TokenStream>,
+    pub ty: TokenStream,
+}
+
+impl Body<'_> {
+    pub(super) fn lower_expr(
+        &mut self,
+        expr: &syn::Expr,
+        stmts: &mut Vec<TokenStream>,
+        hint: Hint,
+    ) -> Expr {
+        match expr {
+            syn::Expr::Lit(ExprLit { lit, .. }) => self.lower_lit(lit, hint),
+            syn::Expr::Path(path) if is_none(expr) => self.lower_none(path, hint),
+            syn::Expr::Path(expr) => self.lower_path(expr),
+
+            syn::Expr::Paren(ExprParen { expr, .. }) | syn::Expr::Group(ExprGroup { expr, .. }) => {
+                self.lower_expr(expr, stmts, hint)
+            }
+
+            syn::Expr::Binary(expr) => self.lower_binary(expr, stmts),
+            syn::Expr::Unary(expr) => self.lower_unary(expr, stmts, hint),
+            syn::Expr::Reference(expr) => self.lower_reference(expr, stmts, hint),
+            syn::Expr::Cast(expr) => self.lower_cast(expr, stmts),
+
+            syn::Expr::If(ExprIf {
+                else_branch: None, ..
+            }) => self.error(expr, "`if` without `else` cannot be used as a value"),
+
+            syn::Expr::If(_) | syn::Expr::Loop(_) | syn::Expr::Match(_) | syn::Expr::Block(_) => {
+                self.lower_tail_expr(expr, stmts, hint)
+            }
+
+            syn::Expr::Struct(expr) => self.lower_struct(expr, stmts),
+            syn::Expr::Field(expr) => self.lower_field(expr, stmts),
+            syn::Expr::Index(expr) => self.lower_index(expr, stmts),
+            syn::Expr::Array(expr) => self.lower_array(expr, stmts, hint),
+            syn::Expr::Repeat(expr) => self.lower_repeat(expr, stmts, hint),
+            syn::Expr::Macro(ExprMacro { mac, .. }) => self.lower_macro(mac, stmts, hint),
+
+            syn::Expr::MethodCall(expr) if is_iter_consumer(expr) => {
+                self.lower_iter_consumer(expr, stmts, hint)
+            }
+
+            // `into` is looked up as `from` in the type it converts to, which
+            // is only known from the context.
+            syn::Expr::MethodCall(ExprMethodCall {
+                receiver,
+                method,
+                turbofish: None,
+                args,
+                ..
+            }) if method == "into" && args.is_empty() => {
+                let Hint::Ty(ty) = hint else {
+                    return self.error(
+                        method,
+                        "the type `into` converts to must be known from its context in shaders",
+                    );
+                };
+
+                let from = Ident::new("from", method.span());
+                let Call { func, args, ty } =
+                    self.lower_args(None, [&**receiver].into_iter(), stmts, |arg_tys| {
+                        method_fn(ty, &from, &arg_tys)
+                    });
+
+                Expr {
+                    ir: quote! {
+                        rsshader::ir::ExprIr::Call {
+                            func: &#func,
+                            args: &[#(#args),*],
+                        }
+                    },
+                    ty,
+                }
+            }
+
+            syn::Expr::Call(call) if is_some_call(call) => self.lower_some(call, stmts, hint),
+
+            syn::Expr::Call(call) if is_array_from_fn(call) => {
+                self.lower_array_from_fn(call, stmts, hint)
+            }
+
+            syn::Expr::Call(call) if is_tuple_struct_call(call) => {
+                self.lower_tuple_struct(call, stmts)
+            }
+
+            syn::Expr::Call(call) if self.closure_arg(&call.func).is_some() => {
+                self.lower_closure_call(call, stmts, hint)
+            }
+
+            syn::Expr::Closure(_) => {
+                let closure = self.closure_arg(expr).unwrap();
+                self.lift_closure(&closure)
+            }
+
+            syn::Expr::Call(_) | syn::Expr::MethodCall(_) => {
+                let Call { func, args, ty } = self.lower_call(expr, stmts);
+
+                Expr {
+                    ir: quote! {
+                        rsshader::ir::ExprIr::Call {
+                            func: &#func,
+                            args: &[#(#args),*],
+                        }
+                    },
+                    ty,
+                }
+            }
+
+            _ => self.error(expr, unsupported_expr_message(expr)),
+        }
+    }
+
+    fn lower_lit(&mut self, lit: &Lit, hint: Hint) -> Expr {
+        match lit {
+            Lit::Bool(lit) => {
+                let value = lit.value;
+
+                Expr {
+                    ir: quote! {
+                        rsshader::ir::ExprIr::Literal(rsshader::ir::Literal::Bool(#value))
+                    },
+                    ty: quote! { bool },
+                }
+            }
+
+            Lit::Int(lit) => {
+                let value = match lit.base10_parse::<u32>() {
+                    Ok(value) => value as i128,
+                    Err(error) => return self.error(lit, error),
+                };
+
+                match (lit.suffix(), hint) {
+                    ("i32", _) => Expr {
+                        ir: quote! {
+                            rsshader::ir::ExprIr::Literal(rsshader::ir::Literal::I32(#value as i32))
+                        },
+                        ty: quote! { i32 },
+                    },
+
+                    ("u32", _) => Expr {
+                        ir: quote! {
+                            rsshader::ir::ExprIr::Literal(rsshader::ir::Literal::U32(#value as u32))
+                        },
+                        ty: quote! { u32 },
+                    },
+
+                    ("usize", _) => Expr {
+                        ir: quote! {
+                            rsshader::ir::ExprIr::Literal(rsshader::ir::Literal::U32(#value as u32))
+                        },
+                        ty: quote! { usize },
+                    },
+
+                    ("f32", _) => Expr {
+                        ir: quote! {
+                            rsshader::ir::ExprIr::Literal(rsshader::ir::Literal::F32(#value as f32))
+                        },
+                        ty: quote! { f32 },
+                    },
+
+                    ("", Hint::Ty(_) | Hint::None) => {
+                        let ty = match hint {
+                            Hint::Ty(ty) => ty.clone(),
+                            _ => self.infer_ty(),
+                        };
+
+                        Expr {
+                            ir: quote! {
+                                rsshader::ir::ExprIr::Literal(rsshader::ir::Literal::int(
+                                    &<#ty as rsshader::reflection::Ty>::IR,
+                                    #value,
+                                ))
+                            },
+                            ty,
+                        }
+                    }
+
+                    // The Rust type of the literal is unknown here, but it is
+                    // only ever used directly as an argument.
+                    ("", Hint::Param { func, idx }) => Expr {
+                        ir: quote! {
+                            rsshader::ir::ExprIr::Literal(rsshader::ir::Literal::int(
+                                &<#func as rsshader::reflection::Fn>::IR.param_type(#idx),
+                                #value,
+                            ))
+                        },
+                        ty: quote! { i32 },
+                    },
+
+                    (suffix, _) => self.error(
+                        lit,
+                        format!("`{suffix}` literals are not supported in shaders"),
+                    ),
+                }
+            }
+
+            Lit::Float(lit) => {
+                if !matches!(lit.suffix(), "" | "f32") {
+                    return self.error(
+                        lit,
+                        format!("`{}` literals are not supported in shaders", lit.suffix()),
+                    );
+                }
+
+                let value = match lit.base10_parse::<f32>() {
+                    Ok(value) => value,
+                    Err(error) => return self.error(lit, error),
+                };
+
+                Expr {
+                    ir: quote! {
+                        rsshader::ir::ExprIr::Literal(rsshader::ir::Literal::F32(#value))
+                    },
+                    ty: quote! { f32 },
+                }
+            }
+
+            Lit::Str(_) | Lit::ByteStr(_) | Lit::CStr(_) => self.error(
+                lit,
+                "string literals are not supported in shaders, use integer codes instead",
+            ),
+            Lit::Char(_) | Lit::Byte(_) => self.error(
+                lit,
+                "character literals are not supported in shaders, use integer codes instead",
+            ),
+
+            _ => self.error(lit, "this literal is not supported in shaders"),
+        }
+    }
+
+    fn lower_path(&mut self, expr: &ExprPath) -> Expr {
+        if expr.qself.is_none()
+            && let Some(ident) = expr.path.get_ident()
+            && let Some(local) = self.find(&ident.to_string())
+        {
+            if let Some(idx) = local.closure {
+                let closure = self.closures[idx].clone();
+                return self.lift_closure(&closure);
+            }
+
+            return Expr {
+                ir: local.ir.clone(),
+                ty: local.ty.clone(),
+            };
+        }
+
+        // Associated consts are functions without parameters.
+        if let Some((ty, name)) = split_assoc_path(expr) {
+            if let Some(message) = std_assoc_message(&ty) {
+                return self.error(&ty, message);
+            }
+
+            if !name.arguments.is_none() {
+                return self.error(name, "associated consts do not take generic arguments");
+            }
+
+            let func = method_fn(&ty, &name.ident, &[]);
+
+            return Expr {
+                ir: quote! {
+                    rsshader::ir::ExprIr::assoc_const(&<#func as rsshader::reflection::Fn>::IR)
+                },
+                ty: quote! { <#func as rsshader::reflection::Fn>::Output },
+            };
+        }
+
+        if expr.qself.is_some() {
+            return self.error(
+                expr,
+                "qualified paths to constants are not supported in shaders",
+            );
+        }
+
+        // Other paths name `#[shader_item]` consts and statics, through their
+        // marker struct. Both projections are spanned at the path, where rustc
+        // reports paths that name no shader const.
+        let path = &expr.path;
+        let span = path.span();
+        let marker = match path
+            .segments
+            .iter()
+            .all(|segment| segment.arguments.is_none())
+        {
+            true => self.item_marker(path).to_token_stream(),
+            false => path.to_token_stream(),
+        };
+        let ir = quote_spanned! {span=> <#marker as rsshader::reflection::Const>::IR };
+
+        Expr {
+            ir: quote! { rsshader::ir::ConstIr::expr(&#ir) },
+            ty: quote_spanned! {span=> <#marker as rsshader::reflection::Const>::Ty },
+        }
+    }
+
+    fn lower_struct(&mut self, expr: &ExprStruct, stmts: &mut Vec<TokenStream>) -> Expr {
+        let ExprStruct {
+            qself,
+            path,
+            fields,
+            rest,
+            ..
+        } = expr;
+
+        if qself.is_some() {
+            return self.error(expr, "qualified struct paths are not supported in shaders");
+        }
+
+        if let Some((ty, variant)) = split_variant_path(path) {
+            return self.lower_variant_struct(expr, &ty, variant, stmts);
+        }
+
+        let fields = fields
+            .iter()
+            .map(|FieldValue { member, expr, .. }| (member.clone(), expr));
+
+        self.struct_constructor(quote! { #path }, fields, rest.as_deref(), stmts)
+    }
+
+    /// Lowers `Type(..)`, which constructs a tuple struct.
+    fn lower_tuple_struct(&mut self, expr: &ExprCall, stmts: &mut Vec<TokenStream>) -> Expr {
+        let ExprCall { func, args, .. } = expr;
+
+        let fields = args.iter().enumerate().map(|(idx, arg)| {
+            let member = Member::Unnamed(Index {
+                index: idx as u32,
+                span: arg.span(),
+            });
+
+            (member, arg)
+        });
+
+        self.struct_constructor(quote! { #func }, fields, None, stmts)
+    }
+
+    /// Constructs a value of `ty` from `fields`, where the missing fields are
+    /// copied from `rest`. Vector types are constructed from their elements.
+    fn struct_constructor<'a>(
+        &mut self,
+        ty: TokenStream,
+        fields: impl Iterator<Item = (Member, &'a syn::Expr)>,
+        rest: Option<&syn::Expr>,
+        stmts: &mut Vec<TokenStream>,
+    ) -> Expr {
+        let fields = fields
+            .map(|(member, expr)| {
+                let field = field_trait(&member);
+                let field_ty = quote! { <#ty as #field>::Ty };
+
+                let value = self.lower_expr(expr, stmts, Hint::Ty(&field_ty));
+                self.unify(&field_ty, &value.ty);
+
+                let value = value.ir;
+
+                quote! {
+                    rsshader::ir::FieldInitIr {
+                        idx: <#ty as #field>::IDX,
+                        value: #value,
+                    }
+                }
+            })
+            .collect::<Vec<_>>();
+
+        // The base is evaluated after the fields, and only once even though
+        // every missing field is read from it.
+        let base = match rest {
+            Some(rest) => {
+                let base = self.lower_expr(rest, stmts, Hint::Ty(&ty));
+                self.unify(&ty, &base.ty);
+
+                let base = self.declare_let(base, stmts).ir;
+                quote! { Some(&#base) }
+            }
+            None => quote! { None },
+        };
+
+        Expr {
+            ir: quote! {
+                rsshader::ir::ExprIr::StructConstructor {
+                    ty: &<#ty as rsshader::reflection::Ty>::IR,
+                    fields: &[#(#fields),*],
+                    base: #base,
+                }
+            },
+            ty,
+        }
+    }
+
+    fn lower_field(&mut self, expr: &ExprField, stmts: &mut Vec<TokenStream>) -> Expr {
+        let ExprField { base, member, .. } = expr;
+
+        let base = self.lower_expr(base, stmts, Hint::None);
+
+        read_field(&auto_deref(base), member)
+    }
+
+    fn lower_index(&mut self, expr: &ExprIndex, stmts: &mut Vec<TokenStream>) -> Expr {
+        let ExprIndex { expr, index, .. } = expr;
+
+        if let syn::Expr::Range(_) = **index {
+            return self.error(
+                index,
+                "slicing is not supported in shaders, index the elements one at a time instead",
+            );
+        }
+
+        let base = self.lower_expr(expr, stmts, Hint::None);
+        let base = auto_deref(base);
+        let index = self.lower_index_operand(index, stmts).ir;
+
+        let base_ty = &base.ty;
+        let base = &base.ir;
+
+        Expr {
+            ir: quote! {
+                rsshader::ir::ExprIr::Index {
+                    base: &#base,
+                    index: &#index,
+                }
+            },
+            ty: element_ty(base_ty),
+        }
+    }
+
+    pub(super) fn lower_index_operand(
+        &mut self,
+        index: &syn::Expr,
+        stmts: &mut Vec<TokenStream>,
+    ) -> Expr {
+        let ty = quote! { usize };
+
+        let index = self.lower_expr(index, stmts, Hint::Ty(&ty));
+        self.unify(&ty, &index.ty);
+
+        index
+    }
+
+    fn lower_array(&mut self, expr: &ExprArray, stmts: &mut Vec<TokenStream>, hint: Hint) -> Expr {
+        let ExprArray { elems, .. } = expr;
+
+        let hint = element_hint(hint);
+
+        let mut elem_ty = None::<TokenStream>;
+        let mut elements = Vec::new();
+        for elem in elems {
+            let elem = match &elem_ty {
+                Some(ty) => {
+                    let ty = ty.clone();
+                    let elem = self.lower_expr(elem, stmts, Hint::Ty(&ty));
+                    self.unify(&ty, &elem.ty);
+
+                    elem
+                }
+                None => match &hint {
+                    Some(ty) => self.lower_expr(elem, stmts, Hint::Ty(ty)),
+                    None => self.lower_expr(elem, stmts, Hint::None),
+                },
+            };
+
+            elem_ty.get_or_insert(elem.ty);
+            elements.push(elem.ir);
+        }
+
+        let Some(elem_ty) = elem_ty else {
+            return self.error(expr, "empty arrays are not supported in shaders");
+        };
+
+        let n = elements.len();
+
+        array_expr(quote! { [#elem_ty; #n] }, &elements)
+    }
+
+    /// Lowers `[value; N]` by evaluating `value` once and repeating it.
+    fn lower_repeat(
+        &mut self,
+        expr: &ExprRepeat,
+        stmts: &mut Vec<TokenStream>,
+        hint: Hint,
+    ) -> Expr {
+        let ExprRepeat { expr, len, .. } = expr;
+
+        // Const parameters and consts are only known per instance, so they are
+        // the length of both the Rust type and the elements of the IR.
+        let n = match &**len {
+            syn::Expr::Lit(ExprLit {
+                lit: Lit::Int(lit), ..
+            }) if matches!(lit.suffix(), "" | "usize") => match lit.base10_parse::<usize>() {
+                Ok(n) if n > 0 => quote! { #n },
+                Ok(_) => return self.error(len, "empty arrays are not supported in shaders"),
+                Err(error) => return self.error(lit, error),
+            },
+
+            syn::Expr::Path(ExprPath { qself: None, .. }) => quote! { { #len } },
+
+            _ => {
+                return self.error(
+                    len,
+                    "array lengths must be integer literals, const parameters or consts in shaders",
+                );
+            }
+        };
+
+        let hint = element_hint(hint);
+        let value = match &hint {
+            Some(ty) => self.lower_expr(expr, stmts, Hint::Ty(ty)),
+            None => self.lower_expr(expr, stmts, Hint::None),
+        };
+
+        let elem_ty = value.ty.clone();
+        let value = self.declare_let(value, stmts).ir;
+        let ty = quote! { [#elem_ty; #n] };
+
+        Expr {
+            ir: quote! {
+                rsshader::ir::ExprIr::ArrayConstructor {
+                    ty: &<#ty as rsshader::reflection::Ty>::IR,
+                    elements: &[#value; #n],
+                }
+            },
+            ty,
+        }
+    }
+
+    fn lower_binary(&mut self, expr: &ExprBinary, stmts: &mut Vec<TokenStream>) -> Expr {
+        let ExprBinary {
+            left, op, right, ..
+        } = expr;
+
+        match op {
+            BinOp::And(_) | BinOp::Or(_) => return self.lower_logical(expr, stmts),
+
+            BinOp::AddAssign(_)
+            | BinOp::SubAssign(_)
+            | BinOp::MulAssign(_)
+            | BinOp::DivAssign(_)
+            | BinOp::RemAssign(_)
+            | BinOp::BitXorAssign(_)
+            | BinOp::BitAndAssign(_)
+            | BinOp::BitOrAssign(_)
+            | BinOp::ShlAssign(_)
+            | BinOp::ShrAssign(_) => {
+                return self.error(op, "compound assignment can only be used as a statement");
+            }
+
+            _ => {}
+        }
+
+        let is_shift = matches!(op, BinOp::Shl(_) | BinOp::Shr(_));
+
+        // An unsuffixed literal takes the type of the other operand, except for
+        // shift amounts which are independent of the shifted value.
+        let (left, right) = if is_unsuffixed_lit(left) && !is_unsuffixed_lit(right) && !is_shift {
+            let right = auto_deref(self.lower_expr(right, stmts, Hint::None));
+            let left = auto_deref(self.lower_expr(left, stmts, Hint::Ty(&right.ty)));
+
+            (left, right)
+        } else {
+            let left = auto_deref(self.lower_expr(left, stmts, Hint::None));
+            let right = if is_shift {
+                self.lower_expr(right, stmts, Hint::None)
+            } else {
+                self.lower_expr(right, stmts, Hint::Ty(&left.ty))
+            };
+            let right = auto_deref(right);
+
+            (left, right)
+        };
+
+        if !is_shift {
+            self.unify(&left.ty, &right.ty);
+        }
+
+        self.binary_op(op, left, right)
+    }
+
+    /// Applies `op` to already lowered operands. Operators on references apply
+    /// to the values they refer to.
+    pub(super) fn binary_op(&mut self, op: &BinOp, left: Expr, right: Expr) -> Expr {
+        let left = auto_deref(left);
+        let right = auto_deref(right);
+
+        let left_ty = &left.ty;
+        let right_ty = &right.ty;
+
+        let op_fn = |op_trait: TokenStream| {
+            (
+                quote! { <#left_ty as rsshader::reflection::#op_trait<#right_ty>>::IR },
+                quote! { <#left_ty as core::ops::#op_trait<#right_ty>>::Output },
+            )
+        };
+
+        let cmp_fn = |cmp_trait: TokenStream, cmp_const: TokenStream| {
+            (
+                quote! { <#left_ty as rsshader::reflection::#cmp_trait<#right_ty>>::#cmp_const },
+                quote! { bool },
+            )
+        };
+
+        let (func, ty) = match op {
+            BinOp::Add(_) => op_fn(quote! { Add }),
+            BinOp::Sub(_) => op_fn(quote! { Sub }),
+            BinOp::Mul(_) => op_fn(quote! { Mul }),
+            BinOp::Div(_) => op_fn(quote! { Div }),
+            BinOp::Rem(_) => op_fn(quote! { Rem }),
+            BinOp::BitXor(_) => op_fn(quote! { BitXor }),
+            BinOp::BitAnd(_) => op_fn(quote! { BitAnd }),
+            BinOp::BitOr(_) => op_fn(quote! { BitOr }),
+            BinOp::Shl(_) => op_fn(quote! { Shl }),
+            BinOp::Shr(_) => op_fn(quote! { Shr }),
+
+            BinOp::Eq(_) => cmp_fn(quote! { PartialEq }, quote! { EQ }),
+            BinOp::Ne(_) => cmp_fn(quote! { PartialEq }, quote! { NE }),
+            BinOp::Lt(_) => cmp_fn(quote! { PartialOrd }, quote! { LT }),
+            BinOp::Gt(_) => cmp_fn(quote! { PartialOrd }, quote! { GT }),
+            BinOp::Le(_) => cmp_fn(quote! { PartialOrd }, quote! { LE }),
+            BinOp::Ge(_) => cmp_fn(quote! { PartialOrd }, quote! { GE }),
+
+            _ => return self.error(op, "this operator is not supported in shader functions"),
+        };
+
+        let left = &left.ir;
+        let right = &right.ir;
+
+        Expr {
+            ir: quote! {
+                rsshader::ir::ExprIr::Call {
+                    func: &#func,
+                    args: &[#left, #right],
+                }
+            },
+            ty,
+        }
+    }
+
+    /// Lowers `&&` and `||`, only evaluating the right operand if needed.
+    fn lower_logical(&mut self, expr: &ExprBinary, stmts: &mut Vec<TokenStream>) -> Expr {
+        let ExprBinary {
+            left, op, right, ..
+        } = expr;
+
+        let ty = quote! { bool };
+        let is_and = matches!(op, BinOp::And(_));
+
+        let left = self.lower_expr(left, stmts, Hint::Ty(&ty)).ir;
+
+        // naga hoists calls out of expressions, so a right operand that calls
+        // a function must not be emitted as a plain operand either.
+        let calls = has_calls(right);
+        let mut right_stmts = Vec::new();
+        let right = self.lower_expr(right, &mut right_stmts, Hint::Ty(&ty)).ir;
+
+        if right_stmts.is_empty() && !calls {
+            let ir = match is_and {
+                true => quote! { rsshader::ir::ExprIr::And { left: &#left, right: &#right } },
+                false => quote! { rsshader::ir::ExprIr::Or { left: &#left, right: &#right } },
+            };
+
+            return Expr { ir, ty };
+        }
+
+        // The statements and calls of the right operand must only run if it
+        // is evaluated, so the operator is lowered to an `if` instead.
+        let result = self.declare_var(&ty, stmts);
+        let place = result.place.unwrap();
+        let result = result.ir;
+
+        stmts.push(quote! {
+            rsshader::ir::StmtIr::Assignment {
+                left: #place,
+                right: #left,
+            }
+        });
+
+        let cond = match is_and {
+            true => result.clone(),
+            false => quote! {
+                rsshader::ir::ExprIr::Call {
+                    func: &<bool as rsshader::reflection::Not>::IR,
+                    args: &[#result],
+                }
+            },
+        };
+
+        stmts.push(quote! {
+            rsshader::ir::StmtIr::If {
+                cond: #cond,
+                then: rsshader::ir::BodyIr {
+                    stmts: &[
+                        #(#right_stmts,)*
+                        rsshader::ir::StmtIr::Assignment {
+                            left: #place,
+                            right: #right,
+                        },
+                    ],
+                },
+                otherwise: rsshader::ir::BodyIr {
+                    stmts: &[],
+                },
+            }
+        });
+
+        Expr { ir: result, ty }
+    }
+
+    fn lower_unary(&mut self, expr: &ExprUnary, stmts: &mut Vec<TokenStream>, hint: Hint) -> Expr {
+        let ExprUnary { op, expr, .. } = expr;
+
+        let op_trait = match op {
+            UnOp::Neg(_) => quote! { Neg },
+            UnOp::Not(_) => quote! { Not },
+
+            UnOp::Deref(_) => {
+                let value = self.lower_expr(expr, stmts, Hint::None);
+
+                return match referent(&value.ty) {
+                    Some(_) => auto_deref(value),
+                    None => self.error(op, "only references can be dereferenced in shaders"),
+                };
+            }
+
+            _ => return self.error(op, "this operator is not supported in shader functions"),
+        };
+
+        let value = auto_deref(self.lower_expr(expr, stmts, hint));
+        let value_ty = &value.ty;
+        let value = &value.ir;
+
+        Expr {
+            ir: quote! {
+                rsshader::ir::ExprIr::Call {
+                    func: &<#value_ty as rsshader::reflection::#op_trait>::IR,
+                    args: &[#value],
+                }
+            },
+            ty: quote! { <#value_ty as core::ops::#op_trait>::Output },
+        }
+    }
+
+    /// Lowers `&value`, which is the value itself since `&T` is passed by
+    /// value, or `&mut place`, which is a pointer to the place.
+    fn lower_reference(
+        &mut self,
+        expr: &ExprReference,
+        stmts: &mut Vec<TokenStream>,
+        hint: Hint,
+    ) -> Expr {
+        let span = expr.span();
+        let ExprReference {
+            mutability, expr, ..
+        } = expr;
+
+        if mutability.is_none() {
+            let referent_hint = match hint {
+                Hint::Ty(ty) => referent(ty).map(|(_, ty)| ty),
+                Hint::None | Hint::Param { .. } => None,
+            };
+
+            let hint = match &referent_hint {
+                Some(ty) => Hint::Ty(ty),
+                None => Hint::None,
+            };
+
+            let value = self.lower_expr(expr, stmts, hint);
+            let ty = &value.ty;
+
+            return Expr {
+                ir: value.ir,
+                ty: quote! { &#ty },
+            };
+        }
+
+        let Place { ir, ty, .. } = self.lower_place(expr, stmts);
+        let ty_ir = ty_ir(&ty);
+
+        // Whether the place can be pointed to is only known once its type is,
+        // so the error is reported at the reference when the IR is evaluated.
+        Expr {
+            ir: quote_spanned! {span=> rsshader::ir::ExprIr::address_of(&#ir, &#ty_ir) },
+            ty: quote! { &mut #ty },
+        }
+    }
+
+    /// Lowers `value as T`. Unsuffixed integer literals are `i32` like in Rust.
+    fn lower_cast(&mut self, expr: &ExprCast, stmts: &mut Vec<TokenStream>) -> Expr {
+        let ExprCast { expr, ty, .. } = expr;
+
+        if let Type::Infer(_) = **ty {
+            return self.error(ty, "the type of a cast must be written out in shaders");
+        }
+
+        if !check_ty(ty, self.errors) {
+            return reported();
+        }
+
+        let value = match is_unsuffixed_int_lit(expr) {
+            true => self.lower_expr(expr, stmts, Hint::Ty(&quote! { i32 })),
+            false => self.lower_expr(expr, stmts, Hint::None),
+        };
+        let value_ty = &value.ty;
+        let value = &value.ir;
+
+        Expr {
+            ir: quote! {
+                rsshader::ir::ExprIr::Call {
+                    func: &<#value_ty as rsshader::reflection::Cast<#ty>>::IR,
+                    args: &[#value],
+                }
+            },
+            ty: ty.to_token_stream(),
+        }
+    }
+
+    /// Lowers a call to a shader function, method or associated function.
+    pub(super) fn lower_call(&mut self, expr: &syn::Expr, stmts: &mut Vec<TokenStream>) -> Call {
+        match expr {
+            syn::Expr::Call(expr) => self.lower_fn_call(expr, stmts),
+            syn::Expr::MethodCall(expr) => self.lower_method_call(expr, stmts),
+            _ => unreachable!("not a call expression"),
+        }
+    }
+
+    fn lower_fn_call(&mut self, expr: &ExprCall, stmts: &mut Vec<TokenStream>) -> Call {
+        let ExprCall { func, args, .. } = expr;
+
+        let func = match &**func {
+            syn::Expr::Path(func)
+                if func.path.get_ident().is_none_or(|ident| {
+                    func.qself.is_some() || self.find(&ident.to_string()).is_none()
+                }) =>
+            {
+                func
+            }
+
+            // Other bindings hold closures that were passed in as values, which
+            // are the first argument of the function they are lowered to.
+            syn::Expr::Path(ExprPath {
+                qself: None, path, ..
+            }) if path.get_ident().is_some() => {
+                let span = func.span();
+
+                return self.lower_args(Some(&**func), args.iter(), stmts, |arg_tys| {
+                    let (closure_ty, arg_tys) = arg_tys.split_first().unwrap();
+
+                    quote_spanned! {span=>
+                        <#closure_ty as rsshader::reflection::Closure<(#(#arg_tys,)*)>>::Fn
+                    }
+                });
+            }
+
+            _ => return self.call_error(func, "only shader functions can be called"),
+        };
+
+        // `Type::name(..)` calls an associated function, which is selected by
+        // the type and the argument types.
+        if let Some((ty, name)) = split_assoc_path(func) {
+            if let Some(message) = std_assoc_message(&ty) {
+                return self.call_error(&ty, &message);
+            }
+
+            if !name.arguments.is_none() {
+                return self.call_error(
+                    name,
+                    "generic arguments cannot be specified for methods in shaders",
+                );
+            }
+
+            return self.lower_args(None, args.iter(), stmts, |arg_tys| {
+                method_fn(&ty, &name.ident, &arg_tys)
+            });
+        }
+
+        let path = match func {
+            ExprPath {
+                qself: None, path, ..
+            } => path,
+
+            _ => return self.call_error(func, "only shader functions can be called"),
+        };
+
+        let has_turbofish = path
+            .segments
+            .iter()
+            .any(|segment| !segment.arguments.is_none());
+
+        if has_turbofish {
+            return self.lower_args(None, args.iter(), stmts, |_| quote! { #path });
+        }
+
+        // The instance is selected at the span of the path, so that rustc
+        // reports functions that cannot be called there.
+        let span = path.span();
+        let marker = self.item_marker(path);
+        self.lower_args(None, args.iter(), stmts, |arg_tys| {
+            quote_spanned! {span=>
+                <#marker as rsshader::reflection::Call<(#(#arg_tys,)*)>>::Fn
+            }
+        })
+    }
+
+    /// Method calls are looked up in the receiver type.
+    fn lower_method_call(&mut self, expr: &ExprMethodCall, stmts: &mut Vec<TokenStream>) -> Call {
+        let ExprMethodCall {
+            receiver,
+            method,
+            turbofish,
+            args,
+            ..
+        } = expr;
+
+        if let Some(turbofish) = turbofish {
+            return self.call_error(
+                turbofish,
+                "generic arguments cannot be specified for methods in shaders",
+            );
+        }
+
+        self.lower_args(Some(receiver), args.iter(), stmts, |arg_tys| {
+            method_fn(&arg_tys[0], method, &arg_tys)
+        })
+    }
+
+    /// Lowers the arguments of a call, and the function selected by `func`
+    /// from the argument types. The receiver of a method call is the first
+    /// argument.
+    ///
+    /// Unsuffixed integer literals and `None` take the type of their
+    /// parameter, which is only known once the instance is selected, so their
+    /// type is `_` and they are lowered last. This is fine since lowering them
+    /// never adds statements.
+    pub(super) fn lower_args<'a>(
+        &mut self,
+        receiver: Option<&syn::Expr>,
+        args: impl Iterator<Item = &'a syn::Expr> + Clone,
+        stmts: &mut Vec<TokenStream>,
+        func: impl FnOnce(Vec<TokenStream>) -> TokenStream,
+    ) -> Call {
+        let receiver = receiver.map(|receiver| self.lower_receiver(receiver, stmts));
+
+        let lowered = args
+            .clone()
+            .map(|arg| match is_unsuffixed_int_lit(arg) || is_none(arg) {
+                true => None,
+                false => Some(self.lower_expr(arg, stmts, Hint::None)),
+            })
+            .collect::<Vec<_>>();
+
+        let func = func(
+            receiver
+                .iter()
+                .map(|(receiver, _)| receiver.ty.clone())
+                .chain(lowered.iter().map(|arg| match arg {
+                    Some(arg) => arg.ty.clone(),
+                    None => quote! { _ },
+                }))
+                .collect(),
+        );
+
+        let offset = receiver.is_some() as usize;
+        let args = receiver
+            .map(|(Expr { ir, ty }, place)| {
+                let ty_ir = ty_ir(&ty);
+                let place = match place {
+                    Some(place) => quote! { Some(&#place) },
+                    None => quote! { None },
+                };
+
+                quote! {
+                    rsshader::ir::ExprIr::receiver(
+                        &<#func as rsshader::reflection::Fn>::IR,
+                        &#ir,
+                        #place,
+                        &#ty_ir,
+                    )
+                }
+            })
+            .into_iter()
+            .chain(
+                args.zip(lowered)
+                    .enumerate()
+                    .map(|(idx, (arg, lowered))| match lowered {
+                        Some(arg) => arg.ir,
+                        None => {
+                            let hint = Hint::Param {
+                                func: &func,
+                                idx: idx + offset,
+                            };
+
+                            self.lower_expr(arg, stmts, hint).ir
+                        }
+                    }),
+            )
+            .collect();
+
+        Call {
+            ty: quote! { <#func as rsshader::reflection::Fn>::Output },
+            func: quote! { <#func as rsshader::reflection::Fn>::IR },
+            args,
+        }
+    }
+
+    /// Lowers the receiver of a method call, along with its place if it is a
+    /// mutable one, which `&mut self` methods take a pointer to. References
+    /// are dereferenced, since methods are looked up in the type they refer
+    /// to.
+    fn lower_receiver(
+        &mut self,
+        receiver: &syn::Expr,
+        stmts: &mut Vec<TokenStream>,
+    ) -> (Expr, Option<TokenStream>) {
+        if self.is_mutable_place(receiver) {
+            let Place { ir, value, ty } = self.lower_place(receiver, stmts);
+
+            return (Expr { ir: value, ty }, Some(ir));
+        }
+
+        let receiver = self.lower_expr(receiver, stmts, Hint::None);
+        let place = match referent(&receiver.ty) {
+            Some((true, _)) => {
+                let ptr = &receiver.ir;
+                Some(quote! { rsshader::ir::PlaceIr::Deref { ptr: &#ptr } })
+            }
+            _ => None,
+        };
+
+        (auto_deref(receiver), place)
+    }
+
+    fn call_error(&mut self, spanned: impl syn::spanned::Spanned, message: &str) -> Call {
+        let error = self.error(spanned, message);
+
+        Call {
+            func: error.ir,
+            args: Vec::new(),
+            ty: error.ty,
+        }
+    }
+}
+
+/// The primitive types of shaders, which are lowercase unlike other types.
+const PRIMITIVES: &[&str] = &["f32", "i32", "u32", "usize", "bool"];
+
+/// Splits `Type::name` or `<Type as Trait>::name` into the type and the name,
+/// if the path names an associated item. Types are told apart from modules by
+/// their capitalization.
+fn split_assoc_path(expr: &ExprPath) -> Option<(TokenStream, &PathSegment)> {
+    let ExprPath { qself, path, .. } = expr;
+
+    let [.., ty, name] = path.segments.iter().collect::<Vec<_>>()[..] else {
+        return match (qself, path.segments.first()) {
+            (Some(QSelf { ty, .. }), Some(name)) => Some((ty.to_token_stream(), name)),
+            _ => None,
+        };
+    };
+
+    if let Some(QSelf { ty, position, .. }) = qself {
+        return (*position == path.segments.len() - 1).then(|| (ty.to_token_stream(), name));
+    }
+
+    if !ty.ident.to_string().starts_with(char::is_uppercase)
+        && !PRIMITIVES.iter().any(|primitive| ty.ident == primitive)
+    {
+        return None;
+    }
+
+    let ty = Path {
+        leading_colon: path.leading_colon,
+        segments: path
+            .segments
+            .iter()
+            .take(path.segments.len() - 1)
+            .cloned()
+            .collect(),
+    };
+
+    Some((ty.to_token_stream(), name))
+}
+
+/// The error for an associated item of a standard library type that shaders
+/// cannot represent, like `Vec::new`.
+fn std_assoc_message(ty: &TokenStream) -> Option<String> {
+    match syn::parse2(ty.clone()) {
+        Ok(Type::Path(TypePath { qself: None, path })) => std_ty_message(&path),
+        _ => None,
+    }
+}
+
+/// The error for an expression that shaders do not support, suggesting what
+/// to use instead where there is an alternative.
+fn unsupported_expr_message(expr: &syn::Expr) -> &'static str {
+    match expr {
+        syn::Expr::Unsafe(_) => {
+            "`unsafe` blocks are not supported in shaders, use a regular block instead"
+        }
+        syn::Expr::Async(_) => {
+            "`async` blocks are not supported in shaders, use a regular block instead"
+        }
+        syn::Expr::Await(_) => "`.await` is not supported in shaders, call the function directly",
+        syn::Expr::TryBlock(_) => {
+            "`try` blocks are not supported in shaders, use a regular block instead"
+        }
+        syn::Expr::Try(_) => "the `?` operator is not supported in shaders, use `match` instead",
+        syn::Expr::Yield(_) => "`yield` is not supported in shaders, use a loop instead",
+        syn::Expr::Const(_) => {
+            "`const` blocks are not supported in shaders, use a `#[shader_item]` const instead"
+        }
+        syn::Expr::Range(_) => "ranges can only be iterated over with `for` in shaders",
+        syn::Expr::Let(_) => "`let` chains are not supported in shaders, nest `if let` instead",
+        syn::Expr::Tuple(_) => {
+            "tuples are not supported in shaders, use a `#[shader_item]` struct instead"
+        }
+        _ => "this expression is not supported in shader functions",
+    }
+}
+
+/// The function that is selected by looking up `name` in `ty` with arguments
+/// of the types in `arg_tys`.
+/// The lookup is spanned at `name`, so that rustc reports a missing method
+/// there.
+fn method_fn(ty: &TokenStream, name: &Ident, arg_tys: &[TokenStream]) -> TokenStream {
+    let span = name.span();
+    let name = name.to_string();
+
+    quote_spanned! {span=>
+        <(#ty, (#(#arg_tys,)*)) as rsshader::reflection::Method<
+            { rsshader::reflection::name_id(#name) },
+            _,
+        >>::Fn
+    }
+}
+
+/// Whether `expr` calls a tuple struct, which is told apart from functions
+/// and enum variants by the capitalization of its path.
+fn is_tuple_struct_call(expr: &ExprCall) -> bool {
+    let syn::Expr::Path(ExprPath {
+        qself: None, path, ..
+    }) = &*expr.func
+    else {
+        return false;
+    };
+
+    let is_type = |segment: &PathSegment| segment.ident.to_string().starts_with(char::is_uppercase);
+
+    match path.segments.iter().rev().collect::<Vec<_>>()[..] {
+        [name] => is_type(name),
+        [name, parent, ..] => is_type(name) && !is_type(parent),
+        [] => false,
+    }
+}
+
+fn is_unsuffixed_int_lit(expr: &syn::Expr) -> bool {
+    match expr {
+        syn::Expr::Lit(ExprLit {
+            lit: Lit::Int(lit), ..
+        }) => lit.suffix().is_empty(),
+
+        syn::Expr::Paren(ExprParen { expr, .. })
+        | syn::Expr::Group(ExprGroup { expr, .. })
+        | syn::Expr::Unary(ExprUnary { expr, .. }) => is_unsuffixed_int_lit(expr),
+
+        _ => false,
+    }
+}
+
+fn is_unsuffixed_lit(expr: &syn::Expr) -> bool {
+    match expr {
+        syn::Expr::Lit(ExprLit {
+            lit: Lit::Int(lit), ..
+        }) => lit.suffix().is_empty(),
+        syn::Expr::Lit(ExprLit {
+            lit: Lit::Float(lit),
+            ..
+        }) => lit.suffix().is_empty(),
+
+        syn::Expr::Paren(ExprParen { expr, .. })
+        | syn::Expr::Group(ExprGroup { expr, .. })
+        | syn::Expr::Unary(ExprUnary { expr, .. }) => is_unsuffixed_lit(expr),
+
+        _ => false,
+    }
+}
+
+/// Reads the value `value` refers to if it is a reference, which `&T` already
+/// is.
+pub(super) fn auto_deref(value: Expr) -> Expr {
+    match referent(&value.ty) {
+        Some((true, ty)) => {
+            let ptr = &value.ir;
+
+            Expr {
+                ir: quote! { rsshader::ir::ExprIr::Deref { ptr: &#ptr } },
+                ty,
+            }
+        }
+        Some((false, ty)) => Expr { ir: value.ir, ty },
+        None => value,
+    }
+}
+
+/// Reads the field `member` of the struct or vector `base`.
+pub(super) fn read_field(base: &Expr, member: &Member) -> Expr {
+    let Expr { ir, ty } = base;
+    let field = field_trait(member);
+
+    Expr {
+        ir: quote! {
+            rsshader::ir::ExprIr::field(&#ir, <#ty as #field>::IDX)
+        },
+        ty: quote! { <#ty as #field>::Ty },
+    }
+}
+
+/// The `rsshader::reflection::Field` impl that describes `member`.
+pub(super) fn field_trait(member: &Member) -> TokenStream {
+    let name = match member {
+        Member::Named(ident) => ident.to_string(),
+        Member::Unnamed(index) => index.index.to_string(),
+    };
+
+    quote! { rsshader::reflection::Field<{ rsshader::reflection::name_id(#name) }> }
+}
+
+fn array_expr(ty: TokenStream, elements: &[TokenStream]) -> Expr {
+    Expr {
+        ir: quote! {
+            rsshader::ir::ExprIr::ArrayConstructor {
+                ty: &<#ty as rsshader::reflection::Ty>::IR,
+                elements: &[#(#elements),*],
+            }
+        },
+        ty,
+    }
+}
+
+/// The element type of an array expected to be of type `hint`.
+fn element_hint(hint: Hint) -> Option<TokenStream> {
+    match hint {
+        Hint::Ty(ty) => Some(element_ty(ty)),
+        Hint::None | Hint::Param { .. } => None,
+    }
+}
+
+/// The type of the elements of `ty`, which is taken directly from array types
+/// so that their placeholders can still be inferred.
+pub(super) fn element_ty(ty: &TokenStream) -> TokenStream {
+    match syn::parse2::<TypeArray>(ty.clone()) {
+        Ok(TypeArray { elem, .. }) => elem.into_token_stream(),
+        Err(_) => quote! { <#ty as core::ops::Index<usize>>::Output },
+    }
+}
@@ -0,0 +1,158 @@
+use proc_macro2::{Group, Ident, Span, TokenStream, TokenTree};
+use quote::{ToTokens, quote};
+use syn::{GenericArgument, PathArguments, Type, TypeGroup, TypePath};
+
+use crate::shader_item::body::Body;
+
+const INFER_PREFIX: &str = "__rsshader_infer";
+
+impl Body<'_> {
+    /// Returns a placeholder for the type of an unsuffixed integer literal,
+    /// which is replaced by the type it is used as once the body is lowered.
+    pub(super) fn infer_ty(&mut self) -> TokenStream {
+        let idx = self.infer_tys.len();
+        self.infer_tys.push(None);
+
+        Ident::new(&format!("{INFER_PREFIX}{idx}"), Span::call_site()).into_token_stream()
+    }
+
+    /// Records that two types are the same, resolving a placeholder if either
+    /// of them is one.
+    pub(super) fn unify(&mut self, a: &TokenStream, b: &TokenStream) {
+        let a = self.shallow_resolve(a);
+        let b = self.shallow_resolve(b);
+
+        if a.to_string() == b.to_string() {
+            return;
+        }
+
+        // A placeholder is never resolved to a type that contains it, like
+        // the output of an operator applied to it.
+        if let Some(idx) = infer_idx(&a)
+            && !self.contains_infer(&b, idx)
+        {
+            self.infer_tys[idx] = Some(b);
+        } else if let Some(idx) = infer_idx(&b)
+            && !self.contains_infer(&a, idx)
+        {
+            self.infer_tys[idx] = Some(a);
+        } else {
+            self.unify_args(&a, &b);
+        }
+    }
+
+    /// Unifies the generic arguments of two instances of the same type, like
+    /// the `Option<_>` of a `None` with the type it is assigned to.
+    fn unify_args(&mut self, a: &TokenStream, b: &TokenStream) {
+        let (Some(a), Some(b)) = (generic_args(a), generic_args(b)) else {
+            return;
+        };
+
+        if a.0 != b.0 || a.1.len() != b.1.len() {
+            return;
+        }
+
+        for (a, b) in a.1.iter().zip(&b.1) {
+            self.unify(a, b);
+        }
+    }
+
+    fn contains_infer(&self, ty: &TokenStream, idx: usize) -> bool {
+        ty.clone().into_iter().any(|token| match token {
+            TokenTree::Ident(ident) => match infer_idx(&ident.into_token_stream()) {
+                Some(other_idx) if other_idx == idx => true,
+                Some(other_idx) => self.infer_tys[other_idx]
+                    .as_ref()
+                    .is_some_and(|resolved| self.contains_infer(resolved, idx)),
+                None => false,
+            },
+
+            TokenTree::Group(group) => self.contains_infer(&group.stream(), idx),
+
+            _ => false,
+        })
+    }
+
+    /// Replaces every placeholder in `tokens` with the type it was resolved
+    /// to, falling back to `i32` like Rust does.
+    pub(super) fn resolve_infer(&self, tokens: TokenStream) -> TokenStream {
+        tokens
+            .into_iter()
+            .map(|token| match token {
+                TokenTree::Ident(ident) if infer_idx(&ident.to_token_stream()).is_some() => {
+                    let resolved = self.shallow_resolve(&ident.into_token_stream());
+
+                    let resolved = match infer_idx(&resolved) {
+                        Some(_) => quote! { i32 },
+                        None => self.resolve_infer(resolved),
+                    };
+
+                    TokenTree::Group(Group::new(proc_macro2::Delimiter::None, resolved))
+                }
+
+                TokenTree::Group(group) => {
+                    let mut resolved =
+                        Group::new(group.delimiter(), self.resolve_infer(group.stream()));
+                    resolved.set_span(group.span());
+
+                    TokenTree::Group(resolved)
+                }
+
+                token => token,
+            })
+            .collect()
+    }
+
+    fn shallow_resolve(&self, ty: &TokenStream) -> TokenStream {
+        let mut ty = ty.clone();
+
+        while let Some(idx) = infer_idx(&ty)
+            && let Some(resolved) = &self.infer_tys[idx]
+        {
+            ty = resolved.clone();
+        }
+
+        ty
+    }
+}
+
+fn infer_idx(ty: &TokenStream) -> Option<usize> {
+    let mut tokens = ty.clone().into_iter();
+
+    match (tokens.next(), tokens.next()) {
+        (Some(TokenTree::Ident(ident)), None) => ident
+            .to_string()
+            .strip_prefix(INFER_PREFIX)
+            .and_then(|idx| idx.parse().ok()),
+
+        _ => None,
+    }
+}
+
+/// Splits a path type into its name and generic type arguments.
+pub(super) fn generic_args(ty: &TokenStream) -> Option<(String, Vec<TokenStream>)> {
+    let mut ty = syn::parse2::<Type>(ty.clone()).ok()?;
+    while let Type::Group(TypeGroup { elem, .. }) = ty {
+        ty = *elem;
+    }
+
+    let Type::Path(TypePath { qself: None, path }) = ty else {
+        return None;
+    };
+
+    let segment = path.segments.last()?;
+    let PathArguments::AngleBracketed(args) = &segment.arguments else {
+        return None;
+    };
+
+    let args = args
+        .args
+        .iter()
+        .filter_map(|arg| match arg {
+            GenericArgument::Type(ty) => Some(ty.to_token_stream()),
+            _ => None,
+        })
+        .collect();
+
+    Some((segment.ident.to_string(), args))
+}
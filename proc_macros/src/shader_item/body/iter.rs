@@ -0,0 +1,1087 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{
+    BinOp, Block, ExprCall, ExprForLoop, ExprGroup, ExprMethodCall, ExprParen, ExprPath,
+    ExprReference, ExprUnary, Stmt, UnOp, spanned::Spanned,
+};
+
+use crate::shader_item::{
+    body::{
+        Body, Expr,
+        closure::{Closure, Item, local_value},
+        control::LoopKind,
+        expr::{Hint, auto_deref, element_ty},
+        reported,
+        stmt::Tail,
+    },
+    util::referent,
+};
+
+/// An iterator chain over arrays, from the outermost adapter inwards. WGSL has
+/// no iterators, so chains are lowered to loops over the arrays with the
+/// closures of the adapters inlined.
+enum Iter<'a> {
+    /// `array.iter()`, `array.into_iter()` or an array iterated over directly.
+    Array {
+        array: &'a syn::Expr,
+        by_ref: bool,
+    },
+    Enumerate(Box<Iter<'a>>),
+    Zip(Box<Iter<'a>>, Box<Iter<'a>>),
+    Map(Box<Iter<'a>>, Closure),
+    Filter(Box<Iter<'a>>, Closure),
+}
+
+/// An iterator whose items can be computed from their index, which is any
+/// iterator without `filter`. The arrays are evaluated before the loop.
+enum Indexed {
+    Array {
+        array: TokenStream,
+        ty: TokenStream,
+        by_ref: bool,
+    },
+    Enumerate(Box<Indexed>),
+    Zip(Box<Indexed>, Box<Indexed>),
+    Map(Box<Indexed>, Closure),
+}
+
+/// Iterator methods that consume the iterator into a value.
+const CONSUMERS: &[&str] = &["sum", "product", "fold", "any", "all", "min_by"];
+
+impl Body<'_> {
+    /// Lowers `sum`, `product`, `fold`, `any`, `all` or `min_by` at the end of
+    /// an iterator chain. `min_by` has to be followed by `unwrap` or
+    /// `unwrap_or`, and an empty iterator unwraps to the zero value.
+    pub(super) fn lower_iter_consumer(
+        &mut self,
+        expr: &ExprMethodCall,
+        stmts: &mut Vec<TokenStream>,
+        hint: Hint,
+    ) -> Expr {
+        let (expr, unwrap) = match expr.method.to_string().as_str() {
+            "unwrap" | "unwrap_or" => match &*expr.receiver {
+                syn::Expr::MethodCall(receiver) => (receiver, Some(expr)),
+                _ => unreachable!("not an iterator consumer"),
+            },
+            _ => (expr, None),
+        };
+
+        let ExprMethodCall {
+            receiver,
+            method,
+            turbofish,
+            args,
+            ..
+        } = expr;
+
+        if let Some(turbofish) = turbofish
+            && !matches!(method.to_string().as_str(), "sum" | "product")
+        {
+            return self.error(
+                turbofish,
+                "generic arguments cannot be specified for methods in shaders",
+            );
+        }
+
+        let Some(iter) = self.parse_iter(receiver) else {
+            return reported();
+        };
+
+        let args = args.iter().collect::<Vec<_>>();
+        match (method.to_string().as_str(), &args[..]) {
+            ("sum" | "product", []) => self.lower_sum(&iter, method == "product", stmts),
+            ("fold", [init, func]) => self.lower_fold(&iter, init, func, stmts, hint),
+            ("any" | "all", [func]) => self.lower_any(&iter, func, method == "all", stmts),
+
+            ("min_by", [func]) => match unwrap {
+                Some(unwrap) => self.lower_min_by(&iter, func, unwrap, stmts),
+                None => self.error(
+                    method,
+                    "the result of `min_by` must be unwrapped with `unwrap` or `unwrap_or` in \
+                     shaders",
+                ),
+            },
+
+            _ => self.error(method, "unexpected arguments for this iterator method"),
+        }
+    }
+
+    /// Lowers `core::array::from_fn(closure)` to a loop that assigns every
+    /// element of the array, with the closure inlined. The length is only
+    /// known from the array type, which has to come from the context.
+    pub(super) fn lower_array_from_fn(
+        &mut self,
+        expr: &ExprCall,
+        stmts: &mut Vec<TokenStream>,
+        hint: Hint,
+    ) -> Expr {
+        let Hint::Ty(ty) = hint else {
+            return self.error(
+                expr,
+                "the array type of `from_fn` must be known from its context in shaders",
+            );
+        };
+
+        let closure = match &expr.args.iter().collect::<Vec<_>>()[..] {
+            [func] => self.closure_arg(func),
+            _ => None,
+        };
+
+        let Some(closure) = closure else {
+            return self.error(
+                &expr.args,
+                "only closures can be passed to `from_fn` in shaders",
+            );
+        };
+
+        let ty = ty.clone();
+        let element_ty = element_ty(&ty);
+        let array = self.declare_var(&ty, stmts);
+        let place = array.place.clone().unwrap();
+        let len = quote! { <#ty as rsshader::reflection::Ty>::IR.array_len() };
+
+        self.lower_counted_loop(&len, stmts, |this, idx, body| {
+            let idx_value = Item::Value(Expr {
+                ir: idx.clone(),
+                ty: quote! { usize },
+            });
+
+            let value = this.lower_closure(&closure, vec![idx_value], body, Hint::Ty(&element_ty));
+            let value = this.item_value(value, &closure.func.body);
+            this.unify(&element_ty, &value.ty);
+
+            let value = value.ir;
+
+            body.push(quote! {
+                rsshader::ir::StmtIr::Assignment {
+                    left: rsshader::ir::PlaceIr::Index {
+                        base: &#place,
+                        index: &#idx,
+                    },
+                    right: #value,
+                }
+            });
+        });
+
+        Expr { ir: array.ir, ty }
+    }
+
+    /// Lowers a `for` loop over an iterator chain or an array.
+    pub(super) fn lower_for_iter(&mut self, expr: &ExprForLoop, stmts: &mut Vec<TokenStream>) {
+        let ExprForLoop {
+            label,
+            pat,
+            expr: iter,
+            body: block,
+            ..
+        } = expr;
+
+        let Some(iter) = self.parse_iter(iter) else {
+            return;
+        };
+
+        let (decls, body) = self.lower_loop_body(
+            LoopKind::Loop,
+            label.as_ref(),
+            &mut Tail::Discard,
+            |this, body| {
+                this.lower_iter_loop(&iter, body, &mut |this, item, body| {
+                    this.push_scope();
+                    this.bind_item(pat, item, body);
+                    this.lower_block_tail(block, body, &mut Tail::Discard);
+                    this.pop_scope();
+                });
+            },
+        );
+
+        stmts.extend(decls);
+        stmts.extend(body);
+
+        self.lower_loop_exit_checks(stmts);
+    }
+
+    fn lower_sum(&mut self, iter: &Iter, is_product: bool, stmts: &mut Vec<TokenStream>) -> Expr {
+        // The type of the sum is only known once an item is lowered, so it is
+        // declared after the loop is.
+        let id = self.next_var_id();
+        let mut sum_ty = None;
+
+        let mut loop_stmts = Vec::new();
+        self.lower_iter_loop(iter, &mut loop_stmts, &mut |this, item, body| {
+            let item = auto_deref(this.item_value(item, iter.span()));
+            let ty = item.ty.clone();
+            let sum = variable(id, &ty);
+
+            let op = match is_product {
+                false => BinOp::Add(Default::default()),
+                true => BinOp::Mul(Default::default()),
+            };
+            let place = sum.place.clone();
+            let value = this.binary_op(&op, sum.value(), item).ir;
+
+            body.push(quote! {
+                rsshader::ir::StmtIr::Assignment {
+                    left: #place,
+                    right: #value,
+                }
+            });
+
+            sum_ty = Some(ty);
+        });
+
+        let Some(ty) = sum_ty else {
+            return reported();
+        };
+
+        // Variables start out as zero.
+        stmts.push(quote! {
+            rsshader::ir::StmtIr::VariableDecl {
+                id: #id,
+                ty: &<#ty as rsshader::reflection::Ty>::IR,
+            }
+        });
+
+        let sum = variable(id, &ty);
+        if is_product {
+            let place = &sum.place;
+
+            stmts.push(quote! {
+                rsshader::ir::StmtIr::Assignment {
+                    left: #place,
+                    right: rsshader::ir::ExprIr::Literal(rsshader::ir::Literal::one(
+                        &<#ty as rsshader::reflection::Ty>::IR,
+                    )),
+                }
+            });
+        }
+
+        stmts.extend(loop_stmts);
+
+        sum.value()
+    }
+
+    fn lower_fold(
+        &mut self,
+        iter: &Iter,
+        init: &syn::Expr,
+        func: &syn::Expr,
+        stmts: &mut Vec<TokenStream>,
+        hint: Hint,
+    ) -> Expr {
+        let Some(closure) = self.closure_arg(func) else {
+            return self.error(
+                func,
+                "only closures can be passed to iterator methods in shaders",
+            );
+        };
+
+        let hint = match hint {
+            Hint::Ty(ty) => Hint::Ty(ty),
+            Hint::None | Hint::Param { .. } => Hint::None,
+        };
+
+        let init = self.lower_expr(init, stmts, hint);
+        let ty = init.ty.clone();
+        let acc = self.declare_var(&ty, stmts);
+        let place = acc.place.clone().unwrap();
+        let init = init.ir;
+
+        stmts.push(quote! {
+            rsshader::ir::StmtIr::Assignment {
+                left: #place,
+                right: #init,
+            }
+        });
+
+        let acc = Expr { ir: acc.ir, ty };
+
+        self.lower_iter_loop(iter, stmts, &mut |this, item, body| {
+            let args = vec![Item::Value(acc.clone()), item];
+            let value = this.lower_closure(&closure, args, body, Hint::Ty(&acc.ty));
+            let value = this.item_value(value, &closure.func.body);
+
+            this.unify(&acc.ty, &value.ty);
+            let value = value.ir;
+
+            body.push(quote! {
+                rsshader::ir::StmtIr::Assignment {
+                    left: #place,
+                    right: #value,
+                }
+            });
+        });
+
+        acc
+    }
+
+    /// Lowers `any`, or `all` if `is_all` is set, which stop at the first item
+    /// that decides the result.
+    fn lower_any(
+        &mut self,
+        iter: &Iter,
+        func: &syn::Expr,
+        is_all: bool,
+        stmts: &mut Vec<TokenStream>,
+    ) -> Expr {
+        let Some(closure) = self.closure_arg(func) else {
+            return self.error(
+                func,
+                "only closures can be passed to iterator methods in shaders",
+            );
+        };
+
+        let ty = quote! { bool };
+        let result = self.declare_var(&ty, stmts);
+        let place = result.place.clone().unwrap();
+
+        stmts.push(quote! {
+            rsshader::ir::StmtIr::Assignment {
+                left: #place,
+                right: rsshader::ir::ExprIr::Literal(rsshader::ir::Literal::Bool(#is_all)),
+            }
+        });
+
+        let is_any = !is_all;
+        self.lower_iter_loop(iter, stmts, &mut |this, item, body| {
+            let value = this.lower_closure(&closure, vec![item], body, Hint::Ty(&ty));
+            let value = auto_deref(this.item_value(value, &closure.func.body)).ir;
+
+            let cond = match is_all {
+                false => value,
+                true => quote! {
+                    rsshader::ir::ExprIr::Call {
+                        func: &<bool as rsshader::reflection::Not>::IR,
+                        args: &[#value],
+                    }
+                },
+            };
+
+            body.push(quote! {
+                rsshader::ir::StmtIr::If {
+                    cond: #cond,
+                    then: rsshader::ir::BodyIr {
+                        stmts: &[
+                            rsshader::ir::StmtIr::Assignment {
+                                left: #place,
+                                right: rsshader::ir::ExprIr::Literal(
+                                    rsshader::ir::Literal::Bool(#is_any),
+                                ),
+                            },
+                            rsshader::ir::StmtIr::Break,
+                        ],
+                    },
+                    otherwise: rsshader::ir::BodyIr {
+                        stmts: &[],
+                    },
+                }
+            });
+        });
+
+        Expr { ir: result.ir, ty }
+    }
+
+    /// Lowers `min_by(..).unwrap()` or `min_by(..).unwrap_or(..)`. The closure
+    /// has to compare with `cmp`, `total_cmp` or `partial_cmp(..).unwrap()`,
+    /// optionally followed by `reverse`, so that it can be lowered to a
+    /// comparison.
+    fn lower_min_by(
+        &mut self,
+        iter: &Iter,
+        func: &syn::Expr,
+        unwrap: &ExprMethodCall,
+        stmts: &mut Vec<TokenStream>,
+    ) -> Expr {
+        let Some(closure) = self.closure_arg(func) else {
+            return self.error(
+                func,
+                "only closures can be passed to iterator methods in shaders",
+            );
+        };
+
+        let Some((left, right)) = parse_ordering(&closure.func.body) else {
+            return self.error(
+                &closure.func.body,
+                "`min_by` closures must compare with `cmp`, `total_cmp` or \
+                 `partial_cmp(..).unwrap()` in shaders",
+            );
+        };
+
+        let found = self.declare_var(&quote! { bool }, stmts);
+        let found_place = found.place.clone().unwrap();
+
+        stmts.push(quote! {
+            rsshader::ir::StmtIr::Assignment {
+                left: #found_place,
+                right: rsshader::ir::ExprIr::Literal(rsshader::ir::Literal::Bool(false)),
+            }
+        });
+
+        // Like with `sum`, the type of the minimum is only known once an item
+        // is lowered.
+        let id = self.next_var_id();
+        let mut item_ty = None;
+
+        let mut loop_stmts = Vec::new();
+        self.lower_iter_loop(iter, &mut loop_stmts, &mut |this, item, body| {
+            let item = this.item_value(item, iter.span());
+            let min = variable(id, &deref_ty(&item.ty));
+            let current = Expr {
+                ir: min.ir.clone(),
+                ty: item.ty.clone(),
+            };
+
+            // The closure takes references to the current minimum and the
+            // item, and the item replaces the minimum if it compares greater.
+            let args = [current, item.clone()]
+                .into_iter()
+                .map(|arg| Item::Value(arg).by_ref())
+                .collect();
+
+            let is_greater = this.in_closure(&closure, args, body, |this, body| {
+                let left = deref_all(this.lower_expr(left, body, Hint::None));
+                let right = deref_all(this.lower_expr(right, body, Hint::None));
+
+                this.binary_op(&BinOp::Gt(Default::default()), left, right)
+                    .ir
+            });
+
+            let found = &found.ir;
+            let place = &min.place;
+            let item_value = &item.ir;
+
+            body.push(quote! {
+                rsshader::ir::StmtIr::If {
+                    cond: rsshader::ir::ExprIr::Or {
+                        left: &rsshader::ir::ExprIr::Call {
+                            func: &<bool as rsshader::reflection::Not>::IR,
+                            args: &[#found],
+                        },
+                        right: &#is_greater,
+                    },
+                    then: rsshader::ir::BodyIr {
+                        stmts: &[
+                            rsshader::ir::StmtIr::Assignment {
+                                left: #place,
+                                right: #item_value,
+                            },
+                            rsshader::ir::StmtIr::Assignment {
+                                left: #found_place,
+                                right: rsshader::ir::ExprIr::Literal(
+                                    rsshader::ir::Literal::Bool(true),
+                                ),
+                            },
+                        ],
+                    },
+                    otherwise: rsshader::ir::BodyIr {
+                        stmts: &[],
+                    },
+                }
+            });
+
+            item_ty = Some(item.ty);
+        });
+
+        let Some(ty) = item_ty else {
+            return reported();
+        };
+
+        let min = variable(id, &deref_ty(&ty));
+        let min_ty = &min.ty;
+
+        stmts.push(quote! {
+            rsshader::ir::StmtIr::VariableDecl {
+                id: #id,
+                ty: &<#min_ty as rsshader::reflection::Ty>::IR,
+            }
+        });
+        stmts.extend(loop_stmts);
+
+        if let [default] = unwrap.args.iter().collect::<Vec<_>>()[..] {
+            let default = deref_all(self.lower_expr(default, stmts, Hint::Ty(&ty)));
+            self.unify(min_ty, &default.ty);
+
+            let found = &found.ir;
+            let place = &min.place;
+            let default = default.ir;
+
+            stmts.push(quote! {
+                rsshader::ir::StmtIr::If {
+                    cond: rsshader::ir::ExprIr::Call {
+                        func: &<bool as rsshader::reflection::Not>::IR,
+                        args: &[#found],
+                    },
+                    then: rsshader::ir::BodyIr {
+                        stmts: &[rsshader::ir::StmtIr::Assignment {
+                            left: #place,
+                            right: #default,
+                        }],
+                    },
+                    otherwise: rsshader::ir::BodyIr {
+                        stmts: &[],
+                    },
+                }
+            });
+        }
+
+        Expr { ir: min.ir, ty }
+    }
+
+    /// Parses an iterator chain, reporting anything that is not supported.
+    /// Expressions that are not a chain are arrays that are iterated over
+    /// directly.
+    fn parse_iter<'a>(&mut self, expr: &'a syn::Expr) -> Option<Iter<'a>> {
+        let ExprMethodCall {
+            receiver,
+            method,
+            turbofish,
+            args,
+            ..
+        } = match expr {
+            syn::Expr::Paren(ExprParen { expr, .. }) | syn::Expr::Group(ExprGroup { expr, .. }) => {
+                return self.parse_iter(expr);
+            }
+
+            syn::Expr::MethodCall(call) if is_iter_chain(expr) => call,
+
+            array => {
+                return Some(Iter::Array {
+                    array,
+                    by_ref: false,
+                });
+            }
+        };
+
+        if let Some(turbofish) = turbofish {
+            self.error(
+                turbofish,
+                "generic arguments cannot be specified for methods in shaders",
+            );
+            return None;
+        }
+
+        let args = args.iter().collect::<Vec<_>>();
+        let iter = match (method.to_string().as_str(), &args[..]) {
+            ("iter", []) => Iter::Array {
+                array: receiver,
+                by_ref: true,
+            },
+            ("into_iter", []) => Iter::Array {
+                array: receiver,
+                by_ref: false,
+            },
+
+            ("enumerate", []) => Iter::Enumerate(Box::new(self.parse_iter(receiver)?)),
+            ("zip", [other]) => Iter::Zip(
+                Box::new(self.parse_iter(receiver)?),
+                Box::new(self.parse_iter(other)?),
+            ),
+
+            ("map", [func]) | ("filter", [func]) => {
+                let inner = Box::new(self.parse_iter(receiver)?);
+
+                let Some(closure) = self.closure_arg(func) else {
+                    self.error(
+                        func,
+                        "only closures can be passed to iterator adapters in shaders",
+                    );
+                    return None;
+                };
+
+                match method == "map" {
+                    true => Iter::Map(inner, closure),
+                    false => Iter::Filter(inner, closure),
+                }
+            }
+
+            _ => {
+                self.error(
+                    method,
+                    "this iterator adapter is not supported in shaders, only `iter`, `into_iter`, \
+                     `enumerate`, `zip`, `map` and `filter` are",
+                );
+                return None;
+            }
+        };
+
+        Some(iter)
+    }
+
+    /// Lowers a loop over the items of `iter`, lowering the body of the loop
+    /// for each item with `consume`.
+    fn lower_iter_loop(
+        &mut self,
+        iter: &Iter,
+        stmts: &mut Vec<TokenStream>,
+        consume: &mut dyn FnMut(&mut Self, Item, &mut Vec<TokenStream>),
+    ) {
+        // Without `filter` every item is computed from the index of the loop.
+        if !iter.is_filtered() {
+            let Some(indexed) = self.lower_indexed(iter, stmts) else {
+                return;
+            };
+            let len = indexed.len();
+
+            self.lower_counted_loop(&len, stmts, |this, idx, body| {
+                let item = this.indexed_item(&indexed, idx, body);
+                consume(this, item, body);
+            });
+
+            return;
+        }
+
+        match iter {
+            Iter::Array { .. } => unreachable!("arrays are never filtered"),
+
+            Iter::Filter(inner, func) => {
+                self.lower_iter_loop(inner, stmts, &mut |this, item, body| {
+                    // The closure takes a reference to the item.
+                    let arg = item.clone().by_ref();
+                    let cond =
+                        this.lower_closure(func, vec![arg], body, Hint::Ty(&quote! { bool }));
+                    let cond = auto_deref(this.item_value(cond, &func.func.body)).ir;
+
+                    let mut then = Vec::new();
+                    consume(this, item, &mut then);
+
+                    body.push(quote! {
+                        rsshader::ir::StmtIr::If {
+                            cond: #cond,
+                            then: rsshader::ir::BodyIr {
+                                stmts: &[#(#then),*],
+                            },
+                            otherwise: rsshader::ir::BodyIr {
+                                stmts: &[],
+                            },
+                        }
+                    });
+                });
+            }
+
+            Iter::Map(inner, func) => {
+                self.lower_iter_loop(inner, stmts, &mut |this, item, body| {
+                    let item = this.lower_closure(func, vec![item], body, Hint::None);
+                    consume(this, item, body);
+                });
+            }
+
+            // The index only counts the items that are not filtered out, so it
+            // has its own counter.
+            Iter::Enumerate(inner) => {
+                let counter = self.declare_counter(stmts);
+
+                self.lower_iter_loop(inner, stmts, &mut |this, item, body| {
+                    let idx = this.declare_let(counter.value(), body);
+                    body.push(counter.increment());
+
+                    consume(
+                        this,
+                        Item::Tuple(vec![Item::Value(local_value(idx)), item]),
+                        body,
+                    );
+                });
+            }
+
+            // Filtered iterators can only be on the left of `zip`, so the
+            // right one is indexed by the number of items that were zipped.
+            Iter::Zip(left, right) => {
+                let Some(right) = self.lower_indexed(right, stmts) else {
+                    return;
+                };
+                let len = right.len();
+                let counter = self.declare_counter(stmts);
+
+                self.lower_iter_loop(left, stmts, &mut |this, item, body| {
+                    body.push(break_if_at_end(&counter.value().ir, &len));
+
+                    let idx = local_value(this.declare_let(counter.value(), body));
+                    body.push(counter.increment());
+
+                    let right = this.indexed_item(&right, &idx.ir, body);
+                    consume(this, Item::Tuple(vec![item, right]), body);
+                });
+            }
+        }
+    }
+
+    /// Evaluates the arrays of an iterator without `filter`.
+    fn lower_indexed(&mut self, iter: &Iter, stmts: &mut Vec<TokenStream>) -> Option<Indexed> {
+        let indexed = match iter {
+            Iter::Array { array, by_ref } => {
+                let is_simple = matches!(strip_reference(array), syn::Expr::Path(_));
+                let value = self.lower_expr(array, stmts, Hint::None);
+                let by_ref = *by_ref || referent(&value.ty).is_some();
+                let value = auto_deref(value);
+
+                let ty = value.ty.clone();
+                let array = match is_simple {
+                    true => value.ir,
+                    false => self.declare_let(value, stmts).ir,
+                };
+
+                Indexed::Array { array, ty, by_ref }
+            }
+
+            Iter::Enumerate(inner) => {
+                Indexed::Enumerate(Box::new(self.lower_indexed(inner, stmts)?))
+            }
+            Iter::Zip(left, right) => Indexed::Zip(
+                Box::new(self.lower_indexed(left, stmts)?),
+                Box::new(self.lower_indexed(right, stmts)?),
+            ),
+            Iter::Map(inner, func) => {
+                Indexed::Map(Box::new(self.lower_indexed(inner, stmts)?), func.clone())
+            }
+
+            Iter::Filter(_, func) => {
+                self.error(
+                    &*func.func,
+                    "filtered iterators can only be on the left of `zip` in shaders",
+                );
+                return None;
+            }
+        };
+
+        Some(indexed)
+    }
+
+    /// The item of `iter` at `idx`.
+    fn indexed_item(
+        &mut self,
+        iter: &Indexed,
+        idx: &TokenStream,
+        stmts: &mut Vec<TokenStream>,
+    ) -> Item {
+        match iter {
+            Indexed::Array { array, ty, by_ref } => {
+                let element_ty = element_ty(ty);
+
+                Item::Value(Expr {
+                    ir: quote! {
+                        rsshader::ir::ExprIr::Index {
+                            base: &#array,
+                            index: &#idx,
+                        }
+                    },
+                    ty: match by_ref {
+                        true => quote! { &#element_ty },
+                        false => element_ty,
+                    },
+                })
+            }
+
+            Indexed::Enumerate(inner) => {
+                let idx_value = Item::Value(Expr {
+                    ir: idx.clone(),
+                    ty: quote! { usize },
+                });
+
+                Item::Tuple(vec![idx_value, self.indexed_item(inner, idx, stmts)])
+            }
+
+            Indexed::Zip(left, right) => Item::Tuple(vec![
+                self.indexed_item(left, idx, stmts),
+                self.indexed_item(right, idx, stmts),
+            ]),
+
+            Indexed::Map(inner, func) => {
+                let item = self.indexed_item(inner, idx, stmts);
+                self.lower_closure(func, vec![item], stmts, Hint::None)
+            }
+        }
+    }
+
+    /// Emits a loop that lowers its body with `lower` for every index below the
+    /// `usize` constant expression `len`.
+    fn lower_counted_loop(
+        &mut self,
+        len: &TokenStream,
+        stmts: &mut Vec<TokenStream>,
+        lower: impl FnOnce(&mut Self, &TokenStream, &mut Vec<TokenStream>),
+    ) {
+        let counter = self.declare_counter(stmts);
+
+        let mut body = vec![break_if_at_end(&counter.ir, len)];
+        lower(self, &counter.ir, &mut body);
+
+        let increment = counter.increment();
+
+        stmts.push(quote! {
+            rsshader::ir::StmtIr::Loop {
+                body: rsshader::ir::BodyIr {
+                    stmts: &[#(#body),*],
+                },
+                continuing: rsshader::ir::BodyIr {
+                    stmts: &[#increment],
+                },
+                break_if: None,
+            }
+        });
+    }
+
+    /// Declares a `usize` variable that starts at zero.
+    fn declare_counter(&mut self, stmts: &mut Vec<TokenStream>) -> Variable {
+        let id = self.next_var_id();
+        let counter = variable(id, &quote! { usize });
+        let place = &counter.place;
+
+        stmts.push(quote! {
+            rsshader::ir::StmtIr::VariableDecl {
+                id: #id,
+                ty: &<usize as rsshader::reflection::Ty>::IR,
+            }
+        });
+        stmts.push(quote! {
+            rsshader::ir::StmtIr::Assignment {
+                left: #place,
+                right: rsshader::ir::ExprIr::Literal(rsshader::ir::Literal::U32(0)),
+            }
+        });
+
+        counter
+    }
+}
+
+impl Iter<'_> {
+    fn is_filtered(&self) -> bool {
+        match self {
+            Iter::Array { .. } => false,
+            Iter::Filter(..) => true,
+            Iter::Enumerate(inner) | Iter::Map(inner, _) => inner.is_filtered(),
+            Iter::Zip(left, right) => left.is_filtered() || right.is_filtered(),
+        }
+    }
+
+    fn span(&self) -> proc_macro2::Span {
+        match self {
+            Iter::Array { array, .. } => array.span(),
+            Iter::Enumerate(inner) | Iter::Zip(inner, _) => inner.span(),
+            Iter::Map(_, func) | Iter::Filter(_, func) => func.func.span(),
+        }
+    }
+}
+
+impl Indexed {
+    /// The number of items, as a `usize` constant expression.
+    fn len(&self) -> TokenStream {
+        match self {
+            Indexed::Array { ty, .. } => {
+                quote! { <#ty as rsshader::reflection::Ty>::IR.array_len() }
+            }
+
+            Indexed::Enumerate(inner) | Indexed::Map(inner, _) => inner.len(),
+
+            Indexed::Zip(left, right) => {
+                let left = left.len();
+                let right = right.len();
+
+                quote! {
+                    {
+                        let (left, right) = (#left, #right);
+                        if left < right { left } else { right }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// A mutable variable that is declared separately.
+struct Variable {
+    ir: TokenStream,
+    ty: TokenStream,
+    place: TokenStream,
+}
+
+impl Variable {
+    fn value(&self) -> Expr {
+        Expr {
+            ir: self.ir.clone(),
+            ty: self.ty.clone(),
+        }
+    }
+
+    /// Adds one to a `usize` counter.
+    fn increment(&self) -> TokenStream {
+        let Self { ir, place, .. } = self;
+
+        quote! {
+            rsshader::ir::StmtIr::Assignment {
+                left: #place,
+                right: rsshader::ir::ExprIr::Call {
+                    func: &<usize as rsshader::reflection::Add>::IR,
+                    args: &[#ir, rsshader::ir::ExprIr::Literal(rsshader::ir::Literal::U32(1))],
+                },
+            }
+        }
+    }
+}
+
+fn variable(id: usize, ty: &TokenStream) -> Variable {
+    Variable {
+        ir: quote! {
+            rsshader::ir::ExprIr::Variable {
+                id: #id,
+                ty: &<#ty as rsshader::reflection::Ty>::IR,
+            }
+        },
+        ty: ty.clone(),
+        place: quote! {
+            rsshader::ir::PlaceIr::Variable {
+                id: #id,
+                ty: &<#ty as rsshader::reflection::Ty>::IR,
+            }
+        },
+    }
+}
+
+fn break_if_at_end(idx: &TokenStream, len: &TokenStream) -> TokenStream {
+    quote! {
+        rsshader::ir::StmtIr::If {
+            cond: rsshader::ir::ExprIr::Call {
+                func: &<usize as rsshader::reflection::PartialOrd>::GE,
+                args: &[
+                    #idx,
+                    rsshader::ir::ExprIr::Literal(rsshader::ir::Literal::U32((#len) as u32)),
+                ],
+            },
+            then: rsshader::ir::BodyIr {
+                stmts: &[rsshader::ir::StmtIr::Break],
+            },
+            otherwise: rsshader::ir::BodyIr {
+                stmts: &[],
+            },
+        }
+    }
+}
+
+/// Whether `expr` calls `core::array::from_fn`.
+pub(super) fn is_array_from_fn(expr: &ExprCall) -> bool {
+    let syn::Expr::Path(ExprPath {
+        qself: None, path, ..
+    }) = &*expr.func
+    else {
+        return false;
+    };
+
+    let names = path
+        .segments
+        .iter()
+        .map(|segment| segment.ident.to_string())
+        .collect::<Vec<_>>();
+
+    names.ends_with(&["array".to_string(), "from_fn".to_string()])
+}
+
+/// Whether `expr` is a chain of methods that starts with `iter` or
+/// `into_iter`.
+pub(super) fn is_iter_chain(expr: &syn::Expr) -> bool {
+    match expr {
+        syn::Expr::Paren(ExprParen { expr, .. }) | syn::Expr::Group(ExprGroup { expr, .. }) => {
+            is_iter_chain(expr)
+        }
+
+        syn::Expr::MethodCall(ExprMethodCall {
+            receiver,
+            method,
+            args,
+            ..
+        }) => {
+            (args.is_empty() && (method == "iter" || method == "into_iter"))
+                || is_iter_chain(receiver)
+        }
+
+        _ => false,
+    }
+}
+
+/// Whether `expr` consumes an iterator chain into a value.
+pub(super) fn is_iter_consumer(expr: &ExprMethodCall) -> bool {
+    let ExprMethodCall {
+        receiver, method, ..
+    } = expr;
+
+    if method == "unwrap" || method == "unwrap_or" {
+        return matches!(
+            &**receiver,
+            syn::Expr::MethodCall(receiver) if receiver.method == "min_by" && is_iter_chain(&receiver.receiver)
+        );
+    }
+
+    CONSUMERS.iter().any(|consumer| method == consumer) && is_iter_chain(receiver)
+}
+
+/// Splits the body of a `min_by` closure into the values it compares, which
+/// are swapped by `reverse`.
+fn parse_ordering(expr: &syn::Expr) -> Option<(&syn::Expr, &syn::Expr)> {
+    match expr {
+        syn::Expr::Paren(ExprParen { expr, .. }) | syn::Expr::Group(ExprGroup { expr, .. }) => {
+            parse_ordering(expr)
+        }
+
+        syn::Expr::Block(block) => match &block.block {
+            Block { stmts, .. } if stmts.len() == 1 => match &stmts[0] {
+                Stmt::Expr(expr, None) => parse_ordering(expr),
+                _ => None,
+            },
+            _ => None,
+        },
+
+        syn::Expr::MethodCall(ExprMethodCall {
+            receiver,
+            method,
+            args,
+            ..
+        }) => match (
+            method.to_string().as_str(),
+            &args.iter().collect::<Vec<_>>()[..],
+        ) {
+            ("cmp" | "total_cmp", [other]) => Some((receiver, other)),
+            ("reverse", []) => parse_ordering(receiver).map(|(left, right)| (right, left)),
+
+            ("unwrap", []) => match &**receiver {
+                syn::Expr::MethodCall(ExprMethodCall {
+                    receiver,
+                    method,
+                    args,
+                    ..
+                }) if method == "partial_cmp" && args.len() == 1 => Some((receiver, &args[0])),
+                _ => None,
+            },
+
+            _ => None,
+        },
+
+        _ => None,
+    }
+}
+
+/// Reads through every level of references of a value.
+fn deref_all(mut value: Expr) -> Expr {
+    while referent(&value.ty).is_some() {
+        value = auto_deref(value);
+    }
+
+    value
+}
+
+/// The type behind every level of references of `ty`.
+fn deref_ty(ty: &TokenStream) -> TokenStream {
+    match referent(ty) {
+        Some((_, ty)) => deref_ty(&ty),
+        None => ty.clone(),
+    }
+}
+
+fn strip_reference(expr: &syn::Expr) -> &syn::Expr {
+    match expr {
+        syn::Expr::Paren(ExprParen { expr, .. })
+        | syn::Expr::Group(ExprGroup { expr, .. })
+        | syn::Expr::Reference(ExprReference { expr, .. }) => strip_reference(expr),
+        syn::Expr::Unary(ExprUnary {
+            op: UnOp::Deref(_),
+            expr,
+            ..
+        }) => strip_reference(expr),
+        expr => expr,
+    }
+}
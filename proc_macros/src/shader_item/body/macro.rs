@@ -0,0 +1,297 @@
+use proc_macro2::{Ident, TokenStream};
+use quote::quote;
+use syn::{
+    Attribute, Error, Macro, Pat, Token, parse::ParseStream, parse_quote, punctuated::Punctuated,
+};
+
+use crate::shader_item::body::{
+    Body, Expr,
+    expr::{Call, Hint},
+    stmt::has_calls,
+};
+
+/// How a known macro is lowered. Macros are opaque tokens, so each one is
+/// expanded by hand.
+#[derive(Clone, Copy)]
+enum KnownMacro {
+    Expr(fn(&mut Body, &Macro, &mut Vec<TokenStream>, Hint) -> Expr),
+    Stmt(fn(&mut Body, &Macro, &mut Vec<TokenStream>)),
+}
+
+/// The macros shader functions can use, looked up by the last segment of their
+/// path.
+const MACROS: &[(&str, KnownMacro)] = &[
+    (
+        "vec2",
+        KnownMacro::Expr(|body, mac, stmts, hint| body.lower_vector_macro(2, mac, stmts, hint)),
+    ),
+    (
+        "vec3",
+        KnownMacro::Expr(|body, mac, stmts, hint| body.lower_vector_macro(3, mac, stmts, hint)),
+    ),
+    (
+        "vec4",
+        KnownMacro::Expr(|body, mac, stmts, hint| body.lower_vector_macro(4, mac, stmts, hint)),
+    ),
+    (
+        "matches",
+        KnownMacro::Expr(|body, mac, stmts, hint| body.lower_matches_macro(mac, stmts, hint)),
+    ),
+    // Shaders cannot panic, so assertions are only checked when the function
+    // runs in Rust.
+    (
+        "assert",
+        KnownMacro::Stmt(|body, mac, stmts| body.lower_assert_macro(1, mac, stmts)),
+    ),
+    (
+        "assert_eq",
+        KnownMacro::Stmt(|body, mac, stmts| body.lower_assert_macro(2, mac, stmts)),
+    ),
+    (
+        "assert_ne",
+        KnownMacro::Stmt(|body, mac, stmts| body.lower_assert_macro(2, mac, stmts)),
+    ),
+    (
+        "debug_assert",
+        KnownMacro::Stmt(|body, mac, stmts| body.lower_assert_macro(1, mac, stmts)),
+    ),
+    (
+        "debug_assert_eq",
+        KnownMacro::Stmt(|body, mac, stmts| body.lower_assert_macro(2, mac, stmts)),
+    ),
+    (
+        "debug_assert_ne",
+        KnownMacro::Stmt(|body, mac, stmts| body.lower_assert_macro(2, mac, stmts)),
+    ),
+];
+
+/// The macros a shader function can use. Besides the known macros, these are
+/// the macros that `#[shader_macros(name = known, ..)]` on the item declares
+/// to be expanded like a known one, for crates whose macros are named
+/// differently.
+#[derive(Debug, Clone, Default)]
+pub struct Macros {
+    aliases: Vec<(String, &'static str)>,
+}
+
+impl Macros {
+    /// Removes the `#[shader_macros(..)]` attributes from `attrs`, and reads
+    /// the macros they declare.
+    pub fn take(attrs: &mut Vec<Attribute>, errors: &mut Vec<Error>) -> Self {
+        let mut aliases = Vec::new();
+
+        attrs.retain(|attr| {
+            if !attr.path().is_ident("shader_macros") {
+                return true;
+            }
+
+            let parser = Punctuated::<(Ident, Ident), Token![,]>::parse_terminated_with;
+            let declared =
+                match attr.parse_args_with(|input: ParseStream| parser(input, parse_alias)) {
+                    Ok(declared) => declared,
+                    Err(error) => {
+                        errors.push(error);
+                        return false;
+                    }
+                };
+
+            for (name, known) in declared {
+                match MACROS.iter().find(|(known_name, _)| known == known_name) {
+                    Some((known_name, _)) => aliases.push((name.to_string(), *known_name)),
+                    None => errors.push(Error::new(
+                        known.span(),
+                        format!(
+                            "`{known}!` is not a known macro, only {} are",
+                            known_names()
+                        ),
+                    )),
+                }
+            }
+
+            false
+        });
+
+        Self { aliases }
+    }
+
+    /// The known macro that `mac` is expanded like, looked up by the last
+    /// segment of its path.
+    fn find(&self, mac: &Macro) -> Option<KnownMacro> {
+        let name = mac.path.segments.last()?.ident.to_string();
+        let name = self
+            .aliases
+            .iter()
+            .find(|(alias, _)| *alias == name)
+            .map_or(name.as_str(), |(_, known_name)| known_name);
+
+        MACROS
+            .iter()
+            .find(|(known_name, _)| *known_name == name)
+            .map(|(_, known)| *known)
+    }
+}
+
+/// Parses `name = known` in `#[shader_macros(..)]`.
+fn parse_alias(input: ParseStream) -> syn::Result<(Ident, Ident)> {
+    let name = input.parse()?;
+    input.parse::<Token![=]>()?;
+    let known = input.parse()?;
+
+    Ok((name, known))
+}
+
+fn known_names() -> String {
+    MACROS
+        .iter()
+        .map(|(name, _)| format!("`{name}!`"))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+impl Body<'_> {
+    pub(super) fn lower_macro(
+        &mut self,
+        mac: &Macro,
+        stmts: &mut Vec<TokenStream>,
+        hint: Hint,
+    ) -> Expr {
+        match self.find_macro(mac) {
+            Some(KnownMacro::Expr(lower)) => lower(self, mac, stmts, hint),
+
+            Some(KnownMacro::Stmt(_)) => self.error(
+                &mac.path,
+                "this macro can only be used as a statement in shaders",
+            ),
+
+            None => self.unknown_macro(mac),
+        }
+    }
+
+    pub(super) fn lower_macro_stmt(&mut self, mac: &Macro, stmts: &mut Vec<TokenStream>) {
+        match self.find_macro(mac) {
+            Some(KnownMacro::Stmt(lower)) => lower(self, mac, stmts),
+
+            // The value is discarded like that of any other expression.
+            Some(KnownMacro::Expr(lower)) => {
+                lower(self, mac, stmts, Hint::None);
+            }
+
+            None => {
+                self.unknown_macro(mac);
+            }
+        }
+    }
+
+    fn find_macro(&self, mac: &Macro) -> Option<KnownMacro> {
+        self.macros.find(mac)
+    }
+
+    fn unknown_macro(&mut self, mac: &Macro) -> Expr {
+        let names = known_names();
+        let aliases = self
+            .macros
+            .aliases
+            .iter()
+            .map(|(name, _)| format!(", `{name}!`"))
+            .collect::<String>();
+
+        self.error(
+            &mac.path,
+            format!(
+                "this macro is not supported in shaders, only {names}{aliases} are, and others can be declared with `#[shader_macros(name = known)]`"
+            ),
+        )
+    }
+
+    /// Lowers an assertion on its first `n` arguments. Only the calls in them
+    /// are kept, since the assertion itself cannot fail in a shader. The
+    /// message is only formatted when the assertion fails, so it is dropped.
+    fn lower_assert_macro(&mut self, n: usize, mac: &Macro, stmts: &mut Vec<TokenStream>) {
+        let args = match mac.parse_body_with(Punctuated::<syn::Expr, Token![,]>::parse_terminated) {
+            Ok(args) => args,
+            Err(error) => {
+                self.error(error.span(), error);
+                return;
+            }
+        };
+
+        for arg in args.iter().take(n).filter(|arg| has_calls(arg)) {
+            self.lower_expr_stmt(arg, stmts);
+        }
+    }
+
+    /// Lowers `vecN!(args..)` to the vector constructor selected by the types
+    /// of the arguments. The vector type is only known from the context.
+    fn lower_vector_macro(
+        &mut self,
+        n: usize,
+        mac: &Macro,
+        stmts: &mut Vec<TokenStream>,
+        hint: Hint,
+    ) -> Expr {
+        let Hint::Ty(ty) = hint else {
+            return self.error(
+                mac,
+                "the vector type of this macro must be known from its context in shaders",
+            );
+        };
+
+        let args = match mac.parse_body_with(Punctuated::<syn::Expr, Token![,]>::parse_terminated) {
+            Ok(args) => args,
+            Err(error) => return self.error(error.span(), error),
+        };
+
+        let Call { func, args, ty } = self.lower_args(None, args.iter(), stmts, |arg_tys| {
+            quote! { rsshader::reflection::VectorConstructorFn<#n, _, #ty, (#(#arg_tys,)*)> }
+        });
+
+        Expr {
+            ir: quote! {
+                rsshader::ir::ExprIr::Call {
+                    func: &#func,
+                    args: &[#(#args),*],
+                }
+            },
+            ty,
+        }
+    }
+
+    /// Lowers `matches!(value, pattern)` to the `match` it expands to.
+    fn lower_matches_macro(
+        &mut self,
+        mac: &Macro,
+        stmts: &mut Vec<TokenStream>,
+        hint: Hint,
+    ) -> Expr {
+        let parser = |input: ParseStream| {
+            let value = input.parse::<syn::Expr>()?;
+            input.parse::<Token![,]>()?;
+            let pat = Pat::parse_multi_with_leading_vert(input)?;
+
+            let guard = match input.parse::<Option<Token![if]>>()? {
+                Some(if_token) => Some((if_token, input.parse::<syn::Expr>()?)),
+                None => None,
+            };
+
+            input.parse::<Option<Token![,]>>()?;
+
+            Ok((value, pat, guard))
+        };
+
+        let (value, pat, guard) = match mac.parse_body_with(parser) {
+            Ok(parsed) => parsed,
+            Err(error) => return self.error(error.span(), error),
+        };
+
+        let guard = guard.map(|(if_token, guard)| quote! { #if_token #guard });
+
+        let expr: syn::Expr = parse_quote! {
+            match #value {
+                #pat #guard => true,
+                _ => false,
+            }
+        };
+
+        self.lower_expr(&expr, stmts, hint)
+    }
+}
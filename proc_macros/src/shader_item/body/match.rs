@@ -0,0 +1,838 @@
+use std::collections::HashSet;
+
+use proc_macro2::{Ident, TokenStream};
+use quote::quote;
+use syn::{
+    Arm, ExprIf, ExprLit, ExprMatch, ExprRange, Lit, Member, Pat, PatIdent, PatOr, PatParen,
+    PatPath, PatStruct, PatTupleStruct, Path, RangeLimits, parse_quote,
+};
+
+use crate::shader_item::{
+    body::{
+        Body, Expr,
+        r#enum::{read_variant_field, variant_tag},
+        expr::{Hint, read_field},
+        stmt::Tail,
+    },
+    util::variant_field_name,
+};
+
+/// The largest range pattern that is expanded into `switch` case selectors.
+const MAX_RANGE_CASES: i128 = 64;
+
+/// One of the alternatives of a pattern.
+#[derive(Debug, Clone)]
+enum Case {
+    Any,
+    Bool(bool),
+    Int(i128),
+    /// An inclusive integer range.
+    Range(i128, i128),
+    /// An enum variant, whose discriminant is an `i128` expression that rustc
+    /// evaluates.
+    Variant(TokenStream),
+}
+
+/// A binding of a pattern, to the scrutinee or to a field of its variant,
+/// named like `Some.0`. `members` are the fields of the structs that the
+/// pattern destructures inside of the variant field.
+struct Binding<'a> {
+    pat: &'a PatIdent,
+    field: Option<String>,
+    members: Vec<Member>,
+}
+
+/// A match arm whose pattern has been parsed.
+struct MatchArm<'a> {
+    cases: Vec<Case>,
+    bindings: Vec<Binding<'a>>,
+    arm: &'a Arm,
+}
+
+impl Body<'_> {
+    /// Lowers a `match` expression whose arms send their values to `tail`.
+    ///
+    /// Integer matches are lowered to a `switch` when every pattern can be
+    /// expressed as case selectors, and to an `if` chain otherwise. Matches on
+    /// variants test the tag of the enum.
+    pub(super) fn lower_match(
+        &mut self,
+        expr: &ExprMatch,
+        stmts: &mut Vec<TokenStream>,
+        tail: &mut Tail,
+    ) {
+        let ExprMatch {
+            expr: scrutinee,
+            arms,
+            ..
+        } = expr;
+
+        // The scrutinee is evaluated once, before any pattern is tested.
+        let scrutinee = self.lower_expr(scrutinee, stmts, Hint::None);
+        let scrutinee = self.declare_let(scrutinee, stmts);
+        let scrutinee = Expr {
+            ir: scrutinee.ir,
+            ty: scrutinee.ty,
+        };
+
+        let mut match_arms = Vec::new();
+        for arm in arms {
+            let mut cases = Vec::new();
+            let mut bindings = Vec::new();
+
+            if !self.parse_pat(&arm.pat, &scrutinee.ty, &mut cases, &mut bindings) {
+                return;
+            }
+
+            match_arms.push(MatchArm {
+                cases,
+                bindings,
+                arm,
+            });
+        }
+
+        let is_variant_match = match_arms
+            .iter()
+            .flat_map(|arm| &arm.cases)
+            .any(|case| matches!(case, Case::Variant(_)));
+
+        let tested = match is_variant_match {
+            true => tag_of(&scrutinee),
+            false => scrutinee.clone(),
+        };
+
+        let is_switch = match_arms.iter().all(|arm| {
+            arm.arm.guard.is_none()
+                && arm.cases.iter().all(|case| match case {
+                    Case::Any | Case::Int(_) | Case::Variant(_) => true,
+                    Case::Bool(_) => false,
+                    Case::Range(start, end) => end - start < MAX_RANGE_CASES,
+                })
+        });
+
+        if is_switch {
+            self.lower_switch(&scrutinee, &tested, &match_arms, stmts, tail);
+        } else if match_arms.iter().any(|arm| arm.arm.guard.is_some()) {
+            self.lower_guarded_match(&scrutinee, &tested, &match_arms, stmts, tail);
+        } else {
+            self.lower_match_chain(&scrutinee, &tested, &match_arms, stmts, tail);
+        }
+    }
+
+    /// Lowers `if let`, which is a match with a wildcard arm for the `else`
+    /// branch.
+    pub(super) fn lower_if_let(
+        &mut self,
+        expr: &ExprIf,
+        stmts: &mut Vec<TokenStream>,
+        tail: &mut Tail,
+    ) {
+        let ExprIf {
+            cond,
+            then_branch,
+            else_branch,
+            ..
+        } = expr;
+
+        let syn::Expr::Let(syn::ExprLet { pat, expr, .. }) = &**cond else {
+            unreachable!("not an `if let`");
+        };
+
+        let otherwise = match else_branch {
+            Some((_, else_branch)) => (**else_branch).clone(),
+            None => parse_quote! { {} },
+        };
+
+        let expr: ExprMatch = parse_quote! {
+            match (#expr) {
+                #pat => #then_branch,
+                _ => #otherwise,
+            }
+        };
+
+        self.lower_match(&expr, stmts, tail);
+    }
+
+    /// Lowers `let pat = init else { diverge };`, declaring the bindings of
+    /// `pat` in the current scope.
+    pub(super) fn lower_let_else(
+        &mut self,
+        pat: &Pat,
+        init: &syn::Expr,
+        diverge: &syn::Expr,
+        hint: Hint,
+        stmts: &mut Vec<TokenStream>,
+    ) {
+        let scrutinee = self.lower_expr(init, stmts, hint);
+        let scrutinee = self.declare_let(scrutinee, stmts);
+        let scrutinee = Expr {
+            ir: scrutinee.ir,
+            ty: scrutinee.ty,
+        };
+
+        let mut cases = Vec::new();
+        let mut bindings = Vec::new();
+
+        if !self.parse_pat(pat, &scrutinee.ty, &mut cases, &mut bindings) {
+            return;
+        }
+
+        let tested = match cases.iter().any(|case| matches!(case, Case::Variant(_))) {
+            true => tag_of(&scrutinee),
+            false => scrutinee.clone(),
+        };
+
+        if let Some(test) = self.pat_test(&tested, &cases) {
+            let mut otherwise = Vec::new();
+            self.push_scope();
+            self.lower_tail(diverge, &mut otherwise, &mut Tail::Discard);
+            self.pop_scope();
+
+            stmts.push(quote! {
+                rsshader::ir::StmtIr::If {
+                    cond: rsshader::ir::ExprIr::Call {
+                        func: &<bool as rsshader::reflection::Not>::IR,
+                        args: &[#test],
+                    },
+                    then: rsshader::ir::BodyIr {
+                        stmts: &[#(#otherwise),*],
+                    },
+                    otherwise: rsshader::ir::BodyIr {
+                        stmts: &[],
+                    },
+                }
+            });
+        }
+
+        self.bind_pat(&scrutinee, &bindings, stmts);
+    }
+
+    fn lower_switch(
+        &mut self,
+        scrutinee: &Expr,
+        tested: &Expr,
+        arms: &[MatchArm],
+        stmts: &mut Vec<TokenStream>,
+        tail: &mut Tail,
+    ) {
+        let ty = &tested.ty;
+
+        let mut cases = Vec::new();
+        let mut default = None;
+
+        self.lower_switch_body(|this| {
+            // WGSL does not allow the same selector twice, so values that an
+            // earlier arm already matches are left out.
+            let mut seen = HashSet::new();
+
+            for (arm_idx, arm) in arms.iter().enumerate() {
+                let is_last = arm_idx + 1 == arms.len();
+
+                // Matches are exhaustive, so the last arm is the default if
+                // there is no wildcard.
+                if is_last || arm.cases.iter().any(|case| matches!(case, Case::Any)) {
+                    default = Some(this.lower_arm(scrutinee, arm, tail));
+                    break;
+                }
+
+                let selectors = arm
+                    .cases
+                    .iter()
+                    .flat_map(|case| match *case {
+                        Case::Int(value) => vec![quote! { #value }],
+                        Case::Range(start, end) => {
+                            (start..=end).map(|value| quote! { #value }).collect()
+                        }
+                        Case::Variant(ref value) => vec![value.clone()],
+                        Case::Any | Case::Bool(_) => unreachable!(),
+                    })
+                    .filter(|selector| seen.insert(selector.to_string()))
+                    .collect::<Vec<_>>();
+
+                if selectors.is_empty() {
+                    continue;
+                }
+
+                let body = this.lower_arm(scrutinee, arm, tail);
+
+                cases.push(quote! {
+                    rsshader::ir::SwitchCaseIr {
+                        selectors: &[#(rsshader::ir::Literal::int(
+                            &<#ty as rsshader::reflection::Ty>::IR,
+                            #selectors,
+                        )),*],
+                        body: rsshader::ir::BodyIr {
+                            stmts: &[#(#body),*],
+                        },
+                    }
+                });
+            }
+        });
+
+        let selector = &tested.ir;
+        let default = default.unwrap_or_default();
+
+        stmts.push(quote! {
+            rsshader::ir::StmtIr::Switch {
+                selector: #selector,
+                cases: &[#(#cases),*],
+                default: rsshader::ir::BodyIr {
+                    stmts: &[#(#default),*],
+                },
+            }
+        });
+
+        self.lower_loop_exit_checks(stmts);
+    }
+
+    /// Lowers a match without guards to an `if` chain.
+    fn lower_match_chain(
+        &mut self,
+        scrutinee: &Expr,
+        tested: &Expr,
+        arms: &[MatchArm],
+        stmts: &mut Vec<TokenStream>,
+        tail: &mut Tail,
+    ) {
+        let mut branches = Vec::new();
+
+        for (arm_idx, arm) in arms.iter().enumerate() {
+            let is_last = arm_idx + 1 == arms.len();
+
+            // Matches are exhaustive, so the last arm needs no test.
+            let test = match is_last {
+                false => self.pat_test(tested, &arm.cases),
+                true => None,
+            };
+
+            let body = self.lower_arm(scrutinee, arm, tail);
+            let is_catch_all = test.is_none();
+
+            branches.push((test, body));
+
+            // Any remaining arms are unreachable.
+            if is_catch_all {
+                break;
+            }
+        }
+
+        let mut otherwise = Vec::new();
+        for (test, body) in branches.into_iter().rev() {
+            otherwise = match test {
+                Some(test) => vec![quote! {
+                    rsshader::ir::StmtIr::If {
+                        cond: #test,
+                        then: rsshader::ir::BodyIr {
+                            stmts: &[#(#body),*],
+                        },
+                        otherwise: rsshader::ir::BodyIr {
+                            stmts: &[#(#otherwise),*],
+                        },
+                    }
+                }],
+                None => body,
+            };
+        }
+
+        stmts.extend(otherwise);
+    }
+
+    /// Lowers a match with guards. Since a failing guard falls through to the
+    /// next arm, a flag keeps track of whether an arm has matched yet.
+    fn lower_guarded_match(
+        &mut self,
+        scrutinee: &Expr,
+        tested: &Expr,
+        arms: &[MatchArm],
+        stmts: &mut Vec<TokenStream>,
+        tail: &mut Tail,
+    ) {
+        let matched = self.declare_var(&quote! { bool }, stmts);
+        let matched_place = matched.place.unwrap();
+        let matched = matched.ir;
+
+        let set_matched = quote! {
+            rsshader::ir::StmtIr::Assignment {
+                left: #matched_place,
+                right: rsshader::ir::ExprIr::Literal(rsshader::ir::Literal::Bool(true)),
+            }
+        };
+
+        stmts.push(quote! {
+            rsshader::ir::StmtIr::Assignment {
+                left: #matched_place,
+                right: rsshader::ir::ExprIr::Literal(rsshader::ir::Literal::Bool(false)),
+            }
+        });
+
+        for arm in arms {
+            let test = self.pat_test(tested, &arm.cases);
+
+            let mut arm_stmts = Vec::new();
+            self.push_scope();
+            self.bind_pat(scrutinee, &arm.bindings, &mut arm_stmts);
+
+            match &arm.arm.guard {
+                Some((_, guard)) => {
+                    let guard = self
+                        .lower_expr(guard, &mut arm_stmts, Hint::Ty(&quote! { bool }))
+                        .ir;
+
+                    let mut body = vec![set_matched.clone()];
+                    self.lower_tail(&arm.arm.body, &mut body, tail);
+
+                    arm_stmts.push(quote! {
+                        rsshader::ir::StmtIr::If {
+                            cond: #guard,
+                            then: rsshader::ir::BodyIr {
+                                stmts: &[#(#body),*],
+                            },
+                            otherwise: rsshader::ir::BodyIr {
+                                stmts: &[],
+                            },
+                        }
+                    });
+                }
+
+                None => {
+                    arm_stmts.push(set_matched.clone());
+                    self.lower_tail(&arm.arm.body, &mut arm_stmts, tail);
+                }
+            }
+
+            self.pop_scope();
+
+            if let Some(test) = test {
+                arm_stmts = vec![quote! {
+                    rsshader::ir::StmtIr::If {
+                        cond: #test,
+                        then: rsshader::ir::BodyIr {
+                            stmts: &[#(#arm_stmts),*],
+                        },
+                        otherwise: rsshader::ir::BodyIr {
+                            stmts: &[],
+                        },
+                    }
+                }];
+            }
+
+            stmts.push(quote! {
+                rsshader::ir::StmtIr::If {
+                    cond: rsshader::ir::ExprIr::Call {
+                        func: &<bool as rsshader::reflection::Not>::IR,
+                        args: &[#matched],
+                    },
+                    then: rsshader::ir::BodyIr {
+                        stmts: &[#(#arm_stmts),*],
+                    },
+                    otherwise: rsshader::ir::BodyIr {
+                        stmts: &[],
+                    },
+                }
+            });
+        }
+    }
+
+    /// Lowers the body of an arm without its guard.
+    fn lower_arm(&mut self, scrutinee: &Expr, arm: &MatchArm, tail: &mut Tail) -> Vec<TokenStream> {
+        let mut body = Vec::new();
+        self.push_scope();
+        self.bind_pat(scrutinee, &arm.bindings, &mut body);
+        self.lower_tail(&arm.arm.body, &mut body, tail);
+        self.pop_scope();
+
+        body
+    }
+
+    /// Declares the bindings of a pattern that matched `scrutinee`.
+    fn bind_pat(&mut self, scrutinee: &Expr, bindings: &[Binding], stmts: &mut Vec<TokenStream>) {
+        for Binding {
+            pat,
+            field,
+            members,
+        } in bindings
+        {
+            let mut value = match field {
+                Some(field) => read_variant_field(scrutinee, field),
+                None => scrutinee.clone(),
+            };
+
+            for member in members {
+                value = read_field(&value, member);
+            }
+
+            self.bind(&pat.ident, pat.mutability.is_some(), value, stmts);
+        }
+    }
+
+    /// Returns a `bool` expression that tests whether the scrutinee, or its tag
+    /// for variants, matches any of `cases`, or `None` if it always does.
+    fn pat_test(&mut self, scrutinee: &Expr, cases: &[Case]) -> Option<TokenStream> {
+        let Expr { ir: scrutinee, ty } = scrutinee;
+
+        let call = |func: TokenStream, args: &[&TokenStream]| {
+            quote! {
+                rsshader::ir::ExprIr::Call {
+                    func: &#func,
+                    args: &[#(#args),*],
+                }
+            }
+        };
+
+        let int = |value: TokenStream| {
+            quote! {
+                rsshader::ir::ExprIr::Literal(rsshader::ir::Literal::int(
+                    &<#ty as rsshader::reflection::Ty>::IR,
+                    #value,
+                ))
+            }
+        };
+
+        let mut tests = Vec::new();
+        for case in cases {
+            tests.push(match case {
+                Case::Any => return None,
+
+                Case::Bool(true) => scrutinee.clone(),
+                Case::Bool(false) => call(
+                    quote! { <bool as rsshader::reflection::Not>::IR },
+                    &[scrutinee],
+                ),
+
+                Case::Int(value) => call(
+                    quote! { <#ty as rsshader::reflection::PartialEq>::EQ },
+                    &[scrutinee, &int(quote! { #value })],
+                ),
+
+                Case::Variant(value) => call(
+                    quote! { <#ty as rsshader::reflection::PartialEq>::EQ },
+                    &[scrutinee, &int(value.clone())],
+                ),
+
+                // Both comparisons are cheap and have no side effects, so they
+                // are combined without short-circuiting.
+                Case::Range(start, end) => call(
+                    quote! { <bool as rsshader::reflection::BitAnd>::IR },
+                    &[
+                        &call(
+                            quote! { <#ty as rsshader::reflection::PartialOrd>::GE },
+                            &[scrutinee, &int(quote! { #start })],
+                        ),
+                        &call(
+                            quote! { <#ty as rsshader::reflection::PartialOrd>::LE },
+                            &[scrutinee, &int(quote! { #end })],
+                        ),
+                    ],
+                ),
+            });
+        }
+
+        tests.into_iter().reduce(|left, right| {
+            call(
+                quote! { <bool as rsshader::reflection::BitOr>::IR },
+                &[&left, &right],
+            )
+        })
+    }
+
+    /// Parses a pattern into the alternatives it matches, returning `false` if
+    /// it is not supported.
+    fn parse_pat<'a>(
+        &mut self,
+        pat: &'a Pat,
+        ty: &TokenStream,
+        cases: &mut Vec<Case>,
+        bindings: &mut Vec<Binding<'a>>,
+    ) -> bool {
+        match pat {
+            Pat::Wild(_) => cases.push(Case::Any),
+
+            Pat::Paren(PatParen { pat, .. }) => return self.parse_pat(pat, ty, cases, bindings),
+
+            // A single identifier is parsed as a binding, but `None` is the
+            // variant.
+            Pat::Ident(PatIdent {
+                by_ref: None,
+                mutability: None,
+                ident,
+                subpat: None,
+                ..
+            }) if ident == "None" => cases.push(Case::Variant(variant_tag(ty, ident))),
+
+            Pat::Ident(
+                pat_ident @ PatIdent {
+                    by_ref: None,
+                    subpat,
+                    ..
+                },
+            ) => {
+                if !bindings.is_empty() || !cases.is_empty() {
+                    self.error(
+                        pat,
+                        "bindings inside `|` patterns are not supported in shaders",
+                    );
+                    return false;
+                }
+
+                bindings.push(Binding {
+                    pat: pat_ident,
+                    field: None,
+                    members: Vec::new(),
+                });
+
+                match subpat {
+                    Some((_, subpat)) => return self.parse_pat(subpat, ty, cases, bindings),
+                    None => cases.push(Case::Any),
+                }
+            }
+
+            Pat::Or(PatOr { cases: pats, .. }) => {
+                for pat in pats {
+                    if !self.parse_pat(pat, ty, cases, bindings) {
+                        return false;
+                    }
+                }
+            }
+
+            Pat::Lit(ExprLit {
+                lit: Lit::Bool(lit),
+                ..
+            }) => cases.push(Case::Bool(lit.value)),
+
+            Pat::Lit(ExprLit {
+                lit: Lit::Int(_), ..
+            }) => {
+                let Some(value) = self.parse_int_pat(pat, ty) else {
+                    return false;
+                };
+
+                cases.push(Case::Int(value));
+            }
+
+            // Paths of more than one segment name enum variants.
+            Pat::Path(PatPath {
+                qself: None, path, ..
+            }) if path.segments.len() > 1 => {
+                let variant = self.parse_variant_path(path, ty);
+                cases.push(Case::Variant(variant_tag(ty, variant)));
+            }
+
+            Pat::TupleStruct(PatTupleStruct {
+                qself: None,
+                path,
+                elems,
+                ..
+            }) => {
+                let variant = self.parse_variant_path(path, ty);
+
+                for (idx, elem) in elems.iter().enumerate() {
+                    let is_last = idx + 1 == elems.len();
+                    let field = variant_field_name(variant, &Member::Unnamed(idx.into()));
+
+                    if !self.parse_field_pat(elem, &field, &[], is_last, cases, bindings) {
+                        return false;
+                    }
+                }
+
+                cases.push(Case::Variant(variant_tag(ty, variant)));
+            }
+
+            Pat::Struct(PatStruct {
+                qself: None,
+                path,
+                fields,
+                ..
+            }) => {
+                let variant = self.parse_variant_path(path, ty);
+
+                for field in fields {
+                    let name = variant_field_name(variant, &field.member);
+
+                    if !self.parse_field_pat(&field.pat, &name, &[], false, cases, bindings) {
+                        return false;
+                    }
+                }
+
+                cases.push(Case::Variant(variant_tag(ty, variant)));
+            }
+
+            Pat::Range(ExprRange {
+                start: Some(start),
+                limits,
+                end: Some(end),
+                ..
+            }) => {
+                let (Some(start), Some(end)) =
+                    (self.parse_int_pat(start, ty), self.parse_int_pat(end, ty))
+                else {
+                    return false;
+                };
+
+                let end = match limits {
+                    RangeLimits::HalfOpen(_) => end - 1,
+                    RangeLimits::Closed(_) => end,
+                };
+
+                if start <= end {
+                    cases.push(Case::Range(start, end));
+                }
+            }
+
+            _ => {
+                self.error(pat, "this pattern is not supported in shader functions");
+                return false;
+            }
+        }
+
+        true
+    }
+
+    /// Parses the path of a variant pattern, unifying the enum it names with
+    /// `ty`, and returns the variant.
+    fn parse_variant_path<'a>(&mut self, path: &'a Path, ty: &TokenStream) -> &'a Ident {
+        if path.segments.len() > 1 {
+            let enum_ty = Path {
+                leading_colon: path.leading_colon,
+                segments: path
+                    .segments
+                    .iter()
+                    .take(path.segments.len() - 1)
+                    .cloned()
+                    .collect(),
+            };
+
+            self.unify(ty, &quote! { #enum_ty });
+        }
+
+        &path.segments.last().unwrap().ident
+    }
+
+    /// Parses the pattern of the variant field `field`, which can bind or
+    /// ignore it, or destructure the struct it holds. `members` lead from the
+    /// variant field to the value `pat` matches. A `..` is allowed as the last
+    /// pattern of a tuple variant or tuple struct.
+    ///
+    /// Structs always match, so the pattern only adds bindings. rustc checks
+    /// the Rust function, so a nested enum variant fails to find its fields.
+    fn parse_field_pat<'a>(
+        &mut self,
+        pat: &'a Pat,
+        field: &str,
+        members: &[Member],
+        is_last: bool,
+        cases: &[Case],
+        bindings: &mut Vec<Binding<'a>>,
+    ) -> bool {
+        match pat {
+            Pat::Wild(_) => {}
+            Pat::Rest(_) if is_last => {}
+
+            Pat::Ident(
+                pat_ident @ PatIdent {
+                    by_ref: None,
+                    subpat: None,
+                    ..
+                },
+            ) => {
+                if !cases.is_empty() {
+                    self.error(
+                        pat,
+                        "bindings inside `|` patterns are not supported in shaders",
+                    );
+                    return false;
+                }
+
+                bindings.push(Binding {
+                    pat: pat_ident,
+                    field: Some(field.to_string()),
+                    members: members.to_vec(),
+                });
+            }
+
+            Pat::Struct(PatStruct {
+                qself: None,
+                fields,
+                ..
+            }) => {
+                for field_pat in fields {
+                    let mut members = members.to_vec();
+                    members.push(field_pat.member.clone());
+
+                    if !self.parse_field_pat(
+                        &field_pat.pat,
+                        field,
+                        &members,
+                        false,
+                        cases,
+                        bindings,
+                    ) {
+                        return false;
+                    }
+                }
+            }
+
+            Pat::TupleStruct(PatTupleStruct {
+                qself: None, elems, ..
+            }) => {
+                for (idx, elem) in elems.iter().enumerate() {
+                    let is_last = idx + 1 == elems.len();
+                    let mut members = members.to_vec();
+                    members.push(Member::Unnamed(idx.into()));
+
+                    if !self.parse_field_pat(elem, field, &members, is_last, cases, bindings) {
+                        return false;
+                    }
+                }
+            }
+
+            _ => {
+                self.error(
+                    pat,
+                    "only identifiers, `_` and struct patterns are supported in the fields of \
+                     variant patterns in shaders",
+                );
+                return false;
+            }
+        }
+
+        true
+    }
+
+    fn parse_int_pat(&mut self, pat: &impl quote::ToTokens, ty: &TokenStream) -> Option<i128> {
+        let tokens = pat.to_token_stream();
+
+        let lit = match syn::parse2::<ExprLit>(tokens) {
+            Ok(ExprLit {
+                lit: Lit::Int(lit), ..
+            }) => lit,
+
+            _ => {
+                self.error(pat, "only integer literals are supported in range patterns");
+                return None;
+            }
+        };
+
+        if !lit.suffix().is_empty() {
+            let suffix = syn::Ident::new(lit.suffix(), lit.span());
+            self.unify(ty, &quote! { #suffix });
+        }
+
+        match lit.base10_parse::<i128>() {
+            Ok(value) => Some(value),
+            Err(error) => {
+                self.error(&lit, error);
+                None
+            }
+        }
+    }
+}
+
+/// The tag of `value`, which is an enum.
+fn tag_of(value: &Expr) -> Expr {
+    let Expr { ir, ty } = value;
+
+    Expr {
+        ir: quote! { rsshader::ir::ExprIr::enum_tag(&#ir) },
+        ty: quote! { <#ty as rsshader::reflection::Enum>::Tag },
+    }
+}
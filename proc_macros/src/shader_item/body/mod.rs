@@ -0,0 +1,500 @@
+use std::collections::HashMap;
+
+use proc_macro2::{Ident, TokenStream};
+use quote::{ToTokens, format_ident, quote, quote_spanned};
+use syn::{
+    Block, ConstParam, Error, FnArg, Generics, Pat, PatIdent, PatType, Path, Receiver, ReturnType,
+    Signature, Type, spanned::Spanned,
+};
+
+use crate::shader_item::util::{referent, replace_self, ty_ir};
+
+mod closure;
+mod control;
+mod r#enum;
+mod expr;
+mod infer;
+mod iter;
+mod r#macro;
+mod r#match;
+mod place;
+mod stmt;
+
+pub use r#macro::Macros;
+
+/// Lowers the signature and block of a `#[shader_item]` function into a
+/// `rsshader::ir::BodyIr` expression. `generics` are all the generic
+/// parameters in scope, `self_ty` is the type of the `impl` block the
+/// function is in, if any, and `macros` are the macros the body can use.
+///
+/// Returns `None` if the function uses anything that cannot be lowered, in
+/// which case the reasons are pushed to `errors`.
+pub fn lower_fn(
+    sig: &Signature,
+    block: &Block,
+    generics: &Generics,
+    self_ty: Option<&TokenStream>,
+    macros: &Macros,
+    errors: &mut Vec<Error>,
+) -> Option<TokenStream> {
+    let error_count = errors.len();
+
+    let mut body = Body {
+        errors,
+        scopes: vec![HashMap::new()],
+        ret_ty: match &sig.output {
+            ReturnType::Default => None,
+            ReturnType::Type(_, ty) => Some(ty.to_token_stream()),
+        },
+        self_ty,
+        next_var_id: 0,
+        loops: Vec::new(),
+        infer_tys: Vec::new(),
+        closure_depth: 0,
+        closures: Vec::new(),
+        generics,
+        lifted: Vec::new(),
+        item_paths: Vec::new(),
+        macros,
+    };
+
+    let mut stmts = Vec::new();
+    body.declare_const_params(generics);
+    body.declare_params(sig, &mut stmts);
+    body.lower_fn_block(block, &mut stmts);
+
+    let stmts = stmts
+        .into_iter()
+        .map(|stmt| body.resolve_infer(stmt))
+        .map(|stmt| match self_ty {
+            Some(self_ty) => replace_self(stmt, self_ty),
+            None => stmt,
+        })
+        .collect::<Vec<_>>();
+
+    let items = body
+        .lifted_items()
+        .into_iter()
+        .map(|item| match self_ty {
+            Some(self_ty) => replace_self(item, self_ty),
+            None => item,
+        })
+        .chain(body.item_imports())
+        .collect::<Vec<_>>();
+
+    if errors.len() > error_count {
+        return None;
+    }
+
+    let body = quote! {
+        rsshader::ir::BodyIr {
+            stmts: &[#(#stmts),*],
+        }
+    };
+
+    // The types generated for closures and the imports of the functions and
+    // consts are only used by the body, so they are declared in a block
+    // around it.
+    if items.is_empty() {
+        Some(body)
+    } else {
+        Some(quote! {
+            {
+                #(#items)*
+
+                #body
+            }
+        })
+    }
+}
+
+struct Body<'e> {
+    errors: &'e mut Vec<Error>,
+    scopes: Vec<HashMap<String, Local>>,
+    ret_ty: Option<TokenStream>,
+    self_ty: Option<&'e TokenStream>,
+    next_var_id: usize,
+    loops: Vec<control::Loop>,
+    infer_tys: Vec<Option<TokenStream>>,
+    /// How many inlined closures are being lowered.
+    closure_depth: usize,
+    /// The closures bound by `let`, which `Local::closure` refers to.
+    closures: Vec<closure::Closure>,
+    /// The generic parameters in scope, which the types generated for closures
+    /// take too.
+    generics: &'e Generics,
+    /// The closures that are passed to functions as values.
+    lifted: Vec<closure::Lifted>,
+    /// The paths to functions and consts, which are imported in the block
+    /// around the body.
+    item_paths: Vec<Path>,
+    macros: &'e Macros,
+}
+
+/// A parameter or local variable that is visible to the body.
+#[derive(Debug, Clone)]
+struct Local {
+    ir: TokenStream,
+    ty: TokenStream,
+    /// The `rsshader::ir::PlaceIr` of the binding if it can be assigned to.
+    place: Option<TokenStream>,
+    /// The index of the closure in `Body::closures` if the binding is one.
+    closure: Option<usize>,
+}
+
+/// A lowered expression. `ir` evaluates to a `rsshader::ir::ExprIr` and `ty` is
+/// the Rust type of the original expression.
+#[derive(Debug, Clone)]
+struct Expr {
+    ir: TokenStream,
+    ty: TokenStream,
+}
+
+impl Body<'_> {
+    /// The alias that the marker struct of the function or const at `path` is
+    /// imported as.
+    fn item_marker(&mut self, path: &Path) -> Ident {
+        let key = path.to_token_stream().to_string();
+        let idx = match self
+            .item_paths
+            .iter()
+            .position(|other| other.to_token_stream().to_string() == key)
+        {
+            Some(idx) => idx,
+            None => {
+                self.item_paths.push(path.clone());
+                self.item_paths.len() - 1
+            }
+        };
+
+        format_ident!("__shader_item_{idx}", span = path.span())
+    }
+
+    /// Imports every path of `item_paths` as its alias. A glob import provides
+    /// a struct under each alias too, which only takes effect if the path
+    /// names no marker struct, so no `#[shader_item]`. Its impls require traits
+    /// that no type implements, so that rustc names the item that is missing
+    /// the attribute. The bounds are higher-ranked so that they are only
+    /// checked where the impls are used.
+    fn item_imports(&self) -> Option<TokenStream> {
+        if self.item_paths.is_empty() {
+            return None;
+        }
+
+        // The impls are spanned at the path, which rustc points to when it
+        // explains why they do not apply.
+        let (fallbacks, imports): (Vec<_>, Vec<_>) = self
+            .item_paths
+            .iter()
+            .enumerate()
+            .map(|(idx, path)| {
+                let module = format_ident!("item_{idx}");
+                let alias = format_ident!("__shader_item_{idx}");
+                let name = &path.segments.last().unwrap().ident;
+
+                let fallback = quote_spanned! {path.span()=>
+                    mod #module {
+                        pub struct #name {}
+
+                        impl<Args> rsshader::reflection::Call<Args> for #name
+                        where
+                            for<'a> &'a (): rsshader::reflection::NotShaderFn<#name>,
+                        {
+                            type Fn = <&'static () as rsshader::reflection::NotShaderFn<#name>>::Fn;
+                        }
+
+                        impl rsshader::reflection::Const for #name
+                        where
+                            for<'a> &'a (): rsshader::reflection::NotShaderConst<#name>,
+                        {
+                            type Ty = <&'static () as rsshader::reflection::NotShaderConst<#name>>::Ty;
+
+                            const IR: rsshader::ir::ConstIr =
+                                <&'static () as rsshader::reflection::NotShaderConst<#name>>::IR;
+                        }
+                    }
+
+                    pub use #module::#name as #alias;
+                };
+                let import = quote! {
+                    #[allow(unused_imports)]
+                    use #path as #alias;
+                };
+
+                (fallback, import)
+            })
+            .unzip();
+
+        Some(quote! {
+            #[allow(
+                dead_code,
+                non_camel_case_types,
+                unused_imports,
+                clippy::upper_case_acronyms
+            )]
+            mod __shader_items {
+                #(#fallbacks)*
+            }
+
+            #[allow(unused_imports)]
+            use __shader_items::*;
+
+            #(#imports)*
+        })
+    }
+
+    /// Makes the const generic parameters of the function usable as values.
+    /// Their values are only known per instance, so they become literals.
+    fn declare_const_params(&mut self, generics: &Generics) {
+        for ConstParam { ident, ty, .. } in generics.const_params() {
+            let literal = match ty {
+                Type::Path(ty) if ty.path.is_ident("usize") || ty.path.is_ident("u32") => {
+                    quote! { U32(#ident as u32) }
+                }
+                Type::Path(ty) if ty.path.is_ident("i32") => quote! { I32(#ident) },
+                Type::Path(ty) if ty.path.is_ident("bool") => quote! { Bool(#ident) },
+
+                _ => {
+                    self.errors.push(Error::new(
+                        ty.span(),
+                        "const parameters of this type are not supported in shader functions",
+                    ));
+                    continue;
+                }
+            };
+
+            self.declare(
+                ident.to_string(),
+                Local {
+                    ir: quote! { rsshader::ir::ExprIr::Literal(rsshader::ir::Literal::#literal) },
+                    ty: ty.to_token_stream(),
+                    place: None,
+                    closure: None,
+                },
+            );
+        }
+    }
+
+    fn declare_params(&mut self, sig: &Signature, stmts: &mut Vec<TokenStream>) {
+        for (idx, input) in sig.inputs.iter().enumerate() {
+            let (name, mutable, ty) = match input {
+                FnArg::Typed(PatType { pat, ty, .. }) => {
+                    let Pat::Ident(PatIdent {
+                        by_ref: None,
+                        mutability,
+                        ident,
+                        subpat: None,
+                        ..
+                    }) = &**pat
+                    else {
+                        self.errors.push(Error::new(
+                            pat.span(),
+                            "only identifier patterns are supported for parameters",
+                        ));
+                        continue;
+                    };
+
+                    (
+                        ident.to_string(),
+                        mutability.is_some(),
+                        ty.to_token_stream(),
+                    )
+                }
+
+                // `self` is passed by value even if the method takes `&self`,
+                // since shader types are `Copy`.
+                FnArg::Receiver(
+                    receiver @ Receiver {
+                        reference,
+                        mutability,
+                        ..
+                    },
+                ) => {
+                    let Some(self_ty) = self.self_ty else {
+                        self.errors.push(Error::new(
+                            receiver.span(),
+                            "`self` is only allowed in methods",
+                        ));
+                        continue;
+                    };
+
+                    match reference {
+                        Some(_) if mutability.is_some() => {
+                            ("self".to_string(), false, quote! { &mut #self_ty })
+                        }
+                        _ => ("self".to_string(), mutability.is_some(), self_ty.clone()),
+                    }
+                }
+            };
+
+            if mutable && referent(&ty).is_some() {
+                self.errors.push(Error::new(
+                    input.span(),
+                    "reference parameters cannot be `mut` in shader functions",
+                ));
+                continue;
+            }
+
+            let ty_ir = ty_ir(&ty);
+            let param = quote! {
+                rsshader::ir::ExprIr::Param {
+                    idx: #idx,
+                    ty: &#ty_ir,
+                }
+            };
+
+            // WGSL parameters are immutable, so mutable parameters are copied
+            // into a variable.
+            let local = if mutable {
+                let local = self.declare_var(&ty, stmts);
+                let place = &local.place;
+
+                stmts.push(quote! {
+                    rsshader::ir::StmtIr::Assignment {
+                        left: #place,
+                        right: #param,
+                    }
+                });
+
+                local
+            } else {
+                Local {
+                    ir: param,
+                    ty,
+                    place: None,
+                    closure: None,
+                }
+            };
+
+            self.declare(name, local);
+        }
+    }
+
+    /// Declares a new mutable variable of type `ty`, without making it visible
+    /// by name.
+    fn declare_var(&mut self, ty: &TokenStream, stmts: &mut Vec<TokenStream>) -> Local {
+        let id = self.next_var_id();
+
+        stmts.push(quote! {
+            rsshader::ir::StmtIr::VariableDecl {
+                id: #id,
+                ty: &<#ty as rsshader::reflection::Ty>::IR,
+            }
+        });
+
+        Local {
+            ir: quote! {
+                rsshader::ir::ExprIr::Variable {
+                    id: #id,
+                    ty: &<#ty as rsshader::reflection::Ty>::IR,
+                }
+            },
+            ty: ty.clone(),
+            place: Some(quote! {
+                rsshader::ir::PlaceIr::Variable {
+                    id: #id,
+                    ty: &<#ty as rsshader::reflection::Ty>::IR,
+                }
+            }),
+            closure: None,
+        }
+    }
+
+    /// Declares a new immutable binding to `value`, without making it visible
+    /// by name.
+    fn declare_let(&mut self, value: Expr, stmts: &mut Vec<TokenStream>) -> Local {
+        let id = self.next_var_id();
+
+        let Expr { ir: value, ty } = value;
+        let ty_ir = ty_ir(&ty);
+
+        stmts.push(quote! {
+            rsshader::ir::StmtIr::LetDecl {
+                id: #id,
+                value: #value,
+            }
+        });
+
+        Local {
+            ir: quote! {
+                rsshader::ir::ExprIr::Variable {
+                    id: #id,
+                    ty: &#ty_ir,
+                }
+            },
+            ty,
+            place: None,
+            closure: None,
+        }
+    }
+
+    /// Binds `ident` to an already evaluated value, copying it into a variable
+    /// if the binding is mutable.
+    fn bind(&mut self, ident: &Ident, mutable: bool, value: Expr, stmts: &mut Vec<TokenStream>) {
+        let local = if mutable {
+            let local = self.declare_var(&value.ty, stmts);
+            let place = &local.place;
+            let value = value.ir;
+
+            stmts.push(quote! {
+                rsshader::ir::StmtIr::Assignment {
+                    left: #place,
+                    right: #value,
+                }
+            });
+
+            local
+        } else {
+            Local {
+                ir: value.ir,
+                ty: value.ty,
+                place: None,
+                closure: None,
+            }
+        };
+
+        self.declare(ident.to_string(), local);
+    }
+
+    fn declare(&mut self, name: String, local: Local) {
+        self.scopes.last_mut().unwrap().insert(name, local);
+    }
+
+    fn find(&self, name: &str) -> Option<&Local> {
+        self.scopes.iter().rev().find_map(|scope| scope.get(name))
+    }
+
+    fn next_var_id(&mut self) -> usize {
+        self.next_var_id += 1;
+        self.next_var_id - 1
+    }
+
+    fn push_scope(&mut self) {
+        self.scopes.push(HashMap::new());
+    }
+
+    fn pop_scope(&mut self) {
+        self.scopes.pop();
+    }
+
+    /// Reports `message` at `spanned` and returns a placeholder expression.
+    ///
+    /// The placeholder is never emitted since the whole body is discarded once
+    /// an error has been reported.
+    fn error(&mut self, spanned: impl Spanned, message: impl std::fmt::Display) -> Expr {
+        self.errors.push(Error::new(spanned.span(), message));
+
+        Expr {
+            ir: quote! { unreachable!() },
+            ty: quote! { () },
+        }
+    }
+}
+
+/// A placeholder for an expression whose error has already been reported.
+fn reported() -> Expr {
+    Expr {
+        ir: quote! { unreachable!() },
+        ty: quote! { () },
+    }
+}
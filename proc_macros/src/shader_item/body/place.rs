@@ -0,0 +1,164 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{ExprField, ExprGroup, ExprIndex, ExprParen, ExprPath, ExprUnary, UnOp};
+
+use crate::shader_item::{
+    body::{
+        Body,
+        expr::{Hint, element_ty, field_trait},
+    },
+    util::referent,
+};
+
+/// A lowered assignable expression. `ir` evaluates to a
+/// `rsshader::ir::PlaceIr`, `value` to a `rsshader::ir::ExprIr` that reads the
+/// place and `ty` is the Rust type of the original expression.
+#[derive(Debug, Clone)]
+pub(super) struct Place {
+    pub ir: TokenStream,
+    pub value: TokenStream,
+    pub ty: TokenStream,
+}
+
+impl Body<'_> {
+    /// Lowers an assignable expression. Indices are evaluated into `stmts`
+    /// once, so the place can be both read and written.
+    pub(super) fn lower_place(&mut self, expr: &syn::Expr, stmts: &mut Vec<TokenStream>) -> Place {
+        match expr {
+            syn::Expr::Paren(ExprParen { expr, .. }) | syn::Expr::Group(ExprGroup { expr, .. }) => {
+                self.lower_place(expr, stmts)
+            }
+
+            syn::Expr::Path(ExprPath {
+                qself: None, path, ..
+            }) if let Some(ident) = path.get_ident()
+                && let Some(local) = self.find(&ident.to_string()) =>
+            {
+                match (&local.place, referent(&local.ty)) {
+                    (Some(place), _) => Place {
+                        ir: place.clone(),
+                        value: local.ir.clone(),
+                        ty: local.ty.clone(),
+                    },
+
+                    // `&mut T` bindings are assigned through.
+                    (None, Some((true, ty))) => deref_place(&local.ir, ty),
+
+                    (None, _) => self.place_error(expr, "cannot assign to an immutable binding"),
+                }
+            }
+
+            syn::Expr::Unary(ExprUnary {
+                op: UnOp::Deref(_),
+                expr: ptr,
+                ..
+            }) => {
+                let ptr = self.lower_expr(ptr, stmts, Hint::None);
+
+                match referent(&ptr.ty) {
+                    Some((true, ty)) => deref_place(&ptr.ir, ty),
+                    _ => self.place_error(expr, "only `&mut` references can be assigned through"),
+                }
+            }
+
+            syn::Expr::Field(ExprField { base, member, .. }) => {
+                let Place { ir, value, ty } = self.lower_place(base, stmts);
+                let field = field_trait(member);
+
+                Place {
+                    ir: quote! {
+                        rsshader::ir::PlaceIr::field(&#ir, <#ty as #field>::IDX)
+                    },
+                    value: quote! {
+                        rsshader::ir::ExprIr::field(&#value, <#ty as #field>::IDX)
+                    },
+                    ty: quote! { <#ty as #field>::Ty },
+                }
+            }
+
+            syn::Expr::Index(ExprIndex { expr, index, .. }) => {
+                let Place { ir, value, ty } = self.lower_place(expr, stmts);
+
+                // Literals and bindings cannot change between reading and
+                // writing the place, anything else is evaluated once.
+                let is_simple = matches!(&**index, syn::Expr::Lit(_) | syn::Expr::Path(_));
+                let index = self.lower_index_operand(index, stmts);
+                let index = match is_simple {
+                    true => index.ir,
+                    false => self.declare_let(index, stmts).ir,
+                };
+
+                Place {
+                    ir: quote! {
+                        rsshader::ir::PlaceIr::Index {
+                            base: &#ir,
+                            index: &#index,
+                        }
+                    },
+                    value: quote! {
+                        rsshader::ir::ExprIr::Index {
+                            base: &#value,
+                            index: &#index,
+                        }
+                    },
+                    ty: element_ty(&ty),
+                }
+            }
+
+            _ => self.place_error(
+                expr,
+                "only local variables, their fields and their elements can be assigned to",
+            ),
+        }
+    }
+
+    /// Whether `expr` is a place that can be assigned to, without lowering
+    /// it.
+    pub(super) fn is_mutable_place(&self, expr: &syn::Expr) -> bool {
+        match expr {
+            syn::Expr::Paren(ExprParen { expr, .. })
+            | syn::Expr::Group(ExprGroup { expr, .. })
+            | syn::Expr::Field(ExprField { base: expr, .. })
+            | syn::Expr::Index(ExprIndex { expr, .. })
+            | syn::Expr::Unary(ExprUnary {
+                op: UnOp::Deref(_),
+                expr,
+                ..
+            }) => self.is_mutable_place(expr),
+
+            syn::Expr::Path(ExprPath {
+                qself: None, path, ..
+            }) => path
+                .get_ident()
+                .and_then(|ident| self.find(&ident.to_string()))
+                .is_some_and(|local| {
+                    local.place.is_some() || matches!(referent(&local.ty), Some((true, _)))
+                }),
+
+            _ => false,
+        }
+    }
+
+    fn place_error(
+        &mut self,
+        spanned: impl syn::spanned::Spanned,
+        message: impl std::fmt::Display,
+    ) -> Place {
+        let error = self.error(spanned, message);
+
+        Place {
+            ir: quote! { unreachable!() },
+            value: error.ir,
+            ty: error.ty,
+        }
+    }
+}
+
+/// The place `ptr` points to, whose type is `ty`.
+fn deref_place(ptr: &TokenStream, ty: TokenStream) -> Place {
+    Place {
+        ir: quote! { rsshader::ir::PlaceIr::Deref { ptr: &#ptr } },
+        value: quote! { rsshader::ir::ExprIr::Deref { ptr: &#ptr } },
+        ty,
+    }
+}
@@ -0,0 +1,608 @@
+use proc_macro2::{Span, TokenStream};
+use quote::{ToTokens, quote};
+use syn::{
+    BinOp, Block, ExprAssign, ExprBinary, ExprBlock, ExprGroup, ExprMacro, ExprMatch, ExprParen,
+    ExprReturn, Local, LocalInit, Pat, PatIdent, PatType, Stmt, StmtMacro,
+    spanned::Spanned,
+    visit::{self, Visit},
+};
+
+use crate::shader_item::{
+    body::{
+        Body, Expr,
+        expr::{Call, Hint, auto_deref},
+        place::Place,
+        reported,
+    },
+    util::{check_ty, referent, ty_ir},
+};
+
+/// Where the value of a tail expression goes.
+#[derive(Debug, Clone)]
+pub(super) enum Tail {
+    /// The value is discarded.
+    Discard,
+    /// The value is returned from the function.
+    Return,
+    /// The value is assigned to the variable `id`, whose type is taken from
+    /// the first value if it is not known yet.
+    Assign { id: usize, ty: Option<TokenStream> },
+}
+
+impl Body<'_> {
+    /// Lowers the outermost block of a function, turning its tail expression
+    /// into a return statement.
+    pub(super) fn lower_fn_block(&mut self, block: &Block, stmts: &mut Vec<TokenStream>) {
+        for (stmt_idx, stmt) in block.stmts.iter().enumerate() {
+            let is_tail = stmt_idx + 1 == block.stmts.len();
+
+            match stmt {
+                Stmt::Expr(expr, None) if is_tail && self.ret_ty.is_some() => {
+                    self.lower_tail(expr, stmts, &mut Tail::Return);
+                }
+
+                _ => self.lower_stmt(stmt, stmts),
+            }
+        }
+    }
+
+    /// Lowers a nested block, sending the value of its tail expression to
+    /// `tail`. Its bindings are scoped to the block, but since every binding
+    /// has a unique id its statements can be emitted directly into `stmts`.
+    pub(super) fn lower_block_tail(
+        &mut self,
+        block: &Block,
+        stmts: &mut Vec<TokenStream>,
+        tail: &mut Tail,
+    ) {
+        self.push_scope();
+
+        for (stmt_idx, stmt) in block.stmts.iter().enumerate() {
+            let is_tail = stmt_idx + 1 == block.stmts.len();
+
+            match stmt {
+                Stmt::Expr(expr, None) if is_tail => self.lower_tail(expr, stmts, tail),
+
+                Stmt::Macro(StmtMacro {
+                    attrs,
+                    mac,
+                    semi_token: None,
+                }) if is_tail => {
+                    let expr = syn::Expr::Macro(ExprMacro {
+                        attrs: attrs.clone(),
+                        mac: mac.clone(),
+                    });
+
+                    self.lower_tail(&expr, stmts, tail)
+                }
+
+                // A block without a tail expression can only produce a value
+                // by diverging, so there is nothing to send to `tail`.
+                _ => self.lower_stmt(stmt, stmts),
+            }
+        }
+
+        self.pop_scope();
+    }
+
+    /// Lowers an expression whose value is sent to `tail`. Control flow is
+    /// lowered in place, so that each branch sends its own value.
+    pub(super) fn lower_tail(
+        &mut self,
+        expr: &syn::Expr,
+        stmts: &mut Vec<TokenStream>,
+        tail: &mut Tail,
+    ) {
+        match expr {
+            syn::Expr::Paren(ExprParen { expr, .. }) | syn::Expr::Group(ExprGroup { expr, .. }) => {
+                self.lower_tail(expr, stmts, tail)
+            }
+
+            // WGSL does not consider a function that ends in a loop to always
+            // return, so values that leave a loop are returned after it
+            // instead. The same goes for a `match` lowered with a flag.
+            _ if matches!(tail, Tail::Return) && returns_after(expr) => {
+                let ret_ty = self.ret_ty.clone().unwrap();
+                let value = self.lower_tail_expr(expr, stmts, Hint::Ty(&ret_ty)).ir;
+
+                stmts.push(quote! {
+                    rsshader::ir::StmtIr::Return {
+                        value: Some(#value),
+                    }
+                });
+            }
+
+            syn::Expr::If(expr) => self.lower_if(expr, stmts, tail),
+            syn::Expr::Loop(_) | syn::Expr::While(_) => self.lower_loop(expr, stmts, tail),
+            syn::Expr::Match(expr) => self.lower_match(expr, stmts, tail),
+
+            syn::Expr::Block(expr @ ExprBlock { label: None, .. }) => {
+                self.lower_block_tail(&expr.block, stmts, tail)
+            }
+
+            syn::Expr::Block(expr) => self.lower_labeled_block(expr, stmts, tail),
+
+            syn::Expr::Return(_) | syn::Expr::Break(_) | syn::Expr::Continue(_) => {
+                self.lower_expr_stmt(expr, stmts)
+            }
+
+            _ => match tail {
+                Tail::Discard => self.lower_expr_stmt(expr, stmts),
+
+                Tail::Return => {
+                    let ret_ty = self.ret_ty.clone();
+                    let hint = match &ret_ty {
+                        Some(ret_ty) => Hint::Ty(ret_ty),
+                        None => Hint::None,
+                    };
+
+                    let value = self.lower_expr(expr, stmts, hint);
+                    if let Some(ret_ty) = &ret_ty {
+                        self.unify(ret_ty, &value.ty);
+                    }
+
+                    let value = value.ir;
+
+                    stmts.push(quote! {
+                        rsshader::ir::StmtIr::Return {
+                            value: Some(#value),
+                        }
+                    });
+                }
+
+                Tail::Assign { id, ty } => {
+                    let value = match ty {
+                        Some(ty) => self.lower_expr(expr, stmts, Hint::Ty(ty)),
+                        None => self.lower_expr(expr, stmts, Hint::None),
+                    };
+
+                    if let Some(ty) = ty {
+                        self.unify(ty, &value.ty);
+                    }
+
+                    let ty = ty.get_or_insert(value.ty);
+                    let value = value.ir;
+
+                    stmts.push(quote! {
+                        rsshader::ir::StmtIr::Assignment {
+                            left: rsshader::ir::PlaceIr::Variable {
+                                id: #id,
+                                ty: &<#ty as rsshader::reflection::Ty>::IR,
+                            },
+                            right: #value,
+                        }
+                    });
+                }
+            },
+        }
+    }
+
+    pub(super) fn lower_stmt(&mut self, stmt: &Stmt, stmts: &mut Vec<TokenStream>) {
+        match stmt {
+            Stmt::Expr(expr, _) => self.lower_expr_stmt(expr, stmts),
+
+            Stmt::Local(local) => self.lower_local(local, stmts),
+
+            Stmt::Item(item) => {
+                self.error(item, "items cannot be declared inside shader functions");
+            }
+
+            Stmt::Macro(StmtMacro { mac, .. }) => self.lower_macro_stmt(mac, stmts),
+        }
+    }
+
+    pub(super) fn lower_expr_stmt(&mut self, expr: &syn::Expr, stmts: &mut Vec<TokenStream>) {
+        match expr {
+            syn::Expr::Return(ExprReturn { expr: value, .. }) => {
+                self.lower_return(expr.span(), value.as_deref(), stmts)
+            }
+
+            syn::Expr::Call(call) if self.closure_arg(&call.func).is_some() => {
+                self.lower_closure_call_stmt(call, stmts)
+            }
+
+            syn::Expr::Call(_) | syn::Expr::MethodCall(_) => self.lower_call_stmt(expr, stmts),
+
+            syn::Expr::Assign(ExprAssign { left, right, .. }) => {
+                self.lower_assign(left, None, right, stmts)
+            }
+
+            syn::Expr::Binary(ExprBinary {
+                left,
+                op:
+                    op @ (BinOp::AddAssign(_)
+                    | BinOp::SubAssign(_)
+                    | BinOp::MulAssign(_)
+                    | BinOp::DivAssign(_)
+                    | BinOp::RemAssign(_)
+                    | BinOp::BitXorAssign(_)
+                    | BinOp::BitAndAssign(_)
+                    | BinOp::BitOrAssign(_)
+                    | BinOp::ShlAssign(_)
+                    | BinOp::ShrAssign(_)),
+                right,
+                ..
+            }) => self.lower_assign(left, Some(op), right, stmts),
+
+            syn::Expr::If(expr) => self.lower_if(expr, stmts, &mut Tail::Discard),
+
+            syn::Expr::Loop(_) | syn::Expr::While(_) => {
+                self.lower_loop(expr, stmts, &mut Tail::Discard)
+            }
+
+            syn::Expr::ForLoop(expr) => self.lower_for(expr, stmts),
+            syn::Expr::Match(expr) => self.lower_match(expr, stmts, &mut Tail::Discard),
+
+            syn::Expr::Macro(ExprMacro { mac, .. }) => self.lower_macro_stmt(mac, stmts),
+
+            syn::Expr::Break(expr) => self.lower_break(expr, stmts),
+            syn::Expr::Continue(expr) => self.lower_continue(expr, stmts),
+
+            syn::Expr::Block(expr @ ExprBlock { label: None, .. }) => {
+                self.lower_block_tail(&expr.block, stmts, &mut Tail::Discard)
+            }
+
+            syn::Expr::Block(expr) => self.lower_labeled_block(expr, stmts, &mut Tail::Discard),
+
+            syn::Expr::Paren(ExprParen { expr, .. }) | syn::Expr::Group(ExprGroup { expr, .. }) => {
+                self.lower_expr_stmt(expr, stmts)
+            }
+
+            // The value is discarded, but calls inside of it can write through
+            // the references they are passed, so it is still evaluated.
+            _ => {
+                let value = self.lower_expr(expr, stmts, Hint::None);
+
+                if has_calls(expr) {
+                    self.declare_let(value, stmts);
+                }
+            }
+        }
+    }
+
+    /// Lowers control flow used as a value. The value is stored in a variable
+    /// that every path producing a value assigns to.
+    pub(super) fn lower_tail_expr(
+        &mut self,
+        expr: &syn::Expr,
+        stmts: &mut Vec<TokenStream>,
+        hint: Hint,
+    ) -> Expr {
+        let id = self.next_var_id();
+        let mut tail = Tail::Assign {
+            id,
+            ty: match hint {
+                Hint::Ty(ty) => Some(ty.clone()),
+                _ => None,
+            },
+        };
+
+        let error_count = self.errors.len();
+        let mut tail_stmts = Vec::new();
+        self.lower_tail(expr, &mut tail_stmts, &mut tail);
+
+        let Tail::Assign { ty: Some(ty), .. } = tail else {
+            // A branch that could not be lowered has already been reported.
+            if self.errors.len() > error_count {
+                return reported();
+            }
+
+            return self.error(expr, "this expression never produces a value");
+        };
+
+        stmts.push(quote! {
+            rsshader::ir::StmtIr::VariableDecl {
+                id: #id,
+                ty: &<#ty as rsshader::reflection::Ty>::IR,
+            }
+        });
+        stmts.extend(tail_stmts);
+
+        Expr {
+            ir: quote! {
+                rsshader::ir::ExprIr::Variable {
+                    id: #id,
+                    ty: &<#ty as rsshader::reflection::Ty>::IR,
+                }
+            },
+            ty,
+        }
+    }
+
+    /// Lowers `left = right`, or `left op= right` if `op` is set. Like in Rust,
+    /// `right` is evaluated before `left`.
+    fn lower_assign(
+        &mut self,
+        left: &syn::Expr,
+        op: Option<&BinOp>,
+        right: &syn::Expr,
+        stmts: &mut Vec<TokenStream>,
+    ) {
+        let mut place_stmts = Vec::new();
+        let Place {
+            ir: left,
+            value,
+            ty,
+        } = self.lower_place(left, &mut place_stmts);
+
+        let is_shift = matches!(op, Some(BinOp::ShlAssign(_) | BinOp::ShrAssign(_)));
+
+        let right = if is_shift {
+            self.lower_expr(right, stmts, Hint::None)
+        } else {
+            let right = self.lower_expr(right, stmts, Hint::Ty(&ty));
+            self.unify(&ty, &right.ty);
+
+            right
+        };
+
+        stmts.extend(place_stmts);
+
+        let Some(op) = op else {
+            let right = right.ir;
+
+            stmts.push(quote! {
+                rsshader::ir::StmtIr::Assignment {
+                    left: #left,
+                    right: #right,
+                }
+            });
+            return;
+        };
+
+        let op_trait = match op {
+            BinOp::AddAssign(_) => quote! { AddAssign },
+            BinOp::SubAssign(_) => quote! { SubAssign },
+            BinOp::MulAssign(_) => quote! { MulAssign },
+            BinOp::DivAssign(_) => quote! { DivAssign },
+            BinOp::RemAssign(_) => quote! { RemAssign },
+            BinOp::BitXorAssign(_) => quote! { BitXorAssign },
+            BinOp::BitAndAssign(_) => quote! { BitAndAssign },
+            BinOp::BitOrAssign(_) => quote! { BitOrAssign },
+            BinOp::ShlAssign(_) => quote! { ShlAssign },
+            BinOp::ShrAssign(_) => quote! { ShrAssign },
+            _ => unreachable!("not a compound assignment operator"),
+        };
+
+        let right = auto_deref(right);
+        let right_ty = &right.ty;
+        let right = &right.ir;
+        let func = quote! { <#ty as rsshader::reflection::#op_trait<#right_ty>>::IR };
+        let ty_ir = ty_ir(&ty);
+
+        stmts.push(quote! {
+            rsshader::ir::StmtIr::compound_assignment(
+                &#func,
+                #left,
+                &[#value, #right],
+                &[
+                    rsshader::ir::ExprIr::AddressOf {
+                        place: &#left,
+                        ty: &#ty_ir,
+                    },
+                    #right,
+                ],
+            )
+        });
+    }
+
+    fn lower_call_stmt(&mut self, expr: &syn::Expr, stmts: &mut Vec<TokenStream>) {
+        let Call { func, args, ty: _ } = self.lower_call(expr, stmts);
+
+        stmts.push(quote! {
+            rsshader::ir::StmtIr::Call {
+                func: &#func,
+                args: &[#(#args),*],
+            }
+        });
+    }
+
+    fn lower_local(&mut self, local: &Local, stmts: &mut Vec<TokenStream>) {
+        let Local { pat, init, .. } = local;
+
+        if let Pat::Type(PatType { ty, .. }) = pat
+            && !check_ty(ty, self.errors)
+        {
+            return;
+        }
+
+        let (pat, annotation) = match pat {
+            Pat::Type(PatType { pat, ty, .. }) => (&**pat, Some(ty.to_token_stream())),
+            pat => (pat, None),
+        };
+
+        let init = match init {
+            Some(LocalInit {
+                expr,
+                diverge: Some((_, diverge)),
+                ..
+            }) => {
+                let hint = match &annotation {
+                    Some(ty) => Hint::Ty(ty),
+                    None => Hint::None,
+                };
+
+                self.lower_let_else(pat, expr, diverge, hint, stmts);
+                return;
+            }
+
+            Some(LocalInit { expr, .. }) => Some(&**expr),
+            None => None,
+        };
+
+        // Closures are inlined where they are called, with the bindings they
+        // capture from here.
+        if let (
+            Pat::Ident(PatIdent {
+                by_ref: None,
+                ident,
+                subpat: None,
+                ..
+            }),
+            Some(syn::Expr::Closure(func)),
+            None,
+        ) = (pat, init, &annotation)
+        {
+            self.declare_closure(ident, func, stmts);
+            return;
+        }
+
+        match pat {
+            // The value is dropped immediately, so this is the same as an
+            // expression statement.
+            Pat::Wild(_) => {
+                if let Some(init) = init {
+                    self.lower_expr_stmt(init, stmts);
+                }
+            }
+
+            Pat::Ident(PatIdent {
+                by_ref: None,
+                mutability,
+                ident,
+                subpat: None,
+                ..
+            }) => {
+                // The initializer is lowered before the binding is declared so
+                // that it can refer to a binding it shadows.
+                let value = init.map(|init| {
+                    let hint = match &annotation {
+                        Some(ty) => Hint::Ty(ty),
+                        None => Hint::None,
+                    };
+
+                    self.lower_expr(init, stmts, hint)
+                });
+
+                let ty = match (&annotation, &value) {
+                    (Some(ty), value) => {
+                        if let Some(value) = value {
+                            self.unify(ty, &value.ty);
+                        }
+
+                        ty.clone()
+                    }
+
+                    (None, Some(value)) => value.ty.clone(),
+                    (None, None) => {
+                        self.error(ident, "type annotations needed for uninitialized bindings");
+                        return;
+                    }
+                };
+
+                // WGSL pointers can only be bound by `let`.
+                if (mutability.is_some() || value.is_none()) && referent(&ty).is_some() {
+                    self.error(
+                        ident,
+                        "references cannot be stored in mutable bindings in shaders",
+                    );
+                    return;
+                }
+
+                let local = match value {
+                    Some(value) if mutability.is_none() => {
+                        self.declare_let(Expr { ir: value.ir, ty }, stmts)
+                    }
+
+                    // Uninitialized bindings are variables too, since they are
+                    // assigned to later on.
+                    value => {
+                        let local = self.declare_var(&ty, stmts);
+
+                        if let (Some(value), Some(place)) = (value, &local.place) {
+                            let value = value.ir;
+
+                            stmts.push(quote! {
+                                rsshader::ir::StmtIr::Assignment {
+                                    left: #place,
+                                    right: #value,
+                                }
+                            });
+                        }
+
+                        local
+                    }
+                };
+
+                self.declare(ident.to_string(), local);
+            }
+
+            _ => {
+                self.error(
+                    pat,
+                    "only identifier patterns are supported for `let` bindings",
+                );
+            }
+        }
+    }
+
+    fn lower_return(
+        &mut self,
+        span: Span,
+        value: Option<&syn::Expr>,
+        stmts: &mut Vec<TokenStream>,
+    ) {
+        if self.closure_depth > 0 {
+            self.errors.push(syn::Error::new(
+                span,
+                "`return` is not supported in closures in shaders",
+            ));
+            return;
+        }
+
+        match (value, &self.ret_ty) {
+            (Some(value), Some(_)) => self.lower_tail(value, stmts, &mut Tail::Return),
+
+            (None, None) => stmts.push(quote! {
+                rsshader::ir::StmtIr::Return {
+                    value: None,
+                }
+            }),
+
+            (Some(value), None) => {
+                self.error(value, "this function does not return a value");
+            }
+
+            (None, Some(_)) => {
+                self.errors
+                    .push(syn::Error::new(span, "expected a return value"));
+            }
+        }
+    }
+}
+
+/// Whether the value of `expr` has to be returned after it, rather than from
+/// inside of it.
+fn returns_after(expr: &syn::Expr) -> bool {
+    match expr {
+        syn::Expr::Loop(_) => true,
+        syn::Expr::Block(ExprBlock { label, .. }) => label.is_some(),
+        syn::Expr::Match(ExprMatch { arms, .. }) => arms.iter().any(|arm| arm.guard.is_some()),
+        _ => false,
+    }
+}
+
+/// Whether evaluating `expr` calls a function. Only calls can have side
+/// effects in shaders.
+pub(super) fn has_calls(expr: &syn::Expr) -> bool {
+    struct FindCalls(bool);
+
+    impl Visit<'_> for FindCalls {
+        fn visit_expr(&mut self, expr: &syn::Expr) {
+            match expr {
+                syn::Expr::Call(_) | syn::Expr::MethodCall(_) | syn::Expr::Macro(_) => {
+                    self.0 = true
+                }
+
+                // The body of a closure only runs where it is called.
+                syn::Expr::Closure(_) => {}
+
+                expr => visit::visit_expr(self, expr),
+            }
+        }
+    }
+
+    let mut find = FindCalls(false);
+    find.visit_expr(expr);
+    find.0
+}
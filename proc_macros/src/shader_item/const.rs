@@ -0,0 +1,113 @@
+use proc_macro2::TokenStream;
+use quote::{quote, quote_spanned};
+use syn::{
+    Error, Ident, ItemConst, ItemStatic, StaticMutability, Type, Visibility, spanned::Spanned,
+};
+
+use crate::shader_item::util::{Labels, check_ty};
+
+/// Constants are evaluated by rustc, and their marker struct reads the value
+/// back into a `ConstIr`. The marker has braces so that it only takes the name
+/// of the const in the type namespace.
+pub fn shader_item(item: ItemConst, errors: &mut Vec<Error>, _labels: &mut Labels) -> TokenStream {
+    let ItemConst {
+        vis,
+        ident,
+        generics,
+        ty,
+        ..
+    } = &item;
+
+    if !generics.params.is_empty() {
+        errors.push(Error::new(
+            generics.span(),
+            "generic constants are not supported in shaders",
+        ));
+
+        return quote! { #item };
+    }
+
+    if !check_ty(ty, errors) {
+        return quote! { #item };
+    }
+
+    let marker = marker(vis, ident, ty);
+
+    quote! {
+        #item
+
+        #marker
+    }
+}
+
+/// Statics are read like constants, so that lookup tables are declared once
+/// per shader however many functions index them.
+pub fn shader_item_static(
+    item: ItemStatic,
+    errors: &mut Vec<Error>,
+    _labels: &mut Labels,
+) -> TokenStream {
+    let ItemStatic {
+        vis,
+        mutability,
+        ident,
+        ty,
+        ..
+    } = &item;
+
+    if let StaticMutability::Mut(mut_token) = mutability {
+        errors.push(Error::new(
+            mut_token.span(),
+            "mutable statics are not supported in shaders",
+        ));
+
+        return quote! { #item };
+    }
+
+    if !check_ty(ty, errors) {
+        return quote! { #item };
+    }
+
+    let marker = marker(vis, ident, ty);
+
+    quote! {
+        #item
+
+        #marker
+    }
+}
+
+/// The marker also checks the value, so that an infinite or NaN float is
+/// reported at the constant rather than when a shader is written.
+fn marker(vis: &Visibility, ident: &Ident, ty: &Type) -> TokenStream {
+    let non_finite = format!(
+        "`{ident}` contains an infinite or NaN float, which cannot be a constant in shaders"
+    );
+
+    let check = quote_spanned! {ident.span()=>
+        const _: () = assert!(
+            rsshader::reflection::finite_floats(<#ident as rsshader::reflection::Const>::IR.values),
+            #non_finite,
+        );
+    };
+
+    quote! {
+        #[doc(hidden)]
+        #[allow(non_camel_case_types)]
+        #vis struct #ident {}
+
+        impl rsshader::reflection::Const for #ident {
+            type Ty = #ty;
+
+            const IR: rsshader::ir::ConstIr = rsshader::ir::ConstIr {
+                ty: &<#ty as rsshader::reflection::Ty>::IR,
+                values: &rsshader::reflection::const_values::<
+                    #ty,
+                    { <#ty as rsshader::reflection::Ty>::IR.primitive_count() },
+                >(&#ident),
+            };
+        }
+
+        #check
+    }
+}
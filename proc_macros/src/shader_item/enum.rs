@@ -0,0 +1,332 @@
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::{
+    Error, Fields, Ident, ItemEnum, Path, Token, Type, Variant, parse_quote,
+    punctuated::Punctuated, spanned::Spanned,
+};
+
+use crate::shader_item::util::{Labels, check_ty, variant_field_name};
+
+/// Fieldless enums are lowered to their discriminant, which is a `u32` unless
+/// the enum is `#[repr(i32)]`. Other enums are lowered to a struct of a `u32`
+/// tag followed by the fields of every variant, since shaders have no unions.
+///
+/// Unit and tuple variants are associated items of the enum, which construct
+/// it. Fieldless variants are consts whose value is a literal.
+pub fn shader_item(item: ItemEnum, errors: &mut Vec<Error>, _labels: &mut Labels) -> TokenStream {
+    let mut item = item;
+
+    if !item.generics.params.is_empty() {
+        errors.push(Error::new(
+            item.generics.span(),
+            "generic enums are not supported in shaders",
+        ));
+
+        return quote! { #item };
+    }
+
+    // Every field is checked so that all of them are reported.
+    let mut fields_supported = true;
+    for field in item.variants.iter().flat_map(|variant| &variant.fields) {
+        fields_supported &= check_ty(&field.ty, errors);
+    }
+
+    if !fields_supported {
+        return quote! { #item };
+    }
+
+    let is_fieldless = item
+        .variants
+        .iter()
+        .all(|variant| matches!(variant.fields, Fields::Unit));
+
+    let enum_impls = match is_fieldless {
+        true => {
+            let Some(repr) = repr(&mut item, errors) else {
+                return quote! { #item };
+            };
+
+            fieldless_impls(&item, &repr)
+        }
+        false => tagged_impls(&item),
+    };
+
+    quote! {
+        #item
+
+        #enum_impls
+    }
+}
+
+fn fieldless_impls(item: &ItemEnum, repr: &Ident) -> TokenStream {
+    let ItemEnum { ident, .. } = item;
+
+    let variant_impls = item.variants.iter().map(|variant| {
+        let variant_ident = &variant.ident;
+        let tag = quote! { #ident::#variant_ident as i128 };
+
+        let value = quote! {
+            rsshader::ir::ExprIr::Literal(rsshader::ir::Literal::int(
+                &<#ident as rsshader::reflection::Ty>::IR,
+                #tag,
+            ))
+        };
+
+        constructor_impls(item, variant, &tag, &[], value)
+    });
+
+    let cast_impls = ["i32", "u32", "usize"].map(|ty| {
+        let ty = Ident::new(ty, ident.span());
+
+        quote! {
+            impl rsshader::reflection::Cast<#ty> for #ident {
+                const IR: rsshader::ir::FnIr = <#repr as rsshader::reflection::Cast<#ty>>::IR;
+            }
+        }
+    });
+
+    // Comparisons need the Rust impl, which only a derive can provide before
+    // this impl is checked.
+    let eq_impl = match derives(item, "PartialEq") {
+        true => quote! {
+            impl rsshader::reflection::PartialEq for #ident {
+                const EQ: rsshader::ir::FnIr = <#repr as rsshader::reflection::PartialEq>::EQ;
+                const NE: rsshader::ir::FnIr = <#repr as rsshader::reflection::PartialEq>::NE;
+            }
+        },
+        false => quote! {},
+    };
+
+    let marker = marker_struct(item);
+
+    quote! {
+        impl rsshader::reflection::Ty for #ident {
+            const IR: rsshader::ir::TypeIr = <#repr as rsshader::reflection::Ty>::IR;
+        }
+
+        impl rsshader::reflection::Enum for #ident {
+            type Tag = #repr;
+        }
+
+        #(#cast_impls)*
+
+        #eq_impl
+
+        #marker
+
+        #(#variant_impls)*
+    }
+}
+
+/// The impls of an enum with fields, whose tag is the index of the variant.
+fn tagged_impls(item: &ItemEnum) -> TokenStream {
+    let ItemEnum { ident, .. } = item;
+
+    let mut field_tys = Vec::new();
+    let mut field_impls = Vec::new();
+    let mut variant_impls = Vec::new();
+
+    for (tag, variant) in item.variants.iter().enumerate() {
+        let tag = tag as u32;
+
+        let mut field_inits = vec![quote! {
+            rsshader::ir::FieldInitIr {
+                idx: 0,
+                value: rsshader::ir::ExprIr::Literal(rsshader::ir::Literal::U32(#tag)),
+            }
+        }];
+
+        for (member, field) in variant.fields.members().zip(&variant.fields) {
+            let ty = &field.ty;
+            let name = variant_field_name(&variant.ident, &member);
+
+            // The tag comes first.
+            let idx = field_tys.len() + 1;
+            let param_idx = field_inits.len() - 1;
+
+            field_impls.push(quote! {
+                impl rsshader::reflection::Field<{ rsshader::reflection::name_id(#name) }>
+                    for #ident
+                {
+                    type Ty = #ty;
+                    const IDX: usize = #idx;
+                }
+            });
+
+            field_inits.push(quote! {
+                rsshader::ir::FieldInitIr {
+                    idx: #idx,
+                    value: rsshader::ir::ExprIr::Param {
+                        idx: #param_idx,
+                        ty: &<#ty as rsshader::reflection::Ty>::IR,
+                    },
+                }
+            });
+
+            field_tys.push(ty);
+        }
+
+        // Struct variants are constructed with struct expressions instead.
+        let param_tys = match &variant.fields {
+            Fields::Named(_) => None,
+            Fields::Unnamed(fields) => Some(fields.unnamed.iter().map(|field| &field.ty).collect()),
+            Fields::Unit => Some(Vec::new()),
+        };
+
+        let value = quote! {
+            rsshader::ir::ExprIr::StructConstructor {
+                ty: &<#ident as rsshader::reflection::Ty>::IR,
+                fields: &[#(#field_inits),*],
+                base: None,
+            }
+        };
+
+        variant_impls.push(match param_tys {
+            Some(param_tys) => {
+                constructor_impls(item, variant, &quote! { #tag as i128 }, &param_tys, value)
+            }
+            None => variant_tag_impl(item, variant, &quote! { #tag as i128 }),
+        });
+    }
+
+    let marker = marker_struct(item);
+
+    quote! {
+        impl rsshader::reflection::Ty for #ident {
+            const IR: rsshader::ir::TypeIr = rsshader::ir::TypeIr::Struct {
+                fields: &[
+                    rsshader::ir::TypeIr::Primitive(rsshader::ir::Primitive::U32),
+                    #(<#field_tys as rsshader::reflection::Ty>::IR),*
+                ],
+            };
+
+            const LAYOUT: rsshader::reflection::Layout =
+                panic!("enums with fields cannot be read as constants");
+        }
+
+        impl rsshader::reflection::Enum for #ident {
+            type Tag = u32;
+        }
+
+        #(#field_impls)*
+
+        #marker
+
+        #(#variant_impls)*
+    }
+}
+
+/// Every variant is a function of the same marker, selected by its name.
+fn marker_ident(item: &ItemEnum) -> Ident {
+    format_ident!("__rsshader_{}_variant", item.ident)
+}
+
+fn marker_struct(item: &ItemEnum) -> TokenStream {
+    let vis = &item.vis;
+    let marker = marker_ident(item);
+
+    quote! {
+        #[doc(hidden)]
+        #[allow(non_camel_case_types)]
+        #vis struct #marker<const NAME: u64>;
+    }
+}
+
+fn variant_tag_impl(item: &ItemEnum, variant: &Variant, tag: &TokenStream) -> TokenStream {
+    let ident = &item.ident;
+    let name = variant.ident.to_string();
+
+    quote! {
+        impl rsshader::reflection::Variant<{ rsshader::reflection::name_id(#name) }> for #ident {
+            const TAG: i128 = #tag;
+        }
+    }
+}
+
+/// The tag of `variant`, and the function that constructs it from parameters
+/// of `param_tys` by returning `value`.
+fn constructor_impls(
+    item: &ItemEnum,
+    variant: &Variant,
+    tag: &TokenStream,
+    param_tys: &[&Type],
+    value: TokenStream,
+) -> TokenStream {
+    let ident = &item.ident;
+    let name = variant.ident.to_string();
+    let marker = marker_ident(item);
+    let marker_ty = quote! { #marker<{ rsshader::reflection::name_id(#name) }> };
+
+    let tag_impl = variant_tag_impl(item, variant, tag);
+
+    quote! {
+        #tag_impl
+
+        impl rsshader::reflection::Fn for #marker_ty {
+            type Output = #ident;
+
+            const IR: rsshader::ir::FnIr = rsshader::ir::FnIr::UserDefined {
+                param_types: &[#(&<#param_tys as rsshader::reflection::Ty>::IR),*],
+                ret_type: Some(&<#ident as rsshader::reflection::Ty>::IR),
+                body: rsshader::ir::BodyIr {
+                    stmts: &[rsshader::ir::StmtIr::Return {
+                        value: Some(#value),
+                    }],
+                },
+            };
+        }
+
+        impl rsshader::reflection::Method<
+            { rsshader::reflection::name_id(#name) },
+            #marker_ty,
+        > for (#ident, (#(#param_tys,)*)) {
+            type Fn = #marker_ty;
+        }
+    }
+}
+
+/// The primitive a fieldless enum is represented as. Enums without a `#[repr]`
+/// are given `#[repr(u32)]`, so that constants can be read as their primitive.
+fn repr(item: &mut ItemEnum, errors: &mut Vec<Error>) -> Option<Ident> {
+    let Some(attr) = item.attrs.iter().find(|attr| attr.path().is_ident("repr")) else {
+        item.attrs.push(parse_quote! { #[repr(u32)] });
+        return Some(parse_quote! { u32 });
+    };
+
+    let reprs = match attr.parse_args_with(Punctuated::<Ident, Token![,]>::parse_terminated) {
+        Ok(reprs) => reprs,
+        Err(error) => {
+            errors.push(error);
+            return None;
+        }
+    };
+
+    match reprs.iter().collect::<Vec<_>>()[..] {
+        [repr] if repr == "u32" || repr == "i32" => Some(repr.clone()),
+
+        _ => {
+            errors.push(Error::new(
+                attr.span(),
+                "shader enums must be `#[repr(u32)]` or `#[repr(i32)]`",
+            ));
+
+            None
+        }
+    }
+}
+
+fn derives(item: &ItemEnum, name: &str) -> bool {
+    item.attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("derive"))
+        .filter_map(|attr| {
+            attr.parse_args_with(Punctuated::<Path, Token![,]>::parse_terminated)
+                .ok()
+        })
+        .flatten()
+        .any(|path| {
+            path.segments
+                .last()
+                .is_some_and(|segment| segment.ident == name)
+        })
+}
@@ -1,36 +1,585 @@
 use proc_macro2::TokenStream;
-use quote::quote;
-use syn::{Error, ItemFn, Signature, spanned::Spanned};
+use quote::{ToTokens, quote};
+use syn::{
+    Block, ConstParam, Error, FnArg, GenericParam, Generics, Ident, ItemFn,
+    ParenthesizedGenericArguments, PatType, PathArguments, PathSegment, PredicateType, Receiver,
+    ReturnType, Signature, TraitBound, Type, TypeParam, TypeParamBound, Visibility, WherePredicate,
+    parse_quote, spanned::Spanned,
+};
 
-use crate::shader_item::util::Labels;
+use crate::shader_item::{
+    body::{self, Macros},
+    util::{Labels, check_ty, mentions, replace_self, ty_ir},
+};
 
-pub fn shader_item(item: ItemFn, errors: &mut Vec<Error>, labels: &mut Labels) -> TokenStream {
-    if item.sig.generics.params.len() > 0 || item.sig.generics.where_clause.is_some() {
-        errors.push(Error::new(
-            item.sig.span(),
-            "generic functions are not supported yet",
-        ));
-        return quote! { #item };
-    }
-
-    labels.find("vertex");
-    labels.find("fragment");
+pub fn shader_item(mut item: ItemFn, errors: &mut Vec<Error>, labels: &mut Labels) -> TokenStream {
+    let vertex_label = labels.find("vertex");
+    let fragment_label = labels.find("fragment");
+    let macros = Macros::take(&mut item.attrs, errors);
 
     let ItemFn {
         vis,
-        sig: Signature { ident, .. },
+        sig: sig @ Signature { ident, .. },
+        block,
         ..
     } = &item;
 
+    let shader_fn = ShaderFn::lower(sig, block, ident.clone(), None, &macros, errors);
+    let shader_fn_items = shader_fn.items(vis);
+
+    let ShaderFn {
+        generics,
+        param_types,
+        body,
+        ..
+    } = &shader_fn;
+
+    let Some(body) = body else {
+        return quote! {
+            #item
+
+            #shader_fn_items
+        };
+    };
+
+    let (impl_generics, _, _) = generics.split_for_impl();
+    let marker_ty = shader_fn.marker_ty();
+    let where_clause = shader_fn.where_clause();
+
+    let entry_point = match (vertex_label, fragment_label) {
+        (None, None) => quote! {},
+
+        (Some(_), Some(label_span)) => {
+            errors.push(Error::new(
+                label_span,
+                "a function cannot be both a vertex and a fragment entry point",
+            ));
+
+            quote! {}
+        }
+
+        (Some(label_span), None) | (None, Some(label_span)) => {
+            // The impl for vertex inputs is selected by inferring its kind.
+            let (variant, input_trait, output_trait) = if vertex_label.is_some() {
+                (
+                    quote! { Vertex },
+                    quote! { VertexInputTy<_> },
+                    quote! { FragInputTy },
+                )
+            } else {
+                (
+                    quote! { Frag },
+                    quote! { FragInputTy },
+                    quote! { FragOutputTy },
+                )
+            };
+
+            match (param_types.as_slice(), &sig.output) {
+                ([input], ReturnType::Type(_, output)) => quote! {
+                    impl #impl_generics rsshader::reflection::EntryPoint for #marker_ty #where_clause {
+                        const IR: rsshader::ir::EntryPointIr = rsshader::ir::EntryPointIr::#variant {
+                            input: &<#input as rsshader::reflection::#input_trait>::IR,
+                            output: &<#output as rsshader::reflection::#output_trait>::IR,
+                            body: #body,
+                        };
+                    }
+                },
+
+                _ => {
+                    errors.push(Error::new(
+                        label_span,
+                        "entry points must take a single input and return an output",
+                    ));
+
+                    quote! {}
+                }
+            }
+        }
+    };
+
+    // Calls without a turbofish select the instance from the argument types.
+    let call_impl = if !shader_fn.is_inferable() {
+        quote! {}
+    } else if generics.params.is_empty() {
+        quote! {
+            impl rsshader::reflection::Call<(#(#param_types,)*)> for #ident {
+                type Fn = Self;
+            }
+        }
+    } else {
+        quote! {
+            impl #impl_generics rsshader::reflection::Call<(#(#param_types,)*)> for #ident #where_clause {
+                type Fn = #marker_ty;
+            }
+        }
+    };
+
     quote! {
         #item
 
-        #[doc(hidden)]
-        #[allow(non_camel_case_types)]
-        #vis struct #ident {}
+        #shader_fn_items
+
+        #call_impl
+
+        #entry_point
+    }
+}
+
+/// The parts of a shader function that free functions and methods have in
+/// common. Every function has a marker struct with the generic parameters in
+/// scope of the function, which implements `rsshader::reflection::Fn` so that
+/// every instance gets its own `FnIr`.
+pub struct ShaderFn {
+    pub marker: Ident,
+    pub generics: Generics,
+    pub self_ty: Option<TokenStream>,
+    pub param_types: Vec<TokenStream>,
+    pub param_irs: Vec<TokenStream>,
+    pub ret_type: Option<TokenStream>,
+    pub body: Option<TokenStream>,
+    pub builtin: Option<TokenStream>,
+}
+
+/// The `impl` block a method is in.
+#[derive(Clone, Copy)]
+pub struct FnOwner<'a> {
+    pub self_ty: &'a Type,
+    pub generics: &'a Generics,
+}
+
+impl ShaderFn {
+    pub fn lower(
+        sig: &Signature,
+        block: &Block,
+        marker: Ident,
+        owner: Option<FnOwner>,
+        macros: &Macros,
+        errors: &mut Vec<Error>,
+    ) -> Self {
+        let mut shader_fn = Self::declare(sig, marker, owner);
+
+        shader_fn.body = if !check_signature(sig, errors) {
+            None
+        } else {
+            body::lower_fn(
+                sig,
+                block,
+                &shader_fn.generics,
+                shader_fn.self_ty.as_ref(),
+                macros,
+                errors,
+            )
+        };
+
+        shader_fn
+    }
+
+    /// A method that maps to the builtin `ir` instead of being lowered. The
+    /// builtin is only selected for shader types, so its where clause requires
+    /// every type in the signature to be one.
+    pub fn builtin(
+        sig: &Signature,
+        marker: Ident,
+        owner: FnOwner,
+        ir: TokenStream,
+        errors: &mut Vec<Error>,
+    ) -> Self {
+        let mut shader_fn = Self::declare(sig, marker, Some(owner));
+
+        let predicates = shader_fn
+            .self_ty
+            .iter()
+            .chain(&shader_fn.param_types)
+            .chain(&shader_fn.ret_type)
+            .map(|ty| -> WherePredicate {
+                parse_quote! { #ty: rsshader::reflection::Ty }
+            })
+            .collect::<Vec<_>>();
+        shader_fn
+            .generics
+            .make_where_clause()
+            .predicates
+            .extend(predicates);
+
+        if check_signature(sig, errors) {
+            shader_fn.builtin = Some(ir);
+        }
+
+        shader_fn
+    }
+
+    /// The signature of a function, without a body.
+    fn declare(sig: &Signature, marker: Ident, owner: Option<FnOwner>) -> Self {
+        let mut generics = match &owner {
+            Some(owner) => {
+                let mut generics = owner.generics.clone();
+                generics.params.extend(sig.generics.params.iter().cloned());
+
+                if let Some(where_clause) = &sig.generics.where_clause {
+                    generics
+                        .make_where_clause()
+                        .predicates
+                        .extend(where_clause.predicates.iter().cloned());
+                }
+
+                generics
+            }
+            None => sig.generics.clone(),
+        };
+        replace_fn_bounds(&mut generics);
+
+        let self_ty = owner.map(|owner| owner.self_ty.to_token_stream());
+        let (param_types, ret_type) = signature_types(sig, self_ty.as_ref());
+
+        // `&mut self` is the only receiver that is not passed by value.
+        let param_irs = sig
+            .inputs
+            .iter()
+            .zip(&param_types)
+            .map(|(input, ty)| match input {
+                FnArg::Receiver(Receiver {
+                    reference: Some(_),
+                    mutability: Some(_),
+                    ..
+                }) => ty_ir(&quote! { &mut #ty }),
+                _ => ty_ir(ty),
+            })
+            .collect();
+
+        Self {
+            marker,
+            generics,
+            self_ty,
+            param_types,
+            param_irs,
+            ret_type,
+            body: None,
+            builtin: None,
+        }
+    }
+
+    /// The marker struct, and its `rsshader::reflection::Fn` impl if the body
+    /// could be lowered or the function maps to a builtin. There is no marker if a const parameter has an
+    /// unsupported type.
+    pub fn items(&self, vis: &Visibility) -> TokenStream {
+        let Self {
+            marker,
+            generics,
+            self_ty,
+            param_irs,
+            ret_type,
+            body,
+            builtin,
+            ..
+        } = self;
+
+        let Some(marker_generics) = marker_generics(generics, self_ty.is_some()) else {
+            return quote! {};
+        };
+
+        let marker_struct = quote! {
+            #[doc(hidden)]
+            #[allow(non_camel_case_types)]
+            #vis struct #marker #marker_generics
+        };
+
+        let (impl_generics, _, _) = generics.split_for_impl();
+        let marker_ty = self.marker_ty();
+        let where_clause = self.where_clause();
+
+        let (ret_type, ret_type_ir) = match ret_type {
+            None => (quote! { () }, quote! { None }),
+            Some(ty) => (
+                quote! { #ty },
+                quote! { Some(&<#ty as rsshader::reflection::Ty>::IR) },
+            ),
+        };
+
+        let ir = match (builtin, body) {
+            (Some(builtin), _) => builtin.clone(),
+            (None, Some(body)) => quote! {
+                rsshader::ir::FnIr::UserDefined {
+                    param_types: &[#(&#param_irs),*],
+                    ret_type: #ret_type_ir,
+                    body: #body,
+                }
+            },
+            (None, None) => return marker_struct,
+        };
+
+        quote! {
+            #marker_struct
+
+            impl #impl_generics rsshader::reflection::Fn for #marker_ty #where_clause {
+                type Output = #ret_type;
+
+                const IR: rsshader::ir::FnIr = #ir;
+            }
+        }
+    }
+
+    /// The marker struct instantiated with the generic parameters in scope.
+    ///
+    /// The marker of a method also takes the type of the `impl` block, so that
+    /// its impls are only as visible as that type.
+    pub fn marker_ty(&self) -> TokenStream {
+        let marker = &self.marker;
+
+        let args = self
+            .self_ty
+            .iter()
+            .cloned()
+            .chain(self.generics.params.iter().filter_map(|param| match param {
+                GenericParam::Type(TypeParam { ident, .. })
+                | GenericParam::Const(ConstParam { ident, .. }) => Some(ident.to_token_stream()),
+                GenericParam::Lifetime(_) => None,
+            }))
+            .collect::<Vec<_>>();
+
+        if args.is_empty() {
+            quote! { #marker }
+        } else {
+            quote! { #marker<#(#args),*> }
+        }
+    }
+
+    /// Whether the argument types of a call select a single instance, which is
+    /// the case if they and the type of the `impl` block mention every generic
+    /// parameter.
+    pub fn is_inferable(&self) -> bool {
+        self.generics.params.iter().all(|param| {
+            let ident = match param {
+                GenericParam::Type(TypeParam { ident, .. })
+                | GenericParam::Const(ConstParam { ident, .. }) => ident,
+                GenericParam::Lifetime(_) => return true,
+            };
+
+            self.param_types
+                .iter()
+                .chain(&self.self_ty)
+                .any(|ty| mentions(ty.clone(), ident))
+        })
+    }
+
+    /// The where clause of the function with every type parameter bound to be
+    /// a shader type.
+    pub fn where_clause(&self) -> TokenStream {
+        let predicates = where_predicates(&self.generics);
+
+        let where_clause = if predicates.is_empty() {
+            quote! {}
+        } else {
+            quote! { where #(#predicates,)* }
+        };
+
+        match &self.self_ty {
+            Some(self_ty) => replace_self(where_clause, self_ty),
+            None => where_clause,
+        }
+    }
+}
+
+/// The predicates of the where clause of a function with generic parameters
+/// `generics`, with every type parameter bound to be a shader type.
+pub fn where_predicates(generics: &Generics) -> Vec<TokenStream> {
+    let original_predicates = generics
+        .where_clause
+        .iter()
+        .flat_map(|where_clause| where_clause.predicates.iter());
+
+    generics
+        .type_params()
+        .map(|TypeParam { ident, .. }| quote! { #ident: rsshader::reflection::Ty })
+        .chain(original_predicates.map(|p| p.to_token_stream()))
+        .collect()
+}
+
+/// Replaces the `Fn`, `FnMut` and `FnOnce` bounds in `generics` with
+/// `rsshader::reflection::Closure`, which the closures passed to shader
+/// functions implement instead.
+fn replace_fn_bounds(generics: &mut Generics) {
+    for param in generics.type_params_mut() {
+        param.bounds.iter_mut().for_each(replace_fn_bound);
+    }
+
+    for predicate in generics
+        .where_clause
+        .iter_mut()
+        .flat_map(|w| &mut w.predicates)
+    {
+        if let WherePredicate::Type(PredicateType { bounds, .. }) = predicate {
+            bounds.iter_mut().for_each(replace_fn_bound);
+        }
+    }
+}
+
+fn replace_fn_bound(bound: &mut TypeParamBound) {
+    let TypeParamBound::Trait(TraitBound { path, .. }) = bound else {
+        return;
+    };
+    let Some(PathSegment {
+        ident,
+        arguments:
+            PathArguments::Parenthesized(ParenthesizedGenericArguments { inputs, output, .. }),
+    }) = path.segments.last()
+    else {
+        return;
+    };
+    if !matches!(ident.to_string().as_str(), "Fn" | "FnMut" | "FnOnce") {
+        return;
+    }
+
+    let inputs = inputs.iter();
+    let output = match output {
+        ReturnType::Default => quote! { () },
+        ReturnType::Type(_, ty) => ty.to_token_stream(),
+    };
+
+    *bound = parse_quote! {
+        rsshader::reflection::Closure<
+            (#(#inputs,)*),
+            Fn: rsshader::reflection::Fn<Output = #output>,
+        >
+    };
+}
+
+/// Reports the qualifiers and types of a signature that shaders do not
+/// support. Returns whether the signature is supported.
+fn check_signature(sig: &Signature, errors: &mut Vec<Error>) -> bool {
+    let error_count = errors.len();
+
+    let Signature {
+        asyncness,
+        unsafety,
+        abi,
+        inputs,
+        variadic,
+        output,
+        ..
+    } = sig;
+
+    if let Some(asyncness) = asyncness {
+        errors.push(Error::new(
+            asyncness.span(),
+            "`async` functions are not supported in shaders, use a regular function instead",
+        ));
+    }
+
+    if let Some(unsafety) = unsafety {
+        errors.push(Error::new(
+            unsafety.span(),
+            "`unsafe` functions are not supported in shaders, use a safe function instead",
+        ));
+    }
+
+    if let Some(abi) = abi {
+        errors.push(Error::new(
+            abi.span(),
+            "`extern` functions are not supported in shaders, use the Rust ABI instead",
+        ));
+    }
+
+    if let Some(variadic) = variadic {
+        errors.push(Error::new(
+            variadic.span(),
+            "variadic functions are not supported in shaders, use an array parameter instead",
+        ));
+    }
+
+    for input in inputs {
+        if let FnArg::Typed(PatType { ty, .. }) = input {
+            check_ty(ty, errors);
+        }
+    }
+
+    if let ReturnType::Type(_, ty) = output {
+        check_ty(ty, errors);
+    }
+
+    errors.len() == error_count
+}
+
+/// The parameter and return types of a function, with `Self` replaced by
+/// `self_ty`. The receiver is a parameter of type `self_ty`.
+pub fn signature_types(
+    sig: &Signature,
+    self_ty: Option<&TokenStream>,
+) -> (Vec<TokenStream>, Option<TokenStream>) {
+    let with_self = |tokens: TokenStream| match self_ty {
+        Some(self_ty) => replace_self(tokens, self_ty),
+        None => tokens,
+    };
+
+    let param_types = sig
+        .inputs
+        .iter()
+        .map(|input| match input {
+            FnArg::Typed(PatType { ty, .. }) => ty.to_token_stream(),
+            FnArg::Receiver(_) => quote! { Self },
+        })
+        .map(with_self)
+        .collect();
+
+    let ret_type = match &sig.output {
+        ReturnType::Default => None,
+        ReturnType::Type(_, ty) => Some(with_self(ty.to_token_stream())),
+    };
+
+    (param_types, ret_type)
+}
+
+/// The generic parameters and body of the marker struct of a function. Every
+/// parameter has a default so that the bare function name is also a type.
+/// Lifetimes are left out, since no instance of a function depends on them.
+///
+/// Returns `None` if a const parameter has a type that shaders do not support,
+/// which is reported when the body is lowered.
+fn marker_generics(generics: &Generics, has_self: bool) -> Option<TokenStream> {
+    if generics.lifetimes().count() == generics.params.len() && !has_self {
+        return Some(quote! { {} });
+    }
+
+    let self_param = has_self.then(|| quote! { __Self = () });
+
+    let params = generics
+        .params
+        .iter()
+        .filter_map(|param| match param {
+            GenericParam::Type(TypeParam { ident, .. }) => Some(Some(quote! { #ident = () })),
+            GenericParam::Const(ConstParam { ident, ty, .. }) => {
+                Some(const_param_default(ty).map(|default| quote! { const #ident: #ty = #default }))
+            }
+            GenericParam::Lifetime(_) => None,
+        })
+        .collect::<Option<Vec<_>>>()?;
+
+    let type_params = has_self.then(|| quote! { __Self }).into_iter().chain(
+        generics
+            .type_params()
+            .map(|param| param.ident.to_token_stream()),
+    );
+
+    let params = self_param.into_iter().chain(params);
+
+    Some(quote! {
+        <#(#params),*> {
+            _marker: core::marker::PhantomData<fn() -> (#(#type_params,)*)>,
+        }
+    })
+}
 
-        impl rsshader::reflection::ShaderFn for #ident {
-            const IR: rsshader::ir::FnIr = todo!();
+/// The default of a const parameter in the marker struct, for the types that
+/// const parameters of shader functions can have.
+fn const_param_default(ty: &Type) -> Option<TokenStream> {
+    match ty {
+        Type::Path(ty)
+            if ["usize", "u32", "i32"]
+                .iter()
+                .any(|int| ty.path.is_ident(int)) =>
+        {
+            Some(quote! { 0 })
         }
+        Type::Path(ty) if ty.path.is_ident("bool") => Some(quote! { false }),
+        _ => None,
     }
 }
@@ -0,0 +1,453 @@
+use std::hash::{DefaultHasher, Hash, Hasher};
+
+use proc_macro2::TokenStream;
+use quote::{ToTokens, format_ident, quote};
+use syn::{
+    AngleBracketedGenericArguments, Attribute, Block, Error, Ident, ImplItem, ImplItemConst,
+    ImplItemFn, ItemImpl, Path, PathArguments, PathSegment, Signature, Type, TypePath, parse_quote,
+    parse_quote_spanned, spanned::Spanned,
+};
+
+use crate::shader_item::{
+    body::Macros,
+    r#fn::{FnOwner, ShaderFn, signature_types},
+    r#trait::trait_item_const,
+    util::{Labels, replace_self},
+};
+
+pub fn shader_item(
+    mut item: ItemImpl,
+    errors: &mut Vec<Error>,
+    _labels: &mut Labels,
+) -> TokenStream {
+    let operator = item
+        .trait_
+        .as_ref()
+        .and_then(|(_, path, _)| path.segments.last())
+        .filter(|segment| MIRRORED_TRAITS.contains(&segment.ident.to_string().as_str()))
+        .cloned();
+
+    if let Some(segment) = item
+        .trait_
+        .as_ref()
+        .and_then(|(_, path, _)| path.segments.last())
+        .filter(|segment| NON_SHADER_TRAITS.contains(&segment.ident.to_string().as_str()))
+    {
+        errors.push(Error::new(
+            segment.span(),
+            format!(
+                "`{}` has no counterpart in shaders, implement it outside of `#[shader_item]`",
+                segment.ident
+            ),
+        ));
+
+        return quote! { #item };
+    }
+
+    let macros = Macros::take(&mut item.attrs, errors);
+
+    let builtins = item
+        .items
+        .iter_mut()
+        .map(|impl_item| match impl_item {
+            ImplItem::Fn(ImplItemFn { attrs, .. }) => take_builtin(attrs, errors),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+
+    let owner = FnOwner {
+        self_ty: &item.self_ty,
+        generics: &item.generics,
+    };
+
+    let trait_path = item.trait_.as_ref().map(|(_, path, _)| path);
+
+    let mut items = Vec::new();
+    let mut trait_consts = Vec::<ImplItem>::new();
+
+    for (impl_item, builtin) in item.items.iter().zip(builtins) {
+        let (sig, block) = match impl_item {
+            ImplItem::Fn(ImplItemFn { sig, block, .. }) => (sig.clone(), block.clone()),
+
+            // Associated consts are lowered as functions without parameters,
+            // so that their value is computed in the shader.
+            ImplItem::Const(ImplItemConst {
+                ident, ty, expr, ..
+            }) => (
+                parse_quote! { fn #ident() -> #ty },
+                parse_quote! { { #expr } },
+            ),
+
+            // The `Output` of operators is only needed by Rust.
+            ImplItem::Type(_) if operator.is_some() => continue,
+
+            _ => {
+                errors.push(Error::new(
+                    impl_item.span(),
+                    "only methods, associated functions and associated consts are supported in shader impls",
+                ));
+
+                continue;
+            }
+        };
+
+        if let Some(operator) = &operator {
+            items.push(operator_impl(
+                &sig, &block, builtin, operator, &owner, &macros, errors,
+            ));
+            continue;
+        }
+
+        if let Some(builtin) = builtin {
+            match trait_path {
+                None => items.push(builtin_method(&sig, &builtin, &owner, errors)),
+                Some(_) => errors.push(Error::new(
+                    builtin.span(),
+                    "`#[builtin]` is only supported in inherent impls and impls of operator \
+                     traits and `From`",
+                )),
+            }
+
+            continue;
+        }
+
+        let marker = marker_ident(&sig, &owner, trait_path);
+        let shader_fn = ShaderFn::lower(&sig, &block, marker, Some(owner), &macros, errors);
+        items.push(shader_fn.items(&parse_quote! { pub }));
+
+        match trait_path {
+            // The trait selects the function through the IR the impl provides.
+            Some(_) => {
+                let const_ident = trait_item_const(&sig.ident);
+                let marker_ty = shader_fn.marker_ty();
+
+                let ir = match shader_fn.body {
+                    Some(_) => quote! { <#marker_ty as rsshader::reflection::Fn>::IR },
+                    None => quote! { unreachable!() },
+                };
+
+                // Spanned at the trait, so that impls of traits without
+                // `#[shader_item]` are reported there.
+                let span = trait_path.span();
+                trait_consts.push(parse_quote_spanned! {span=>
+                    const #const_ident: rsshader::ir::FnIr = #ir;
+                });
+            }
+
+            None => items.push(method_impl(&sig, &shader_fn, errors)),
+        }
+    }
+
+    item.items.extend(trait_consts);
+
+    quote! {
+        #item
+
+        #(#items)*
+    }
+}
+
+/// The `core::ops` and `core::convert` traits that have a counterpart in
+/// `rsshader::reflection`, including the compound assignment operators.
+const MIRRORED_TRAITS: &[&str] = &[
+    "Add",
+    "Sub",
+    "Mul",
+    "Div",
+    "Rem",
+    "Shl",
+    "Shr",
+    "BitAnd",
+    "BitOr",
+    "BitXor",
+    "Neg",
+    "Not",
+    "AddAssign",
+    "SubAssign",
+    "MulAssign",
+    "DivAssign",
+    "RemAssign",
+    "ShlAssign",
+    "ShrAssign",
+    "BitAndAssign",
+    "BitOrAssign",
+    "BitXorAssign",
+    "From",
+];
+
+/// Standard library traits that shaders can't use, and whose impls would
+/// otherwise fail with errors about the generated items.
+const NON_SHADER_TRAITS: &[&str] = &[
+    "PartialEq",
+    "Eq",
+    "PartialOrd",
+    "Ord",
+    "Clone",
+    "Copy",
+    "Debug",
+    "Display",
+    "Default",
+    "Hash",
+    "Index",
+    "IndexMut",
+    "Deref",
+    "DerefMut",
+    "Drop",
+    "Iterator",
+    "IntoIterator",
+    "Into",
+    "TryFrom",
+    "TryInto",
+    "AsRef",
+    "AsMut",
+    "Borrow",
+    "BorrowMut",
+    "Sum",
+    "Product",
+    "FromStr",
+    "Fn",
+    "FnMut",
+    "FnOnce",
+];
+
+/// Implements the `rsshader::reflection` counterpart of an operator trait or
+/// `From`, so that the operator or conversion can be used on the type in
+/// shaders. It is either lowered as a user function, or maps to the builtin
+/// named by `#[builtin(..)]`.
+fn operator_impl(
+    sig: &Signature,
+    block: &Block,
+    builtin: Option<Ident>,
+    operator: &PathSegment,
+    owner: &FnOwner,
+    macros: &Macros,
+    errors: &mut Vec<Error>,
+) -> TokenStream {
+    let self_ty = owner.self_ty;
+    let op_trait = &operator.ident;
+
+    let rhs = match (op_trait.to_string().as_str(), &operator.arguments) {
+        ("Neg" | "Not", _) => None,
+
+        (_, PathArguments::AngleBracketed(AngleBracketedGenericArguments { args, .. })) => Some(
+            replace_self(args.to_token_stream(), &self_ty.to_token_stream()),
+        ),
+
+        _ => Some(self_ty.to_token_stream()),
+    };
+
+    let (generics, where_clause, ir, items) = match builtin {
+        Some(builtin) => {
+            let Some(ir) = builtin_op(&builtin, self_ty, rhs.as_ref()) else {
+                errors.push(Error::new(builtin.span(), "unknown builtin operator"));
+                return quote! {};
+            };
+
+            let mut generics = owner.generics.clone();
+            let predicates = &mut generics.make_where_clause().predicates;
+            predicates.push(parse_quote! { #self_ty: rsshader::reflection::Ty });
+            if let Some(rhs) = &rhs {
+                predicates.push(parse_quote! { #rhs: rsshader::reflection::Ty });
+            }
+
+            let where_clause = generics.where_clause.to_token_stream();
+
+            (generics, where_clause, ir, quote! {})
+        }
+
+        None => {
+            let marker = marker_ident(sig, owner, Some(&parse_quote! { #operator }));
+            let shader_fn = ShaderFn::lower(sig, block, marker, Some(*owner), macros, errors);
+            let items = shader_fn.items(&parse_quote! { pub });
+
+            if shader_fn.body.is_none() {
+                return items;
+            }
+
+            let marker_ty = shader_fn.marker_ty();
+            let ir = quote! { <#marker_ty as rsshader::reflection::Fn>::IR };
+
+            (
+                shader_fn.generics.clone(),
+                shader_fn.where_clause(),
+                ir,
+                items,
+            )
+        }
+    };
+
+    let (impl_generics, _, _) = generics.split_for_impl();
+    let rhs = rhs.map(|rhs| quote! { <#rhs> });
+
+    quote! {
+        #items
+
+        impl #impl_generics rsshader::reflection::#op_trait #rhs for #self_ty #where_clause {
+            const IR: rsshader::ir::FnIr = #ir;
+        }
+    }
+}
+
+/// The IR of the builtin operator `name` on `self_ty`, which is either a
+/// primitive or a vector. `convert` converts from `rhs`, the type `From` is
+/// implemented for.
+fn builtin_op(name: &Ident, self_ty: &Type, rhs: Option<&TokenStream>) -> Option<TokenStream> {
+    let (constructor, op) = match name.to_string().as_str() {
+        "convert" => {
+            return Some(quote! {
+                rsshader::ir::FnIr::Builtin(rsshader::ir::BuiltinFn::conversion(
+                    &<#rhs as rsshader::reflection::Ty>::IR,
+                    &<#self_ty as rsshader::reflection::Ty>::IR,
+                ))
+            });
+        }
+
+        "add" => (quote! { numeric_op }, quote! { NumericOp::Add }),
+        "sub" => (quote! { numeric_op }, quote! { NumericOp::Sub }),
+        "mul" => (quote! { numeric_op }, quote! { NumericOp::Mul }),
+        "div" => (quote! { numeric_op }, quote! { NumericOp::Div }),
+        "rem" => (quote! { numeric_op }, quote! { NumericOp::Rem }),
+        "shl" => (quote! { int_op }, quote! { IntOp::Shl }),
+        "shr" => (quote! { int_op }, quote! { IntOp::Shr }),
+        "neg" => (
+            quote! { signed_numeric_op },
+            quote! { SignedNumericOp::Neg },
+        ),
+        "not" => (quote! { bitwise_op }, quote! { BitwiseOp::Not }),
+        "bitand" => (quote! { bitwise_op }, quote! { BitwiseOp::BitAnd }),
+        "bitor" => (quote! { bitwise_op }, quote! { BitwiseOp::BitOr }),
+        "bitxor" => (quote! { bitwise_op }, quote! { BitwiseOp::BitXor }),
+        _ => return None,
+    };
+
+    Some(quote! {
+        rsshader::ir::FnIr::Builtin(rsshader::ir::BuiltinFn::#constructor(
+            rsshader::ir::#op,
+            &<#self_ty as rsshader::reflection::Ty>::IR,
+        ))
+    })
+}
+
+/// An inherent function that maps to the builtin named by `#[builtin(..)]`,
+/// which is `new_vec` for the constructors of vector types.
+fn builtin_method(
+    sig: &Signature,
+    builtin: &Ident,
+    owner: &FnOwner,
+    errors: &mut Vec<Error>,
+) -> TokenStream {
+    let self_ty = owner.self_ty.to_token_stream();
+    let (param_types, _) = signature_types(sig, Some(&self_ty));
+
+    let ir = match builtin.to_string().as_str() {
+        "new_vec" => quote! {
+            rsshader::ir::FnIr::Builtin(rsshader::ir::BuiltinFn::vector_new(
+                &<#self_ty as rsshader::reflection::Ty>::IR,
+                &[#(&<#param_types as rsshader::reflection::Ty>::IR),*],
+            ))
+        },
+        _ => {
+            errors.push(Error::new(builtin.span(), "unknown builtin function"));
+            return quote! {};
+        }
+    };
+
+    let marker = marker_ident(sig, owner, None);
+    let shader_fn = ShaderFn::builtin(sig, marker, *owner, ir, errors);
+    let items = shader_fn.items(&parse_quote! { pub });
+    let method = method_impl(sig, &shader_fn, errors);
+
+    quote! {
+        #items
+
+        #method
+    }
+}
+
+/// Removes `#[builtin(name)]` from `attrs` and returns the name.
+fn take_builtin(attrs: &mut Vec<Attribute>, errors: &mut Vec<Error>) -> Option<Ident> {
+    let idx = attrs
+        .iter()
+        .position(|attr| attr.path().is_ident("builtin"))?;
+    let attr = attrs.remove(idx);
+
+    match attr.parse_args::<Ident>() {
+        Ok(name) => Some(name),
+        Err(error) => {
+            errors.push(error);
+            None
+        }
+    }
+}
+
+/// Implements `rsshader::reflection::Method` for an inherent method, so that
+/// it can be called by name.
+fn method_impl(sig: &Signature, shader_fn: &ShaderFn, errors: &mut Vec<Error>) -> TokenStream {
+    let ShaderFn {
+        generics,
+        self_ty,
+        param_types,
+        body,
+        builtin,
+        ..
+    } = shader_fn;
+
+    if body.is_none() && builtin.is_none() {
+        return quote! {};
+    }
+
+    // Methods are selected by their argument types only.
+    if !shader_fn.is_inferable() {
+        errors.push(Error::new(
+            sig.generics.span(),
+            "every generic parameter of a shader method must be used in its parameter types",
+        ));
+
+        return quote! {};
+    }
+
+    let name = sig.ident.to_string();
+    let (impl_generics, _, _) = generics.split_for_impl();
+    let marker_ty = shader_fn.marker_ty();
+    let where_clause = shader_fn.where_clause();
+
+    quote! {
+        impl #impl_generics rsshader::reflection::Method<
+            { rsshader::reflection::name_id(#name) },
+            #marker_ty,
+        > for (#self_ty, (#(#param_types,)*)) #where_clause {
+            type Fn = #marker_ty;
+        }
+    }
+}
+
+/// Methods of every `impl` block in a module share a namespace, so the type and
+/// a hash of it and the trait are part of the name of the marker.
+fn marker_ident(sig: &Signature, owner: &FnOwner, trait_path: Option<&Path>) -> Ident {
+    let type_name = match owner.self_ty {
+        Type::Path(TypePath { path, .. }) => path
+            .segments
+            .last()
+            .map(|segment| segment.ident.to_string())
+            .unwrap_or_default(),
+
+        _ => String::new(),
+    };
+
+    let mut hasher = DefaultHasher::new();
+    owner
+        .self_ty
+        .to_token_stream()
+        .to_string()
+        .hash(&mut hasher);
+    owner
+        .generics
+        .to_token_stream()
+        .to_string()
+        .hash(&mut hasher);
+    trait_path
+        .map(|path| path.to_token_stream().to_string())
+        .hash(&mut hasher);
+
+    format_ident!("__rsshader_{type_name}_{}_{:x}", sig.ident, hasher.finish())
+}
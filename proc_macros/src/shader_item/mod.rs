@@ -3,10 +3,15 @@ use syn::{Item, parse_macro_input};
 
 use crate::shader_item::util::Labels;
 
+mod body;
 mod util;
 
+mod r#const;
+mod r#enum;
 mod r#fn;
+mod r#impl;
 mod r#struct;
+mod r#trait;
 
 pub fn shader_item(
     attr: proc_macro::TokenStream,
@@ -19,10 +24,12 @@ pub fn shader_item(
 
     let item_output = match item {
         Item::Struct(item) => r#struct::shader_item(item, &mut errors, &mut labels),
+        Item::Enum(item) => r#enum::shader_item(item, &mut errors, &mut labels),
         Item::Fn(item) => r#fn::shader_item(item, &mut errors, &mut labels),
-        Item::Const(item) => {
-            quote! { #item compile_error!("constants do not need to be annotated with #[shader_item]"); }
-        }
+        Item::Impl(item) => r#impl::shader_item(item, &mut errors, &mut labels),
+        Item::Trait(item) => r#trait::shader_item(item, &mut errors, &mut labels),
+        Item::Const(item) => r#const::shader_item(item, &mut errors, &mut labels),
+        Item::Static(item) => r#const::shader_item_static(item, &mut errors, &mut labels),
         Item::Use(item) => {
             quote! { #item compile_error!("use statements do not need to be annotated with #[shader_item]"); }
         }
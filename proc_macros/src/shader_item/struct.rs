@@ -2,14 +2,15 @@ use std::mem::take;
 
 use proc_macro2::{Span, TokenStream};
 use quote::{ToTokens, quote};
-use syn::{Error, Ident, Index, ItemStruct, Member, Type};
+use syn::{Error, Ident, ItemStruct, Member, Type, TypeArray, spanned::Spanned};
 
-use crate::shader_item::util::Labels;
+use crate::shader_item::util::{Labels, check_ty};
 
 pub fn shader_item(item: ItemStruct, errors: &mut Vec<Error>, labels: &mut Labels) -> TokenStream {
     let mut syn_item = item;
 
     let mut item = {
+        let members = syn_item.fields.members().collect::<Vec<_>>();
         let (impl_generics, ty_params, where_clause) = syn_item.generics.split_for_impl();
 
         Struct {
@@ -33,15 +34,9 @@ pub fn shader_item(item: ItemStruct, errors: &mut Vec<Error>, labels: &mut Label
             fields: syn_item
                 .fields
                 .iter_mut()
-                .enumerate()
-                .map(|(field_idx, field)| Field {
-                    ident: match field.ident.clone() {
-                        Some(ident) => Member::Named(ident),
-                        None => Member::Unnamed(Index {
-                            index: field_idx as u32,
-                            span: Span::call_site(),
-                        }),
-                    },
+                .zip(members)
+                .map(|(field, member)| Field {
+                    member,
                     ty: field.ty.clone(),
                     labels: Labels::from_attributes(&mut field.attrs, errors),
                 })
@@ -49,6 +44,25 @@ pub fn shader_item(item: ItemStruct, errors: &mut Vec<Error>, labels: &mut Label
         }
     };
 
+    // Every field is checked so that all of them are reported.
+    let mut fields_supported = true;
+    for field in &item.fields {
+        fields_supported &= check_ty(&field.ty, errors);
+    }
+
+    if let Some(label_span) = labels.find("vector") {
+        let vector_impls = vector_impls(&item, label_span, errors);
+
+        for field in take(&mut item.fields) {
+            field.labels.finish(errors);
+        }
+
+        return match fields_supported {
+            true => quote! { #syn_item #vector_impls },
+            false => quote! { #syn_item },
+        };
+    }
+
     let fragment_label = fragment_label(&mut item, errors, labels);
 
     let where_clause = {
@@ -58,7 +72,7 @@ pub fn shader_item(item: ItemStruct, errors: &mut Vec<Error>, labels: &mut Label
             .ty_params
             .iter()
             .map(|param| {
-                quote! { #param: rsshader::reflection::ShaderType }
+                quote! { #param: rsshader::reflection::Ty }
             })
             .collect::<Vec<_>>();
 
@@ -71,36 +85,37 @@ pub fn shader_item(item: ItemStruct, errors: &mut Vec<Error>, labels: &mut Label
 
     let field_irs = item
         .fields
-        .iter_mut()
-        .map(
-            |Field {
-                 ident,
-                 ty,
-                 labels: field_labels,
-             }| {
-                let metadata = if labels.find("fragment").is_some()
-                    && field_labels.find("position").is_some()
-                {
-                    quote! { Some(rsshader::ir::FieldMetadataIr::Position) }
-                } else {
-                    quote! { None }
-                };
-
-                quote! {
-                    rsshader::ir::FieldIr {
-                        ty: &<#ty as rsshader::reflection::ShaderType>::IR,
-                        rust_offset: core::mem::offset_of!(Self, #ident),
-                        metadata: #metadata,
-                    }
-                }
-            },
-        )
+        .iter()
+        .map(|Field { ty, .. }| {
+            quote! { <#ty as rsshader::reflection::Ty>::IR }
+        })
         .collect::<Vec<_>>();
 
+    let (field_members, field_layouts) = item
+        .fields
+        .iter()
+        .map(|Field { member, ty, .. }| {
+            (member, quote! { <#ty as rsshader::reflection::Ty>::LAYOUT })
+        })
+        .unzip::<_, _, Vec<_>, Vec<_>>();
+
+    let layout = quote! {
+        rsshader::reflection::Layout::Struct {
+            offsets: &[#(core::mem::offset_of!(Self, #field_members)),*],
+            fields: &[#(#field_layouts),*],
+        }
+    };
+
+    let field_impls = field_impls(&item, &where_clause);
+
     for field in take(&mut item.fields) {
         field.labels.finish(errors);
     }
 
+    if !fields_supported {
+        return quote! { #syn_item };
+    }
+
     let Struct {
         ident,
         impl_generics,
@@ -111,16 +126,105 @@ pub fn shader_item(item: ItemStruct, errors: &mut Vec<Error>, labels: &mut Label
     quote! {
         #syn_item
 
-        impl #impl_generics rsshader::reflection::ShaderType for #ident #impl_ty_params #where_clause {
-            const IR: rsshader::ir::TypeIr = rsshader::ir::TypeIr::Struct(rsshader::ir::StructIr {
+        impl #impl_generics rsshader::reflection::Ty for #ident #impl_ty_params #where_clause {
+            const IR: rsshader::ir::TypeIr = rsshader::ir::TypeIr::Struct {
                 fields: &[#(#field_irs),*],
-            });
+            };
+
+            const LAYOUT: rsshader::reflection::Layout = #layout;
+        }
+
+        // Whether the fields are attributes is checked once it is used.
+        impl #impl_generics rsshader::reflection::VertexInputTy for #ident #impl_ty_params #where_clause {
+            const IR: rsshader::ir::VertexInputIr =
+                rsshader::ir::VertexInputIr::new(&<Self as rsshader::reflection::Ty>::IR);
         }
 
+        #(#field_impls)*
+
         #fragment_label
     }
 }
 
+fn field_impls(item: &Struct, where_clause: &TokenStream) -> Vec<TokenStream> {
+    let Struct {
+        ident,
+        impl_generics,
+        impl_ty_params,
+        ..
+    } = item;
+
+    item.fields
+        .iter()
+        .enumerate()
+        .map(|(idx, Field { member, ty, .. })| {
+            let name = match member {
+                Member::Named(ident) => ident.to_string(),
+                Member::Unnamed(index) => index.index.to_string(),
+            };
+
+            quote! {
+                impl #impl_generics rsshader::reflection::Field<{ rsshader::reflection::name_id(#name) }>
+                    for #ident #impl_ty_params #where_clause
+                {
+                    type Ty = #ty;
+                    const IDX: usize = #idx;
+                }
+            }
+        })
+        .collect()
+}
+
+/// A `#[shader_item(vector)]` struct is a vector in shaders, and holds the
+/// array of its elements in Rust, such as `struct Vector<const N: usize,
+/// T>([T; N])`.
+fn vector_impls(item: &Struct, label_span: Span, errors: &mut Vec<Error>) -> TokenStream {
+    let Struct {
+        ident,
+        impl_generics,
+        impl_ty_params,
+        ..
+    } = item;
+
+    let [Field { ty, .. }] = &item.fields[..] else {
+        errors.push(Error::new(
+            label_span,
+            "vector structs must have a single field holding an array of the elements",
+        ));
+
+        return quote! {};
+    };
+    let Type::Array(TypeArray { elem, len, .. }) = ty else {
+        errors.push(Error::new(
+            ty.span(),
+            "the field of a vector struct must be an array of the elements",
+        ));
+
+        return quote! {};
+    };
+
+    let original_predicates = &item.where_predicates;
+    let ty_params = &item.ty_params;
+    let where_clause = quote! {
+        where
+            #elem: rsshader::reflection::PrimitiveTy,
+            #(#ty_params: rsshader::reflection::Ty,)*
+            #(#original_predicates,)*
+    };
+
+    quote! {
+        impl #impl_generics rsshader::reflection::Ty for #ident #impl_ty_params #where_clause {
+            const IR: rsshader::ir::TypeIr =
+                rsshader::ir::TypeIr::vector_of_array(&<#ty as rsshader::reflection::Ty>::IR);
+        }
+
+        impl #impl_generics rsshader::reflection::VectorTy<{ #len }, #elem>
+            for #ident #impl_ty_params #where_clause
+        {
+        }
+    }
+}
+
 struct Struct {
     ident: Ident,
     ty_params: Vec<Ident>,
@@ -131,7 +235,7 @@ struct Struct {
 }
 
 struct Field {
-    ident: Member,
+    member: Member,
     ty: Type,
     labels: Labels,
 }
@@ -155,7 +259,7 @@ fn fragment_label(item: &mut Struct, errors: &mut Vec<Error>, labels: &mut Label
             .ty_params
             .iter()
             .map(|param| {
-                quote! { #param: rsshader::reflection::ShaderType }
+                quote! { #param: rsshader::reflection::Ty }
             })
             .collect::<Vec<_>>();
 
@@ -166,14 +270,21 @@ fn fragment_label(item: &mut Struct, errors: &mut Vec<Error>, labels: &mut Label
         }
     };
 
+    // The WGSL backend emits the first attribute of a fragment input as its
+    // `@builtin(position)`, so the position field has to come first.
     let mut position_type = None;
-    for field in &mut item.fields {
+    for (field_idx, field) in item.fields.iter_mut().enumerate() {
         if let Some(position_label) = field.labels.find("position") {
             if position_type.is_some() {
                 errors.push(Error::new(
                     position_label,
                     "found multiple fields marked as #[position]",
                 ));
+            } else if field_idx != 0 {
+                errors.push(Error::new(
+                    position_label,
+                    "the field marked as #[position] must be the first field",
+                ));
             } else {
                 position_type = Some(field.ty.clone());
             }
@@ -182,7 +293,7 @@ fn fragment_label(item: &mut Struct, errors: &mut Vec<Error>, labels: &mut Label
 
     let assert_position_type = if let Some(position_type) = position_type {
         quote! {
-            const fn _assert_position<T: rsshader::reflection::VectorType<4, f32>>() {}
+            const fn _assert_position<T: rsshader::reflection::VectorTy<4, f32>>() {}
 
             _assert_position::<#position_type>();
         }
@@ -196,9 +307,11 @@ fn fragment_label(item: &mut Struct, errors: &mut Vec<Error>, labels: &mut Label
     };
 
     quote! {
-        impl #impl_generics rsshader::reflection::FragmentType for #ident #impl_ty_params #where_clause {
-            const __: () = {
+        impl #impl_generics rsshader::reflection::FragInputTy for #ident #impl_ty_params #where_clause {
+            const IR: rsshader::ir::FragInputIr = {
                 #assert_position_type
+
+                rsshader::ir::FragInputIr(&<Self as rsshader::reflection::Ty>::IR)
             };
         }
     }
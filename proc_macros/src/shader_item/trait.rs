@@ -0,0 +1,141 @@
+use proc_macro2::TokenStream;
+use quote::{ToTokens, format_ident, quote};
+use syn::{
+    Block, Error, Generics, Ident, ItemTrait, Signature, TraitItem, TraitItemConst, TraitItemFn,
+    Type, parse_quote, spanned::Spanned,
+};
+
+use crate::shader_item::{
+    body::Macros,
+    r#fn::{FnOwner, ShaderFn, signature_types},
+    util::Labels,
+};
+
+/// Every method and associated const of a shader trait is called through a
+/// marker that is generic over the implementor. Its IR is read from a hidden
+/// associated const, which the `#[shader_item]` impls of the trait define and
+/// which defaults to the lowered default body if there is one. This way each
+/// impl gets its own `FnIr`, and calls in generic functions are only resolved
+/// once the function is instantiated.
+pub fn shader_item(
+    mut item: ItemTrait,
+    errors: &mut Vec<Error>,
+    _labels: &mut Labels,
+) -> TokenStream {
+    if !item.generics.params.is_empty() {
+        errors.push(Error::new(
+            item.generics.span(),
+            "generic shader traits are not supported yet",
+        ));
+
+        return item.into_token_stream();
+    }
+
+    let macros = Macros::take(&mut item.attrs, errors);
+
+    let ident = &item.ident;
+    let vis = &item.vis;
+
+    let impl_ty: Type = parse_quote! { __Impl };
+    let impl_generics: Generics = parse_quote! { <__Impl: #ident> };
+    let owner = FnOwner {
+        self_ty: &impl_ty,
+        generics: &impl_generics,
+    };
+
+    let mut items = Vec::new();
+    let mut hidden_consts = Vec::<TraitItem>::new();
+
+    for trait_item in &item.items {
+        let (sig, default): (Signature, Option<Block>) = match trait_item {
+            TraitItem::Fn(TraitItemFn { sig, default, .. }) => (sig.clone(), default.clone()),
+
+            TraitItem::Const(TraitItemConst {
+                ident, ty, default, ..
+            }) => (
+                parse_quote! { fn #ident() -> #ty },
+                default.as_ref().map(|(_, expr)| parse_quote! { { #expr } }),
+            ),
+
+            _ => {
+                errors.push(Error::new(
+                    trait_item.span(),
+                    "only methods, associated functions and associated consts are supported in shader traits",
+                ));
+
+                continue;
+            }
+        };
+
+        if !sig.generics.params.is_empty() {
+            errors.push(Error::new(
+                sig.generics.span(),
+                "generic methods are not supported in shader traits yet",
+            ));
+
+            continue;
+        }
+
+        let const_ident = trait_item_const(&sig.ident);
+
+        let default_ir = default.map(|block| {
+            let marker = format_ident!("__rsshader_{ident}_{}_default", sig.ident);
+            let shader_fn =
+                ShaderFn::lower(&sig, &block, marker.clone(), Some(owner), &macros, errors);
+            items.push(shader_fn.items(vis));
+
+            match shader_fn.body {
+                Some(_) => quote! { = <#marker<Self, Self> as rsshader::reflection::Fn>::IR },
+                None => quote! { = unreachable!() },
+            }
+        });
+
+        hidden_consts.push(parse_quote! {
+            #[doc(hidden)]
+            #[allow(non_upper_case_globals)]
+            const #const_ident: rsshader::ir::FnIr #default_ir;
+        });
+
+        let marker = format_ident!("__rsshader_{ident}_{}", sig.ident);
+        let name = sig.ident.to_string();
+        let (param_types, ret_type) = signature_types(&sig, Some(&quote! { __Impl }));
+        let ret_type = ret_type.unwrap_or_else(|| quote! { () });
+
+        items.push(quote! {
+            #[doc(hidden)]
+            #[allow(non_camel_case_types)]
+            #vis struct #marker<__Impl> {
+                _marker: core::marker::PhantomData<fn() -> __Impl>,
+            }
+
+            impl<__Impl: #ident> rsshader::reflection::Fn for #marker<__Impl> {
+                type Output = #ret_type;
+
+                const IR: rsshader::ir::FnIr = <__Impl as #ident>::#const_ident;
+            }
+
+            impl<__Impl: #ident> rsshader::reflection::Method<
+                { rsshader::reflection::name_id(#name) },
+                #marker<__Impl>,
+            > for (__Impl, (#(#param_types,)*)) {
+                type Fn = #marker<__Impl>;
+            }
+        });
+    }
+
+    item.supertraits
+        .push(parse_quote! { rsshader::reflection::Ty });
+    item.colon_token.get_or_insert_default();
+    item.items.extend(hidden_consts);
+
+    quote! {
+        #item
+
+        #(#items)*
+    }
+}
+
+/// The hidden associated const that holds the IR of an item of a shader trait.
+pub fn trait_item_const(ident: &Ident) -> Ident {
+    format_ident!("__rsshader_{ident}")
+}
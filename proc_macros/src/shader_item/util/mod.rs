@@ -1,2 +1,6 @@
 mod labels;
+mod tokens;
+mod ty;
 pub use labels::*;
+pub use tokens::*;
+pub use ty::*;
@@ -0,0 +1,31 @@
+use proc_macro2::{Group, Ident, TokenStream, TokenTree};
+
+/// Returns whether `ident` appears anywhere in `tokens`.
+pub fn mentions(tokens: TokenStream, ident: &Ident) -> bool {
+    tokens.into_iter().any(|token| match token {
+        TokenTree::Ident(token) => token == *ident,
+        TokenTree::Group(group) => mentions(group.stream(), ident),
+        _ => false,
+    })
+}
+
+/// Replaces every `Self` in `tokens` with `self_ty`, for code that is moved out
+/// of the `impl` block it was written in.
+pub fn replace_self(tokens: TokenStream, self_ty: &TokenStream) -> TokenStream {
+    tokens
+        .into_iter()
+        .flat_map(|token| match token {
+            TokenTree::Ident(ident) if ident == "Self" => self_ty.clone(),
+
+            TokenTree::Group(group) => {
+                let mut new_group =
+                    Group::new(group.delimiter(), replace_self(group.stream(), self_ty));
+                new_group.set_span(group.span());
+
+                TokenTree::Group(new_group).into()
+            }
+
+            token => token.into(),
+        })
+        .collect()
+}
@@ -0,0 +1,133 @@
+use proc_macro2::TokenStream;
+use quote::{ToTokens, quote};
+use syn::{
+    Error, GenericArgument, Ident, Member, Path, PathArguments, QSelf, Type, TypeArray, TypeGroup,
+    TypeParen, TypePath, TypeReference, TypeTuple, spanned::Spanned,
+};
+
+/// Splits `&T` or `&mut T` into whether the reference is mutable and `T`.
+pub fn referent(ty: &TokenStream) -> Option<(bool, TokenStream)> {
+    let TypeReference {
+        mutability, elem, ..
+    } = syn::parse2(ty.clone()).ok()?;
+
+    Some((mutability.is_some(), elem.into_token_stream()))
+}
+
+/// The `rsshader::ir::TypeIr` of `ty`. References do not implement
+/// `rsshader::reflection::Ty`: `&T` is passed by value and `&mut T` as a
+/// pointer.
+pub fn ty_ir(ty: &TokenStream) -> TokenStream {
+    match referent(ty) {
+        Some((true, ty)) => quote! {
+            rsshader::ir::TypeIr::Ptr {
+                t: &<#ty as rsshader::reflection::Ty>::IR,
+            }
+        },
+        Some((false, ty)) => quote! { <#ty as rsshader::reflection::Ty>::IR },
+        None => quote! { <#ty as rsshader::reflection::Ty>::IR },
+    }
+}
+
+/// Reports every part of `ty` that shaders cannot represent, suggesting what
+/// to use instead. Returns whether `ty` is supported.
+pub fn check_ty(ty: &Type, errors: &mut Vec<Error>) -> bool {
+    let error_count = errors.len();
+    report_unsupported_ty(ty, errors);
+
+    errors.len() == error_count
+}
+
+fn report_unsupported_ty(ty: &Type, errors: &mut Vec<Error>) {
+    let message = match ty {
+        Type::Array(TypeArray { elem, .. })
+        | Type::Group(TypeGroup { elem, .. })
+        | Type::Paren(TypeParen { elem, .. })
+        | Type::Reference(TypeReference { elem, .. }) => {
+            return report_unsupported_ty(elem, errors);
+        }
+
+        Type::Tuple(TypeTuple { elems, .. }) => {
+            for elem in elems {
+                report_unsupported_ty(elem, errors);
+            }
+
+            return;
+        }
+
+        Type::Path(TypePath { qself, path }) => {
+            if let Some(QSelf { ty, .. }) = qself {
+                report_unsupported_ty(ty, errors);
+            }
+
+            for segment in &path.segments {
+                if let PathArguments::AngleBracketed(args) = &segment.arguments {
+                    for arg in &args.args {
+                        if let GenericArgument::Type(ty) = arg {
+                            report_unsupported_ty(ty, errors);
+                        }
+                    }
+                }
+            }
+
+            if let Some(message) = std_ty_message(path) {
+                errors.push(Error::new(ty.span(), message));
+            }
+
+            return;
+        }
+
+        Type::Slice(_) => {
+            "slices are not supported in shaders, use a fixed-size array `[T; N]` instead"
+        }
+        Type::TraitObject(_) => {
+            "trait objects are not supported in shaders, use a generic parameter instead"
+        }
+        Type::ImplTrait(_) => {
+            "`impl Trait` types are not supported in shaders, use a generic parameter instead"
+        }
+        Type::Ptr(_) => "raw pointers are not supported in shaders, use a reference instead",
+        Type::BareFn(_) => {
+            "function pointers are not supported in shaders, call a shader function directly \
+             instead"
+        }
+
+        _ => return,
+    };
+
+    errors.push(Error::new(ty.span(), message));
+}
+
+/// The error for a standard library type that shaders cannot represent, if
+/// `path` names one. Shaders cannot allocate, so this includes every heap
+/// type.
+pub fn std_ty_message(path: &Path) -> Option<String> {
+    let name = &path.segments.last()?.ident;
+    let first = &path.segments[0].ident;
+
+    if path.segments.len() > 1 && first != "std" && first != "alloc" && first != "core" {
+        return None;
+    }
+
+    let alternative = match name.to_string().as_str() {
+        "Vec" | "VecDeque" | "LinkedList" | "BinaryHeap" => "use a fixed-size array `[T; N]`",
+        "HashMap" | "HashSet" | "BTreeMap" | "BTreeSet" => "use a fixed-size array `[T; N]`",
+        "String" | "str" | "char" => "use integer codes",
+        "Box" | "Rc" | "Arc" | "Cow" => "store the value directly",
+        "Cell" | "RefCell" | "Mutex" | "RwLock" => "use a `let mut` binding",
+        _ => return None,
+    };
+
+    Some(format!(
+        "`{name}` is not supported in shaders, {alternative} instead"
+    ))
+}
+
+/// The name a field of an enum variant is looked up by, like `Some.0`, which
+/// cannot clash with the name of a struct field.
+pub fn variant_field_name(variant: &Ident, member: &Member) -> String {
+    match member {
+        Member::Named(ident) => format!("{variant}.{ident}"),
+        Member::Unnamed(index) => format!("{variant}.{}", index.index),
+    }
+}
@@ -0,0 +1,59 @@
+use rsshader_macros::ConstEq;
+
+use crate::ir::{ExprIr, LinkedShaderIr, Literal, TypeIr};
+
+/// The value of a Rust `const` or `static` item, evaluated by rustc.
+///
+/// `values` are the primitives of the value in the order they appear in `ty`,
+/// so that values of any shape fit in a flat slice.
+#[derive(Debug, Clone, Copy, ConstEq)]
+pub struct ConstIr {
+    pub ty: &'static TypeIr,
+    pub values: &'static [Literal],
+}
+
+impl ConstIr {
+    pub const fn id(&self, shader: &LinkedShaderIr) -> usize {
+        let mut i = 0;
+        loop {
+            if shader.consts[i].eq(self) {
+                break i;
+            }
+
+            i += 1;
+        }
+    }
+
+    /// Primitives are embedded as literals, and other values are declared
+    /// once per shader.
+    pub const fn expr(&'static self) -> ExprIr {
+        match self.ty {
+            TypeIr::Primitive(_) => ExprIr::Literal(self.values[0]),
+            _ => ExprIr::Const(self),
+        }
+    }
+}
+
+impl TypeIr {
+    /// The number of primitives in a value of this type.
+    pub const fn primitive_count(&self) -> usize {
+        match self {
+            TypeIr::Primitive(_) => 1,
+            TypeIr::Vector { n, t: _ } => n.as_usize(),
+
+            TypeIr::Struct { fields } => {
+                let mut sum = 0;
+                let mut i = 0;
+                while i < fields.len() {
+                    sum += fields[i].primitive_count();
+                    i += 1;
+                }
+
+                sum
+            }
+
+            TypeIr::Array { n, t } => *n * t.primitive_count(),
+            TypeIr::Ptr { .. } => panic!("references cannot be constants"),
+        }
+    }
+}
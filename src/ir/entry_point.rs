@@ -2,7 +2,7 @@ use core::marker::PhantomData;
 
 use rsshader_macros::ConstEq;
 
-use crate::ir::{BodyIr, Inner, Iter, LinkedShaderIr, TypeIr};
+use crate::ir::{BodyIr, Inner, Iter, LinkedShaderIr, Primitive, TypeIr};
 
 #[derive(Debug, Clone, Copy, ConstEq)]
 pub enum EntryPointIr {
@@ -41,6 +41,40 @@ impl EntryPointIr {
 }
 
 impl VertexInputIr {
+    /// The vertex input of type `ty`, which must be an attribute or a struct
+    /// of attributes. Attributes are `f32`, `i32` and `u32` scalars and
+    /// vectors.
+    pub const fn new(ty: &'static TypeIr) -> Self {
+        const fn is_attribute(ty: &TypeIr) -> bool {
+            matches!(
+                ty,
+                TypeIr::Primitive(Primitive::F32 | Primitive::I32 | Primitive::U32)
+                    | TypeIr::Vector {
+                        n: _,
+                        t: Primitive::F32 | Primitive::I32 | Primitive::U32,
+                    }
+            )
+        }
+
+        if let TypeIr::Struct { fields } = ty {
+            let mut i = 0;
+            while i < fields.len() {
+                if !is_attribute(&fields[i]) {
+                    panic!(
+                        "the fields of vertex inputs must be `f32`, `i32` or `u32` scalars or \
+                        vectors"
+                    );
+                }
+
+                i += 1;
+            }
+        } else if !is_attribute(ty) {
+            panic!("vertex inputs must be `f32`, `i32` or `u32` scalars or vectors, or structs");
+        }
+
+        Self(ty)
+    }
+
     pub const fn attrs(&self) -> Iter<&'static TypeIr> {
         Iter(Inner::Attributes { ty: self.0, idx: 0 }, PhantomData)
     }
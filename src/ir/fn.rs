@@ -1,7 +1,8 @@
 use rsshader_macros::ConstEq;
 
 use crate::ir::{
-    BitwisePrimitive, Int, Length, LinkedShaderIr, Numeric, Primitive, SignedNumeric, TypeIr,
+    BitwisePrimitive, ConstIr, Int, Length, LinkedShaderIr, Numeric, Primitive, SignedNumeric,
+    TypeIr,
 };
 
 #[derive(Debug, Clone, Copy, ConstEq)]
@@ -33,6 +34,10 @@ pub enum StmtIr {
         id: usize,
         ty: &'static TypeIr,
     },
+    LetDecl {
+        id: usize,
+        value: ExprIr,
+    },
     Assignment {
         left: PlaceIr,
         right: ExprIr,
@@ -40,6 +45,29 @@ pub enum StmtIr {
     Return {
         value: Option<ExprIr>,
     },
+    If {
+        cond: ExprIr,
+        then: BodyIr,
+        otherwise: BodyIr,
+    },
+    Loop {
+        body: BodyIr,
+        continuing: BodyIr,
+        break_if: Option<ExprIr>,
+    },
+    Switch {
+        selector: ExprIr,
+        cases: &'static [SwitchCaseIr],
+        default: BodyIr,
+    },
+    Break,
+    Continue,
+}
+
+#[derive(Debug, Clone, Copy, ConstEq)]
+pub struct SwitchCaseIr {
+    pub selectors: &'static [Literal],
+    pub body: BodyIr,
 }
 
 #[derive(Debug, Clone, Copy, ConstEq)]
@@ -57,13 +85,83 @@ pub enum ExprIr {
         func: &'static FnIr,
         args: &'static [ExprIr],
     },
+    /// Constructs a struct, or a vector from its elements.
+    StructConstructor {
+        ty: &'static TypeIr,
+        fields: &'static [FieldInitIr],
+        /// The value the fields missing from `fields` are copied from, as in
+        /// `S { a, ..base }`. Without a base, missing fields are zero.
+        base: Option<&'static ExprIr>,
+    },
+    StructField {
+        idx: usize,
+        base: &'static ExprIr,
+    },
+    VectorElement {
+        idx: usize,
+        base: &'static ExprIr,
+    },
+    ArrayConstructor {
+        ty: &'static TypeIr,
+        elements: &'static [ExprIr],
+    },
+    /// Indexes into an array or a vector.
+    Index {
+        base: &'static ExprIr,
+        index: &'static ExprIr,
+    },
+    /// A `const` or `static` item that is declared once per shader.
+    Const(&'static ConstIr),
+    /// Reads the value `ptr` points to.
+    Deref {
+        ptr: &'static ExprIr,
+    },
+    /// A pointer to `place`, whose type is `ty`.
+    AddressOf {
+        place: &'static PlaceIr,
+        ty: &'static TypeIr,
+    },
+    /// Short-circuiting `&&`.
+    And {
+        left: &'static ExprIr,
+        right: &'static ExprIr,
+    },
+    /// Short-circuiting `||`.
+    Or {
+        left: &'static ExprIr,
+        right: &'static ExprIr,
+    },
+}
+
+#[derive(Debug, Clone, Copy, ConstEq)]
+pub struct FieldInitIr {
+    pub idx: usize,
+    pub value: ExprIr,
 }
 
 #[derive(Debug, Clone, Copy, ConstEq)]
 pub enum PlaceIr {
-    Variable { id: usize, ty: &'static TypeIr },
-    VectorElement { idx: usize, base: &'static PlaceIr },
-    StructField { idx: usize, base: &'static PlaceIr },
+    Variable {
+        id: usize,
+        ty: &'static TypeIr,
+    },
+    VectorElement {
+        idx: usize,
+        base: &'static PlaceIr,
+    },
+    StructField {
+        idx: usize,
+        base: &'static PlaceIr,
+    },
+    /// Indexes into an array or a vector.
+    Index {
+        base: &'static PlaceIr,
+        index: &'static ExprIr,
+    },
+    /// The value `ptr` points to.
+    Deref {
+        ptr: &'static ExprIr,
+    },
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -85,6 +183,45 @@ impl FnIr {
             i += 1;
         }
     }
+
+    pub const fn param_type(&self, idx: usize) -> TypeIr {
+        match self {
+            Self::UserDefined {
+                param_types,
+                ret_type: _,
+                body: _,
+            } => *param_types[idx],
+
+            Self::Builtin(func) => func.param_type(idx),
+        }
+    }
+}
+
+impl StmtIr {
+    /// `left op= right`, where `func` is the function of the operator.
+    /// Builtins assign the operator applied to `values`, the value of `left`
+    /// and `right`, while user functions are called with `ptr_args`, a pointer
+    /// to `left` and `right`.
+    pub const fn compound_assignment(
+        func: &'static FnIr,
+        left: PlaceIr,
+        values: &'static [ExprIr],
+        ptr_args: &'static [ExprIr],
+    ) -> Self {
+        match func {
+            FnIr::Builtin(_) => Self::Assignment {
+                left,
+                right: ExprIr::Call { func, args: values },
+            },
+            FnIr::UserDefined { .. } => {
+                left.assert_addressable();
+                Self::Call {
+                    func,
+                    args: ptr_args,
+                }
+            }
+        }
+    }
 }
 
 impl ExprIr {
@@ -107,11 +244,182 @@ impl ExprIr {
 
                 FnIr::Builtin(func) => func.ret_type().unwrap(),
             },
+
+            Self::StructConstructor {
+                ty,
+                fields: _,
+                base: _,
+            } => **ty,
+            Self::StructField { idx, base } => field_ty(&base.ty(), *idx),
+            Self::VectorElement { idx, base } => field_ty(&base.ty(), *idx),
+
+            Self::ArrayConstructor { ty, elements: _ } => **ty,
+            Self::Index { base, index: _ } => element_ty(&base.ty()),
+
+            Self::Const(value) => *value.ty,
+
+            Self::Deref { ptr } => pointee_ty(&ptr.ty()),
+            Self::AddressOf { place: _, ty } => TypeIr::Ptr { t: ty },
+
+            Self::And { left: _, right: _ } | Self::Or { left: _, right: _ } => {
+                TypeIr::Primitive(Primitive::Bool)
+            }
+        }
+    }
+
+    /// Accesses field `idx` of `base`, which is either a struct or a vector.
+    pub const fn field(base: &'static ExprIr, idx: usize) -> Self {
+        match base.ty() {
+            TypeIr::Vector { .. } => Self::VectorElement { idx, base },
+            _ => Self::StructField { idx, base },
+        }
+    }
+
+    /// The value of an associated const, which is a function without
+    /// parameters. Consts that return a literal, like enum variants, are
+    /// inlined instead of called.
+    pub const fn assoc_const(func: &'static FnIr) -> Self {
+        if let FnIr::UserDefined {
+            body:
+                BodyIr {
+                    stmts:
+                        [
+                            StmtIr::Return {
+                                value: Some(Self::Literal(value)),
+                            },
+                        ],
+                },
+            ..
+        } = func
+        {
+            return Self::Literal(*value);
         }
+
+        Self::Call { func, args: &[] }
+    }
+
+    /// The tag of an enum value, which tells its variants apart. Fieldless
+    /// enums are their own tag, and other enums are structs whose first field
+    /// is the tag.
+    pub const fn enum_tag(base: &'static ExprIr) -> Self {
+        match base.ty() {
+            TypeIr::Struct { .. } => Self::StructField { idx: 0, base },
+            _ => *base,
+        }
+    }
+
+    /// A pointer to `place`, whose type is `ty`. Vector elements cannot be
+    /// pointed to.
+    pub const fn address_of(place: &'static PlaceIr, ty: &'static TypeIr) -> Self {
+        place.assert_addressable();
+        Self::AddressOf { place, ty }
+    }
+
+    /// The receiver of a method call to `func`, whose value is `value`.
+    /// `&mut self` methods take a pointer to the receiver instead, which must
+    /// be a place of type `ty`.
+    pub const fn receiver(
+        func: &FnIr,
+        value: &'static ExprIr,
+        place: Option<&'static PlaceIr>,
+        ty: &'static TypeIr,
+    ) -> Self {
+        match (func.param_type(0), place) {
+            (TypeIr::Ptr { .. }, Some(place)) => Self::address_of(place, ty),
+            (TypeIr::Ptr { .. }, None) => {
+                panic!("`&mut self` methods can only be called on mutable places in shaders")
+            }
+            _ => *value,
+        }
+    }
+}
+
+impl PlaceIr {
+    /// Panics if the place is a vector element, which cannot be pointed to.
+    pub const fn assert_addressable(&self) {
+        let mut base = self;
+        loop {
+            match base {
+                Self::Variable { .. } | Self::Deref { .. } => return,
+
+                Self::VectorElement { .. } => break,
+                Self::Index { base: array, .. } if matches!(array.ty(), TypeIr::Vector { .. }) => {
+                    break;
+                }
+
+                Self::StructField { base: parent, .. } | Self::Index { base: parent, .. } => {
+                    base = parent;
+                }
+            }
+        }
+
+        panic!(
+            "references to vector elements are not supported in shaders, copy the element into a `let mut` binding and pass a reference to that instead"
+        )
+    }
+
+    pub const fn ty(&self) -> TypeIr {
+        match self {
+            Self::Variable { id: _, ty } => **ty,
+            Self::VectorElement { idx, base } => field_ty(&base.ty(), *idx),
+            Self::StructField { idx, base } => field_ty(&base.ty(), *idx),
+            Self::Index { base, index: _ } => element_ty(&base.ty()),
+            Self::Deref { ptr } => pointee_ty(&ptr.ty()),
+        }
+    }
+
+    /// Accesses field `idx` of `base`, which is either a struct or a vector.
+    pub const fn field(base: &'static PlaceIr, idx: usize) -> Self {
+        match base.ty() {
+            TypeIr::Vector { .. } => Self::VectorElement { idx, base },
+            _ => Self::StructField { idx, base },
+        }
+    }
+}
+
+const fn field_ty(ty: &TypeIr, idx: usize) -> TypeIr {
+    match ty {
+        TypeIr::Struct { fields } => fields[idx],
+        TypeIr::Vector { n: _, t } => TypeIr::Primitive(*t),
+        _ => panic!("field access on a type without fields"),
+    }
+}
+
+const fn pointee_ty(ty: &TypeIr) -> TypeIr {
+    match ty {
+        TypeIr::Ptr { t } => **t,
+        _ => panic!("dereferencing a value that is not a pointer"),
+    }
+}
+
+const fn element_ty(ty: &TypeIr) -> TypeIr {
+    match ty {
+        TypeIr::Array { n: _, t } => **t,
+        TypeIr::Vector { n: _, t } => TypeIr::Primitive(*t),
+        _ => panic!("indexing a type that is neither an array nor a vector"),
     }
 }
 
 impl Literal {
+    /// Creates an integer literal whose type is inferred from its context.
+    pub const fn int(ty: &TypeIr, value: i128) -> Self {
+        match ty {
+            TypeIr::Primitive(Primitive::I32) => Self::I32(value as i32),
+            TypeIr::Primitive(Primitive::U32) => Self::U32(value as u32),
+            _ => panic!("integer literal inferred to a non-integer type"),
+        }
+    }
+
+    /// The number one of a numeric type, which products start from.
+    pub const fn one(ty: &TypeIr) -> Self {
+        match ty {
+            TypeIr::Primitive(Primitive::F32) => Self::F32(1.0),
+            TypeIr::Primitive(Primitive::I32) => Self::I32(1),
+            TypeIr::Primitive(Primitive::U32) => Self::U32(1),
+            _ => panic!("only numbers can be multiplied together"),
+        }
+    }
+
     pub const fn eq(&self, other: &Self) -> bool {
         match (self, other) {
             (Literal::F32(a), Literal::F32(b)) => a.to_bits() == b.to_bits(),
@@ -149,6 +457,18 @@ pub enum BuiltinFn {
         op: BitwiseOp,
         ty: BitwisePrimitive,
     },
+    ScalarWrappingOp {
+        op: WrappingOp,
+        ty: Int,
+    },
+    ScalarSaturatingOp {
+        op: SaturatingOp,
+        ty: Int,
+    },
+    ScalarEuclidOp {
+        op: EuclidOp,
+        ty: Numeric,
+    },
 
     VectorConstructor {
         op: VectorConstructor,
@@ -158,6 +478,10 @@ pub enum BuiltinFn {
         n: Length,
         t: Primitive,
     },
+    VectorFromArray {
+        n: Length,
+        t: Primitive,
+    },
     VectorElement {
         i: usize,
         n: Length,
@@ -190,6 +514,18 @@ pub enum BuiltinFn {
         t: BitwisePrimitive,
     },
 
+    /// Converts between primitives like Rust's `as`. Floats are converted to
+    /// integers by rounding toward zero and saturating, with NaN becoming `0`.
+    ScalarConversion {
+        from: Primitive,
+        to: Primitive,
+    },
+    VectorizedConversion {
+        n: Length,
+        from: Primitive,
+        to: Primitive,
+    },
+
     VectorPrimitiveOp {
         op: VectorPrimitiveOp,
         n: Length,
@@ -235,6 +571,38 @@ pub enum BitwiseOp {
     BitXor,
 }
 
+/// The `wrapping_*` methods of Rust integers. Division by zero, which panics
+/// in Rust, returns the dividend for `Div` and `0` for `Rem`.
+#[derive(Debug, Clone, Copy, ConstEq)]
+pub enum WrappingOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+    Neg,
+    Shl,
+    Shr,
+}
+
+/// The `saturating_*` methods of Rust integers.
+#[derive(Debug, Clone, Copy, ConstEq)]
+pub enum SaturatingOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+}
+
+/// The `div_euclid` and `rem_euclid` methods of Rust numbers, where overflow
+/// and division by zero behave like [`WrappingOp::Div`] and
+/// [`WrappingOp::Rem`].
+#[derive(Debug, Clone, Copy, ConstEq)]
+pub enum EuclidOp {
+    DivEuclid,
+    RemEuclid,
+}
+
 #[derive(Debug, Clone, Copy, ConstEq)]
 pub enum VectorConstructor {
     Vec2From11,
@@ -257,19 +625,217 @@ pub enum VectorPrimitiveOp {
 }
 
 impl BuiltinFn {
-    const fn ret_type(&self) -> Option<TypeIr> {
+    /// The scalar or vectorized form of `op` for operands of type `ty`.
+    pub const fn numeric_op(op: NumericOp, ty: &TypeIr) -> Self {
+        match *ty {
+            TypeIr::Primitive(t) => Self::ScalarNumericOp {
+                op,
+                ty: Numeric::from_primitive(t),
+            },
+            TypeIr::Vector { n, t } => Self::VectorizedNumericOp {
+                op,
+                n,
+                t: Numeric::from_primitive(t),
+            },
+            _ => panic!("only primitives and vectors have builtin operators"),
+        }
+    }
+
+    /// The scalar or vectorized form of `op` for operands of type `ty`.
+    pub const fn int_op(op: IntOp, ty: &TypeIr) -> Self {
+        match *ty {
+            TypeIr::Primitive(t) => Self::ScalarIntOp {
+                op,
+                ty: Int::from_primitive(t),
+            },
+            TypeIr::Vector { n, t } => Self::VectorizedIntOp {
+                op,
+                n,
+                t: Int::from_primitive(t),
+            },
+            _ => panic!("only primitives and vectors have builtin operators"),
+        }
+    }
+
+    /// The scalar or vectorized form of `op` for operands of type `ty`.
+    pub const fn signed_numeric_op(op: SignedNumericOp, ty: &TypeIr) -> Self {
+        match *ty {
+            TypeIr::Primitive(t) => Self::ScalarSignedNumericOp {
+                op,
+                ty: SignedNumeric::from_primitive(t),
+            },
+            TypeIr::Vector { n, t } => Self::VectorizedSignedNumericOp {
+                op,
+                n,
+                t: SignedNumeric::from_primitive(t),
+            },
+            _ => panic!("only primitives and vectors have builtin operators"),
+        }
+    }
+
+    /// The scalar or vectorized form of `op` for operands of type `ty`.
+    pub const fn bitwise_op(op: BitwiseOp, ty: &TypeIr) -> Self {
+        match *ty {
+            TypeIr::Primitive(t) => Self::ScalarBitwiseOp {
+                op,
+                ty: BitwisePrimitive::from_primitive(t),
+            },
+            TypeIr::Vector { n, t } => Self::VectorizedBitwiseOp {
+                op,
+                n,
+                t: BitwisePrimitive::from_primitive(t),
+            },
+            _ => panic!("only primitives and vectors have builtin operators"),
+        }
+    }
+
+    /// The scalar or vectorized conversion from `from` to `to`, which must
+    /// both be primitives or vectors of the same length.
+    pub const fn conversion(from: &TypeIr, to: &TypeIr) -> Self {
+        match (*from, *to) {
+            (TypeIr::Primitive(from), TypeIr::Primitive(to)) => Self::ScalarConversion { from, to },
+            (TypeIr::Vector { n, t: from }, TypeIr::Vector { n: to_n, t: to })
+                if n as usize == to_n as usize =>
+            {
+                Self::VectorizedConversion { n, from, to }
+            }
+            _ => panic!("only primitives and vectors of the same length can be converted"),
+        }
+    }
+
+    /// The constructor of the vector type `ty` from arguments of the types
+    /// `params`, for `#[builtin(new_vec)]`. A single element is splatted, an
+    /// array is unpacked, and scalars and shorter vectors are concatenated.
+    pub const fn vector_new(ty: &TypeIr, params: &[&TypeIr]) -> Self {
+        let TypeIr::Vector { n, t } = *ty else {
+            panic!("`#[builtin(new_vec)]` can only construct vectors");
+        };
+
+        if let [TypeIr::Array { n: len, t: elem }] = params
+            && *len == n.as_usize()
+            && elem.eq(&TypeIr::Primitive(t))
+        {
+            return Self::VectorFromArray { n, t };
+        }
+
+        let mut lens = [0; 4];
+        let mut i = 0;
+        while i < params.len() {
+            lens[i] = match *params[i] {
+                TypeIr::Primitive(p) if p.eq(&t) => 1,
+                TypeIr::Vector { n: len, t: p } if p.eq(&t) => len.as_usize(),
+                _ => panic!(
+                    "`#[builtin(new_vec)]` takes an array, or scalars and vectors of the \
+                    element type"
+                ),
+            };
+
+            i += 1;
+        }
+
+        let op = match (n, &lens) {
+            (_, [1, 0, 0, 0]) => return Self::VectorSplat { n, t },
+            (Length::Two, [1, 1, 0, 0]) => VectorConstructor::Vec2From11,
+            (Length::Three, [1, 1, 1, 0]) => VectorConstructor::Vec3From111,
+            (Length::Three, [1, 2, 0, 0]) => VectorConstructor::Vec3From12,
+            (Length::Three, [2, 1, 0, 0]) => VectorConstructor::Vec3From21,
+            (Length::Four, [1, 1, 1, 1]) => VectorConstructor::Vec4From1111,
+            (Length::Four, [1, 1, 2, 0]) => VectorConstructor::Vec4From112,
+            (Length::Four, [1, 2, 1, 0]) => VectorConstructor::Vec4From121,
+            (Length::Four, [1, 3, 0, 0]) => VectorConstructor::Vec4From13,
+            (Length::Four, [2, 1, 1, 0]) => VectorConstructor::Vec4From211,
+            (Length::Four, [2, 2, 0, 0]) => VectorConstructor::Vec4From22,
+            (Length::Four, [3, 1, 0, 0]) => VectorConstructor::Vec4From31,
+            _ => {
+                panic!("the arguments of `#[builtin(new_vec)]` do not add up to the vector length")
+            }
+        };
+
+        Self::VectorConstructor { op, t }
+    }
+
+    /// Shift amounts of operators can be either `i32` or `u32` in Rust, and
+    /// are reported as `u32`.
+    pub const fn param_type(&self, idx: usize) -> TypeIr {
+        match self {
+            Self::ScalarPrimitiveOp { op: _, ty } => TypeIr::Primitive(*ty),
+            Self::ScalarNumericOp { op: _, ty } => TypeIr::Primitive(ty.as_primitive()),
+            Self::ScalarIntOp { op: _, ty: _ } if idx == 1 => TypeIr::Primitive(Primitive::U32),
+            Self::ScalarIntOp { op: _, ty } => TypeIr::Primitive(ty.as_primitive()),
+            Self::ScalarSignedNumericOp { op: _, ty } => TypeIr::Primitive(ty.as_primitive()),
+            Self::ScalarBitwiseOp { op: _, ty } => TypeIr::Primitive(ty.as_primitive()),
+            Self::ScalarWrappingOp {
+                op: WrappingOp::Shl | WrappingOp::Shr,
+                ty: _,
+            } if idx == 1 => TypeIr::Primitive(Primitive::U32),
+            Self::ScalarWrappingOp { op: _, ty } => TypeIr::Primitive(ty.as_primitive()),
+            Self::ScalarSaturatingOp { op: _, ty } => TypeIr::Primitive(ty.as_primitive()),
+            Self::ScalarEuclidOp { op: _, ty } => TypeIr::Primitive(ty.as_primitive()),
+
+            Self::VectorConstructor { op, t } => match op.param_len(idx) {
+                1 => TypeIr::Primitive(*t),
+                2 => TypeIr::Vector {
+                    n: Length::Two,
+                    t: *t,
+                },
+                _ => TypeIr::Vector {
+                    n: Length::Three,
+                    t: *t,
+                },
+            },
+            Self::VectorSplat { n: _, t } => TypeIr::Primitive(*t),
+            Self::VectorFromArray { n, t } => TypeIr::Array {
+                n: n.as_usize(),
+                t: t.ir(),
+            },
+            Self::VectorElement { i: _, n, t } => TypeIr::Vector { n: *n, t: *t },
+
+            Self::VectorizedPrimitiveOp { op: _, n, t } => TypeIr::Vector { n: *n, t: *t },
+            Self::VectorizedNumericOp { op: _, n, t } => TypeIr::Vector {
+                n: *n,
+                t: t.as_primitive(),
+            },
+            Self::VectorizedIntOp { op: _, n, t: _ } if idx == 1 => TypeIr::Vector {
+                n: *n,
+                t: Primitive::U32,
+            },
+            Self::VectorizedIntOp { op: _, n, t } => TypeIr::Vector {
+                n: *n,
+                t: t.as_primitive(),
+            },
+            Self::VectorizedSignedNumericOp { op: _, n, t } => TypeIr::Vector {
+                n: *n,
+                t: t.as_primitive(),
+            },
+            Self::VectorizedBitwiseOp { op: _, n, t } => TypeIr::Vector {
+                n: *n,
+                t: t.as_primitive(),
+            },
+
+            Self::ScalarConversion { from, to: _ } => TypeIr::Primitive(*from),
+            Self::VectorizedConversion { n, from, to: _ } => TypeIr::Vector { n: *n, t: *from },
+
+            Self::VectorPrimitiveOp { op: _, n, t } => TypeIr::Vector { n: *n, t: *t },
+        }
+    }
+
+    pub const fn ret_type(&self) -> Option<TypeIr> {
         match self {
             Self::ScalarPrimitiveOp { op, ty } => Some(TypeIr::Primitive(op.ret_type(*ty))),
             Self::ScalarNumericOp { op, ty } => Some(TypeIr::Primitive(op.ret_type(*ty))),
             Self::ScalarIntOp { op, ty } => Some(TypeIr::Primitive(op.ret_type(*ty))),
             Self::ScalarSignedNumericOp { op, ty } => Some(TypeIr::Primitive(op.ret_type(*ty))),
             Self::ScalarBitwiseOp { op, ty } => Some(TypeIr::Primitive(op.ret_type(*ty))),
+            Self::ScalarWrappingOp { op: _, ty } => Some(TypeIr::Primitive(ty.as_primitive())),
+            Self::ScalarSaturatingOp { op: _, ty } => Some(TypeIr::Primitive(ty.as_primitive())),
+            Self::ScalarEuclidOp { op: _, ty } => Some(TypeIr::Primitive(ty.as_primitive())),
 
             Self::VectorConstructor { op, t } => Some(TypeIr::Vector {
                 n: op.ret_len(),
                 t: *t,
             }),
             Self::VectorSplat { n, t } => Some(TypeIr::Vector { n: *n, t: *t }),
+            Self::VectorFromArray { n, t } => Some(TypeIr::Vector { n: *n, t: *t }),
             Self::VectorElement { i: _, n: _, t } => Some(TypeIr::Primitive(*t)),
 
             Self::VectorizedPrimitiveOp { op, n, t } => Some(TypeIr::Vector {
@@ -293,6 +859,9 @@ impl BuiltinFn {
                 t: op.ret_type(*t),
             }),
 
+            Self::ScalarConversion { from: _, to } => Some(TypeIr::Primitive(*to)),
+            Self::VectorizedConversion { n, from: _, to } => Some(TypeIr::Vector { n: *n, t: *to }),
+
             Self::VectorPrimitiveOp { op, n, t } => Some(op.ret_type(*n, *t)),
         }
     }
@@ -340,7 +909,26 @@ impl BitwiseOp {
 }
 
 impl VectorConstructor {
-    const fn ret_len(&self) -> Length {
+    /// The length of parameter `idx`, where `1` is a scalar.
+    pub const fn param_len(&self, idx: usize) -> usize {
+        let lens: &[usize] = match self {
+            Self::Vec2From11 => &[1, 1],
+            Self::Vec3From111 => &[1, 1, 1],
+            Self::Vec3From12 => &[1, 2],
+            Self::Vec3From21 => &[2, 1],
+            Self::Vec4From1111 => &[1, 1, 1, 1],
+            Self::Vec4From112 => &[1, 1, 2],
+            Self::Vec4From121 => &[1, 2, 1],
+            Self::Vec4From13 => &[1, 3],
+            Self::Vec4From211 => &[2, 1, 1],
+            Self::Vec4From22 => &[2, 2],
+            Self::Vec4From31 => &[3, 1],
+        };
+
+        lens[idx]
+    }
+
+    pub const fn ret_len(&self) -> Length {
         match self {
             Self::Vec2From11 => Length::Two,
 
@@ -10,15 +10,23 @@ pub(in crate::ir) enum Inner {
     Attributes { ty: &'static TypeIr, idx: usize },
 }
 
-impl Iter<TypeIr> {
-    pub const fn next(&mut self) -> Option<&TypeIr> {
+impl Iter<&'static TypeIr> {
+    pub const fn next(&mut self) -> Option<&'static TypeIr> {
         match &mut self.0 {
             Inner::Attributes { ty, idx } => {
-                const fn peek(ty: &TypeIr, idx: usize) -> Option<&TypeIr> {
+                const fn peek(ty: &'static TypeIr, idx: usize) -> Option<&'static TypeIr> {
                     const fn count(ty: &TypeIr) -> usize {
                         match ty {
                             TypeIr::Primitive(_) | TypeIr::Vector { .. } => 1,
 
+                            TypeIr::Array { .. } => {
+                                panic!("arrays cannot be used as shader attributes")
+                            }
+
+                            TypeIr::Ptr { .. } => {
+                                panic!("references cannot be used as shader attributes")
+                            }
+
                             TypeIr::Struct { fields } => {
                                 let mut sum = 0;
                                 let mut i = 0;
@@ -41,6 +49,14 @@ impl Iter<TypeIr> {
                             }
                         }
 
+                        TypeIr::Array { .. } => {
+                            panic!("arrays cannot be used as shader attributes")
+                        }
+
+                        TypeIr::Ptr { .. } => {
+                            panic!("references cannot be used as shader attributes")
+                        }
+
                         TypeIr::Struct { fields } => {
                             let mut field_idx = 0;
                             let mut idx_in_field = idx;
@@ -48,7 +64,9 @@ impl Iter<TypeIr> {
                                 if field_idx < fields.len() {
                                     let field_attr_count = count(&fields[field_idx]);
                                     if idx_in_field < field_attr_count {
-                                        break Some(peek(&fields[field_idx], idx_in_field).unwrap());
+                                        break Some(
+                                            peek(&fields[field_idx], idx_in_field).unwrap(),
+                                        );
                                     } else {
                                         field_idx += 1;
                                         idx_in_field -= field_attr_count;
@@ -1,8 +1,10 @@
+mod r#const;
 mod entry_point;
 mod r#fn;
 mod iter;
 mod shader;
 mod ty;
+pub use r#const::*;
 pub use entry_point::*;
 pub use r#fn::*;
 pub use iter::*;
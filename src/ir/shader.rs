@@ -1,10 +1,25 @@
 use rsshader_macros::ConstEq;
 
-use crate::ir::{EntryPointIr, FnIr, FragInputIr, FragOutputIr, TypeIr, VertexInputIr};
+use crate::ir::{ConstIr, EntryPointIr, FnIr, FragInputIr, FragOutputIr, TypeIr, VertexInputIr};
 
 #[derive(Debug, Clone, Copy, ConstEq)]
 pub struct ShaderIr {
     pub entry_points: &'static [&'static EntryPointIr],
+    pub arithmetic: Arithmetic,
+}
+
+/// How integer operators behave where the target language and Rust disagree,
+/// or where the target language leaves the result unspecified.
+#[derive(Debug, Clone, Copy, ConstEq)]
+pub enum Arithmetic {
+    /// Operators map directly to those of the target language.
+    Native,
+    /// Operators behave like in Rust with overflow checks disabled: shift
+    /// amounts are masked to the bit width, and integer division behaves like
+    /// [`WrappingOp::Div`](crate::ir::WrappingOp::Div) and
+    /// [`WrappingOp::Rem`](crate::ir::WrappingOp::Rem), since the shader
+    /// cannot panic.
+    Rust,
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -15,6 +30,8 @@ pub struct LinkedShaderIr {
     pub types: &'static [&'static TypeIr],
     pub entry_points: &'static [&'static EntryPointIr],
     pub fns: &'static [&'static FnIr],
+    pub consts: &'static [&'static ConstIr],
+    pub arithmetic: Arithmetic,
 }
 
 #[doc(hidden)]
@@ -22,8 +39,8 @@ pub mod linker {
     use core::mem::MaybeUninit;
 
     use crate::ir::{
-        BodyIr, BuiltinFn, EntryPointIr, ExprIr, FnIr, FragInputIr, FragOutputIr, LinkedShaderIr,
-        Literal, PlaceIr, Primitive, ShaderIr, StmtIr, TypeIr, VertexInputIr,
+        Arithmetic, BodyIr, ConstIr, EntryPointIr, ExprIr, FnIr, FragInputIr, FragOutputIr, Iter,
+        LinkedShaderIr, Literal, PlaceIr, Primitive, ShaderIr, StmtIr, TypeIr, VertexInputIr,
     };
 
     #[derive(Debug, Clone, Copy)]
@@ -34,13 +51,16 @@ pub mod linker {
         const TY_CAP: usize,
         const ENTRY_POINT_CAP: usize,
         const FN_CAP: usize,
+        const CONST_CAP: usize,
     > {
         vertex_inputs: LinkerVec<&'static VertexInputIr, VERTEX_INPUT_CAP>,
         frag_inputs: LinkerVec<&'static FragInputIr, FRAG_INPUT_CAP>,
         frag_outputs: LinkerVec<&'static FragOutputIr, FRAG_OUTPUT_CAP>,
-        types: LinkerVec<TypeIr, TY_CAP>,
+        types: LinkerVec<&'static TypeIr, TY_CAP>,
         entry_points: LinkerVec<&'static EntryPointIr, ENTRY_POINT_CAP>,
         fns: LinkerVec<&'static FnIr, FN_CAP>,
+        consts: LinkerVec<&'static ConstIr, CONST_CAP>,
+        arithmetic: Arithmetic,
     }
 
     #[derive(Debug, Clone, Copy)]
@@ -56,7 +76,17 @@ pub mod linker {
         const TY_CAP: usize,
         const ENTRY_POINT_CAP: usize,
         const FN_CAP: usize,
-    > Linker<VERTEX_INPUT_CAP, FRAG_INPUT_CAP, FRAG_OUTPUT_CAP, TY_CAP, ENTRY_POINT_CAP, FN_CAP>
+        const CONST_CAP: usize,
+    >
+        Linker<
+            VERTEX_INPUT_CAP,
+            FRAG_INPUT_CAP,
+            FRAG_OUTPUT_CAP,
+            TY_CAP,
+            ENTRY_POINT_CAP,
+            FN_CAP,
+            CONST_CAP,
+        >
     {
         pub const fn new(shader: &'static ShaderIr) -> Self {
             let mut output = Self {
@@ -66,6 +96,8 @@ pub mod linker {
                 types: LinkerVec::new(),
                 entry_points: LinkerVec::new(),
                 fns: LinkerVec::new(),
+                consts: LinkerVec::new(),
+                arithmetic: shader.arithmetic,
             };
 
             let mut i = 0;
@@ -86,6 +118,8 @@ pub mod linker {
                 types: self.types.view(),
                 entry_points: self.entry_points.view(),
                 fns: self.fns.view(),
+                consts: self.consts.view(),
+                arithmetic: self.arithmetic,
             }
         }
 
@@ -101,6 +135,9 @@ pub mod linker {
                     self.vertex_inputs.link(input);
                     self.frag_inputs.link(output);
 
+                    self.link_ty(input.0);
+                    self.link_ty(output.0);
+
                     self.link_attr_iter(input.attrs());
                     self.link_attr_iter(output.attrs());
                     self.link_body(body);
@@ -113,6 +150,9 @@ pub mod linker {
                     self.frag_inputs.link(input);
                     self.frag_outputs.link(output);
 
+                    self.link_ty(input.0);
+                    self.link_ty(output.0);
+
                     self.link_attr_iter(input.attrs());
                     self.link_attr_iter(output.attrs());
                     self.link_body(body);
@@ -120,7 +160,7 @@ pub mod linker {
             }
         }
 
-        const fn link_attr_iter(&mut self, mut iter: AttrIter) {
+        const fn link_attr_iter(&mut self, mut iter: Iter<&'static TypeIr>) {
             while let Some(ty) = iter.next() {
                 self.link_ty(ty);
             }
@@ -132,60 +172,108 @@ pub mod linker {
             match ty {
                 TypeIr::Primitive(_) => {}
 
-                TypeIr::Vector(ty) => match ty.primitive {
+                TypeIr::Vector { n: _, t } => match t {
                     Primitive::F32 => self.link_ty(&TypeIr::Primitive(Primitive::F32)),
                     Primitive::I32 => self.link_ty(&TypeIr::Primitive(Primitive::I32)),
                     Primitive::U32 => self.link_ty(&TypeIr::Primitive(Primitive::U32)),
                     Primitive::Bool => self.link_ty(&TypeIr::Primitive(Primitive::Bool)),
                 },
 
-                TypeIr::Struct(ty) => {
+                TypeIr::Struct { fields } => {
                     let mut i = 0;
-                    while i < ty.fields.len() {
-                        self.link_ty(ty.fields[i]);
+                    while i < fields.len() {
+                        self.link_ty(&fields[i]);
                         i += 1;
                     }
                 }
+
+                TypeIr::Array { n: _, t } => self.link_ty(t),
+                TypeIr::Ptr { t } => self.link_ty(t),
             }
         }
 
-        const fn link_body(&mut self, body: &BodyIr) {
-            let BodyIr {
-                stmts,
-                expr_bank: _,
-                stmt_bank: _,
-            } = body;
+        const fn link_body(&mut self, body: &'static BodyIr) {
+            let BodyIr { stmts } = body;
 
             let mut i = 0;
             while i < stmts.len() {
-                self.link_stmt(stmts[i].access(&body.stmt_bank), body);
+                self.link_stmt(&stmts[i]);
 
                 i += 1;
             }
         }
 
-        const fn link_stmt(&mut self, stmt: &StmtIr, body: &BodyIr) {
+        const fn link_stmt(&mut self, stmt: &'static StmtIr) {
             match stmt {
-                StmtIr::VariableDecl { var } => {
-                    let VariableIr { id: _, ty } = var;
+                StmtIr::Call { func, args } => {
+                    self.link_fn(func);
+                    self.link_exprs(args);
+                }
 
+                StmtIr::VariableDecl { id: _, ty } => {
                     self.link_ty(ty);
                 }
 
+                StmtIr::LetDecl { id: _, value } => {
+                    self.link_expr(value);
+                }
+
                 StmtIr::Assignment { left, right } => {
-                    self.link_place(left, body);
-                    self.link_expr(right, body);
+                    self.link_place(left);
+                    self.link_expr(right);
                 }
 
                 StmtIr::Return { value: Some(value) } => {
-                    self.link_expr(value, body);
+                    self.link_expr(value);
                 }
 
                 StmtIr::Return { value: None } => {}
+
+                StmtIr::If {
+                    cond,
+                    then,
+                    otherwise,
+                } => {
+                    self.link_expr(cond);
+                    self.link_body(then);
+                    self.link_body(otherwise);
+                }
+
+                StmtIr::Loop {
+                    body,
+                    continuing,
+                    break_if,
+                } => {
+                    self.link_body(body);
+                    self.link_body(continuing);
+
+                    if let Some(break_if) = break_if {
+                        self.link_expr(break_if);
+                    }
+                }
+
+                StmtIr::Switch {
+                    selector,
+                    cases,
+                    default,
+                } => {
+                    self.link_expr(selector);
+
+                    let mut i = 0;
+                    while i < cases.len() {
+                        self.link_body(&cases[i].body);
+
+                        i += 1;
+                    }
+
+                    self.link_body(default);
+                }
+
+                StmtIr::Break | StmtIr::Continue => {}
             }
         }
 
-        const fn link_expr(&mut self, expr: &ExprIr, body: &BodyIr) {
+        const fn link_expr(&mut self, expr: &'static ExprIr) {
             match expr {
                 ExprIr::Literal(Literal::F32(_)) => {
                     self.link_ty(&TypeIr::Primitive(Primitive::F32))
@@ -200,119 +288,131 @@ pub mod linker {
                     self.link_ty(&TypeIr::Primitive(Primitive::Bool))
                 }
 
-                ExprIr::Variable(var) => {
-                    let VariableIr { id: _, ty } = var;
+                ExprIr::Param { idx: _, ty } => {
+                    self.link_ty(ty);
+                }
 
+                ExprIr::Variable { id: _, ty } => {
                     self.link_ty(ty);
                 }
 
                 ExprIr::Call { func, args } => {
                     self.link_fn(func);
+                    self.link_exprs(args);
+                }
+
+                ExprIr::StructConstructor { ty, fields, base } => {
+                    self.link_ty(ty);
 
                     let mut i = 0;
-                    while i < args.len() {
-                        self.link_expr(args[i].access(&body.expr_bank), body);
+                    while i < fields.len() {
+                        self.link_expr(&fields[i].value);
 
                         i += 1;
                     }
+
+                    if let Some(base) = base {
+                        self.link_expr(base);
+                    }
+                }
+
+                ExprIr::StructField { idx: _, base } | ExprIr::VectorElement { idx: _, base } => {
+                    self.link_expr(base);
+                }
+
+                ExprIr::ArrayConstructor { ty, elements } => {
+                    self.link_ty(ty);
+                    self.link_exprs(elements);
+                }
+
+                ExprIr::Index { base, index } => {
+                    self.link_expr(base);
+                    self.link_expr(index);
+                }
+
+                ExprIr::Const(value) => {
+                    self.consts.link(value);
+                    self.link_ty(value.ty);
+                }
+
+                ExprIr::Deref { ptr } => {
+                    self.link_expr(ptr);
+                }
+
+                ExprIr::AddressOf { place, ty } => {
+                    self.link_place(place);
+                    self.link_ty(ty);
+                }
+
+                ExprIr::And { left, right } | ExprIr::Or { left, right } => {
+                    self.link_expr(left);
+                    self.link_expr(right);
                 }
             }
         }
 
-        const fn link_place(&mut self, place: &PlaceIr, _body: &BodyIr) {
-            match place {
-                PlaceIr::Variable(var) => {
-                    let VariableIr { id: _, ty } = var;
+        const fn link_exprs(&mut self, exprs: &'static [ExprIr]) {
+            let mut i = 0;
+            while i < exprs.len() {
+                self.link_expr(&exprs[i]);
 
+                i += 1;
+            }
+        }
+
+        const fn link_place(&mut self, place: &'static PlaceIr) {
+            match place {
+                PlaceIr::Variable { id: _, ty } => {
                     self.link_ty(ty);
                 }
+
+                PlaceIr::VectorElement { idx: _, base } => {
+                    self.link_place(base);
+                }
+
+                PlaceIr::StructField { idx: _, base } => {
+                    self.link_place(base);
+                }
+
+                PlaceIr::Index { base, index } => {
+                    self.link_place(base);
+                    self.link_expr(index);
+                }
+
+                PlaceIr::Deref { ptr } => {
+                    self.link_expr(ptr);
+                }
             }
         }
 
         const fn link_fn(&mut self, func: &'static FnIr) {
-            self.fns.link(func);
-
             match func {
                 FnIr::UserDefined {
-                    param_types: params,
-                    ret_type: ret_ty,
+                    param_types,
+                    ret_type,
                     body,
                 } => {
-                    let mut i = 0;
-                    while i < params.len() {
-                        let VariableIr { id: _, ty } = &params[i];
+                    self.fns.link(func);
 
-                        self.link_ty(ty);
+                    let mut i = 0;
+                    while i < param_types.len() {
+                        self.link_ty(param_types[i]);
 
                         i += 1;
                     }
 
-                    if let Some(ret_ty) = ret_ty {
-                        self.link_ty(ret_ty);
+                    if let Some(ret_type) = ret_type {
+                        self.link_ty(ret_type);
                     }
 
                     self.link_body(body);
                 }
 
-                FnIr::Builtin(BuiltinFn::ScalarNeg { ty }) => {
-                    self.link_ty(ty.as_primitive().as_type())
-                }
-                FnIr::Builtin(BuiltinFn::VectorNeg { n, t }) => self.link_ty(&Type),
-
-                FnIr::Builtin(BuiltinFn::Not { ty }) => self.link_ty(ty),
-
-                FnIr::Builtin(BuiltinFn::Add { left, right }) => {
-                    self.link_ty(left);
-                    self.link_ty(right);
-                }
-                FnIr::Builtin(BuiltinFn::Sub { left, right }) => {
-                    self.link_ty(left);
-                    self.link_ty(right);
-                }
-                FnIr::Builtin(BuiltinFn::Mul { left, right }) => {
-                    self.link_ty(left);
-                    self.link_ty(right);
-                }
-                FnIr::Builtin(BuiltinFn::Div { left, right }) => {
-                    self.link_ty(left);
-                    self.link_ty(right);
-                }
-                FnIr::Builtin(BuiltinFn::Rem { left, right }) => {
-                    self.link_ty(left);
-                    self.link_ty(right);
-                }
-                FnIr::Builtin(BuiltinFn::Shl { left, right }) => {
-                    self.link_ty(left);
-                    self.link_ty(right);
-                }
-                FnIr::Builtin(BuiltinFn::Shr { left, right }) => {
-                    self.link_ty(left);
-                    self.link_ty(right);
-                }
-                FnIr::Builtin(BuiltinFn::BitAnd { left, right }) => {
-                    self.link_ty(left);
-                    self.link_ty(right);
-                }
-                FnIr::Builtin(BuiltinFn::BitOr { left, right }) => {
-                    self.link_ty(left);
-                    self.link_ty(right);
-                }
-                FnIr::Builtin(BuiltinFn::BitXor { left, right }) => {
-                    self.link_ty(left);
-                    self.link_ty(right);
-                }
-
-                FnIr::Builtin(BuiltinFn::Eq { ty }) => self.link_ty(ty),
-                FnIr::Builtin(BuiltinFn::Ne { ty }) => self.link_ty(ty),
-                FnIr::Builtin(BuiltinFn::Lt { ty }) => self.link_ty(ty),
-                FnIr::Builtin(BuiltinFn::Le { ty }) => self.link_ty(ty),
-                FnIr::Builtin(BuiltinFn::Ge { ty }) => self.link_ty(ty),
-
-                FnIr::Builtin(BuiltinFn::And) => self.link_ty(&TypeIr::Primitive(Primitive::Bool)),
-                FnIr::Builtin(BuiltinFn::Or) => self.link_ty(&TypeIr::Primitive(Primitive::Bool)),
-
-                FnIr::Builtin(BuiltinFn::StructConstructor { ty }) => {
-                    self.link_ty(ty);
+                // builtins only ever operate on primitives, vectors and
+                // arrays of primitives, which need no declaration. They are linked so that formatters can
+                // emit helper functions for them.
+                FnIr::Builtin(_) => {
+                    self.fns.link(func);
                 }
             }
         }
@@ -376,7 +476,7 @@ pub mod linker {
 
             mod _mod3 {
                 use super::*;
-                type T = TypeIr;
+                type T = &'static TypeIr;
                 $impl
             }
 
@@ -391,79 +491,14 @@ pub mod linker {
                 type T = &'static FnIr;
                 $impl
             }
-        };
-    }
 
-    use for_linked_types;
-}
-
-pub mod linker2 {
-
-    pub struct Linker<const CAP: usize> {}
-}
-
-mod buffer {
-    use core::{marker::PhantomData, mem::MaybeUninit};
-
-    #[derive(Clone, Copy)]
-    pub struct Buffer<const CAP: usize> {
-        bytes: MaybeUninit<[u8; CAP]>,
-        next_idx: usize,
-    }
-
-    #[derive(Clone, Copy)]
-    pub struct BufferPtr<T: Copy> {
-        idx: usize,
-        _danny: PhantomData<T>,
-    }
-
-    impl<const CAP: usize> Buffer<CAP> {
-        pub const fn new() -> Self {
-            Self {
-                bytes: MaybeUninit::uninit(),
-                next_idx: 0,
-            }
-        }
-
-        pub const fn alloc<T: Copy>(&mut self, value: T) -> BufferPtr<T> {
-            if self.next_idx + core::mem::size_of::<T>() > CAP {
-                panic!("allocation exceeded capacity");
-            }
-
-            let idx = self.next_idx;
-            self.next_idx += core::mem::size_of::<T>();
-
-            // SAFETY: the pointer is guaranteed to be in bounds
-            let ptr =
-                unsafe { &mut *(self.bytes.as_mut_ptr().byte_add(idx) as *mut MaybeUninit<T>) };
-
-            ptr.write(value);
-
-            BufferPtr {
-                idx,
-                _danny: PhantomData,
+            mod _mod6 {
+                use super::*;
+                type T = &'static ConstIr;
+                $impl
             }
-        }
+        };
     }
 
-    impl<T: Copy> BufferPtr<T> {
-        pub const unsafe fn access<'buf, const CAP: usize>(
-            &self,
-            buffer: &'buf Buffer<CAP>,
-        ) -> &'buf T {
-            // SAFETY:
-            // the pointer is guaranteed to be in bounds and initialized as long as
-            // buffer is the original buffer.
-            unsafe {
-                buffer
-                    .bytes
-                    .as_ptr()
-                    .byte_add(self.idx)
-                    .cast::<MaybeUninit<T>>()
-                    .as_ref()
-                    .unwrap_unchecked()
-                    .assume_init_ref()
-            }
-        }
-    }
+    use for_linked_types;
 }
@@ -7,6 +7,8 @@ pub enum TypeIr {
     Primitive(Primitive),
     Vector { n: Length, t: Primitive },
     Struct { fields: &'static [TypeIr] },
+    Array { n: usize, t: &'static TypeIr },
+    Ptr { t: &'static TypeIr },
 }
 
 impl TypeIr {
@@ -20,6 +22,30 @@ impl TypeIr {
             i += 1;
         }
     }
+
+    /// The vector type of `#[shader_item(vector)]` structs, which hold an
+    /// array `ty` of its elements.
+    pub const fn vector_of_array(ty: &TypeIr) -> Self {
+        match *ty {
+            Self::Array {
+                n,
+                t: Self::Primitive(t),
+            } => Self::Vector {
+                n: Length::from_usize(n),
+                t: *t,
+            },
+            _ => panic!("vectors must hold an array of `f32`, `i32`, `u32` or `bool`"),
+        }
+    }
+
+    /// The number of elements of an array type, which iterators over it
+    /// loop over.
+    pub const fn array_len(&self) -> usize {
+        match self {
+            Self::Array { n, t: _ } => *n,
+            _ => panic!("only arrays can be iterated over in shaders"),
+        }
+    }
 }
 
 ////////////////////////////////////////////////////////////////////////////////
@@ -75,6 +101,19 @@ pub enum BitwisePrimitive {
     Bool,
 }
 
+impl Primitive {
+    /// The type of the primitive, as a `'static` reference for the types
+    /// that refer to it.
+    pub const fn ir(self) -> &'static TypeIr {
+        match self {
+            Self::F32 => &TypeIr::Primitive(Self::F32),
+            Self::I32 => &TypeIr::Primitive(Self::I32),
+            Self::U32 => &TypeIr::Primitive(Self::U32),
+            Self::Bool => &TypeIr::Primitive(Self::Bool),
+        }
+    }
+}
+
 impl Numeric {
     pub const fn as_primitive(self) -> Primitive {
         match self {
@@ -83,6 +122,15 @@ impl Numeric {
             Self::U32 => Primitive::U32,
         }
     }
+
+    pub const fn from_primitive(primitive: Primitive) -> Self {
+        match primitive {
+            Primitive::F32 => Self::F32,
+            Primitive::I32 => Self::I32,
+            Primitive::U32 => Self::U32,
+            Primitive::Bool => panic!("`bool` is not a numeric type"),
+        }
+    }
 }
 
 impl Int {
@@ -92,6 +140,14 @@ impl Int {
             Self::U32 => Primitive::U32,
         }
     }
+
+    pub const fn from_primitive(primitive: Primitive) -> Self {
+        match primitive {
+            Primitive::I32 => Self::I32,
+            Primitive::U32 => Self::U32,
+            Primitive::F32 | Primitive::Bool => panic!("only integers can be shifted"),
+        }
+    }
 }
 
 impl Sint {
@@ -125,6 +181,14 @@ impl SignedNumeric {
             Self::I32 => Primitive::I32,
         }
     }
+
+    pub const fn from_primitive(primitive: Primitive) -> Self {
+        match primitive {
+            Primitive::F32 => Self::F32,
+            Primitive::I32 => Self::I32,
+            Primitive::U32 | Primitive::Bool => panic!("only `f32` and `i32` can be negated"),
+        }
+    }
 }
 
 impl BitwisePrimitive {
@@ -135,6 +199,15 @@ impl BitwisePrimitive {
             Self::U32 => Primitive::U32,
         }
     }
+
+    pub const fn from_primitive(primitive: Primitive) -> Self {
+        match primitive {
+            Primitive::Bool => Self::Bool,
+            Primitive::I32 => Self::I32,
+            Primitive::U32 => Self::U32,
+            Primitive::F32 => panic!("`f32` is not a bitwise type"),
+        }
+    }
 }
 
 ////////////////////////////////////////////////////////////////////////////////
@@ -147,3 +220,22 @@ pub enum Length {
     Three,
     Four,
 }
+
+impl Length {
+    pub const fn from_usize(n: usize) -> Self {
+        match n {
+            2 => Self::Two,
+            3 => Self::Three,
+            4 => Self::Four,
+            _ => panic!("vectors must have 2, 3 or 4 elements"),
+        }
+    }
+
+    pub const fn as_usize(self) -> usize {
+        match self {
+            Self::Two => 2,
+            Self::Three => 3,
+            Self::Four => 4,
+        }
+    }
+}
@@ -6,7 +6,9 @@ pub mod wgsl;
 
 #[macro_export]
 macro_rules! shader {
-    ($($entry_points:path),* $(,)? => $fmt_fn:path) => {
-        $crate::rsshader_macros::shader!($($entry_points),* => $fmt_fn)
+    ($($entry_points:path),* $(,)? $(; arithmetic = $arithmetic:ident)? => $fmt_fn:path) => {
+        $crate::rsshader_macros::shader!(
+            $($entry_points),* $(; arithmetic = $arithmetic)? => $fmt_fn
+        )
     };
 }
@@ -1,15 +1,20 @@
 use crate::{
     ir::{
-        BodyIr, BuiltinFn, EntryPointIr, ExprIr, FnIr, FragInputIr, FragOutputIr, Length, LinkedShaderIr, Literal, PlaceIr, Primitive,
-        StmtIr, StructIr, TypeIr, VariableIr, VectorIr, VertexInputIr,
+        Arithmetic, BitwiseOp, BitwisePrimitive, BodyIr, BuiltinFn, ConstIr, EntryPointIr,
+        EuclidOp, ExprIr, FieldInitIr, FnIr, FragInputIr, FragOutputIr, Int, IntOp, Length,
+        LinkedShaderIr, Literal, Numeric, NumericOp, PlaceIr, Primitive, PrimitiveOp, SaturatingOp,
+        SignedNumericOp, StmtIr, SwitchCaseIr, TypeIr, VectorPrimitiveOp, VertexInputIr,
+        WrappingOp,
     },
     lang::Formatter,
 };
 
 #[macro_export]
 macro_rules! wgsl {
-    ($($entry_point:path),* $(,)?) => {
-        $crate::shader!($($entry_point),* => $crate::lang::wgsl::fmt)
+    ($($entry_point:path),* $(,)? $(; arithmetic = $arithmetic:ident)?) => {
+        $crate::shader!(
+            $($entry_point),* $(; arithmetic = $arithmetic)? => $crate::lang::wgsl::fmt
+        )
     };
 }
 
@@ -29,11 +34,16 @@ pub const fn fmt(f: &mut Formatter, shader: &LinkedShaderIr) {
     fmt_all!(fmt_frag_input => shader.frag_inputs);
     fmt_all!(fmt_frag_output => shader.frag_outputs);
     fmt_all!(fmt_ty => shader.types);
+    fmt_all!(fmt_const => shader.consts);
     fmt_all!(fmt_entry_point => shader.entry_points);
     fmt_all!(fmt_fn => shader.fns);
 }
 
-const fn fmt_vertex_input(f: &mut Formatter, vertex_input: &VertexInputIr, shader: &LinkedShaderIr) {
+const fn fmt_vertex_input(
+    f: &mut Formatter,
+    vertex_input: &VertexInputIr,
+    shader: &LinkedShaderIr,
+) {
     f.write_str("struct vertex_input");
     f.write_i128(vertex_input.id(shader) as i128);
     f.write_str(" {\n");
@@ -44,11 +54,11 @@ const fn fmt_vertex_input(f: &mut Formatter, vertex_input: &VertexInputIr, shade
         f.write_str("\t@location(");
         f.write_i128(attr_idx as i128);
         f.write_str(")");
-        f.write_str("\tattr");
+        f.write_str(" attr");
         f.write_i128(attr_idx as i128);
         f.write_str(": ");
-        fmt_ty(f, attr_ty, shader);
-        f.write_str(";\n");
+        fmt_type_name(f, attr_ty, shader);
+        f.write_str(",\n");
 
         attr_idx += 1;
     }
@@ -68,15 +78,25 @@ const fn fmt_frag_input(f: &mut Formatter, frag_input: &FragInputIr, shader: &Li
             f.write_str("\t@builtin(position)");
         } else {
             f.write_str("\t@location(");
-            f.write_i128(attr_idx as i128);
+            f.write_i128(attr_idx as i128 - 1);
             f.write_str(")");
+
+            // integer attributes cannot be interpolated.
+            if let TypeIr::Primitive(Primitive::I32 | Primitive::U32)
+            | TypeIr::Vector {
+                n: _,
+                t: Primitive::I32 | Primitive::U32,
+            } = attr_ty
+            {
+                f.write_str(" @interpolate(flat)");
+            }
         }
 
-        f.write_str("\tattr");
+        f.write_str(" attr");
         f.write_i128(attr_idx as i128);
-        f.write_str(":");
-        fmt_ty(f, attr_ty, shader);
-        f.write_str(";\n");
+        f.write_str(": ");
+        fmt_type_name(f, attr_ty, shader);
+        f.write_str(",\n");
 
         attr_idx += 1;
     }
@@ -95,11 +115,11 @@ const fn fmt_frag_output(f: &mut Formatter, frag_output: &FragOutputIr, shader:
         f.write_str("\t@location(");
         f.write_i128(attr_idx as i128);
         f.write_str(")");
-        f.write_str("\tattr");
+        f.write_str(" attr");
         f.write_i128(attr_idx as i128);
-        f.write_str(":");
-        fmt_ty(f, attr_ty, shader);
-        f.write_str(";\n");
+        f.write_str(": ");
+        fmt_type_name(f, attr_ty, shader);
+        f.write_str(",\n");
 
         attr_idx += 1;
     }
@@ -107,16 +127,14 @@ const fn fmt_frag_output(f: &mut Formatter, frag_output: &FragOutputIr, shader:
     f.write_str("}\n\n");
 }
 
-const fn fmt_ty(f: &mut Formatter, ty: &'static TypeIr, shader: &LinkedShaderIr) {
+const fn fmt_ty(f: &mut Formatter, ty: &TypeIr, shader: &LinkedShaderIr) {
     match ty {
-        TypeIr::Primitive(Primitive::F32 | Primitive::I32 | Primitive::U32 | Primitive::Bool) => {}
+        TypeIr::Primitive(_)
+        | TypeIr::Vector { .. }
+        | TypeIr::Array { .. }
+        | TypeIr::Ptr { .. } => {}
 
-        TypeIr::Vector(VectorIr {
-            primitive: Primitive::F32 | Primitive::I32 | Primitive::U32 | Primitive::Bool,
-            length: Length::Two | Length::Three | Length::Four,
-        }) => {}
-
-        TypeIr::Struct(StructIr { fields }) => {
+        TypeIr::Struct { fields } => {
             f.write_str("struct type");
             f.write_i128(ty.id(shader) as i128);
             f.write_str(" {\n");
@@ -126,7 +144,6 @@ const fn fmt_ty(f: &mut Formatter, ty: &'static TypeIr, shader: &LinkedShaderIr)
                 f.write_str("\tfield");
                 f.write_i128(field_idx as i128);
                 f.write_str(": ");
-
                 fmt_type_name(f, &fields[field_idx], shader);
                 f.write_str(",\n");
 
@@ -138,45 +155,282 @@ const fn fmt_ty(f: &mut Formatter, ty: &'static TypeIr, shader: &LinkedShaderIr)
     }
 }
 
-const fn fmt_entry_point(f: &mut Formatter, entry_point: &'static EntryPointIr, shader: &LinkedShaderIr) {
-    match entry_point {
-        EntryPointIr::Vertex { input, output, body } => {
-            f.write_str("@vertex\n");
-            f.write_str("fn entry_point");
-            f.write_i128(entry_point.id(shader) as i128);
-            f.write_str("(vertex: vertex_input");
-            f.write_i128(input.id(shader) as i128);
-            f.write_str(") -> frag_input");
-            f.write_i128(output.id(shader) as i128);
-            f.write_str(" {\n");
+const fn fmt_const(f: &mut Formatter, value: &ConstIr, shader: &LinkedShaderIr) {
+    f.write_str("const const");
+    f.write_i128(value.id(shader) as i128);
+    f.write_str(": ");
+    fmt_type_name(f, value.ty, shader);
+    f.write_str(" = ");
+    fmt_value(f, value.ty, value.values, &mut 0, shader);
+    f.write_str(";\n\n");
+}
+
+/// Writes the value of type `ty` whose primitives start at `values[*idx]`, and
+/// advances `idx` past them.
+const fn fmt_value(
+    f: &mut Formatter,
+    ty: &TypeIr,
+    values: &[Literal],
+    idx: &mut usize,
+    shader: &LinkedShaderIr,
+) {
+    let len = match ty {
+        TypeIr::Primitive(_) => {
+            fmt_const_literal(f, &values[*idx], shader);
+            *idx += 1;
 
-            fmt_body(f, body, shader);
+            return;
+        }
 
-            f.write_str("}\n\n");
+        TypeIr::Vector { n, t: _ } => n.as_usize(),
+        TypeIr::Struct { fields } => fields.len(),
+        TypeIr::Array { n, t: _ } => *n,
+        TypeIr::Ptr { .. } => panic!("references cannot be constants"),
+    };
+
+    fmt_type_name(f, ty, shader);
+    f.write_str("(");
+
+    let mut i = 0;
+    while i < len {
+        if i > 0 {
+            f.write_str(", ");
         }
 
-        EntryPointIr::Frag { input, output, body } => {
-            f.write_str("@fragment\n");
-            f.write_str("fn entry_point");
-            f.write_i128(entry_point.id(shader) as i128);
-            f.write_str("(frag: frag_input");
-            f.write_i128(input.id(shader) as i128);
-            f.write_str(") -> frag_output");
-            f.write_i128(output.id(shader) as i128);
-            f.write_str(" {\n");
+        let element_ty = match ty {
+            TypeIr::Vector { n: _, t } => TypeIr::Primitive(*t),
+            TypeIr::Struct { fields } => fields[i],
+            TypeIr::Array { n: _, t } => **t,
+            TypeIr::Primitive(_) | TypeIr::Ptr { .. } => unreachable!(),
+        };
 
-            fmt_body(f, body, shader);
+        fmt_value(f, &element_ty, values, idx, shader);
 
-            f.write_str("}\n\n");
+        i += 1;
+    }
+
+    f.write_str(")");
+}
+
+/// Module-scope constants must be const-expressions, which `bitcast` is not in
+/// every implementation, so numbers are written as literals.
+const fn fmt_const_literal(f: &mut Formatter, value: &Literal, shader: &LinkedShaderIr) {
+    match value {
+        Literal::F32(value) => {
+            let bits = value.to_bits();
+            let exponent = ((bits >> 23) & 0xFF) as i128;
+            let mantissa = bits & 0x7FFFFF;
+
+            if bits >> 31 == 1 {
+                f.write_str("-");
+            }
+
+            // Hexadecimal floats represent every finite value exactly.
+            match exponent {
+                0xFF => panic!("constants cannot contain infinite or NaN floats"),
+                0 => f.write_str("0x0."),
+                _ => f.write_str("0x1."),
+            }
+
+            f.write_u32_hex(mantissa << 9);
+            f.write_str("p");
+            f.write_i128(if exponent == 0 { -126 } else { exponent - 127 });
+            f.write_str("f");
+        }
+
+        Literal::I32(i32::MIN) => f.write_str("(-2147483647i - 1i)"),
+        Literal::I32(value) => {
+            f.write_i128(*value as i128);
+            f.write_str("i");
+        }
+
+        Literal::U32(_) | Literal::Bool(_) => fmt_expr(f, &ExprIr::Literal(*value), shader),
+    }
+}
+
+const fn fmt_entry_point(f: &mut Formatter, entry_point: &EntryPointIr, shader: &LinkedShaderIr) {
+    let id = entry_point.id(shader);
+
+    let (stage, input_name, input_id, input_ty, output_name, output_id, output_ty, body) =
+        match entry_point {
+            EntryPointIr::Vertex {
+                input,
+                output,
+                body,
+            } => (
+                "@vertex",
+                "vertex_input",
+                input.id(shader),
+                input.0,
+                "frag_input",
+                output.id(shader),
+                output.0,
+                body,
+            ),
+
+            EntryPointIr::Frag {
+                input,
+                output,
+                body,
+            } => (
+                "@fragment",
+                "frag_input",
+                input.id(shader),
+                input.0,
+                "frag_output",
+                output.id(shader),
+                output.0,
+                body,
+            ),
+        };
+
+    // The user-facing body operates on the user's types, while the stage
+    // inputs and outputs are flattened into attributes. The body is emitted as
+    // its own function and the entry point converts between the two.
+    f.write_str("fn entry_point");
+    f.write_i128(id as i128);
+    f.write_str("_body(param0: ");
+    fmt_type_name(f, input_ty, shader);
+    f.write_str(") -> ");
+    fmt_type_name(f, output_ty, shader);
+    f.write_str(" {\n");
+
+    fmt_body(f, body, 1, shader);
+
+    f.write_str("}\n\n");
+
+    f.write_str(stage);
+    f.write_str("\nfn entry_point");
+    f.write_i128(id as i128);
+    f.write_str("(input: ");
+    f.write_str(input_name);
+    f.write_i128(input_id as i128);
+    f.write_str(") -> ");
+    f.write_str(output_name);
+    f.write_i128(output_id as i128);
+    f.write_str(" {\n");
+
+    f.write_str("\tlet output = entry_point");
+    f.write_i128(id as i128);
+    f.write_str("_body(");
+    fmt_attrs_constructor(f, input_ty, &mut 0, shader);
+    f.write_str(");\n");
+
+    f.write_str("\treturn ");
+    f.write_str(output_name);
+    f.write_i128(output_id as i128);
+    f.write_str("(");
+    fmt_attrs_destructor(f, output_ty, &AttrPath::Root, &mut 0);
+    f.write_str(");\n");
+
+    f.write_str("}\n\n");
+}
+
+/// Writes an expression that rebuilds `ty` from the attributes of `input`,
+/// starting at attribute `attr_idx`.
+const fn fmt_attrs_constructor(
+    f: &mut Formatter,
+    ty: &TypeIr,
+    attr_idx: &mut usize,
+    shader: &LinkedShaderIr,
+) {
+    match ty {
+        TypeIr::Primitive(_) | TypeIr::Vector { .. } => {
+            f.write_str("input.attr");
+            f.write_i128(*attr_idx as i128);
+
+            *attr_idx += 1;
+        }
+
+        TypeIr::Struct { fields } => {
+            fmt_type_name(f, ty, shader);
+            f.write_str("(");
+
+            let mut field_idx = 0;
+            while field_idx < fields.len() {
+                if field_idx > 0 {
+                    f.write_str(", ");
+                }
+
+                fmt_attrs_constructor(f, &fields[field_idx], attr_idx, shader);
+
+                field_idx += 1;
+            }
+
+            f.write_str(")");
+        }
+
+        TypeIr::Array { .. } => panic!("arrays cannot be used as shader attributes"),
+        TypeIr::Ptr { .. } => panic!("references cannot be used as shader attributes"),
+    }
+}
+
+#[derive(Clone, Copy)]
+enum AttrPath<'a> {
+    Root,
+    Field {
+        idx: usize,
+        parent: &'a AttrPath<'a>,
+    },
+}
+
+/// Writes the comma separated attributes of `output`, which is of type `ty`.
+const fn fmt_attrs_destructor(
+    f: &mut Formatter,
+    ty: &TypeIr,
+    path: &AttrPath,
+    attr_idx: &mut usize,
+) {
+    match ty {
+        TypeIr::Primitive(_) | TypeIr::Vector { .. } => {
+            if *attr_idx > 0 {
+                f.write_str(", ");
+            }
+
+            fmt_attr_path(f, path);
+
+            *attr_idx += 1;
+        }
+
+        TypeIr::Struct { fields } => {
+            let mut field_idx = 0;
+            while field_idx < fields.len() {
+                fmt_attrs_destructor(
+                    f,
+                    &fields[field_idx],
+                    &AttrPath::Field {
+                        idx: field_idx,
+                        parent: path,
+                    },
+                    attr_idx,
+                );
+
+                field_idx += 1;
+            }
+        }
+
+        TypeIr::Array { .. } => panic!("arrays cannot be used as shader attributes"),
+        TypeIr::Ptr { .. } => panic!("references cannot be used as shader attributes"),
+    }
+}
+
+const fn fmt_attr_path(f: &mut Formatter, path: &AttrPath) {
+    match path {
+        AttrPath::Root => f.write_str("output"),
+
+        AttrPath::Field { idx, parent } => {
+            fmt_attr_path(f, parent);
+            f.write_str(".field");
+            f.write_i128(*idx as i128);
         }
     }
 }
 
-const fn fmt_fn(f: &mut Formatter, func: &'static FnIr, shader: &LinkedShaderIr) {
+const fn fmt_fn(f: &mut Formatter, func: &FnIr, shader: &LinkedShaderIr) {
     match func {
         FnIr::UserDefined {
-            param_types: params,
-            ret_type: ret_ty,
+            param_types,
+            ret_type,
             body,
         } => {
             f.write_str("fn fn");
@@ -184,114 +438,153 @@ const fn fmt_fn(f: &mut Formatter, func: &'static FnIr, shader: &LinkedShaderIr)
             f.write_str("(");
 
             let mut param_idx = 0;
-            while param_idx < params.len() {
+            while param_idx < param_types.len() {
                 if param_idx > 0 {
                     f.write_str(", ");
                 }
 
-                f.write_str("var");
-                f.write_i128(params[param_idx].id as i128);
+                f.write_str("param");
+                f.write_i128(param_idx as i128);
                 f.write_str(": ");
-                fmt_type_name(f, &params[param_idx].ty, shader);
+                fmt_type_name(f, param_types[param_idx], shader);
 
                 param_idx += 1;
             }
 
             f.write_str(")");
 
-            if let Some(ret_ty) = ret_ty {
+            if let Some(ret_type) = ret_type {
                 f.write_str(" -> ");
-                fmt_type_name(f, ret_ty, shader);
+                fmt_type_name(f, ret_type, shader);
             }
 
             f.write_str(" {\n");
 
-            fmt_body(f, body, shader);
+            fmt_body(f, body, 1, shader);
 
             f.write_str("}\n\n");
         }
 
-        FnIr::Builtin(BuiltinFn::Neg { ty }) => match ty {
-            TypeIr::Primitive(Primitive::F32 | Primitive::I32 | Primitive::U32) => {}
-            TypeIr::Vector(VectorIr {
-                length: Length::Two | Length::Three | Length::Four,
-                primitive: Primitive::F32 | Primitive::I32 | Primitive::U32,
-            }) => {}
+        FnIr::Builtin(builtin) => {
+            let Some(template) = builtin_template(builtin, shader) else {
+                return;
+            };
 
-            TypeIr::Struct(_) => panic!("struct neg not supported"),
-            TypeIr::Primitive(Primitive::Bool)
-            | TypeIr::Vector(VectorIr {
-                length: _,
-                primitive: Primitive::Bool,
-            }) => panic!("bool neg not supported"),
-        },
+            if !is_helper(template) {
+                return;
+            }
 
-        FnIr::Builtin(BuiltinFn::Not { ty }) => match ty {
-            TypeIr::Primitive(Primitive::Bool | Primitive::I32 | Primitive::U32) => {}
-            TypeIr::Vector(VectorIr {
-                length: Length::Two | Length::Three | Length::Four,
-                primitive: Primitive::Bool | Primitive::I32 | Primitive::U32,
-            }) => {}
-
-            TypeIr::Struct(_) => panic!("struct neg not supported"),
-            TypeIr::Primitive(Primitive::Bool)
-            | TypeIr::Vector(VectorIr {
-                length: _,
-                primitive: Primitive::Bool,
-            }) => panic!("bool neg not supported"),
-        },
+            f.write_str("fn fn");
+            f.write_i128(func.id(shader) as i128);
+            f.write_str("(");
+
+            let arity = template_arity(template);
+            let mut param_idx = 0;
+            while param_idx < arity {
+                if param_idx > 0 {
+                    f.write_str(", ");
+                }
+
+                f.write_str("param");
+                f.write_i128(param_idx as i128);
+                f.write_str(": ");
+                fmt_type_name(f, &builtin.param_type(param_idx), shader);
+
+                param_idx += 1;
+            }
+
+            f.write_str(") -> ");
+            fmt_type_name(f, &builtin.ret_type().unwrap(), shader);
+            f.write_str(" {\n");
+            fmt_tabs(f, 1);
+            f.write_str("return ");
+            fmt_template(f, template, builtin, None, shader);
+            f.write_str(";\n}\n\n");
+        }
     }
 }
 
-const fn fmt_type_name(f: &mut Formatter, ty: &'static TypeIr, shader: &LinkedShaderIr) {
+const fn fmt_type_name(f: &mut Formatter, ty: &TypeIr, shader: &LinkedShaderIr) {
     match ty {
-        TypeIr::Primitive(Primitive::F32) => f.write_str("f32"),
-        TypeIr::Primitive(Primitive::I32) => f.write_str("i32"),
-        TypeIr::Primitive(Primitive::U32) => f.write_str("u32"),
-        TypeIr::Primitive(Primitive::Bool) => f.write_str("bool"),
+        TypeIr::Primitive(t) => fmt_primitive_name(f, t),
 
-        TypeIr::Vector(VectorIr { length, primitive }) => {
+        TypeIr::Vector { n, t } => {
             f.write_str("vec");
 
-            match length {
+            match n {
                 Length::Two => f.write_str("2"),
                 Length::Three => f.write_str("3"),
                 Length::Four => f.write_str("4"),
             }
 
-            match primitive {
-                Primitive::F32 => f.write_str("f"),
-                Primitive::I32 => f.write_str("i"),
-                Primitive::U32 => f.write_str("u"),
-                Primitive::Bool => f.write_str("b"),
-            }
+            f.write_str("<");
+            fmt_primitive_name(f, t);
+            f.write_str(">");
         }
 
-        TypeIr::Struct(_) | TypeIr::VertexAttributes(_) | TypeIr::FragmentAttributes(_) | TypeIr::RenderOutputAttributes(_) => {
+        TypeIr::Struct { .. } => {
             f.write_str("type");
-            f.write_i128(shader.type_id(ty) as i128);
+            f.write_i128(ty.id(shader) as i128);
+        }
+
+        TypeIr::Array { n, t } => {
+            f.write_str("array<");
+            fmt_type_name(f, t, shader);
+            f.write_str(", ");
+            f.write_i128(*n as i128);
+            f.write_str(">");
+        }
+
+        TypeIr::Ptr { t } => {
+            f.write_str("ptr<function, ");
+            fmt_type_name(f, t, shader);
+            f.write_str(">");
         }
     }
 }
 
-const fn fmt_body(f: &mut Formatter, body: &'static BodyIr, shader: &LinkedShaderIr) {}
+const fn fmt_primitive_name(f: &mut Formatter, t: &Primitive) {
+    match t {
+        Primitive::F32 => f.write_str("f32"),
+        Primitive::I32 => f.write_str("i32"),
+        Primitive::U32 => f.write_str("u32"),
+        Primitive::Bool => f.write_str("bool"),
+    }
+}
 
-const fn fmt_stmt(
-    f: &mut Formatter,
-    stmt: &'static StmtIr,
-    expr_bank: &'static [ExprIr],
-    stmt_bank: &'static [StmtIr],
-    tab_lvl: usize,
-    shader: &'static LinkedShaderIr,
-) {
+const fn fmt_body(f: &mut Formatter, body: &BodyIr, tab_lvl: usize, shader: &LinkedShaderIr) {
+    let mut i = 0;
+    while i < body.stmts.len() {
+        fmt_stmt(f, &body.stmts[i], tab_lvl, shader);
+
+        i += 1;
+    }
+}
+
+const fn fmt_tabs(f: &mut Formatter, tab_lvl: usize) {
     let mut i = 0;
     while i < tab_lvl {
         f.write_str("\t");
         i += 1;
     }
+}
+
+const fn fmt_stmt(f: &mut Formatter, stmt: &StmtIr, tab_lvl: usize, shader: &LinkedShaderIr) {
+    fmt_tabs(f, tab_lvl);
 
     match stmt {
-        StmtIr::VariableDecl(VariableIr { id, ty }) => {
+        StmtIr::Call { func, args } => {
+            // WGSL only allows user functions to be called as statements, so
+            // builtin calls are discarded through a phony assignment.
+            if let FnIr::Builtin(_) = func {
+                f.write_str("_ = ");
+            }
+
+            fmt_call(f, func, args, shader);
+            f.write_str(";\n");
+        }
+
+        StmtIr::VariableDecl { id, ty } => {
             f.write_str("var var");
             f.write_i128(*id as i128);
             f.write_str(": ");
@@ -299,241 +592,772 @@ const fn fmt_stmt(
             f.write_str(";\n");
         }
 
-        StmtIr::Assignment(left, right) => {
+        StmtIr::LetDecl { id, value } => {
+            f.write_str("let var");
+            f.write_i128(*id as i128);
+            f.write_str(" = ");
+            fmt_expr(f, value, shader);
+            f.write_str(";\n");
+        }
+
+        StmtIr::Assignment { left, right } => {
             fmt_place(f, left, shader);
             f.write_str(" = ");
-            fmt_expr(f, right, expr_bank, stmt_bank, shader);
+            fmt_expr(f, right, shader);
             f.write_str(";\n");
         }
 
-        StmtIr::Return(expr) => {
+        StmtIr::Return { value } => {
             f.write_str("return");
 
-            if let Some(expr) = expr {
+            if let Some(value) = value {
                 f.write_str(" ");
-                fmt_expr(f, expr, expr_bank, stmt_bank, shader);
+                fmt_expr(f, value, shader);
             }
 
             f.write_str(";\n");
         }
+
+        StmtIr::If {
+            cond,
+            then,
+            otherwise,
+        } => {
+            f.write_str("if ");
+            fmt_expr(f, cond, shader);
+            f.write_str(" {\n");
+            fmt_body(f, then, tab_lvl + 1, shader);
+            fmt_tabs(f, tab_lvl);
+            f.write_str("}");
+
+            if !otherwise.stmts.is_empty() {
+                f.write_str(" else {\n");
+                fmt_body(f, otherwise, tab_lvl + 1, shader);
+                fmt_tabs(f, tab_lvl);
+                f.write_str("}");
+            }
+
+            f.write_str("\n");
+        }
+
+        StmtIr::Loop {
+            body,
+            continuing,
+            break_if,
+        } => {
+            f.write_str("loop {\n");
+            fmt_body(f, body, tab_lvl + 1, shader);
+
+            if !continuing.stmts.is_empty() || break_if.is_some() {
+                fmt_tabs(f, tab_lvl + 1);
+                f.write_str("continuing {\n");
+                fmt_body(f, continuing, tab_lvl + 2, shader);
+
+                if let Some(break_if) = break_if {
+                    fmt_tabs(f, tab_lvl + 2);
+                    f.write_str("break if ");
+                    fmt_expr(f, break_if, shader);
+                    f.write_str(";\n");
+                }
+
+                fmt_tabs(f, tab_lvl + 1);
+                f.write_str("}\n");
+            }
+
+            fmt_tabs(f, tab_lvl);
+            f.write_str("}\n");
+        }
+
+        StmtIr::Switch {
+            selector,
+            cases,
+            default,
+        } => {
+            f.write_str("switch ");
+            fmt_expr(f, selector, shader);
+            f.write_str(" {\n");
+
+            let mut i = 0;
+            while i < cases.len() {
+                let SwitchCaseIr { selectors, body } = &cases[i];
+
+                fmt_tabs(f, tab_lvl + 1);
+                f.write_str("case ");
+
+                let mut j = 0;
+                while j < selectors.len() {
+                    if j > 0 {
+                        f.write_str(", ");
+                    }
+
+                    fmt_case_selector(f, &selectors[j], shader);
+
+                    j += 1;
+                }
+
+                f.write_str(": {\n");
+                fmt_body(f, body, tab_lvl + 2, shader);
+                fmt_tabs(f, tab_lvl + 1);
+                f.write_str("}\n");
+
+                i += 1;
+            }
+
+            fmt_tabs(f, tab_lvl + 1);
+            f.write_str("default: {\n");
+            fmt_body(f, default, tab_lvl + 2, shader);
+            fmt_tabs(f, tab_lvl + 1);
+            f.write_str("}\n");
+
+            fmt_tabs(f, tab_lvl);
+            f.write_str("}\n");
+        }
+
+        StmtIr::Break => f.write_str("break;\n"),
+        StmtIr::Continue => f.write_str("continue;\n"),
     }
 }
 
-const fn fmt_expr(
-    f: &mut Formatter,
-    expr: &'static ExprIr,
-    expr_bank: &'static [ExprIr],
-    stmt_bank: &'static [StmtIr],
-    shader: &'static LinkedShaderIr,
-) {
+/// Case selectors need to be integer literals, so unlike other `i32` literals
+/// they cannot be written as bitcasts.
+const fn fmt_case_selector(f: &mut Formatter, selector: &Literal, shader: &LinkedShaderIr) {
+    match selector {
+        Literal::I32(i32::MIN) => f.write_str("(-2147483647i - 1i)"),
+
+        Literal::I32(value) => {
+            f.write_i128(*value as i128);
+            f.write_str("i");
+        }
+
+        _ => fmt_expr(f, &ExprIr::Literal(*selector), shader),
+    }
+}
+
+const fn fmt_expr(f: &mut Formatter, expr: &ExprIr, shader: &LinkedShaderIr) {
     match expr {
         ExprIr::Literal(Literal::F32(value)) => {
             f.write_str("bitcast<f32>(0x");
             f.write_u32_hex(value.to_bits());
-            f.write_str(">");
+            f.write_str("u)");
         }
         ExprIr::Literal(Literal::I32(value)) => {
             f.write_str("bitcast<i32>(0x");
             f.write_u32_hex(value.cast_unsigned());
-            f.write_str(">");
+            f.write_str("u)");
         }
         ExprIr::Literal(Literal::U32(value)) => {
-            f.write_str("bitcast<u32>(0x");
+            f.write_str("0x");
             f.write_u32_hex(*value);
-            f.write_str(">");
+            f.write_str("u");
         }
         ExprIr::Literal(Literal::Bool(false)) => f.write_str("false"),
         ExprIr::Literal(Literal::Bool(true)) => f.write_str("true"),
 
-        ExprIr::Variable(VariableIr { id, ty: _ }) => {
+        ExprIr::Param { idx, ty: _ } => {
+            f.write_str("param");
+            f.write_i128(*idx as i128);
+        }
+
+        ExprIr::Variable { id, ty: _ } => {
             f.write_str("var");
             f.write_i128(*id as i128);
         }
 
-        ExprIr::Call { func: function, args } => match function {
-            FnIr::UserDefined { .. } => {
-                f.write_str("fn");
-                f.write_i128(shader.fn_id(function) as i128);
-                f.write_str("(");
+        ExprIr::Call { func, args } => fmt_call(f, func, args, shader),
 
-                let mut arg_idx = 0;
-                while arg_idx < args.len() {
-                    if arg_idx > 0 {
-                        f.write_str(", ");
-                    }
+        ExprIr::StructConstructor { ty, fields, base } => {
+            fmt_struct_constructor(f, ty, fields, *base, shader)
+        }
 
-                    fmt_expr(f, &expr_bank[args[arg_idx].0], expr_bank, stmt_bank, shader);
+        ExprIr::StructField { idx, base } => fmt_field(f, base, *idx, shader),
 
-                    arg_idx += 1;
-                }
+        ExprIr::VectorElement { idx, base } => {
+            f.write_str("(");
+            fmt_expr(f, base, shader);
+            f.write_str(")");
+            fmt_vector_element(f, *idx);
+        }
 
-                f.write_str(")");
-            }
-
-            FnIr::Builtin(BuiltinFn::Neg(_)) => {
-                f.write_str("-(");
-                fmt_expr(f, &expr_bank[args[0].0], expr_bank, stmt_bank, shader);
-                f.write_str(")");
-            }
-            FnIr::Builtin(BuiltinFn::Not(_)) => {
-                f.write_str("!(");
-                fmt_expr(f, &expr_bank[args[0].0], expr_bank, stmt_bank, shader);
-                f.write_str(")");
-            }
-            FnIr::Builtin(BuiltinFn::Add(_, _)) => {
-                f.write_str("(");
-                fmt_expr(f, &expr_bank[args[0].0], expr_bank, stmt_bank, shader);
-                f.write_str(") + (");
-                fmt_expr(f, &expr_bank[args[1].0], expr_bank, stmt_bank, shader);
-                f.write_str(")");
-            }
-            FnIr::Builtin(BuiltinFn::Sub(_, _)) => {
-                f.write_str("(");
-                fmt_expr(f, &expr_bank[args[0].0], expr_bank, stmt_bank, shader);
-                f.write_str(") - (");
-                fmt_expr(f, &expr_bank[args[1].0], expr_bank, stmt_bank, shader);
-                f.write_str(")");
-            }
-            FnIr::Builtin(BuiltinFn::Mul(_, _)) => {
-                f.write_str("(");
-                fmt_expr(f, &expr_bank[args[0].0], expr_bank, stmt_bank, shader);
-                f.write_str(") * (");
-                fmt_expr(f, &expr_bank[args[1].0], expr_bank, stmt_bank, shader);
-                f.write_str(")");
-            }
-            FnIr::Builtin(BuiltinFn::Div(_, _)) => {
-                f.write_str("(");
-                fmt_expr(f, &expr_bank[args[0].0], expr_bank, stmt_bank, shader);
-                f.write_str(") / (");
-                fmt_expr(f, &expr_bank[args[1].0], expr_bank, stmt_bank, shader);
-                f.write_str(")");
-            }
-            FnIr::Builtin(BuiltinFn::Rem(_, _)) => {
-                f.write_str("(");
-                fmt_expr(f, &expr_bank[args[0].0], expr_bank, stmt_bank, shader);
-                f.write_str(") % (");
-                fmt_expr(f, &expr_bank[args[1].0], expr_bank, stmt_bank, shader);
-                f.write_str(")");
-            }
-            FnIr::Builtin(BuiltinFn::Shl(_, _)) => {
-                f.write_str("(");
-                fmt_expr(f, &expr_bank[args[0].0], expr_bank, stmt_bank, shader);
-                f.write_str(") << (");
-                fmt_expr(f, &expr_bank[args[1].0], expr_bank, stmt_bank, shader);
-                f.write_str(")");
-            }
-            FnIr::Builtin(BuiltinFn::Shr(_, _)) => {
-                f.write_str("(");
-                fmt_expr(f, &expr_bank[args[0].0], expr_bank, stmt_bank, shader);
-                f.write_str(") >> (");
-                fmt_expr(f, &expr_bank[args[1].0], expr_bank, stmt_bank, shader);
-                f.write_str(")");
-            }
-            FnIr::Builtin(BuiltinFn::BitAnd(_, _)) => {
-                f.write_str("(");
-                fmt_expr(f, &expr_bank[args[0].0], expr_bank, stmt_bank, shader);
-                f.write_str(") & (");
-                fmt_expr(f, &expr_bank[args[1].0], expr_bank, stmt_bank, shader);
-                f.write_str(")");
-            }
-            FnIr::Builtin(BuiltinFn::BitOr(_, _)) => {
-                f.write_str("(");
-                fmt_expr(f, &expr_bank[args[0].0], expr_bank, stmt_bank, shader);
-                f.write_str(") | (");
-                fmt_expr(f, &expr_bank[args[1].0], expr_bank, stmt_bank, shader);
-                f.write_str(")");
-            }
-            FnIr::Builtin(BuiltinFn::BitXor(_, _)) => {
-                f.write_str("(");
-                fmt_expr(f, &expr_bank[args[0].0], expr_bank, stmt_bank, shader);
-                f.write_str(") ^ (");
-                fmt_expr(f, &expr_bank[args[1].0], expr_bank, stmt_bank, shader);
-                f.write_str(")");
-            }
-            FnIr::Builtin(BuiltinFn::Eq(_)) => {
-                f.write_str("(");
-                fmt_expr(f, &expr_bank[args[0].0], expr_bank, stmt_bank, shader);
-                f.write_str(") == (");
-                fmt_expr(f, &expr_bank[args[1].0], expr_bank, stmt_bank, shader);
-                f.write_str(")");
-            }
-            FnIr::Builtin(BuiltinFn::Ne(_)) => {
-                f.write_str("(");
-                fmt_expr(f, &expr_bank[args[0].0], expr_bank, stmt_bank, shader);
-                f.write_str(") != (");
-                fmt_expr(f, &expr_bank[args[1].0], expr_bank, stmt_bank, shader);
-                f.write_str(")");
-            }
-            FnIr::Builtin(BuiltinFn::Lt(_)) => {
-                f.write_str("(");
-                fmt_expr(f, &expr_bank[args[0].0], expr_bank, stmt_bank, shader);
-                f.write_str(") < (");
-                fmt_expr(f, &expr_bank[args[1].0], expr_bank, stmt_bank, shader);
-                f.write_str(")");
-            }
-            FnIr::Builtin(BuiltinFn::Gt(_)) => {
-                f.write_str("(");
-                fmt_expr(f, &expr_bank[args[0].0], expr_bank, stmt_bank, shader);
-                f.write_str(") > (");
-                fmt_expr(f, &expr_bank[args[1].0], expr_bank, stmt_bank, shader);
-                f.write_str(")");
-            }
-            FnIr::Builtin(BuiltinFn::Le(_)) => {
-                f.write_str("(");
-                fmt_expr(f, &expr_bank[args[0].0], expr_bank, stmt_bank, shader);
-                f.write_str(") <= (");
-                fmt_expr(f, &expr_bank[args[1].0], expr_bank, stmt_bank, shader);
-                f.write_str(")");
-            }
-            FnIr::Builtin(BuiltinFn::Ge(_)) => {
-                f.write_str("(");
-                fmt_expr(f, &expr_bank[args[0].0], expr_bank, stmt_bank, shader);
-                f.write_str(") >= (");
-                fmt_expr(f, &expr_bank[args[1].0], expr_bank, stmt_bank, shader);
-                f.write_str(")");
-            }
-            FnIr::Builtin(BuiltinFn::And) => {
-                f.write_str("(");
-                fmt_expr(f, &expr_bank[args[0].0], expr_bank, stmt_bank, shader);
-                f.write_str(") && (");
-                fmt_expr(f, &expr_bank[args[1].0], expr_bank, stmt_bank, shader);
-                f.write_str(")");
-            }
-            FnIr::Builtin(BuiltinFn::Or) => {
-                f.write_str("(");
-                fmt_expr(f, &expr_bank[args[0].0], expr_bank, stmt_bank, shader);
-                f.write_str(") || (");
-                fmt_expr(f, &expr_bank[args[1].0], expr_bank, stmt_bank, shader);
-                f.write_str(")");
-            }
-
-            FnIr::Builtin(BuiltinFn::StructConstructor { ty }) => {
-                f.write_str("type");
-                f.write_i128(shader.type_id(ty) as i128);
-                f.write_str("(");
-
-                let mut arg_idx = 0;
-                while arg_idx < args.len() {
-                    if arg_idx > 0 {
-                        f.write_str(", ");
-                    }
+        ExprIr::ArrayConstructor { ty, elements } => {
+            fmt_type_name(f, ty, shader);
+            fmt_args(f, elements, shader);
+        }
+
+        ExprIr::Index { base, index } => {
+            f.write_str("(");
+            fmt_expr(f, base, shader);
+            f.write_str(")[");
+            fmt_expr(f, index, shader);
+            f.write_str("]");
+        }
+
+        ExprIr::Const(value) => {
+            f.write_str("const");
+            f.write_i128(value.id(shader) as i128);
+        }
+
+        ExprIr::Deref { ptr } => {
+            f.write_str("(*");
+            fmt_expr(f, ptr, shader);
+            f.write_str(")");
+        }
 
-                    fmt_expr(f, &expr_bank[args[arg_idx].0], expr_bank, stmt_bank, shader);
+        ExprIr::AddressOf { place, ty: _ } => {
+            f.write_str("&");
+            fmt_place(f, place, shader);
+        }
+
+        ExprIr::And { left, right } => fmt_binary_op(f, "&&", &[**left, **right], shader),
+        ExprIr::Or { left, right } => fmt_binary_op(f, "||", &[**left, **right], shader),
+    }
+}
+
+/// Fields are written in declaration order, which may differ from the order of
+/// `fields`.
+const fn fmt_struct_constructor(
+    f: &mut Formatter,
+    ty: &TypeIr,
+    fields: &[FieldInitIr],
+    base: Option<&ExprIr>,
+    shader: &LinkedShaderIr,
+) {
+    // Struct literals of vector types construct the vector from its elements.
+    let len = match ty {
+        TypeIr::Struct { fields } => fields.len(),
+        TypeIr::Vector { n, t: _ } => n.as_usize(),
+        _ => panic!("struct constructor of a type without fields"),
+    };
+
+    fmt_type_name(f, ty, shader);
+    f.write_str("(");
+
+    let mut field_idx = 0;
+    while field_idx < len {
+        if field_idx > 0 {
+            f.write_str(", ");
+        }
+
+        let mut init_idx = 0;
+        while init_idx < fields.len() && fields[init_idx].idx != field_idx {
+            init_idx += 1;
+        }
 
-                    arg_idx += 1;
+        if init_idx < fields.len() {
+            fmt_expr(f, &fields[init_idx].value, shader);
+        } else if let Some(base) = base {
+            match ty {
+                TypeIr::Vector { .. } => {
+                    f.write_str("(");
+                    fmt_expr(f, base, shader);
+                    f.write_str(")");
+                    fmt_vector_element(f, field_idx);
                 }
+                _ => fmt_field(f, base, field_idx, shader),
+            }
+        } else {
+            // The zero value, like for the fields of the variants of an enum
+            // other than the one that is constructed.
+            match ty {
+                TypeIr::Struct { fields } => fmt_type_name(f, &fields[field_idx], shader),
+                TypeIr::Vector { n: _, t } => fmt_type_name(f, &TypeIr::Primitive(*t), shader),
+                _ => unreachable!(),
+            }
+            f.write_str("()");
+        }
+
+        field_idx += 1;
+    }
+
+    f.write_str(")");
+}
 
-                f.write_str(")");
+const fn fmt_field(f: &mut Formatter, base: &ExprIr, idx: usize, shader: &LinkedShaderIr) {
+    f.write_str("(");
+    fmt_expr(f, base, shader);
+    f.write_str(").field");
+    f.write_i128(idx as i128);
+}
+
+const fn fmt_vector_element(f: &mut Formatter, idx: usize) {
+    f.write_str(match idx {
+        0 => ".x",
+        1 => ".y",
+        2 => ".z",
+        3 => ".w",
+        _ => panic!("vector element out of range"),
+    });
+}
+
+const fn fmt_call(f: &mut Formatter, func: &FnIr, args: &[ExprIr], shader: &LinkedShaderIr) {
+    match func {
+        FnIr::UserDefined { .. } => {
+            f.write_str("fn");
+            f.write_i128(func.id(shader) as i128);
+            fmt_args(f, args, shader);
+        }
+
+        FnIr::Builtin(func) => fmt_builtin_call(f, func, args, shader),
+    }
+}
+
+const fn fmt_args(f: &mut Formatter, args: &[ExprIr], shader: &LinkedShaderIr) {
+    f.write_str("(");
+
+    let mut arg_idx = 0;
+    while arg_idx < args.len() {
+        if arg_idx > 0 {
+            f.write_str(", ");
+        }
+
+        fmt_expr(f, &args[arg_idx], shader);
+
+        arg_idx += 1;
+    }
+
+    f.write_str(")");
+}
+
+const fn fmt_builtin_call(
+    f: &mut Formatter,
+    func: &BuiltinFn,
+    args: &[ExprIr],
+    shader: &LinkedShaderIr,
+) {
+    if let Some(template) = builtin_template(func, shader) {
+        fmt_template(f, template, func, Some(args), shader);
+        return;
+    }
+
+    match func {
+        BuiltinFn::ScalarPrimitiveOp { op, ty: _ }
+        | BuiltinFn::VectorizedPrimitiveOp { op, n: _, t: _ } => {
+            fmt_binary_op(f, primitive_op_str(op), args, shader)
+        }
+
+        BuiltinFn::ScalarNumericOp { op, ty: _ }
+        | BuiltinFn::VectorizedNumericOp { op, n: _, t: _ } => {
+            fmt_binary_op(f, numeric_op_str(op), args, shader)
+        }
+
+        BuiltinFn::ScalarIntOp { op, ty: _ } => {
+            fmt_shift_op(f, op, &TypeIr::Primitive(Primitive::U32), args, shader)
+        }
+        BuiltinFn::VectorizedIntOp { op, n, t: _ } => fmt_shift_op(
+            f,
+            op,
+            &TypeIr::Vector {
+                n: *n,
+                t: Primitive::U32,
+            },
+            args,
+            shader,
+        ),
+
+        BuiltinFn::ScalarSignedNumericOp { op, ty: _ }
+        | BuiltinFn::VectorizedSignedNumericOp { op, n: _, t: _ } => match op {
+            SignedNumericOp::Neg => fmt_unary_op(f, "-", args, shader),
+        },
+
+        BuiltinFn::ScalarBitwiseOp { op, ty }
+        | BuiltinFn::VectorizedBitwiseOp { op, n: _, t: ty } => {
+            let is_bool = matches!(ty, BitwisePrimitive::Bool);
+
+            match op {
+                BitwiseOp::Not if is_bool => fmt_unary_op(f, "!", args, shader),
+                BitwiseOp::Not => fmt_unary_op(f, "~", args, shader),
+                BitwiseOp::BitAnd => fmt_binary_op(f, "&", args, shader),
+                BitwiseOp::BitOr => fmt_binary_op(f, "|", args, shader),
+                BitwiseOp::BitXor if is_bool => fmt_binary_op(f, "!=", args, shader),
+                BitwiseOp::BitXor => fmt_binary_op(f, "^", args, shader),
             }
+        }
+
+        BuiltinFn::VectorConstructor { op, t } => {
+            fmt_type_name(
+                f,
+                &TypeIr::Vector {
+                    n: op.ret_len(),
+                    t: *t,
+                },
+                shader,
+            );
+            fmt_args(f, args, shader);
+        }
+
+        BuiltinFn::VectorSplat { n, t } => {
+            fmt_type_name(f, &TypeIr::Vector { n: *n, t: *t }, shader);
+            fmt_args(f, args, shader);
+        }
+
+        BuiltinFn::VectorElement { i, n: _, t: _ } => {
+            f.write_str("(");
+            fmt_expr(f, &args[0], shader);
+            f.write_str(")[");
+            f.write_i128(*i as i128);
+            f.write_str("]");
+        }
+
+        BuiltinFn::ScalarWrappingOp { .. }
+        | BuiltinFn::ScalarSaturatingOp { .. }
+        | BuiltinFn::ScalarEuclidOp { .. }
+        | BuiltinFn::VectorFromArray { .. }
+        | BuiltinFn::ScalarConversion { .. }
+        | BuiltinFn::VectorizedConversion { .. } => unreachable!(),
+
+        BuiltinFn::VectorPrimitiveOp { op, n: _, t: _ } => {
+            f.write_str(match op {
+                VectorPrimitiveOp::Eq => "all",
+                VectorPrimitiveOp::Ne => "any",
+            });
+            f.write_str("(");
+            fmt_binary_op(
+                f,
+                match op {
+                    VectorPrimitiveOp::Eq => "==",
+                    VectorPrimitiveOp::Ne => "!=",
+                },
+                args,
+                shader,
+            );
+            f.write_str(")");
+        }
+    }
+}
+
+const fn fmt_unary_op(f: &mut Formatter, op: &str, args: &[ExprIr], shader: &LinkedShaderIr) {
+    f.write_str(op);
+    f.write_str("(");
+    fmt_expr(f, &args[0], shader);
+    f.write_str(")");
+}
+
+const fn fmt_binary_op(f: &mut Formatter, op: &str, args: &[ExprIr], shader: &LinkedShaderIr) {
+    f.write_str("(");
+    fmt_expr(f, &args[0], shader);
+    f.write_str(") ");
+    f.write_str(op);
+    f.write_str(" (");
+    fmt_expr(f, &args[1], shader);
+    f.write_str(")");
+}
+
+/// WGSL requires the shift amount to be unsigned, so it is converted to
+/// `shift_ty` first.
+const fn fmt_shift_op(
+    f: &mut Formatter,
+    op: &IntOp,
+    shift_ty: &TypeIr,
+    args: &[ExprIr],
+    shader: &LinkedShaderIr,
+) {
+    f.write_str("(");
+    fmt_expr(f, &args[0], shader);
+    f.write_str(match op {
+        IntOp::Shl => ") << ",
+        IntOp::Shr => ") >> ",
+    });
+    fmt_type_name(f, shift_ty, shader);
+    f.write_str("(");
+    fmt_expr(f, &args[1], shader);
+    f.write_str(")");
+}
+
+/// The WGSL of builtins that are more than a single operator, written as a
+/// template for [`fmt_template`]. Builtins whose template uses an argument
+/// more than once are emitted as helper functions, so that every argument
+/// is evaluated once.
+///
+/// WGSL integer arithmetic already wraps, so only division and shifts need to
+/// be guarded to behave like in Rust. Float to integer conversions of NaN and
+/// out of range values are unspecified in WGSL, so they are made to saturate
+/// and map NaN to `0` like Rust's `as`.
+const fn builtin_template(func: &BuiltinFn, shader: &LinkedShaderIr) -> Option<&'static str> {
+    let is_rust = matches!(shader.arithmetic, Arithmetic::Rust);
+
+    Some(match func {
+        BuiltinFn::ScalarNumericOp { op, ty } => match (op, ty) {
+            (NumericOp::Div, Numeric::I32 | Numeric::U32) if is_rust => "$0 / $G",
+            (NumericOp::Rem, Numeric::I32 | Numeric::U32) if is_rust => "$0 % $G",
+            _ => return None,
+        },
+        BuiltinFn::VectorizedNumericOp { op, n: _, t } => match (op, t) {
+            (NumericOp::Div, Numeric::I32 | Numeric::U32) if is_rust => "$0 / $G",
+            (NumericOp::Rem, Numeric::I32 | Numeric::U32) if is_rust => "$0 % $G",
+            _ => return None,
+        },
+
+        BuiltinFn::ScalarIntOp { op, ty: _ } | BuiltinFn::VectorizedIntOp { op, n: _, t: _ }
+            if is_rust =>
+        {
+            match op {
+                IntOp::Shl => "$0 << ($U($1) & $U(31))",
+                IntOp::Shr => "$0 >> ($U($1) & $U(31))",
+            }
+        }
+
+        BuiltinFn::ScalarWrappingOp { op, ty } => match (op, ty) {
+            (WrappingOp::Add, _) => "$0 + $1",
+            (WrappingOp::Sub, _) => "$0 - $1",
+            (WrappingOp::Mul, _) => "$0 * $1",
+            (WrappingOp::Div, _) => "$0 / $G",
+            (WrappingOp::Rem, _) => "$0 % $G",
+            (WrappingOp::Neg, Int::I32) => "-$0",
+            (WrappingOp::Neg, Int::U32) => "$T(0) - $0",
+            (WrappingOp::Shl, _) => "$0 << ($1 & 31u)",
+            (WrappingOp::Shr, _) => "$0 >> ($1 & 31u)",
+        },
+
+        BuiltinFn::ScalarSaturatingOp { op, ty } => match (op, ty) {
+            (SaturatingOp::Add, Int::I32) => {
+                "$0 + clamp($1, $T(-2147483648) - min($0, $T(0)), $T(2147483647) - max($0, $T(0)))"
+            }
+            (SaturatingOp::Add, Int::U32) => "$0 + min($1, ~$0)",
+            (SaturatingOp::Sub, Int::I32) => {
+                "$0 - clamp($1, max($0, $T(-1)) - $T(2147483647), min($0, $T(-1)) - $T(-2147483648))"
+            }
+            (SaturatingOp::Sub, Int::U32) => "$0 - min($0, $1)",
+            // the product overflowed if dividing it by one operand does not
+            // give back the other, except for `-1` where the division itself
+            // overflows.
+            (SaturatingOp::Mul, Int::I32) => {
+                "select($0 * $1, select($T(-2147483648), $T(2147483647), ($0 < $T(0)) == ($1 < $T(0))), \
+                (($0 != $T(0)) & ($0 != $T(-1)) & (($0 * $1) / select($0, $T(1), ($0 == $T(0)) | ($0 == $T(-1))) != $1)) \
+                | (($0 == $T(-1)) & ($1 == $T(-2147483648))))"
+            }
+            (SaturatingOp::Mul, Int::U32) => {
+                "select($0 * $1, $T(4294967295), ($0 != $T(0)) & ($1 > $T(4294967295) / max($0, $T(1))))"
+            }
+            (SaturatingOp::Div, Int::I32) => {
+                "select($0 / $G, $T(2147483647), ($0 == $T(-2147483648)) & ($1 == $T(-1)))"
+            }
+            (SaturatingOp::Div, Int::U32) => "$0 / $G",
+        },
+
+        BuiltinFn::ScalarEuclidOp { op, ty } => match (op, ty) {
+            (EuclidOp::DivEuclid, Numeric::F32) => {
+                "trunc($0 / $1) - select($T(0), sign($1), $0 % $1 < $T(0))"
+            }
+            (EuclidOp::RemEuclid, Numeric::F32) => {
+                "$0 % $1 + select($T(0), abs($1), $0 % $1 < $T(0))"
+            }
+            (EuclidOp::DivEuclid, Numeric::I32) => {
+                "$0 / $G - select($T(0), sign($1), $0 % $G < $T(0))"
+            }
+            (EuclidOp::RemEuclid, Numeric::I32) => {
+                "$0 % $G + select($T(0), abs($1), $0 % $G < $T(0))"
+            }
+            (EuclidOp::DivEuclid, Numeric::U32) => "$0 / $G",
+            (EuclidOp::RemEuclid, Numeric::U32) => "$0 % $G",
         },
+
+        BuiltinFn::VectorFromArray { n, t: _ } => match n {
+            Length::Two => "$R($0[0], $0[1])",
+            Length::Three => "$R($0[0], $0[1], $0[2])",
+            Length::Four => "$R($0[0], $0[1], $0[2], $0[3])",
+        },
+
+        // the bounds are the closest floats inside and outside of the range
+        // of the integer type.
+        BuiltinFn::ScalarConversion { from, to }
+        | BuiltinFn::VectorizedConversion { n: _, from, to } => match (from, to) {
+            (Primitive::F32, Primitive::I32) => {
+                "select(select($R(clamp($0, $T(-2147483648.0), $T(2147483520.0))), $R(2147483647), \
+                $0 >= $T(2147483648.0)), $R(0), (bitcast<$U>($0) & $U(0x7fffffff)) > $U(0x7f800000))"
+            }
+            (Primitive::F32, Primitive::U32) => {
+                "select(select($R(clamp($0, $T(0.0), $T(4294967040.0))), $R(4294967295), \
+                $0 >= $T(4294967296.0)), $R(0), (bitcast<$U>($0) & $U(0x7fffffff)) > $U(0x7f800000))"
+            }
+            _ => "$R($0)",
+        },
+
+        _ => return None,
+    })
+}
+
+/// Writes `template` for a call to `func`, where `$0` and `$1` are the
+/// arguments, `$T` is the type of the first parameter, `$U` is that type with
+/// `u32` elements and `$R` is the return type. `$G` is the second argument
+/// guarded for integer division, replacing a divisor of `0` with `1`, and
+/// `-1` with `1` if the dividend is the minimum, so the division wraps like in
+/// Rust.
+///
+/// Without `args`, the parameters of a helper function are written instead.
+const fn fmt_template(
+    f: &mut Formatter,
+    template: &str,
+    func: &BuiltinFn,
+    args: Option<&[ExprIr]>,
+    shader: &LinkedShaderIr,
+) {
+    if let Some(args) = args
+        && is_helper(template)
+    {
+        f.write_str("fn");
+        f.write_i128(FnIr::Builtin(*func).id(shader) as i128);
+        fmt_args(f, args, shader);
+        return;
+    }
+
+    let ty = func.param_type(0);
+    let bytes = template.as_bytes();
+
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] != b'$' {
+            f.write_char(bytes[i] as char);
+
+            i += 1;
+            continue;
+        }
+
+        match bytes[i + 1] {
+            b'0' | b'1' => {
+                let idx = (bytes[i + 1] - b'0') as usize;
+
+                match args {
+                    Some(args) => {
+                        f.write_str("(");
+                        fmt_expr(f, &args[idx], shader);
+                        f.write_str(")");
+                    }
+                    None => {
+                        f.write_str("param");
+                        f.write_i128(idx as i128);
+                    }
+                }
+            }
+            b'T' => fmt_type_name(f, &ty, shader),
+            b'U' => fmt_type_name(f, &with_primitive(&ty, Primitive::U32), shader),
+            b'R' => fmt_type_name(f, &func.ret_type().unwrap(), shader),
+            b'G' => {
+                let divisor = match element_primitive(&ty) {
+                    Primitive::I32 => {
+                        "select($1, $T(1), ($1 == $T(0)) | (($0 == $T(-2147483648)) & ($1 == $T(-1))))"
+                    }
+                    _ => "select($1, $T(1), $1 == $T(0))",
+                };
+
+                fmt_template(f, divisor, func, args, shader);
+            }
+            _ => panic!("unknown template placeholder"),
+        }
+
+        i += 2;
+    }
+}
+
+/// Builtins are emitted as helper functions if their template uses an
+/// argument more than once.
+const fn is_helper(template: &str) -> bool {
+    let bytes = template.as_bytes();
+    let mut uses = [0; 2];
+
+    let mut i = 0;
+    while i + 1 < bytes.len() {
+        if bytes[i] == b'$' {
+            match bytes[i + 1] {
+                b'0' => uses[0] += 1,
+                b'1' => uses[1] += 1,
+                b'G' => return true,
+                _ => {}
+            }
+        }
+
+        i += 1;
+    }
+
+    uses[0] > 1 || uses[1] > 1
+}
+
+/// The number of arguments `template` refers to.
+const fn template_arity(template: &str) -> usize {
+    let bytes = template.as_bytes();
+    let mut arity = 0;
+
+    let mut i = 0;
+    while i + 1 < bytes.len() {
+        if bytes[i] == b'$' {
+            match bytes[i + 1] {
+                b'0' if arity < 1 => arity = 1,
+                b'1' | b'G' => arity = 2,
+                _ => {}
+            }
+        }
+
+        i += 1;
     }
+
+    arity
 }
 
-const fn fmt_place(f: &mut Formatter, place: &'static PlaceIr, _shader: &'static LinkedShaderIr) {
+/// The primitive of `ty`, or of its elements.
+const fn element_primitive(ty: &TypeIr) -> Primitive {
+    match ty {
+        TypeIr::Primitive(t) | TypeIr::Vector { n: _, t } => *t,
+        _ => panic!("only primitives and vectors have a primitive type"),
+    }
+}
+
+/// `ty` with its primitive, or the primitive of its elements, replaced by `t`.
+const fn with_primitive(ty: &TypeIr, t: Primitive) -> TypeIr {
+    match ty {
+        TypeIr::Primitive(_) => TypeIr::Primitive(t),
+        TypeIr::Vector { n, t: _ } => TypeIr::Vector { n: *n, t },
+        _ => panic!("only primitives and vectors have a primitive type"),
+    }
+}
+
+const fn primitive_op_str(op: &PrimitiveOp) -> &'static str {
+    match op {
+        PrimitiveOp::Eq => "==",
+        PrimitiveOp::Ne => "!=",
+    }
+}
+
+const fn numeric_op_str(op: &NumericOp) -> &'static str {
+    match op {
+        NumericOp::Lt => "<",
+        NumericOp::Gt => ">",
+        NumericOp::Le => "<=",
+        NumericOp::Ge => ">=",
+        NumericOp::Add => "+",
+        NumericOp::Sub => "-",
+        NumericOp::Mul => "*",
+        NumericOp::Div => "/",
+        NumericOp::Rem => "%",
+    }
+}
+
+const fn fmt_place(f: &mut Formatter, place: &PlaceIr, shader: &LinkedShaderIr) {
     match place {
-        PlaceIr::Variable(VariableIr { id, ty: _ }) => {
+        PlaceIr::Variable { id, ty: _ } => {
             f.write_str("var");
             f.write_i128(*id as i128);
         }
+
+        PlaceIr::VectorElement { idx, base } => {
+            fmt_place(f, base, shader);
+            fmt_vector_element(f, *idx);
+        }
+
+        PlaceIr::StructField { idx, base } => {
+            fmt_place(f, base, shader);
+            f.write_str(".field");
+            f.write_i128(*idx as i128);
+        }
+
+        PlaceIr::Index { base, index } => {
+            fmt_place(f, base, shader);
+            f.write_str("[");
+            fmt_expr(f, index, shader);
+            f.write_str("]");
+        }
+
+        PlaceIr::Deref { ptr } => {
+            f.write_str("(*");
+            fmt_expr(f, ptr, shader);
+            f.write_str(")");
+        }
     }
 }
@@ -0,0 +1,200 @@
+use crate::{
+    ir::{ConstIr, Literal, Primitive, TypeIr},
+    reflection::Ty,
+};
+
+/// Implemented by the marker struct of a `#[shader_item]` const or static,
+/// which has the same name as the item.
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` is not a shader const or static",
+    label = "not a `#[shader_item]` const or static"
+)]
+pub trait Const {
+    type Ty: Ty;
+
+    const IR: ConstIr;
+}
+
+/// Implemented by no type. A path to a name that is not a `#[shader_item]`
+/// const or static selects a struct generated in its place, whose `Const`
+/// impl requires this so that the error names the item.
+#[diagnostic::on_unimplemented(
+    message = "`{C}` is not a `#[shader_item]` const or static",
+    label = "not a shader const or static",
+    note = "add `#[shader_item]` to `{C}` to use it in shaders"
+)]
+pub trait NotShaderConst<C> {
+    type Ty: Ty;
+
+    const IR: ConstIr;
+}
+
+/// Where the primitives of a value are in its memory, so that the values of
+/// constants can be read at compile time.
+#[derive(Debug, Clone, Copy)]
+pub enum Layout {
+    /// A primitive of the type in the `TypeIr`.
+    Primitive,
+    /// A `usize`, which is lowered to `u32`.
+    Usize,
+    /// A vector, whose elements are stored consecutively.
+    Vector,
+    Struct {
+        offsets: &'static [usize],
+        fields: &'static [Layout],
+    },
+    Array {
+        stride: usize,
+        t: &'static Layout,
+    },
+}
+
+impl Layout {
+    /// The layout of primitives and vectors. Struct and array types provide
+    /// their own layout.
+    pub const fn of(ty: &TypeIr) -> Self {
+        match ty {
+            TypeIr::Primitive(_) => Self::Primitive,
+            TypeIr::Vector { .. } => Self::Vector,
+            TypeIr::Struct { .. } | TypeIr::Array { .. } => {
+                panic!("struct and array types must provide their layout")
+            }
+            TypeIr::Ptr { .. } => panic!("references cannot be constants"),
+        }
+    }
+}
+
+/// Reads the primitives of `value` in the order they appear in its `TypeIr`.
+/// `LEN` must be the [`TypeIr::primitive_count`] of `T`.
+pub const fn const_values<T: Ty, const LEN: usize>(value: &T) -> [Literal; LEN] {
+    let mut values = [Literal::Bool(false); LEN];
+    let mut idx = 0;
+
+    // SAFETY: the layout of `T` describes where its primitives are.
+    unsafe {
+        read(
+            value as *const T as *const u8,
+            &T::IR,
+            &T::LAYOUT,
+            &mut values,
+            &mut idx,
+        );
+    }
+
+    assert!(idx == LEN, "`LEN` must be the primitive count of `T`");
+
+    values
+}
+
+/// Whether every float in `values` is finite. Shader constants are written as
+/// literals, which cannot be infinite or NaN.
+pub const fn finite_floats(values: &[Literal]) -> bool {
+    let mut i = 0;
+    while i < values.len() {
+        if let Literal::F32(value) = values[i]
+            && !value.is_finite()
+        {
+            return false;
+        }
+
+        i += 1;
+    }
+
+    true
+}
+
+/// # Safety
+///
+/// `ptr` must point to a value of type `ty` with layout `layout`.
+const unsafe fn read(
+    ptr: *const u8,
+    ty: &TypeIr,
+    layout: &Layout,
+    values: &mut [Literal],
+    idx: &mut usize,
+) {
+    match (ty, layout) {
+        (TypeIr::Primitive(t), Layout::Primitive) => {
+            values[*idx] = unsafe { read_primitive(ptr, *t) };
+            *idx += 1;
+        }
+
+        (TypeIr::Primitive(Primitive::U32), Layout::Usize) => {
+            let value = unsafe { (ptr as *const usize).read() };
+            assert!(
+                value <= u32::MAX as usize,
+                "`usize` constant does not fit in `u32`"
+            );
+
+            values[*idx] = Literal::U32(value as u32);
+            *idx += 1;
+        }
+
+        (TypeIr::Vector { n, t }, Layout::Vector) => {
+            let mut i = 0;
+            while i < n.as_usize() {
+                values[*idx] = unsafe { read_primitive(ptr.add(i * primitive_size(*t)), *t) };
+                *idx += 1;
+
+                i += 1;
+            }
+        }
+
+        (
+            TypeIr::Struct { fields },
+            Layout::Struct {
+                offsets,
+                fields: field_layouts,
+            },
+        ) => {
+            let mut i = 0;
+            while i < fields.len() {
+                unsafe {
+                    read(
+                        ptr.add(offsets[i]),
+                        &fields[i],
+                        &field_layouts[i],
+                        values,
+                        idx,
+                    );
+                }
+
+                i += 1;
+            }
+        }
+
+        (TypeIr::Array { n, t }, Layout::Array { stride, t: layout }) => {
+            let mut i = 0;
+            while i < *n {
+                unsafe { read(ptr.add(i * *stride), t, layout, values, idx) };
+
+                i += 1;
+            }
+        }
+
+        _ => panic!("the layout of a type does not match its `TypeIr`"),
+    }
+}
+
+/// # Safety
+///
+/// `ptr` must point to a value of type `t`.
+const unsafe fn read_primitive(ptr: *const u8, t: Primitive) -> Literal {
+    unsafe {
+        match t {
+            Primitive::F32 => Literal::F32((ptr as *const f32).read()),
+            Primitive::I32 => Literal::I32((ptr as *const i32).read()),
+            Primitive::U32 => Literal::U32((ptr as *const u32).read()),
+            Primitive::Bool => Literal::Bool((ptr as *const bool).read()),
+        }
+    }
+}
+
+const fn primitive_size(t: Primitive) -> usize {
+    match t {
+        Primitive::F32 => size_of::<f32>(),
+        Primitive::I32 => size_of::<i32>(),
+        Primitive::U32 => size_of::<u32>(),
+        Primitive::Bool => size_of::<bool>(),
+    }
+}
@@ -0,0 +1,71 @@
+use core::marker::PhantomData;
+
+use crate::{
+    ir::{BuiltinFn, FnIr},
+    reflection::{Fn, Method, Ty, name_id},
+};
+
+/// Implemented for the pairs of types that can be converted with `as`.
+pub trait Cast<T: Ty>: Ty {
+    const IR: FnIr;
+}
+
+pub trait From<T>: core::convert::From<T> + Ty {
+    const IR: FnIr;
+}
+
+/// The function `T::from` with a parameter of type `U`, so that conversions
+/// are called like any other associated function.
+pub struct FromFn<T, U> {
+    _marker: PhantomData<fn(U) -> T>,
+}
+
+impl<T: From<U>, U> Fn for FromFn<T, U> {
+    type Output = T;
+
+    const IR: FnIr = <T as From<U>>::IR;
+}
+
+impl<T: From<U>, U> Method<{ name_id("from") }, FromFn<T, U>> for (T, (U,)) {
+    type Fn = FromFn<T, U>;
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Primitive Impls
+////////////////////////////////////////////////////////////////////////////////
+
+macro_rules! impl_cast {
+    ($($from:ident => $($to:ident),*);* $(;)?) => {$($(
+        impl Cast<$to> for $from {
+            const IR: FnIr = conversion::<$from, $to>();
+        }
+    )*)*};
+}
+
+macro_rules! impl_from {
+    ($($from:ident => $($to:ident),*);* $(;)?) => {$($(
+        impl From<$from> for $to {
+            const IR: FnIr = conversion::<$from, $to>();
+        }
+    )*)*};
+}
+
+impl_cast! {
+    f32 => f32, i32, u32, usize;
+    i32 => f32, i32, u32, usize;
+    u32 => f32, i32, u32, usize;
+    usize => f32, i32, u32, usize;
+    bool => i32, u32, usize, bool;
+}
+
+impl_from! {
+    f32 => f32;
+    i32 => i32;
+    u32 => u32;
+    usize => usize;
+    bool => f32, i32, u32, usize, bool;
+}
+
+const fn conversion<T: Ty, U: Ty>() -> FnIr {
+    FnIr::Builtin(BuiltinFn::conversion(&T::IR, &U::IR))
+}
@@ -0,0 +1,138 @@
+use core::marker::PhantomData;
+
+use crate::{
+    ir::{BodyIr, ExprIr, FnIr, Literal, Primitive, StmtIr, TypeIr},
+    reflection::{Field, Fn, Layout, Method, PartialEq, Ty, name_id},
+};
+
+/// Implemented by `#[shader_item]` enums and `Option`. Fieldless enums are
+/// lowered to their tag, and other enums to a struct of their tag followed by
+/// the fields of every variant.
+pub trait Enum: Ty {
+    /// The primitive that tells the variants apart.
+    type Tag: Ty;
+}
+
+/// Implemented by enums for each of their variants. `NAME` is the [`name_id`]
+/// of the variant, and its fields are [`Field`]s of the enum named like
+/// `Some.0`.
+pub trait Variant<const NAME: u64>: Enum {
+    const TAG: i128;
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Option Impls
+////////////////////////////////////////////////////////////////////////////////
+
+impl<T: Ty> Ty for Option<T> {
+    const IR: TypeIr = TypeIr::Struct {
+        fields: &[TypeIr::Primitive(Primitive::U32), T::IR],
+    };
+
+    const LAYOUT: Layout = panic!("enums with fields cannot be read as constants");
+}
+
+impl<T: Ty> Enum for Option<T> {
+    type Tag = u32;
+}
+
+impl<T: Ty> Variant<{ name_id("None") }> for Option<T> {
+    const TAG: i128 = 0;
+}
+
+impl<T: Ty> Variant<{ name_id("Some") }> for Option<T> {
+    const TAG: i128 = 1;
+}
+
+impl<T: Ty> Field<{ name_id("Some.0") }> for Option<T> {
+    type Ty = T;
+    const IDX: usize = 1;
+}
+
+/// The method of `Option<T>` with the [`name_id`] `NAME`, like `is_some` or
+/// `unwrap_or`.
+pub struct OptionMethod<T, const NAME: u64> {
+    _marker: PhantomData<fn() -> T>,
+}
+
+/// The expressions the methods of `Option<T>` are made of.
+struct OptionIr<T> {
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T: Ty> OptionIr<T> {
+    const SELF: ExprIr = ExprIr::Param {
+        idx: 0,
+        ty: &<Option<T> as Ty>::IR,
+    };
+
+    const VALUE: ExprIr = ExprIr::StructField {
+        idx: 1,
+        base: &Self::SELF,
+    };
+
+    const TAG: ExprIr = ExprIr::StructField {
+        idx: 0,
+        base: &Self::SELF,
+    };
+
+    const IS_SOME: ExprIr = ExprIr::Call {
+        func: &<u32 as PartialEq>::EQ,
+        args: &[Self::TAG, ExprIr::Literal(Literal::U32(1))],
+    };
+
+    const IS_NONE: ExprIr = ExprIr::Call {
+        func: &<u32 as PartialEq>::EQ,
+        args: &[Self::TAG, ExprIr::Literal(Literal::U32(0))],
+    };
+}
+
+macro_rules! impl_methods {
+    ($($name:literal($($param:ty),*) -> $output:ty => [$($stmt:expr),*];)*) => {$(
+        impl<T: Ty> Fn for OptionMethod<T, { name_id($name) }> {
+            type Output = $output;
+
+            const IR: FnIr = FnIr::UserDefined {
+                param_types: &[&<Option<T> as Ty>::IR, $(&<$param as Ty>::IR),*],
+                ret_type: Some(&<$output as Ty>::IR),
+                body: BodyIr {
+                    stmts: &[$($stmt),*],
+                },
+            };
+        }
+
+        impl<T: Ty> Method<{ name_id($name) }, OptionMethod<T, { name_id($name) }>>
+            for (Option<T>, (Option<T>, $($param,)*))
+        {
+            type Fn = OptionMethod<T, { name_id($name) }>;
+        }
+    )*};
+}
+
+impl_methods! {
+    "is_some"() -> bool => [StmtIr::Return {
+        value: Some(OptionIr::<T>::IS_SOME),
+    }];
+
+    "is_none"() -> bool => [StmtIr::Return {
+        value: Some(OptionIr::<T>::IS_NONE),
+    }];
+
+    "unwrap_or"(T) -> T => [
+        StmtIr::If {
+            cond: OptionIr::<T>::IS_SOME,
+            then: BodyIr {
+                stmts: &[StmtIr::Return {
+                    value: Some(OptionIr::<T>::VALUE),
+                }],
+            },
+            otherwise: BodyIr { stmts: &[] },
+        },
+        StmtIr::Return {
+            value: Some(ExprIr::Param {
+                idx: 1,
+                ty: &T::IR,
+            }),
+        }
+    ];
+}
@@ -1,9 +1,79 @@
-use crate::ir::{EntryPointIr, FnIr};
+use crate::{
+    ir::{EntryPointIr, FnIr},
+    reflection::Ty,
+};
 
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` is not a shader function",
+    label = "not a `#[shader_item]` function"
+)]
 pub trait Fn {
+    type Output;
+
     const IR: FnIr;
 }
 
 pub trait EntryPoint: Fn {
     const IR: EntryPointIr;
 }
+
+/// Selects the instance of a shader function that is called with arguments of
+/// the types in `Args`, so generic functions can be called without a
+/// turbofish.
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` is not a shader function that takes arguments of types `{Args}`",
+    label = "not callable in shaders with these arguments",
+    note = "only `#[shader_item]` functions can be called in shaders, and generic ones only if \
+            the argument types select a single instance"
+)]
+pub trait Call<Args> {
+    type Fn: Fn;
+}
+
+/// Implemented by no type. A call to a name that is not a `#[shader_item]`
+/// function selects a struct generated in its place, whose `Call` impl
+/// requires this so that the error names the function.
+#[diagnostic::on_unimplemented(
+    message = "`{F}` is not a `#[shader_item]` function",
+    label = "not a shader function",
+    note = "add `#[shader_item]` to `{F}` to call it in shaders"
+)]
+pub trait NotShaderFn<F> {
+    type Fn: Fn;
+}
+
+/// Selects the method, associated function or associated const with the
+/// [`name_id`](super::name_id) `NAME`. `Self` is the type the item is looked
+/// up in, followed by the tuple of the argument types including the receiver.
+/// Associated consts are functions without parameters.
+///
+/// `F` is always `Self::Fn`. Since it is defined next to the function, it lets
+/// that crate implement this for lookups on foreign types, like every
+/// implementor of a shader trait. Calls leave it to be inferred.
+#[diagnostic::on_unimplemented(
+    message = "no shader method or associated item with these argument types is found in \
+               `{Self}`",
+    label = "not found in `#[shader_item]` impls",
+    note = "the type is followed by the argument types, including the receiver",
+    note = "only items of `#[shader_item]` impls and traits can be used in shaders"
+)]
+pub trait Method<const NAME: u64, F: Fn> {
+    type Fn: Fn;
+}
+
+/// Selects the function a closure is lowered to when it is called with
+/// arguments of the types in `Args`. Closures that are passed to other
+/// functions are values of a type generated for them, which holds what they
+/// capture and is the first parameter of the function.
+///
+/// `Fn`, `FnMut` and `FnOnce` bounds of shader functions are replaced with
+/// this trait.
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` is not a closure that takes arguments of types `{Args}` in shaders",
+    label = "not callable in shaders with these arguments",
+    note = "only closures written in `#[shader_item]` functions can be passed to other shader \
+            functions"
+)]
+pub trait Closure<Args>: Ty {
+    type Fn: Fn;
+}
@@ -1,4 +1,16 @@
+mod r#const;
+mod convert;
+mod r#enum;
 mod r#fn;
+mod num;
+mod ops;
 mod ty;
+mod vector;
+pub use r#const::*;
+pub use convert::*;
+pub use r#enum::*;
 pub use r#fn::*;
+pub use num::*;
+pub use ops::*;
 pub use ty::*;
+pub use vector::*;
@@ -0,0 +1,74 @@
+use core::marker::PhantomData;
+
+use crate::{
+    ir::{BuiltinFn, EuclidOp, FnIr, Int, Numeric, SaturatingOp, WrappingOp},
+    reflection::{Fn, Method, name_id},
+};
+
+/// The inherent method of a primitive with the [`name_id`] `NAME`, like
+/// `wrapping_add` or `rem_euclid`.
+pub struct PrimitiveMethod<T, const NAME: u64> {
+    _marker: PhantomData<fn() -> T>,
+}
+
+macro_rules! impl_methods {
+    ($ty:ident {$($name:literal($($param:ident),*) => $ir:expr;)*}) => {$(
+        impl Fn for PrimitiveMethod<$ty, { name_id($name) }> {
+            type Output = $ty;
+
+            const IR: FnIr = FnIr::Builtin($ir);
+        }
+
+        impl Method<{ name_id($name) }, PrimitiveMethod<$ty, { name_id($name) }>>
+            for ($ty, ($ty, $($param,)*))
+        {
+            type Fn = PrimitiveMethod<$ty, { name_id($name) }>;
+        }
+    )*};
+}
+
+macro_rules! impl_int_methods {
+    ($($ty:ident => $int:ident),*) => {$(
+        impl_methods!($ty {
+            "wrapping_add"($ty) => wrapping_op(WrappingOp::Add, Int::$int);
+            "wrapping_sub"($ty) => wrapping_op(WrappingOp::Sub, Int::$int);
+            "wrapping_mul"($ty) => wrapping_op(WrappingOp::Mul, Int::$int);
+            "wrapping_div"($ty) => wrapping_op(WrappingOp::Div, Int::$int);
+            "wrapping_rem"($ty) => wrapping_op(WrappingOp::Rem, Int::$int);
+            "wrapping_neg"() => wrapping_op(WrappingOp::Neg, Int::$int);
+            "wrapping_shl"(u32) => wrapping_op(WrappingOp::Shl, Int::$int);
+            "wrapping_shr"(u32) => wrapping_op(WrappingOp::Shr, Int::$int);
+
+            "saturating_add"($ty) => saturating_op(SaturatingOp::Add, Int::$int);
+            "saturating_sub"($ty) => saturating_op(SaturatingOp::Sub, Int::$int);
+            "saturating_mul"($ty) => saturating_op(SaturatingOp::Mul, Int::$int);
+            "saturating_div"($ty) => saturating_op(SaturatingOp::Div, Int::$int);
+        });
+    )*};
+}
+
+macro_rules! impl_euclid_methods {
+    ($($ty:ident => $numeric:ident),*) => {$(
+        impl_methods!($ty {
+            "div_euclid"($ty) => euclid_op(EuclidOp::DivEuclid, Numeric::$numeric);
+            "rem_euclid"($ty) => euclid_op(EuclidOp::RemEuclid, Numeric::$numeric);
+        });
+    )*};
+}
+
+// `usize` is a `u32` in shaders, so it only has the methods that give the
+// same results for both widths.
+impl_int_methods!(i32 => I32, u32 => U32);
+impl_euclid_methods!(i32 => I32, u32 => U32, usize => U32, f32 => F32);
+
+const fn wrapping_op(op: WrappingOp, ty: Int) -> BuiltinFn {
+    BuiltinFn::ScalarWrappingOp { op, ty }
+}
+
+const fn saturating_op(op: SaturatingOp, ty: Int) -> BuiltinFn {
+    BuiltinFn::ScalarSaturatingOp { op, ty }
+}
+
+const fn euclid_op(op: EuclidOp, ty: Numeric) -> BuiltinFn {
+    BuiltinFn::ScalarEuclidOp { op, ty }
+}
@@ -0,0 +1,274 @@
+use crate::ir::{
+    BitwiseOp, BitwisePrimitive, BuiltinFn, FnIr, Int, IntOp, Numeric, NumericOp, Primitive,
+    PrimitiveOp, SignedNumeric, SignedNumericOp,
+};
+
+pub trait Add<Rhs = Self>: core::ops::Add<Rhs> {
+    const IR: FnIr;
+}
+
+pub trait Sub<Rhs = Self>: core::ops::Sub<Rhs> {
+    const IR: FnIr;
+}
+
+pub trait Mul<Rhs = Self>: core::ops::Mul<Rhs> {
+    const IR: FnIr;
+}
+
+pub trait Div<Rhs = Self>: core::ops::Div<Rhs> {
+    const IR: FnIr;
+}
+
+pub trait Rem<Rhs = Self>: core::ops::Rem<Rhs> {
+    const IR: FnIr;
+}
+
+pub trait Shl<Rhs = Self>: core::ops::Shl<Rhs> {
+    const IR: FnIr;
+}
+
+pub trait Shr<Rhs = Self>: core::ops::Shr<Rhs> {
+    const IR: FnIr;
+}
+
+pub trait BitAnd<Rhs = Self>: core::ops::BitAnd<Rhs> {
+    const IR: FnIr;
+}
+
+pub trait BitOr<Rhs = Self>: core::ops::BitOr<Rhs> {
+    const IR: FnIr;
+}
+
+pub trait BitXor<Rhs = Self>: core::ops::BitXor<Rhs> {
+    const IR: FnIr;
+}
+
+/// Compound assignment operators. Builtins assign the result of the operator
+/// they are named after to the place, while user functions are passed a
+/// pointer to it.
+pub trait AddAssign<Rhs = Self>: core::ops::AddAssign<Rhs> {
+    const IR: FnIr;
+}
+
+pub trait SubAssign<Rhs = Self>: core::ops::SubAssign<Rhs> {
+    const IR: FnIr;
+}
+
+pub trait MulAssign<Rhs = Self>: core::ops::MulAssign<Rhs> {
+    const IR: FnIr;
+}
+
+pub trait DivAssign<Rhs = Self>: core::ops::DivAssign<Rhs> {
+    const IR: FnIr;
+}
+
+pub trait RemAssign<Rhs = Self>: core::ops::RemAssign<Rhs> {
+    const IR: FnIr;
+}
+
+pub trait ShlAssign<Rhs = Self>: core::ops::ShlAssign<Rhs> {
+    const IR: FnIr;
+}
+
+pub trait ShrAssign<Rhs = Self>: core::ops::ShrAssign<Rhs> {
+    const IR: FnIr;
+}
+
+pub trait BitAndAssign<Rhs = Self>: core::ops::BitAndAssign<Rhs> {
+    const IR: FnIr;
+}
+
+pub trait BitOrAssign<Rhs = Self>: core::ops::BitOrAssign<Rhs> {
+    const IR: FnIr;
+}
+
+pub trait BitXorAssign<Rhs = Self>: core::ops::BitXorAssign<Rhs> {
+    const IR: FnIr;
+}
+
+pub trait Neg: core::ops::Neg {
+    const IR: FnIr;
+}
+
+pub trait Not: core::ops::Not {
+    const IR: FnIr;
+}
+
+pub trait PartialEq<Rhs = Self>: core::cmp::PartialEq<Rhs> {
+    const EQ: FnIr;
+    const NE: FnIr;
+}
+
+pub trait PartialOrd<Rhs = Self>: core::cmp::PartialOrd<Rhs> {
+    const LT: FnIr;
+    const GT: FnIr;
+    const LE: FnIr;
+    const GE: FnIr;
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Primitive Impls
+////////////////////////////////////////////////////////////////////////////////
+
+macro_rules! impl_numeric_ops {
+    ($($ty:ident => $numeric:ident),*) => {$(
+        impl Add for $ty {
+            const IR: FnIr = numeric_op(NumericOp::Add, Numeric::$numeric);
+        }
+
+        impl AddAssign for $ty {
+            const IR: FnIr = numeric_op(NumericOp::Add, Numeric::$numeric);
+        }
+
+        impl Sub for $ty {
+            const IR: FnIr = numeric_op(NumericOp::Sub, Numeric::$numeric);
+        }
+
+        impl SubAssign for $ty {
+            const IR: FnIr = numeric_op(NumericOp::Sub, Numeric::$numeric);
+        }
+
+        impl Mul for $ty {
+            const IR: FnIr = numeric_op(NumericOp::Mul, Numeric::$numeric);
+        }
+
+        impl MulAssign for $ty {
+            const IR: FnIr = numeric_op(NumericOp::Mul, Numeric::$numeric);
+        }
+
+        impl Div for $ty {
+            const IR: FnIr = numeric_op(NumericOp::Div, Numeric::$numeric);
+        }
+
+        impl DivAssign for $ty {
+            const IR: FnIr = numeric_op(NumericOp::Div, Numeric::$numeric);
+        }
+
+        impl Rem for $ty {
+            const IR: FnIr = numeric_op(NumericOp::Rem, Numeric::$numeric);
+        }
+
+        impl RemAssign for $ty {
+            const IR: FnIr = numeric_op(NumericOp::Rem, Numeric::$numeric);
+        }
+
+        impl PartialOrd for $ty {
+            const LT: FnIr = numeric_op(NumericOp::Lt, Numeric::$numeric);
+            const GT: FnIr = numeric_op(NumericOp::Gt, Numeric::$numeric);
+            const LE: FnIr = numeric_op(NumericOp::Le, Numeric::$numeric);
+            const GE: FnIr = numeric_op(NumericOp::Ge, Numeric::$numeric);
+        }
+    )*};
+}
+
+macro_rules! impl_shift_ops {
+    ($($ty:ident => $int:ident),*) => {$(
+        impl Shl<i32> for $ty {
+            const IR: FnIr = int_op(IntOp::Shl, Int::$int);
+        }
+
+        impl ShlAssign<i32> for $ty {
+            const IR: FnIr = int_op(IntOp::Shl, Int::$int);
+        }
+
+        impl Shl<u32> for $ty {
+            const IR: FnIr = int_op(IntOp::Shl, Int::$int);
+        }
+
+        impl ShlAssign<u32> for $ty {
+            const IR: FnIr = int_op(IntOp::Shl, Int::$int);
+        }
+
+        impl Shr<i32> for $ty {
+            const IR: FnIr = int_op(IntOp::Shr, Int::$int);
+        }
+
+        impl ShrAssign<i32> for $ty {
+            const IR: FnIr = int_op(IntOp::Shr, Int::$int);
+        }
+
+        impl Shr<u32> for $ty {
+            const IR: FnIr = int_op(IntOp::Shr, Int::$int);
+        }
+
+        impl ShrAssign<u32> for $ty {
+            const IR: FnIr = int_op(IntOp::Shr, Int::$int);
+        }
+    )*};
+}
+
+macro_rules! impl_bitwise_ops {
+    ($($ty:ident => $bitwise:ident),*) => {$(
+        impl BitAnd for $ty {
+            const IR: FnIr = bitwise_op(BitwiseOp::BitAnd, BitwisePrimitive::$bitwise);
+        }
+
+        impl BitAndAssign for $ty {
+            const IR: FnIr = bitwise_op(BitwiseOp::BitAnd, BitwisePrimitive::$bitwise);
+        }
+
+        impl BitOr for $ty {
+            const IR: FnIr = bitwise_op(BitwiseOp::BitOr, BitwisePrimitive::$bitwise);
+        }
+
+        impl BitOrAssign for $ty {
+            const IR: FnIr = bitwise_op(BitwiseOp::BitOr, BitwisePrimitive::$bitwise);
+        }
+
+        impl BitXor for $ty {
+            const IR: FnIr = bitwise_op(BitwiseOp::BitXor, BitwisePrimitive::$bitwise);
+        }
+
+        impl BitXorAssign for $ty {
+            const IR: FnIr = bitwise_op(BitwiseOp::BitXor, BitwisePrimitive::$bitwise);
+        }
+
+        impl Not for $ty {
+            const IR: FnIr = bitwise_op(BitwiseOp::Not, BitwisePrimitive::$bitwise);
+        }
+    )*};
+}
+
+macro_rules! impl_primitive_ops {
+    ($($ty:ident => $primitive:ident),*) => {$(
+        impl PartialEq for $ty {
+            const EQ: FnIr = primitive_op(PrimitiveOp::Eq, Primitive::$primitive);
+            const NE: FnIr = primitive_op(PrimitiveOp::Ne, Primitive::$primitive);
+        }
+    )*};
+}
+
+impl_numeric_ops!(f32 => F32, i32 => I32, u32 => U32, usize => U32);
+impl_shift_ops!(i32 => I32, u32 => U32, usize => U32);
+impl_bitwise_ops!(i32 => I32, u32 => U32, usize => U32, bool => Bool);
+impl_primitive_ops!(f32 => F32, i32 => I32, u32 => U32, usize => U32, bool => Bool);
+
+impl Neg for f32 {
+    const IR: FnIr = FnIr::Builtin(BuiltinFn::ScalarSignedNumericOp {
+        op: SignedNumericOp::Neg,
+        ty: SignedNumeric::F32,
+    });
+}
+
+impl Neg for i32 {
+    const IR: FnIr = FnIr::Builtin(BuiltinFn::ScalarSignedNumericOp {
+        op: SignedNumericOp::Neg,
+        ty: SignedNumeric::I32,
+    });
+}
+
+const fn numeric_op(op: NumericOp, ty: Numeric) -> FnIr {
+    FnIr::Builtin(BuiltinFn::ScalarNumericOp { op, ty })
+}
+
+const fn int_op(op: IntOp, ty: Int) -> FnIr {
+    FnIr::Builtin(BuiltinFn::ScalarIntOp { op, ty })
+}
+
+const fn bitwise_op(op: BitwiseOp, ty: BitwisePrimitive) -> FnIr {
+    FnIr::Builtin(BuiltinFn::ScalarBitwiseOp { op, ty })
+}
+
+const fn primitive_op(op: PrimitiveOp, ty: Primitive) -> FnIr {
+    FnIr::Builtin(BuiltinFn::ScalarPrimitiveOp { op, ty })
+}
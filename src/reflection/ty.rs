@@ -1,14 +1,46 @@
-use crate::ir::{FragInputIr, FragOutputIr, Length, Primitive, TypeIr, VertexInputIr};
+use crate::{
+    ir::{FragInputIr, FragOutputIr, Length, Primitive, TypeIr, VertexInputIr},
+    reflection::Layout,
+};
 
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` is not a shader type",
+    label = "not a shader type",
+    note = "shader types are primitives, vectors, fixed-size arrays and `#[shader_item]` structs and enums"
+)]
 pub trait Ty: Copy + 'static + Send + Sync {
     const IR: TypeIr;
+
+    const LAYOUT: Layout = Layout::of(&Self::IR);
 }
 
 pub trait PrimitiveTy: Ty {}
 
 pub trait VectorTy<const N: usize, T: PrimitiveTy>: Ty {}
 
-pub trait VertexInputTy: Ty {
+/// Implemented by shader structs for each of their fields, and by enums for the
+/// fields of their variants. `NAME` is the [`name_id`] of the field.
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` has no field with this name in shaders",
+    label = "no such field in shaders",
+    note = "only the fields of vectors and of `#[shader_item]` structs and enums can be used in \
+            shaders"
+)]
+pub trait Field<const NAME: u64> {
+    type Ty: Ty;
+    const IDX: usize;
+}
+
+/// The types vertex entry points can take: `f32`, `i32`, `u32`, vectors of
+/// them, and `#[shader_item]` structs whose fields are those. `Kind` keeps the
+/// impl for vectors apart from the others, and is inferred.
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` cannot be the input of a vertex shader",
+    label = "not a vertex input type",
+    note = "vertex inputs are `f32`, `i32`, `u32`, vectors of them and `#[shader_item]` structs \
+            whose fields are those"
+)]
+pub trait VertexInputTy<Kind = ()>: Ty {
     const IR: VertexInputIr;
 }
 
@@ -35,11 +67,62 @@ impl Ty for u32 {
     const IR: TypeIr = TypeIr::Primitive(Primitive::U32);
 }
 
+/// Indices are `usize` in Rust, so it is lowered to `u32` like on other 32-bit
+/// targets.
+impl Ty for usize {
+    const IR: TypeIr = TypeIr::Primitive(Primitive::U32);
+
+    const LAYOUT: Layout = Layout::Usize;
+}
+
 impl PrimitiveTy for bool {}
 impl Ty for bool {
     const IR: TypeIr = TypeIr::Primitive(Primitive::Bool);
 }
 
+/// Identifies a field, method or associated const by its name. Tuple struct
+/// fields are named by their index.
+pub const fn name_id(name: &str) -> u64 {
+    // FNV-1a
+    let bytes = name.as_bytes();
+
+    let mut hash = 0xcbf29ce484222325_u64;
+    let mut i = 0;
+    while i < bytes.len() {
+        hash ^= bytes[i] as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+
+        i += 1;
+    }
+
+    hash
+}
+
+impl<T: Ty, const N: usize> Ty for [T; N] {
+    const IR: TypeIr = TypeIr::Array { n: N, t: &T::IR };
+
+    const LAYOUT: Layout = Layout::Array {
+        stride: size_of::<T>(),
+        t: &T::LAYOUT,
+    };
+}
+
+impl VertexInputTy for f32 {
+    const IR: VertexInputIr = VertexInputIr::new(&<f32 as Ty>::IR);
+}
+
+impl VertexInputTy for i32 {
+    const IR: VertexInputIr = VertexInputIr::new(&<i32 as Ty>::IR);
+}
+
+impl VertexInputTy for u32 {
+    const IR: VertexInputIr = VertexInputIr::new(&<u32 as Ty>::IR);
+}
+
+impl<V: VectorTy<N, T>, const N: usize, T: PrimitiveTy> VertexInputTy<[T; N]> for V {
+    const IR: VertexInputIr = VertexInputIr::new(&<V as Ty>::IR);
+}
+
 impl<T: VectorTy<4, f32>> FragOutputTy for T {
     const IR: FragOutputIr = FragOutputIr(&TypeIr::Vector {
         n: Length::Four,
@@ -0,0 +1,62 @@
+use core::marker::PhantomData;
+
+use crate::{
+    ir::{BuiltinFn, FnIr, Length, Primitive, VectorConstructor},
+    reflection::{Fn, VectorTy},
+};
+
+/// The builtin that `vec2!`, `vec3!` and `vec4!` lower to, which builds a
+/// vector `V` of length `N` from arguments of the types in the tuple `Args`.
+/// The arguments are scalars of type `T` or shorter vectors of `T`.
+///
+/// `T` is left to be inferred, and keeps the impls for different element types
+/// apart even for vectors that implement `VectorTy` more than once.
+pub struct VectorConstructorFn<const N: usize, T, V, Args> {
+    _marker: PhantomData<fn(Args) -> (T, V)>,
+}
+
+macro_rules! impl_vector_constructor {
+    ($n:literal, [$($generics:tt)*], $args:ty => $ir:expr) => {
+        impl<V: VectorTy<$n, T>, $($generics)*> Fn for VectorConstructorFn<$n, T, V, $args> {
+            type Output = V;
+
+            const IR: FnIr = FnIr::Builtin($ir);
+        }
+    };
+}
+
+macro_rules! impl_vector_constructors {
+    ($($t:ident => $primitive:ident),*) => {$(
+        const _: () = {
+            type T = $t;
+
+            const fn splat(n: Length) -> BuiltinFn {
+                BuiltinFn::VectorSplat { n, t: Primitive::$primitive }
+            }
+
+            const fn constructor(op: VectorConstructor) -> BuiltinFn {
+                BuiltinFn::VectorConstructor { op, t: Primitive::$primitive }
+            }
+
+            impl_vector_constructor!(2, [], (T,) => splat(Length::Two));
+            impl_vector_constructor!(3, [], (T,) => splat(Length::Three));
+            impl_vector_constructor!(4, [], (T,) => splat(Length::Four));
+
+            impl_vector_constructor!(2, [], (T, T) => constructor(VectorConstructor::Vec2From11));
+
+            impl_vector_constructor!(3, [], (T, T, T) => constructor(VectorConstructor::Vec3From111));
+            impl_vector_constructor!(3, [A: VectorTy<2, T>], (T, A) => constructor(VectorConstructor::Vec3From12));
+            impl_vector_constructor!(3, [A: VectorTy<2, T>], (A, T) => constructor(VectorConstructor::Vec3From21));
+
+            impl_vector_constructor!(4, [], (T, T, T, T) => constructor(VectorConstructor::Vec4From1111));
+            impl_vector_constructor!(4, [A: VectorTy<2, T>], (T, T, A) => constructor(VectorConstructor::Vec4From112));
+            impl_vector_constructor!(4, [A: VectorTy<2, T>], (T, A, T) => constructor(VectorConstructor::Vec4From121));
+            impl_vector_constructor!(4, [A: VectorTy<3, T>], (T, A) => constructor(VectorConstructor::Vec4From13));
+            impl_vector_constructor!(4, [A: VectorTy<2, T>], (A, T, T) => constructor(VectorConstructor::Vec4From211));
+            impl_vector_constructor!(4, [A: VectorTy<2, T>, B: VectorTy<2, T>], (A, B) => constructor(VectorConstructor::Vec4From22));
+            impl_vector_constructor!(4, [A: VectorTy<3, T>], (A, T) => constructor(VectorConstructor::Vec4From31));
+        };
+    )*};
+}
+
+impl_vector_constructors!(f32 => F32, i32 => I32, u32 => U32, bool => Bool);
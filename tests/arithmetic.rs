@@ -0,0 +1,169 @@
+#![allow(dead_code)]
+
+#[macro_use]
+mod common;
+
+use common::{assert_contains, assert_not_contains, eval::Shader};
+use rsshader::{shader_item, wgsl};
+
+#[shader_item]
+fn arith(a: i32, b: u32, x: f32) -> i32 {
+    let w = a
+        .wrapping_add(a)
+        .wrapping_sub(1)
+        .wrapping_mul(a)
+        .wrapping_div(3)
+        .wrapping_rem(3)
+        .wrapping_neg();
+    let s = a.wrapping_shl(b).wrapping_shr(33);
+    let u = b
+        .wrapping_neg()
+        .saturating_add(b)
+        .saturating_sub(7)
+        .saturating_mul(b)
+        .saturating_div(2);
+    let v = a.saturating_add(w).saturating_sub(s).saturating_mul(a);
+    let e = a.div_euclid(-3) + a.rem_euclid(5) + (b.div_euclid(2) + b.rem_euclid(5)) as i32;
+    let f = x.div_euclid(0.5) + x.rem_euclid(-1.5);
+    w.wrapping_add(s)
+        .wrapping_add(u as i32)
+        .wrapping_add(v)
+        .wrapping_add(e)
+        .wrapping_add(f as i32)
+}
+
+fragment!(fs_arith => arith(0i32, 0u32, 0.0));
+
+#[test]
+fn lowers_integer_methods() {
+    let shader = Shader::new(wgsl!(fs_arith));
+
+    for (a, b, x) in [
+        (0, 0, 0.0),
+        (7, 3, 2.25),
+        (-9, 40, -3.5),
+        (i32::MAX, u32::MAX, 1e3),
+    ] {
+        assert_eq!(
+            shader.call(&[a.into(), b.into(), x.into()]),
+            arith(a, b, x).into(),
+            "arith({a}, {b}, {x})"
+        );
+    }
+}
+
+#[shader_item]
+fn ops(a: i32, b: i32, n: u32) -> i32 {
+    (a + b) * (a - b) + a / b + a % b + (a << n) + (a >> n)
+}
+
+fragment!(fs_ops => ops(0i32, 3i32, 0u32));
+
+#[test]
+fn keeps_native_operators_by_default() {
+    let wgsl = wgsl!(fs_ops);
+    assert_not_contains(wgsl, "select(");
+}
+
+#[test]
+fn follows_rust_in_rust_arithmetic() {
+    let wgsl = wgsl!(fs_ops; arithmetic = Rust);
+    assert_contains(wgsl, "select(");
+
+    let shader = Shader::new(wgsl);
+    for (a, b, n) in [(7, 3, 1), (-9, 4, 31), (-9, -4, 0), (1000, 30, 3)] {
+        assert_eq!(
+            shader.call(&[a.into(), b.into(), n.into()]),
+            ops(a, b, n).into(),
+            "ops({a}, {b}, {n})"
+        );
+    }
+}
+
+#[shader_item]
+fn edges(a: i32, b: i32, n: u32) -> i32 {
+    let w = a.wrapping_div(b) ^ a.wrapping_rem(b) ^ a.wrapping_shl(n) ^ a.wrapping_shr(n);
+    let s = a.saturating_div(b) ^ a.saturating_mul(b) ^ a.saturating_add(b) ^ a.saturating_sub(b);
+    let u =
+        (a as u32).saturating_mul(n) ^ (a as u32).saturating_add(n) ^ n.saturating_sub(a as u32);
+    w ^ s ^ u as i32
+}
+
+fragment!(fs_edges => edges(0i32, -1i32, 0u32));
+
+#[test]
+fn matches_rust_at_overflow_edges() {
+    let shader = Shader::new(wgsl!(fs_edges));
+
+    for (a, b, n) in [
+        (i32::MIN, -1, 0),
+        (i32::MIN, 2, 31),
+        (i32::MAX, -2, 32),
+        (-7, 3, 33),
+        (7, -3, 63),
+        (-1, i32::MIN, u32::MAX),
+        (65536, 65536, 4),
+    ] {
+        assert_eq!(
+            shader.call(&[a.into(), b.into(), n.into()]),
+            edges(a, b, n).into(),
+            "edges({a}, {b}, {n})"
+        );
+    }
+}
+
+#[shader_item]
+fn euclid(a: i32, b: i32, x: f32, y: f32) -> f32 {
+    let i = a.div_euclid(b) ^ (a.rem_euclid(b) << 8) ^ ((a % b) << 16);
+    let f = x.div_euclid(y) * 100.0 + x.rem_euclid(y) + x % y;
+    i as f32 + f
+}
+
+fragment!(fs_euclid => euclid(0i32, 3i32, 0.0, -1.5));
+
+#[test]
+fn matches_rust_for_negative_euclidean_division() {
+    let shader = Shader::new(wgsl!(fs_euclid; arithmetic = Rust));
+
+    for (a, b, x, y) in [
+        (-7, 3, -7.5, 2.0),
+        (7, -3, 7.5, -2.0),
+        (-7, -3, -7.5, -2.0),
+        (-6, 3, -6.0, 1.5),
+        (i32::MIN, 7, -0.25, 0.5),
+    ] {
+        assert_eq!(
+            shader.call(&[a.into(), b.into(), x.into(), y.into()]),
+            euclid(a, b, x, y).into(),
+            "euclid({a}, {b}, {x}, {y})"
+        );
+    }
+}
+
+#[shader_item]
+fn div(a: i32, b: i32, n: u32) -> i32 {
+    (a / b) ^ (a % b) ^ (a << n) ^ (a >> n)
+}
+
+fragment!(fs_div => div(0i32, 3i32, 0u32));
+
+#[test]
+fn guards_division_and_shifts_in_rust_arithmetic() {
+    let shader = Shader::new(wgsl!(fs_div; arithmetic = Rust));
+
+    // Rust panics where the shader wraps, and a divisor of `0` is replaced
+    // with `1`.
+    for (a, b, n) in [(i32::MIN, -1, 32), (7, 0, 33), (-7, 0, 40), (-9, 4, 63)] {
+        let b_guarded = if b == 0 { 1 } else { b };
+        let expected = a.wrapping_div(b_guarded)
+            ^ a.wrapping_rem(b_guarded)
+            ^ a.wrapping_shl(n)
+            ^ a.wrapping_shr(n);
+
+        assert_eq!(
+            shader.call(&[a.into(), b.into(), n.into()]),
+            expected.into(),
+            "div({a}, {b}, {n})"
+        );
+    }
+}
@@ -0,0 +1,161 @@
+#![allow(dead_code)]
+
+#[macro_use]
+mod common;
+
+use common::{Frag, Vec4, assert_contains, eval::Shader, splat, validate};
+use rsshader::{shader_item, wgsl};
+
+#[shader_item]
+fn double(x: f32) -> f32 {
+    x * 2.0 + -x / 4.0
+}
+
+#[shader_item]
+fn shift(x: u32) -> bool {
+    (x << 2) >= 8
+}
+
+#[shader_item]
+fn noop(_x: u32) {}
+
+#[shader_item]
+fn calls(n: u32) -> bool {
+    noop(n);
+    shift(n + 1)
+}
+
+fragment!(fs_main => double(0.0));
+fragment!(fs_calls => calls(0u32));
+
+#[test]
+fn lowers_function_bodies() {
+    let wgsl = wgsl!(fs_main);
+    validate(wgsl);
+
+    assert_contains(wgsl, "@fragment");
+}
+
+#[test]
+fn evaluates_like_rust() {
+    let shader = Shader::new(wgsl!(fs_main));
+
+    for x in [0.0, 1.5, -3.25] {
+        assert_eq!(shader.call(&[x.into()]), double(x).into());
+    }
+}
+
+#[test]
+fn calls_other_shader_functions() {
+    let shader = Shader::new(wgsl!(fs_calls));
+
+    for n in 0..4 {
+        assert_eq!(shader.call(&[n.into()]), calls(n).into(), "calls({n})");
+    }
+}
+
+#[shader_item]
+fn locals(mut a: f32, b: f32) -> f32 {
+    let x = a * 2.0;
+    let x = x + 1.0;
+    let mut y: f32;
+    y = b;
+    {
+        let x = 3.0;
+        a += x;
+        y += 1.0;
+    }
+    let mut z = 1.0;
+    z *= 2.0;
+    a + x + y + z
+}
+
+fragment!(fs_locals => locals(0.0, 0.0));
+
+#[test]
+fn scopes_shadowed_bindings() {
+    let shader = Shader::new(wgsl!(fs_locals));
+
+    for (a, b) in [(0.0, 0.0), (1.5, 3.0), (-2.0, 10.0)] {
+        assert_eq!(shader.call(&[a.into(), b.into()]), locals(a, b).into());
+    }
+}
+
+#[shader_item]
+fn blocks(a: f32, n: u32) -> f32 {
+    let sq = {
+        let t = a * 2.0;
+        t * t
+    };
+    if n == 0 {
+        return sq;
+    }
+    let v = 'blk: {
+        if n > 5 {
+            break 'blk 1.0;
+        }
+        if n > 3 {
+            return 0.0;
+        }
+        sq + 1.0
+    };
+    {
+        let w = v;
+        w + sq
+    }
+}
+
+fragment!(fs_blocks => blocks(0.0, 0u32));
+
+#[test]
+fn lowers_block_values_and_early_returns() {
+    let shader = Shader::new(wgsl!(fs_blocks));
+
+    for n in 0..8 {
+        assert_eq!(shader.call(&[1.5.into(), n.into()]), blocks(1.5, n).into());
+    }
+}
+
+#[shader_item]
+#[derive(Clone, Copy)]
+struct Vertex {
+    position: Vec4,
+    value: f32,
+    idx: u32,
+}
+
+#[shader_item(vertex)]
+fn vs_main(v: Vertex) -> Frag {
+    Frag {
+        position: v.position,
+        value: double(v.value),
+        idx: v.idx,
+    }
+}
+
+#[shader_item(vertex)]
+fn vs_position(position: Vec4) -> Frag {
+    Frag {
+        position,
+        value: 1.0,
+        idx: 0,
+    }
+}
+
+#[shader_item(vertex)]
+fn vs_index(idx: u32) -> Frag {
+    Frag {
+        position: splat(0.0),
+        value: 0.0,
+        idx,
+    }
+}
+
+#[test]
+fn lowers_vertex_entry_points() {
+    for wgsl in [wgsl!(vs_main), wgsl!(vs_position), wgsl!(vs_index)] {
+        validate(wgsl);
+
+        assert_contains(wgsl, "@vertex");
+    }
+}
@@ -0,0 +1,95 @@
+#![allow(dead_code)]
+
+#[macro_use]
+mod common;
+
+use common::eval::Shader;
+use rsshader::{shader_item, wgsl};
+
+#[shader_item]
+#[allow(clippy::redundant_closure_call)]
+fn closures(x: f32) -> f32 {
+    let xs = [x, 1.0, 2.0, -x];
+    let sq = |v: f32| v * v;
+    let scale = 2.0;
+    let scaled = |v: f32| {
+        let s = v * scale;
+        s + 1.0
+    };
+    let scale = 10.0;
+    let mut count = 0;
+    let mut inc = || count += 1;
+    inc();
+    inc();
+    let mut n = 3;
+    let owned = move || n as f32;
+    n = 7;
+    let pair = |(a, b): (f32, f32)| a * b;
+    let total = xs.iter().map(|v| sq(*v)).sum::<f32>() + xs.into_iter().map(scaled).sum::<f32>();
+    let imm = (|a: f32, b: f32| a - b)(x, 1.0);
+    sq(x + 1.0)
+        + scaled(x)
+        + scale
+        + count as f32
+        + owned()
+        + n as f32
+        + pair((x, 2.0))
+        + total
+        + imm
+}
+
+fragment!(fs_closures => closures(0.0));
+
+#[test]
+fn inlines_closures() {
+    let shader = Shader::new(wgsl!(fs_closures));
+
+    for x in [0.5, -2.0] {
+        assert_eq!(shader.call(&[x.into()]), closures(x).into());
+    }
+}
+
+#[shader_item]
+fn apply<F: Fn(T) -> T, T>(f: F, x: T) -> T {
+    f(x)
+}
+
+#[shader_item]
+fn apply_twice<F>(f: F, x: f32) -> f32
+where
+    F: Fn(f32) -> f32,
+{
+    f(f(x))
+}
+
+#[shader_item]
+fn scale_by<const N: u32>(x: f32) -> f32 {
+    apply(|y| y * N as f32 + x, x)
+}
+
+#[shader_item]
+fn passed_closures(x: f32, n: u32) -> f32 {
+    let k = 3.0;
+    let offset = |v: f32| v + k;
+    let a = apply(|y| y, x);
+    let b = apply(|y| y * 2.0, x);
+    let c = apply(|m| m + n, 1u32);
+    let d = apply_twice(offset, x);
+    let e = apply_twice(|v| offset(v) * k, x);
+    let f = apply(|v: f32| -> f32 { v.rem_euclid(k) }, -x);
+    a + b + c as f32 + d + e + f + scale_by::<3>(x)
+}
+
+fragment!(fs_passed_closures => passed_closures(0.0, 0u32));
+
+#[test]
+fn lowers_closures_passed_to_functions() {
+    let shader = Shader::new(wgsl!(fs_passed_closures));
+
+    for (x, n) in [(0.5, 0), (-2.0, 3)] {
+        assert_eq!(
+            shader.call(&[x.into(), n.into()]),
+            passed_closures(x, n).into()
+        );
+    }
+}
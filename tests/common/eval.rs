@@ -0,0 +1,684 @@
+//! A small interpreter for the scalar and vector subset of WGSL, following the
+//! runtime semantics of the WGSL spec. It lets tests compare what a shader
+//! computes with what the same function computes in Rust, without a GPU.
+
+use std::collections::HashMap;
+
+use naga::{
+    BinaryOperator, Block, Expression, Function, Handle, Literal, LocalVariable, MathFunction,
+    Module, ScalarKind, Statement, SwitchValue, TypeInner, UnaryOperator,
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Bool(bool),
+    I32(i32),
+    U32(u32),
+    F32(f32),
+    Vector(Vec<Value>),
+    Composite(Vec<Value>),
+    /// A local variable of the frame at a depth of the call stack, and the
+    /// path to a member of it.
+    Pointer(usize, Handle<LocalVariable>, Vec<usize>),
+}
+
+impl From<bool> for Value {
+    fn from(value: bool) -> Self {
+        Self::Bool(value)
+    }
+}
+
+impl From<i32> for Value {
+    fn from(value: i32) -> Self {
+        Self::I32(value)
+    }
+}
+
+impl From<u32> for Value {
+    fn from(value: u32) -> Self {
+        Self::U32(value)
+    }
+}
+
+impl From<f32> for Value {
+    fn from(value: f32) -> Self {
+        Self::F32(value)
+    }
+}
+
+/// A parsed shader whose tested function is the first one the body of its
+/// first entry point calls.
+pub struct Shader {
+    module: Module,
+    tested: Handle<Function>,
+}
+
+impl Shader {
+    pub fn new(source: &str) -> Self {
+        let module = super::validate(source);
+
+        // The entry point only converts its input for the function with the
+        // body of the Rust entry point.
+        let entry_point = &module.entry_points[0].function;
+        let body = first_call(&entry_point.body).expect("the entry point has no body");
+        let tested = first_call(&module.functions[body].body).expect("the body calls no function");
+
+        Self { module, tested }
+    }
+
+    /// Runs the tested function with `args`.
+    pub fn call(&self, args: &[Value]) -> Value {
+        Frame::new(&self.module, self.tested, args.to_vec(), &mut Vec::new())
+            .run()
+            .expect("the tested function returns no value")
+    }
+}
+
+fn first_call(block: &Block) -> Option<Handle<Function>> {
+    block.iter().find_map(|stmt| match stmt {
+        Statement::Call { function, .. } => Some(*function),
+        Statement::Block(block) => first_call(block),
+        _ => None,
+    })
+}
+
+enum Flow {
+    Next,
+    Break,
+    Continue,
+    Return(Option<Value>),
+}
+
+/// The local variables of every frame of the call stack, so that pointers can
+/// be passed to callees.
+type Memory = Vec<HashMap<Handle<LocalVariable>, Value>>;
+
+struct Frame<'m, 'a> {
+    module: &'m Module,
+    func: &'m Function,
+    args: Vec<Value>,
+    values: HashMap<Handle<Expression>, Value>,
+    memory: &'a mut Memory,
+    depth: usize,
+}
+
+impl<'m, 'a> Frame<'m, 'a> {
+    fn new(
+        module: &'m Module,
+        func: Handle<Function>,
+        args: Vec<Value>,
+        memory: &'a mut Memory,
+    ) -> Self {
+        let depth = memory.len();
+        memory.push(HashMap::new());
+
+        Self {
+            module,
+            func: &module.functions[func],
+            args,
+            values: HashMap::new(),
+            memory,
+            depth,
+        }
+    }
+
+    fn run(mut self) -> Option<Value> {
+        for (handle, local) in self.func.local_variables.iter() {
+            let value = match local.init {
+                Some(init) => self.eval(init),
+                None => zero(self.module, local.ty),
+            };
+
+            self.memory[self.depth].insert(handle, value);
+        }
+
+        let value = match self.block(&self.func.body) {
+            Flow::Return(value) => value,
+            _ => None,
+        };
+
+        self.memory.pop();
+        value
+    }
+
+    fn block(&mut self, block: &'m Block) -> Flow {
+        for stmt in block.iter() {
+            let flow = self.stmt(stmt);
+
+            if !matches!(flow, Flow::Next) {
+                return flow;
+            }
+        }
+
+        Flow::Next
+    }
+
+    fn stmt(&mut self, stmt: &'m Statement) -> Flow {
+        match stmt {
+            Statement::Emit(range) => {
+                for handle in range.clone() {
+                    let value = self.compute(handle);
+                    self.values.insert(handle, value);
+                }
+            }
+
+            Statement::Block(block) => return self.block(block),
+
+            Statement::If {
+                condition,
+                accept,
+                reject,
+            } => {
+                return match self.eval(*condition) {
+                    Value::Bool(true) => self.block(accept),
+                    _ => self.block(reject),
+                };
+            }
+
+            Statement::Switch { selector, cases } => {
+                let selector = self.eval(*selector);
+
+                let mut matched = false;
+                for case in cases {
+                    matched |= match case.value {
+                        SwitchValue::I32(value) => selector == Value::I32(value),
+                        SwitchValue::U32(value) => selector == Value::U32(value),
+                        SwitchValue::Default => true,
+                    };
+
+                    if matched {
+                        match self.block(&case.body) {
+                            Flow::Next if case.fall_through => continue,
+                            Flow::Next | Flow::Break => break,
+                            flow => return flow,
+                        }
+                    }
+                }
+            }
+
+            Statement::Loop {
+                body,
+                continuing,
+                break_if,
+            } => loop {
+                match self.block(body) {
+                    Flow::Break => break,
+                    Flow::Return(value) => return Flow::Return(value),
+                    Flow::Next | Flow::Continue => {}
+                }
+
+                if let Flow::Return(value) = self.block(continuing) {
+                    return Flow::Return(value);
+                }
+
+                if let Some(break_if) = break_if
+                    && self.eval(*break_if) == Value::Bool(true)
+                {
+                    break;
+                }
+            },
+
+            Statement::Break => return Flow::Break,
+            Statement::Continue => return Flow::Continue,
+            Statement::Return { value } => {
+                return Flow::Return(value.map(|value| self.eval(value)));
+            }
+
+            Statement::Store { pointer, value } => {
+                let Value::Pointer(depth, local, path) = self.eval(*pointer) else {
+                    panic!("only stores to local variables are supported");
+                };
+
+                let value = self.eval(*value);
+                let mut place = self.memory[depth].get_mut(&local).unwrap();
+                for idx in path {
+                    place = match place {
+                        Value::Vector(members) | Value::Composite(members) => &mut members[idx],
+                        _ => panic!("only vectors and composites have members"),
+                    };
+                }
+
+                *place = value;
+            }
+
+            Statement::Call {
+                function,
+                arguments,
+                result,
+            } => {
+                let args = arguments.iter().map(|arg| self.eval(*arg)).collect();
+                let value = Frame::new(self.module, *function, args, self.memory).run();
+
+                if let (Some(result), Some(value)) = (result, value) {
+                    self.values.insert(*result, value);
+                }
+            }
+
+            stmt => panic!("unsupported statement {stmt:?}"),
+        }
+
+        Flow::Next
+    }
+
+    /// The value of an expression that was emitted, or that needs no emitting.
+    fn eval(&mut self, handle: Handle<Expression>) -> Value {
+        match self.values.get(&handle) {
+            Some(value) => value.clone(),
+            None => self.compute(handle),
+        }
+    }
+
+    fn compute(&mut self, handle: Handle<Expression>) -> Value {
+        match &self.func.expressions[handle] {
+            Expression::Literal(literal) => literal_value(*literal),
+
+            Expression::ZeroValue(ty) => zero(self.module, *ty),
+            Expression::Constant(constant) => {
+                global(self.module, self.module.constants[*constant].init)
+            }
+            Expression::FunctionArgument(idx) => self.args[*idx as usize].clone(),
+            Expression::LocalVariable(local) => Value::Pointer(self.depth, *local, Vec::new()),
+
+            Expression::Load { pointer } => match self.eval(*pointer) {
+                Value::Pointer(depth, local, path) => path
+                    .into_iter()
+                    .fold(self.memory[depth][&local].clone(), member),
+                _ => panic!("only loads of local variables are supported"),
+            },
+
+            Expression::Compose { ty, components } => {
+                let components = components
+                    .iter()
+                    .map(|component| self.eval(*component))
+                    .collect::<Vec<_>>();
+
+                match self.module.types[*ty].inner {
+                    TypeInner::Vector { .. } => Value::Vector(
+                        components
+                            .into_iter()
+                            .flat_map(|component| match component {
+                                Value::Vector(lanes) => lanes,
+                                lane => vec![lane],
+                            })
+                            .collect(),
+                    ),
+                    _ => Value::Composite(components),
+                }
+            }
+
+            Expression::Splat { size, value } => {
+                Value::Vector(vec![self.eval(*value); *size as usize])
+            }
+
+            Expression::AccessIndex { base, index } => access(self.eval(*base), *index as usize),
+
+            Expression::Access { base, index } => {
+                let index = match self.eval(*index) {
+                    Value::I32(index) => index as usize,
+                    Value::U32(index) => index as usize,
+                    index => panic!("unsupported index {index:?}"),
+                };
+
+                access(self.eval(*base), index)
+            }
+
+            Expression::Unary { op, expr } => {
+                let value = self.eval(*expr);
+                map(&value, &|value| unary(*op, value))
+            }
+
+            Expression::Binary { op, left, right } => {
+                let (left, right) = (self.eval(*left), self.eval(*right));
+                zip(&left, &right, &|left, right| binary(*op, left, right))
+            }
+
+            Expression::Select {
+                condition,
+                accept,
+                reject,
+            } => {
+                let condition = self.eval(*condition);
+                let (accept, reject) = (self.eval(*accept), self.eval(*reject));
+
+                match (condition, accept, reject) {
+                    (Value::Bool(condition), accept, reject) => match condition {
+                        true => accept,
+                        false => reject,
+                    },
+
+                    (Value::Vector(conditions), Value::Vector(accept), Value::Vector(reject)) => {
+                        Value::Vector(
+                            conditions
+                                .into_iter()
+                                .zip(accept.into_iter().zip(reject))
+                                .map(|(condition, (accept, reject))| match condition {
+                                    Value::Bool(true) => accept,
+                                    _ => reject,
+                                })
+                                .collect(),
+                        )
+                    }
+
+                    _ => panic!("mismatched select operands"),
+                }
+            }
+
+            Expression::Math {
+                fun,
+                arg,
+                arg1,
+                arg2,
+                ..
+            } => {
+                let arg = self.eval(*arg);
+                let arg1 = arg1.map(|arg1| self.eval(arg1));
+                let arg2 = arg2.map(|arg2| self.eval(arg2));
+
+                match (arg1, arg2) {
+                    (None, _) => map(&arg, &|arg| math(*fun, &[arg])),
+                    (Some(arg1), None) => zip(&arg, &arg1, &|arg, arg1| math(*fun, &[arg, arg1])),
+                    (Some(arg1), Some(arg2)) => {
+                        let lanes = |value: &Value| match value {
+                            Value::Vector(lanes) => lanes.clone(),
+                            value => vec![value.clone()],
+                        };
+
+                        let (a, b, c) = (lanes(&arg), lanes(&arg1), lanes(&arg2));
+                        let result = (0..a.len())
+                            .map(|i| math(*fun, &[&a[i], &b[i], &c[i]]))
+                            .collect::<Vec<_>>();
+
+                        match arg {
+                            Value::Vector(_) => Value::Vector(result),
+                            _ => result.into_iter().next().unwrap(),
+                        }
+                    }
+                }
+            }
+
+            Expression::As {
+                expr,
+                kind,
+                convert,
+            } => {
+                let value = self.eval(*expr);
+                map(&value, &|value| cast(value, *kind, convert.is_some()))
+            }
+
+            Expression::CallResult(_) => panic!("the result of a call is used before the call"),
+
+            expr => panic!("unsupported expression {expr:?}"),
+        }
+    }
+}
+
+/// The value of a const-expression at module scope.
+fn global(module: &Module, handle: Handle<Expression>) -> Value {
+    match &module.global_expressions[handle] {
+        Expression::Literal(literal) => literal_value(*literal),
+        Expression::ZeroValue(ty) => zero(module, *ty),
+        Expression::Constant(constant) => global(module, module.constants[*constant].init),
+        Expression::Compose { ty, components } => {
+            let components = components
+                .iter()
+                .map(|component| global(module, *component));
+
+            match module.types[*ty].inner {
+                TypeInner::Vector { .. } => Value::Vector(components.collect()),
+                _ => Value::Composite(components.collect()),
+            }
+        }
+        expr => panic!("unsupported const-expression {expr:?}"),
+    }
+}
+
+fn literal_value(literal: Literal) -> Value {
+    match literal {
+        Literal::Bool(value) => Value::Bool(value),
+        Literal::I32(value) => Value::I32(value),
+        Literal::U32(value) => Value::U32(value),
+        Literal::F32(value) => Value::F32(value),
+        literal => panic!("unsupported literal {literal:?}"),
+    }
+}
+
+fn zero(module: &Module, ty: Handle<naga::Type>) -> Value {
+    let scalar = |scalar: naga::Scalar| match scalar.kind {
+        ScalarKind::Bool => Value::Bool(false),
+        ScalarKind::Sint => Value::I32(0),
+        ScalarKind::Uint => Value::U32(0),
+        ScalarKind::Float => Value::F32(0.0),
+        kind => panic!("unsupported scalar {kind:?}"),
+    };
+
+    match module.types[ty].inner {
+        TypeInner::Scalar(s) => scalar(s),
+        TypeInner::Vector { size, scalar: s } => Value::Vector(vec![scalar(s); size as usize]),
+        TypeInner::Struct { ref members, .. } => Value::Composite(
+            members
+                .iter()
+                .map(|member| zero(module, member.ty))
+                .collect(),
+        ),
+        TypeInner::Array {
+            base,
+            size: naga::ArraySize::Constant(size),
+            ..
+        } => Value::Composite(vec![zero(module, base); size.get() as usize]),
+        ref inner => panic!("unsupported type {inner:?}"),
+    }
+}
+
+/// A member of a vector or composite, or a pointer to one.
+fn access(base: Value, idx: usize) -> Value {
+    match base {
+        Value::Pointer(depth, local, mut path) => {
+            path.push(idx);
+            Value::Pointer(depth, local, path)
+        }
+        base => member(base, idx),
+    }
+}
+
+fn member(value: Value, idx: usize) -> Value {
+    match value {
+        Value::Vector(mut members) | Value::Composite(mut members) => members.swap_remove(idx),
+        value => panic!("{value:?} has no members"),
+    }
+}
+
+fn map(value: &Value, f: &dyn Fn(&Value) -> Value) -> Value {
+    match value {
+        Value::Vector(lanes) => Value::Vector(lanes.iter().map(f).collect()),
+        value => f(value),
+    }
+}
+
+/// Applies `f` to the lanes of vectors, splatting scalars like the mixed
+/// scalar and vector operators of WGSL.
+fn zip(left: &Value, right: &Value, f: &dyn Fn(&Value, &Value) -> Value) -> Value {
+    match (left, right) {
+        (Value::Vector(left), Value::Vector(right)) => {
+            Value::Vector(left.iter().zip(right).map(|(l, r)| f(l, r)).collect())
+        }
+        (Value::Vector(left), right) => Value::Vector(left.iter().map(|l| f(l, right)).collect()),
+        (left, Value::Vector(right)) => Value::Vector(right.iter().map(|r| f(left, r)).collect()),
+        (left, right) => f(left, right),
+    }
+}
+
+fn unary(op: UnaryOperator, value: &Value) -> Value {
+    match (op, value) {
+        (UnaryOperator::Negate, Value::I32(value)) => Value::I32(value.wrapping_neg()),
+        (UnaryOperator::Negate, Value::F32(value)) => Value::F32(-value),
+        (UnaryOperator::LogicalNot, Value::Bool(value)) => Value::Bool(!value),
+        (UnaryOperator::BitwiseNot, Value::I32(value)) => Value::I32(!value),
+        (UnaryOperator::BitwiseNot, Value::U32(value)) => Value::U32(!value),
+        _ => panic!("unsupported unary {op:?} of {value:?}"),
+    }
+}
+
+fn binary(op: BinaryOperator, left: &Value, right: &Value) -> Value {
+    use BinaryOperator as Op;
+
+    macro_rules! int_ops {
+        ($variant:ident, $l:expr, $r:expr, $overflows:expr) => {{
+            let (l, r) = ($l, $r);
+
+            // Division by zero and overflowing division give the dividend,
+            // and their remainder is zero.
+            let is_undefined = r == 0 || $overflows(l, r);
+
+            match op {
+                Op::Add => Value::$variant(l.wrapping_add(r)),
+                Op::Subtract => Value::$variant(l.wrapping_sub(r)),
+                Op::Multiply => Value::$variant(l.wrapping_mul(r)),
+                Op::Divide if is_undefined => Value::$variant(l),
+                Op::Divide => Value::$variant(l / r),
+                Op::Modulo if is_undefined => Value::$variant(0),
+                Op::Modulo => Value::$variant(l % r),
+                Op::And => Value::$variant(l & r),
+                Op::InclusiveOr => Value::$variant(l | r),
+                Op::ExclusiveOr => Value::$variant(l ^ r),
+                Op::Equal => Value::Bool(l == r),
+                Op::NotEqual => Value::Bool(l != r),
+                Op::Less => Value::Bool(l < r),
+                Op::LessEqual => Value::Bool(l <= r),
+                Op::Greater => Value::Bool(l > r),
+                Op::GreaterEqual => Value::Bool(l >= r),
+                _ => panic!("unsupported binary {op:?}"),
+            }
+        }};
+    }
+
+    match (left, right) {
+        (Value::I32(l), Value::U32(r)) => match op {
+            Op::ShiftLeft => Value::I32(l.wrapping_shl(*r)),
+            Op::ShiftRight => Value::I32(l.wrapping_shr(*r)),
+            _ => panic!("unsupported binary {op:?} of i32 and u32"),
+        },
+        (Value::U32(l), Value::U32(r)) if matches!(op, Op::ShiftLeft | Op::ShiftRight) => {
+            match op {
+                Op::ShiftLeft => Value::U32(l.wrapping_shl(*r)),
+                _ => Value::U32(l.wrapping_shr(*r)),
+            }
+        }
+
+        (Value::I32(l), Value::I32(r)) => int_ops!(I32, *l, *r, |l, r| l == i32::MIN && r == -1),
+        (Value::U32(l), Value::U32(r)) => int_ops!(U32, *l, *r, |_, _| false),
+
+        (Value::F32(l), Value::F32(r)) => {
+            let (l, r) = (*l, *r);
+
+            match op {
+                Op::Add => Value::F32(l + r),
+                Op::Subtract => Value::F32(l - r),
+                Op::Multiply => Value::F32(l * r),
+                Op::Divide => Value::F32(l / r),
+                Op::Modulo => Value::F32(l - r * (l / r).trunc()),
+                Op::Equal => Value::Bool(l == r),
+                Op::NotEqual => Value::Bool(l != r),
+                Op::Less => Value::Bool(l < r),
+                Op::LessEqual => Value::Bool(l <= r),
+                Op::Greater => Value::Bool(l > r),
+                Op::GreaterEqual => Value::Bool(l >= r),
+                _ => panic!("unsupported binary {op:?} of f32"),
+            }
+        }
+
+        (Value::Bool(l), Value::Bool(r)) => match op {
+            Op::And | Op::LogicalAnd => Value::Bool(*l && *r),
+            Op::InclusiveOr | Op::LogicalOr => Value::Bool(*l || *r),
+            Op::Equal => Value::Bool(l == r),
+            Op::NotEqual | Op::ExclusiveOr => Value::Bool(l != r),
+            _ => panic!("unsupported binary {op:?} of bool"),
+        },
+
+        _ => panic!("unsupported binary {op:?} of {left:?} and {right:?}"),
+    }
+}
+
+fn math(fun: MathFunction, args: &[&Value]) -> Value {
+    use MathFunction as M;
+
+    match (fun, args) {
+        (M::Abs, [Value::I32(x)]) => Value::I32(x.wrapping_abs()),
+        (M::Abs, [Value::F32(x)]) => Value::F32(x.abs()),
+        (M::Abs, [Value::U32(x)]) => Value::U32(*x),
+        (M::Sign, [Value::I32(x)]) => Value::I32(x.signum()),
+        (M::Sign, [Value::F32(x)]) => Value::F32(match *x {
+            x if x > 0.0 => 1.0,
+            x if x < 0.0 => -1.0,
+            _ => 0.0,
+        }),
+        (M::Trunc, [Value::F32(x)]) => Value::F32(x.trunc()),
+        (M::Floor, [Value::F32(x)]) => Value::F32(x.floor()),
+
+        (M::Min, [Value::I32(x), Value::I32(y)]) => Value::I32(*x.min(y)),
+        (M::Min, [Value::U32(x), Value::U32(y)]) => Value::U32(*x.min(y)),
+        (M::Min, [Value::F32(x), Value::F32(y)]) => Value::F32(x.min(*y)),
+        (M::Max, [Value::I32(x), Value::I32(y)]) => Value::I32(*x.max(y)),
+        (M::Max, [Value::U32(x), Value::U32(y)]) => Value::U32(*x.max(y)),
+        (M::Max, [Value::F32(x), Value::F32(y)]) => Value::F32(x.max(*y)),
+
+        // `clamp(e, low, high)` is `min(max(e, low), high)`.
+        (M::Clamp, [x, low, high]) => math(M::Min, &[&math(M::Max, &[x, low]), high]),
+
+        _ => panic!("unsupported math function {fun:?} of {args:?}"),
+    }
+}
+
+/// Float to integer conversions of NaN and of values out of range are not
+/// specified by WGSL. This gives a value no sane template would expect.
+const UNSPECIFIED: i64 = 0x5eed;
+
+fn cast(value: &Value, kind: ScalarKind, convert: bool) -> Value {
+    if !convert {
+        let bits = match *value {
+            Value::I32(x) => x as u32,
+            Value::U32(x) => x,
+            Value::F32(x) => x.to_bits(),
+            _ => panic!("unsupported bitcast of {value:?}"),
+        };
+
+        return match kind {
+            ScalarKind::Sint => Value::I32(bits as i32),
+            ScalarKind::Uint => Value::U32(bits),
+            ScalarKind::Float => Value::F32(f32::from_bits(bits)),
+            _ => panic!("unsupported bitcast to {kind:?}"),
+        };
+    }
+
+    let float_to_int = |x: f32, min: f64, max: f64| match x.trunc() as f64 {
+        x if x >= min && x <= max => x as i64,
+        _ => UNSPECIFIED,
+    };
+
+    match (value.clone(), kind) {
+        (Value::Bool(x), ScalarKind::Bool) => Value::Bool(x),
+        (Value::Bool(x), ScalarKind::Sint) => Value::I32(x as i32),
+        (Value::Bool(x), ScalarKind::Uint) => Value::U32(x as u32),
+        (Value::Bool(x), ScalarKind::Float) => Value::F32(x as u8 as f32),
+
+        (Value::I32(x), ScalarKind::Bool) => Value::Bool(x != 0),
+        (Value::I32(x), ScalarKind::Sint) => Value::I32(x),
+        (Value::I32(x), ScalarKind::Uint) => Value::U32(x as u32),
+        (Value::I32(x), ScalarKind::Float) => Value::F32(x as f32),
+
+        (Value::U32(x), ScalarKind::Bool) => Value::Bool(x != 0),
+        (Value::U32(x), ScalarKind::Sint) => Value::I32(x as i32),
+        (Value::U32(x), ScalarKind::Uint) => Value::U32(x),
+        (Value::U32(x), ScalarKind::Float) => Value::F32(x as f32),
+
+        (Value::F32(x), ScalarKind::Bool) => Value::Bool(x != 0.0),
+        (Value::F32(x), ScalarKind::Sint) => {
+            Value::I32(float_to_int(x, i32::MIN as f64, i32::MAX as f64) as i32)
+        }
+        (Value::F32(x), ScalarKind::Uint) => {
+            Value::U32(float_to_int(x, 0.0, u32::MAX as f64) as u32)
+        }
+        (Value::F32(x), ScalarKind::Float) => Value::F32(x),
+
+        _ => panic!("unsupported conversion of {value:?} to {kind:?}"),
+    }
+}
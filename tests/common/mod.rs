@@ -0,0 +1,174 @@
+//! Types and checks shared by the integration tests. Each test builds a shader
+//! with `wgsl!` and runs the output through naga.
+#![allow(dead_code, unused_macros)]
+
+pub mod eval;
+
+use rsshader::{
+    ir::{BuiltinFn, FnIr, Length, Primitive, TypeIr},
+    shader_item,
+};
+
+/// Parses and validates `source` with naga, panicking with the source and the
+/// error otherwise.
+pub fn validate(source: &str) -> naga::Module {
+    let module = match naga::front::wgsl::parse_str(source) {
+        Ok(module) => module,
+        Err(error) => panic!("{source}\n{}", error.emit_to_string(source)),
+    };
+
+    let mut validator = naga::valid::Validator::new(
+        naga::valid::ValidationFlags::all(),
+        naga::valid::Capabilities::all(),
+    );
+
+    if let Err(error) = validator.validate(&module) {
+        panic!("{source}\n{}", error.emit_to_string(source));
+    }
+
+    module
+}
+
+/// Asserts that `source` contains `pattern`.
+#[track_caller]
+pub fn assert_contains(source: &str, pattern: &str) {
+    assert!(
+        source.contains(pattern),
+        "{source}\ndoes not contain `{pattern}`"
+    );
+}
+
+/// Asserts that `source` does not contain `pattern`.
+#[track_caller]
+pub fn assert_not_contains(source: &str, pattern: &str) {
+    assert!(!source.contains(pattern), "{source}\ncontains `{pattern}`");
+}
+
+/// Declares a vector type with its lanes as fields, the way math crates
+/// integrate with rsshader.
+macro_rules! vector {
+    ($ident:ident: $n:literal x $t:ident => $primitive:ident, $length:ident { $($lane:ident $idx:literal),* }) => {
+        #[derive(Debug, Clone, Copy, PartialEq)]
+        pub struct $ident {
+            $(pub $lane: $t),*
+        }
+
+        impl rsshader::reflection::Ty for $ident {
+            const IR: TypeIr = TypeIr::Vector {
+                n: Length::$length,
+                t: Primitive::$primitive,
+            };
+        }
+
+        impl rsshader::reflection::VectorTy<$n, $t> for $ident {}
+
+        impl From<$ident> for eval::Value {
+            fn from(value: $ident) -> Self {
+                Self::Vector(vec![$(value.$lane.into()),*])
+            }
+        }
+
+        $(
+            impl rsshader::reflection::Field<{ rsshader::reflection::name_id(stringify!($lane)) }>
+                for $ident
+            {
+                type Ty = $t;
+                const IDX: usize = $idx;
+            }
+        )*
+    };
+}
+
+vector!(Vec2: 2 x f32 => F32, Two { x 0, y 1 });
+vector!(Vec3: 3 x f32 => F32, Three { x 0, y 1, z 2 });
+vector!(Vec4: 4 x f32 => F32, Four { x 0, y 1, z 2, w 3 });
+vector!(IVec4: 4 x i32 => I32, Four { x 0, y 1, z 2, w 3 });
+vector!(UVec4: 4 x u32 => U32, Four { x 0, y 1, z 2, w 3 });
+
+/// Stand-ins for the constructor macros of math crates. Shader bodies expand
+/// them by name, so only their Rust meaning matters here.
+macro_rules! vec2 {
+    ($x:expr, $y:expr $(,)?) => {
+        $crate::common::Vec2 { x: $x, y: $y }
+    };
+}
+
+macro_rules! vec4 {
+    ($x:expr, $y:expr, $z:expr, $w:expr $(,)?) => {
+        $crate::common::Vec4 {
+            x: $x,
+            y: $y,
+            z: $z,
+            w: $w,
+        }
+    };
+    ($xy:expr, $z:expr, $w:expr $(,)?) => {{
+        let xy: $crate::common::Vec2 = $xy;
+        $crate::common::Vec4 {
+            x: xy.x,
+            y: xy.y,
+            z: $z,
+            w: $w,
+        }
+    }};
+    ($v:expr $(,)?) => {{
+        let v: f32 = $v;
+        $crate::common::Vec4 {
+            x: v,
+            y: v,
+            z: v,
+            w: v,
+        }
+    }};
+}
+
+/// The input of the fragment entry points of the tests.
+#[shader_item(fragment)]
+#[derive(Clone, Copy)]
+pub struct Frag {
+    #[position]
+    pub position: Vec4,
+    pub value: f32,
+    pub idx: u32,
+}
+
+/// A `Vec4` with every element set to `x`, standing in for the splat
+/// constructor of a math crate.
+pub fn splat(x: f32) -> Vec4 {
+    Vec4 {
+        x,
+        y: x,
+        z: x,
+        w: x,
+    }
+}
+
+#[doc(hidden)]
+#[allow(non_camel_case_types)]
+pub struct splat {}
+
+impl rsshader::reflection::Fn for splat {
+    type Output = Vec4;
+
+    const IR: FnIr = FnIr::Builtin(BuiltinFn::VectorSplat {
+        n: Length::Four,
+        t: Primitive::F32,
+    });
+}
+
+impl rsshader::reflection::Call<(f32,)> for splat {
+    type Fn = Self;
+}
+
+/// Declares the fragment entry point `$fs`, whose body calls the tested
+/// function first so that `eval::Shader` can run it with any arguments. The
+/// arguments written here only need the right types.
+macro_rules! fragment {
+    ($fs:ident => $($call:tt)*) => {
+        #[rsshader::shader_item(fragment)]
+        fn $fs(_f: $crate::common::Frag) -> $crate::common::Vec4 {
+            $($call)*;
+            $crate::common::splat(0.0)
+        }
+    };
+}
@@ -0,0 +1,115 @@
+#![allow(dead_code)]
+
+#[macro_use]
+mod common;
+
+use common::{Vec4, assert_contains, eval::Shader};
+use rsshader::{shader_item, wgsl};
+
+#[shader_item]
+#[derive(Clone, Copy)]
+struct Inner {
+    a: u32,
+    b: f32,
+}
+
+#[shader_item]
+#[derive(Clone, Copy)]
+struct Pair(Inner, bool);
+
+#[shader_item]
+const GAMMA: f32 = 2.2;
+
+const fn make_kernel() -> [f32; 5] {
+    let mut k = [0.0; 5];
+    let mut i = 0;
+    while i < 5 {
+        k[i] = i as f32 * 0.25;
+        i += 1;
+    }
+    k
+}
+
+#[shader_item]
+const KERNEL: [f32; 5] = make_kernel();
+
+#[shader_item]
+const PAIRS: [Pair; 2] = [
+    Pair(Inner { a: 7, b: 1.5 }, true),
+    Pair(Inner { a: 9, b: -2.0 }, false),
+];
+
+#[shader_item]
+const ORIGIN: Vec4 = Vec4 {
+    x: 0.0,
+    y: 1.0,
+    z: 2.0,
+    w: 3.0,
+};
+
+#[shader_item]
+const COUNT: usize = 3;
+
+#[shader_item]
+const MIN: i32 = i32::MIN;
+
+#[shader_item]
+fn consts(i: u32) -> f32 {
+    let p = PAIRS[(i % 2) as usize];
+    let v = ORIGIN;
+    GAMMA * KERNEL[(i % 5) as usize]
+        + KERNEL[2]
+        + p.0.b
+        + v.y
+        + COUNT as f32
+        + crate::GAMMA
+        + (MIN + 1) as f32
+}
+
+fragment!(fs_consts => consts(0u32));
+
+#[test]
+fn embeds_const_items() {
+    let wgsl = wgsl!(fs_consts);
+    let shader = Shader::new(wgsl);
+
+    for i in 0..6 {
+        assert_eq!(shader.call(&[i.into()]), consts(i).into(), "consts({i})");
+    }
+}
+
+#[shader_item]
+static BAYER: [u32; 16] = [0, 8, 2, 10, 12, 4, 14, 6, 3, 11, 1, 9, 15, 7, 13, 5];
+
+#[shader_item]
+static TONEMAP: [Vec4; 2] = [
+    Vec4 {
+        x: 0.0,
+        y: 0.5,
+        z: 1.0,
+        w: 1.0,
+    },
+    ORIGIN,
+];
+
+#[shader_item]
+fn dither(i: u32) -> f32 {
+    let t = BAYER;
+    BAYER[(i & 15) as usize] as f32 / 16.0
+        + TONEMAP[(i & 1) as usize].y
+        + t[(i % 16) as usize] as f32
+        + BAYER[3] as f32
+}
+
+fragment!(fs_dither => dither(0u32));
+
+#[test]
+fn declares_statics_as_lookup_tables() {
+    let wgsl = wgsl!(fs_dither);
+    assert_contains(wgsl, "array<u32, 16>");
+
+    let shader = Shader::new(wgsl);
+    for i in [0, 5, 16, 31] {
+        assert_eq!(shader.call(&[i.into()]), dither(i).into(), "dither({i})");
+    }
+}
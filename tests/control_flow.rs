@@ -0,0 +1,246 @@
+#![allow(dead_code)]
+
+#[macro_use]
+mod common;
+
+use common::{assert_contains, eval::Shader};
+use rsshader::{shader_item, wgsl};
+
+#[shader_item]
+fn branches(x: f32, n: u32) -> f32 {
+    let y: f32;
+    if x > 0.0 {
+        y = x;
+    } else if x < -1.0 {
+        y = -x;
+    } else {
+        y = 1.0;
+    }
+    if n == 0 {
+        return y;
+    }
+    let z = if n > 2 {
+        y * 2.0
+    } else if n > 1 {
+        y
+    } else {
+        return 0.0;
+    };
+    let w: u32 = if z > 1.0 { 1 } else { 2 };
+    if w == 1 { z } else { -z }
+}
+
+fragment!(fs_branches => branches(0.0, 0u32));
+
+#[test]
+fn lowers_if_statements_and_expressions() {
+    let shader = Shader::new(wgsl!(fs_branches));
+
+    for x in [2.0, 0.5, -0.5, -3.0] {
+        for n in 0..4 {
+            assert_eq!(
+                shader.call(&[x.into(), n.into()]),
+                branches(x, n).into(),
+                "branches({x}, {n})"
+            );
+        }
+    }
+}
+
+#[shader_item]
+fn loops(n: u32) -> u32 {
+    let mut i = 0;
+    let mut acc = 0;
+    while i < n {
+        i += 1;
+        if i == 3 {
+            continue;
+        }
+        acc += i;
+    }
+    let mut rounds = 0;
+    let found = 'outer: loop {
+        rounds += 1;
+        let mut j: u32 = 0;
+        loop {
+            j += 1;
+            if j > 10 {
+                break 'outer j + rounds;
+            }
+            if j == rounds {
+                continue 'outer;
+            }
+            if j == 7 && rounds < 10 {
+                break;
+            }
+        }
+        acc += 1;
+    };
+    if found > 2 { found * 2 } else { acc }
+}
+
+fragment!(fs_loops => loops(0u32));
+
+#[test]
+fn lowers_loops_with_labels() {
+    let shader = Shader::new(wgsl!(fs_loops));
+
+    for n in 0..6 {
+        assert_eq!(shader.call(&[n.into()]), loops(n).into(), "loops({n})");
+    }
+}
+
+#[shader_item]
+fn ranges(n: u32, m: i32) -> u32 {
+    let mut acc = 0;
+    for i in 0..n {
+        acc += i;
+    }
+    for i in (1..=n).rev() {
+        if i == 2 {
+            continue;
+        }
+        acc += i * 3;
+    }
+    'outer: for j in (-4..m).step_by(3) {
+        for k in (0..=10).rev().step_by(2) {
+            if j > 5 {
+                break 'outer;
+            }
+            acc += k;
+        }
+    }
+    for mut k in 0..4u32 {
+        k *= 2;
+        acc += k;
+    }
+    acc
+}
+
+fragment!(fs_ranges => ranges(0u32, 0i32));
+
+#[test]
+fn lowers_for_loops_over_ranges() {
+    let shader = Shader::new(wgsl!(fs_ranges));
+
+    for (n, m) in [(0, 0), (4, 3), (5, -5), (2, 12)] {
+        assert_eq!(shader.call(&[n.into(), m.into()]), ranges(n, m).into());
+    }
+}
+
+#[shader_item]
+fn matches(n: u32, m: i32, b: bool) -> f32 {
+    let mut acc = 0.0;
+    match n {
+        0 => acc = 1.0,
+        1 | 2 => {
+            acc = 2.0;
+        }
+        3..=5 => acc = 3.0,
+        _ => {}
+    }
+    let x = match m {
+        -1 => 0.5,
+        -2147483648 => 0.25,
+        k @ 0..=3 => {
+            if k > 1 {
+                acc
+            } else {
+                -acc
+            }
+        }
+        _ => 0.0,
+    };
+    let z = match b {
+        true => x,
+        false => -x,
+    };
+    for i in 0..n {
+        match i {
+            2 => break,
+            3 => continue,
+            _ => acc += 1.0,
+        }
+    }
+    match m {
+        k if k > 10 => z,
+        k if k < -10 => acc,
+        _ => acc + z,
+    }
+}
+
+fragment!(fs_matches => matches(0u32, 0i32, true));
+
+#[test]
+fn lowers_match_to_switch_and_if_chains() {
+    let wgsl = wgsl!(fs_matches);
+    assert_contains(wgsl, "switch");
+
+    let shader = Shader::new(wgsl);
+    for n in [0, 1, 2, 4, 9] {
+        for m in [-2147483648, -20, -1, 0, 3, 11] {
+            for b in [true, false] {
+                assert_eq!(
+                    shader.call(&[n.into(), m.into(), b.into()]),
+                    matches(n, m, b).into(),
+                    "matches({n}, {m}, {b})"
+                );
+            }
+        }
+    }
+}
+
+#[shader_item]
+fn shift(x: u32) -> bool {
+    (x << 2) >= 8
+}
+
+#[shader_item]
+fn logic(a: u32, b: bool) -> bool {
+    let c = a > 2 && shift(a);
+    let d = b || a == 0 && !c;
+    let e = c
+        && match a {
+            1 => true,
+            _ => b,
+        };
+    c || d || e
+}
+
+fragment!(fs_logic => logic(0u32, true));
+
+#[shader_item]
+fn bump(c: &mut u32) -> bool {
+    *c += 1;
+    *c > 101
+}
+
+#[shader_item]
+fn guarded(a: u32) -> u32 {
+    let mut c = 100;
+    let _ = a > 2 && bump(&mut c);
+    let _ = a == 0 || bump(&mut c);
+    c
+}
+
+fragment!(fs_guarded => guarded(0u32));
+
+#[test]
+fn short_circuits_logical_operators() {
+    let shader = Shader::new(wgsl!(fs_logic));
+
+    for a in 0..5 {
+        for b in [true, false] {
+            assert_eq!(shader.call(&[a.into(), b.into()]), logic(a, b).into());
+        }
+    }
+}
+
+#[test]
+fn skips_calls_in_unevaluated_operands() {
+    let shader = Shader::new(wgsl!(fs_guarded));
+
+    for a in 0..5 {
+        assert_eq!(shader.call(&[a.into()]), guarded(a).into());
+    }
+}
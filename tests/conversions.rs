@@ -0,0 +1,110 @@
+#![allow(dead_code)]
+
+#[macro_use]
+mod common;
+
+use common::{IVec4, UVec4, Vec4, eval::Shader, splat};
+use rsshader::{shader_item, wgsl};
+
+#[shader_item]
+#[derive(Clone, Copy)]
+pub struct Complex {
+    re: f32,
+    im: f32,
+}
+
+#[shader_item]
+impl From<bool> for Complex {
+    fn from(b: bool) -> Self {
+        Complex {
+            re: f32::from(b),
+            im: 0.0,
+        }
+    }
+}
+
+#[shader_item]
+impl From<Vec4> for IVec4 {
+    #[builtin(convert)]
+    fn from(v: Vec4) -> Self {
+        IVec4 {
+            x: v.x as i32,
+            y: v.y as i32,
+            z: v.z as i32,
+            w: v.w as i32,
+        }
+    }
+}
+
+#[shader_item]
+impl From<Vec4> for UVec4 {
+    #[builtin(convert)]
+    fn from(v: Vec4) -> Self {
+        UVec4 {
+            x: v.x as u32,
+            y: v.y as u32,
+            z: v.z as u32,
+            w: v.w as u32,
+        }
+    }
+}
+
+#[shader_item]
+#[allow(clippy::unnecessary_cast)]
+fn casts(x: f32) -> u32 {
+    let i = x as i32;
+    let u = (x * 2.0) as u32;
+    let f = i as f32 + u as f32 + 1 as f32;
+    let n = -1i32 as u32;
+    let b = u > 2;
+    let k: u32 = b.into();
+    let c: Complex = b.into();
+    let idx = (u % 2) as usize;
+    let g = [1.0, 2.0][idx] + f + c.re + Complex::from(true).re;
+    n ^ k ^ ((b as u32) << 1) ^ ((i32::from(b) as u32) << 2) ^ g as u32
+}
+
+fragment!(fs_casts => casts(0.0));
+
+#[test]
+fn casts_like_rust() {
+    let shader = Shader::new(wgsl!(fs_casts));
+
+    // Float to integer casts saturate and map NaN to zero.
+    for x in [0.0, 1.5, -3.75, 1e10, -1e10, f32::NAN, f32::INFINITY] {
+        assert_eq!(shader.call(&[x.into()]), casts(x).into(), "casts({x})");
+    }
+}
+
+#[shader_item]
+fn vector_from(v: Vec4) -> IVec4 {
+    let a = IVec4::from(v);
+    let b: UVec4 = v.into();
+    IVec4 {
+        z: b.z as i32,
+        w: b.w as i32,
+        ..a
+    }
+}
+
+fragment!(fs_vector_from => vector_from(splat(0.0)));
+
+#[test]
+fn converts_vectors_like_rust() {
+    let shader = Shader::new(wgsl!(fs_vector_from));
+
+    // Each lane saturates and maps NaN to zero like a scalar cast.
+    let lanes = [
+        [0.0, 1.5, -3.75, 2147483520.0],
+        [1e10, -1e10, f32::NAN, f32::INFINITY],
+        [-2147483648.0, 4294967040.0, -1.0, f32::NEG_INFINITY],
+    ];
+    for [x, y, z, w] in lanes {
+        let v = Vec4 { x, y, z, w };
+        assert_eq!(
+            shader.call(&[v.into()]),
+            vector_from(v).into(),
+            "vector_from({v:?})"
+        );
+    }
+}
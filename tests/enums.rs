@@ -0,0 +1,215 @@
+#![allow(dead_code)]
+
+#[macro_use]
+mod common;
+
+use common::eval::Shader;
+use rsshader::{shader_item, wgsl};
+
+#[shader_item]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Mode {
+    A,
+    B = 4,
+    C,
+}
+
+#[shader_item]
+#[repr(i32)]
+#[derive(Clone, Copy, PartialEq)]
+enum Sign {
+    Neg = -1,
+    Zero,
+    Pos,
+}
+
+#[shader_item]
+const DEFAULT_MODE: Mode = Mode::C;
+
+#[shader_item]
+#[derive(Clone, Copy)]
+struct Settings {
+    mode: Mode,
+    scale: f32,
+}
+
+#[shader_item]
+impl Mode {
+    fn weight(self) -> f32 {
+        match self {
+            Mode::A => 1.0,
+            Self::B | Mode::C => 2.0,
+        }
+    }
+}
+
+#[shader_item]
+fn modes(n: u32, x: f32) -> f32 {
+    let m = match n {
+        0 => Mode::A,
+        1 => Mode::B,
+        _ => DEFAULT_MODE,
+    };
+    let s = if x < 0.0 {
+        Sign::Neg
+    } else if x == 0.0 {
+        Sign::Zero
+    } else {
+        Sign::Pos
+    };
+    let settings = Settings {
+        mode: m,
+        scale: 2.0,
+    };
+    let mut total = settings.mode.weight() * settings.scale;
+    if m == Mode::B && m != DEFAULT_MODE {
+        total += 1.0;
+    }
+    let y = match s {
+        Sign::Neg => -1.0,
+        Sign::Zero => 0.0,
+        _ => 1.0,
+    };
+    let g = match m {
+        Mode::A if y > 0.0 => 3.0,
+        Mode::C => 4.0,
+        _ => 5.0,
+    };
+    total + y + g + m as u32 as f32 + s as i32 as f32
+}
+
+fragment!(fs_modes => modes(0u32, 0.0));
+
+#[test]
+fn lowers_fieldless_enums_to_integers() {
+    let shader = Shader::new(wgsl!(fs_modes));
+
+    for n in 0..3 {
+        for x in [-1.0, 0.0, 2.0] {
+            assert_eq!(
+                shader.call(&[n.into(), x.into()]),
+                modes(n, x).into(),
+                "modes({n}, {x})"
+            );
+        }
+    }
+}
+
+#[shader_item]
+#[derive(Clone, Copy)]
+struct Hit {
+    t: f32,
+    id: u32,
+}
+
+#[shader_item]
+#[derive(Clone, Copy)]
+enum Shape {
+    Circle(f32),
+    Rect { w: f32, h: f32 },
+    Empty,
+}
+
+#[shader_item]
+fn intersect(t: f32) -> Option<Hit> {
+    if t < 0.0 {
+        return None;
+    }
+    Some(Hit { t, id: 1 })
+}
+
+#[shader_item]
+fn area(s: Shape) -> f32 {
+    match s {
+        Shape::Circle(r) => 3.0 * r * r,
+        Shape::Rect { w, h } => w * h,
+        Shape::Empty => 0.0,
+    }
+}
+
+#[shader_item]
+fn pick(a: Option<f32>, b: f32) -> f32 {
+    a.unwrap_or(b)
+}
+
+#[shader_item]
+fn options(t: f32) -> f32 {
+    let mut best = None;
+    let mut i = 0;
+    while i < 3 {
+        if let Some(hit) = intersect(t - i as f32) {
+            best = Some(hit.t);
+        }
+        i += 1;
+    }
+    let Some(hit) = intersect(t) else {
+        return -1.0;
+    };
+    let n = match best {
+        Some(x) if x > 1.0 => x,
+        Some(_) => 1.0,
+        None => 0.0,
+    };
+    let shapes =
+        area(Shape::Circle(1.0)) + area(Shape::Rect { w: 2.0, h: hit.t }) + area(Shape::Empty);
+    let c = if intersect(t).is_some() { 1.0 } else { 2.0 };
+    let d = if best.is_none() { 4.0 } else { 0.0 };
+    let q = if let Shape::Rect { w, .. } = (Shape::Rect { w: 1.0, h: 2.0 }) {
+        w
+    } else {
+        0.0
+    };
+    n + shapes
+        + c
+        + d
+        + q
+        + pick(None, 2.0)
+        + pick(Some(3.0), 1.0)
+        + best.unwrap_or(0.5)
+        + hit.id as f32
+}
+
+fragment!(fs_options => options(0.0));
+
+#[test]
+fn lowers_enums_with_fields_to_tagged_structs() {
+    let shader = Shader::new(wgsl!(fs_options));
+
+    for t in [-1.0, 0.5, 1.5, 4.0] {
+        assert_eq!(shader.call(&[t.into()]), options(t).into(), "options({t})");
+    }
+}
+
+#[shader_item]
+#[derive(Clone, Copy)]
+struct Pair(f32, Hit);
+
+#[shader_item]
+fn nested(t: f32) -> f32 {
+    let a = match intersect(t) {
+        Some(Hit { t, id }) => t + id as f32,
+        None => 0.0,
+    };
+    let pair = if t > 1.0 {
+        Some(Pair(t, Hit { t: 2.0, id: 3 }))
+    } else {
+        None
+    };
+    let b = if let Some(Pair(x, Hit { id, .. })) = pair {
+        x * id as f32
+    } else {
+        -1.0
+    };
+    a + b
+}
+
+fragment!(fs_nested => nested(0.0));
+
+#[test]
+fn destructures_structs_inside_variant_patterns() {
+    let shader = Shader::new(wgsl!(fs_nested));
+
+    for t in [-1.0, 0.5, 1.5] {
+        assert_eq!(shader.call(&[t.into()]), nested(t).into(), "nested({t})");
+    }
+}
@@ -0,0 +1,109 @@
+#![allow(dead_code)]
+
+#[macro_use]
+mod common;
+
+use common::{Frag, Vec4, assert_contains, eval::Shader};
+use rsshader::{shader_item, wgsl};
+
+#[shader_item]
+fn scale<T: rsshader::reflection::Mul<Output = T>>(x: T, k: T) -> T {
+    x * k
+}
+
+#[shader_item]
+fn sum_n<const N: usize>(x: f32) -> f32 {
+    let mut s = 0.0;
+    for _ in 0..N {
+        s += x;
+    }
+    s
+}
+
+#[shader_item]
+fn last<T: Copy, const N: usize>(arr: [T; N]) -> T {
+    arr[N - 1]
+}
+
+#[shader_item]
+fn generics(x: f32, n: u32) -> f32 {
+    let a = scale(x, 2.0);
+    let b = scale(n, 4);
+    let c = sum_n::<3>(a) + sum_n::<5>(1.0) + sum_n::<3>(x);
+    let d = last([b, 2, 3]) + last([1u32, n]);
+    let e = c * scale::<f32>(1.0, 2.0) + last::<f32, 1>([x]);
+    if d > 6 { e } else { -e }
+}
+
+fragment!(fs_generics => generics(0.0, 0u32));
+
+#[test]
+fn monomorphizes_generic_functions() {
+    let shader = Shader::new(wgsl!(fs_generics));
+
+    for (x, n) in [(0.5, 0), (-2.0, 3)] {
+        assert_eq!(shader.call(&[x.into(), n.into()]), generics(x, n).into());
+    }
+}
+
+#[shader_item(fragment)]
+fn fs_blur<const N: usize>(f: Frag) -> Vec4 {
+    let mut p = f.position;
+    p.x *= sum_n::<N>(f.value);
+    p
+}
+
+#[test]
+fn instantiates_generic_entry_points() {
+    let wgsl = wgsl!(fs_blur::<5>);
+    let shader = Shader::new(wgsl);
+    assert_contains(wgsl, "@fragment");
+
+    assert_eq!(shader.call(&[1.5.into()]), sum_n::<5>(1.5).into());
+}
+
+#[shader_item]
+const SIZE: usize = 3;
+
+#[shader_item]
+fn fill<const N: usize>(x: f32) -> f32 {
+    let xs = [x; N];
+    let mut ys = [0.5; SIZE];
+    ys[SIZE - 1] = x;
+    xs[N - 1] * 2.0 + ys[0] + ys[2]
+}
+
+fragment!(fs_fill => fill::<4>(0.0));
+
+#[test]
+fn repeats_with_const_lengths() {
+    let shader = Shader::new(wgsl!(fs_fill));
+
+    for x in [0.5, -2.0] {
+        assert_eq!(shader.call(&[x.into()]), fill::<4>(x).into());
+    }
+}
+
+#[shader_item]
+fn replace<'a, T: Copy>(a: &'a mut T, b: &'a T) -> T {
+    let old = *a;
+    *a = *b;
+    old
+}
+
+#[shader_item]
+fn lifetimes(x: f32, y: f32) -> f32 {
+    let mut m = x;
+    replace(&mut m, &y) + replace::<f32>(&mut m, &1.0) * m
+}
+
+fragment!(fs_lifetimes => lifetimes(0.0, 0.0));
+
+#[test]
+fn ignores_lifetime_parameters() {
+    let shader = Shader::new(wgsl!(fs_lifetimes));
+
+    for (x, y) in [(0.5, 2.0), (3.0, -1.0)] {
+        assert_eq!(shader.call(&[x.into(), y.into()]), lifetimes(x, y).into());
+    }
+}
@@ -0,0 +1,344 @@
+#![allow(dead_code)]
+
+#[macro_use]
+mod common;
+
+use common::{Vec4, eval::Shader, splat, validate};
+use rsshader::{shader_item, wgsl};
+
+#[shader_item]
+#[derive(Clone, Copy)]
+struct Inner {
+    a: u32,
+    b: f32,
+}
+
+#[shader_item]
+impl Inner {
+    const ONE: Self = Inner { a: 1, b: 1.0 };
+    const SCALE: f32 = 2.5;
+
+    fn new(a: u32) -> Self {
+        Self { a, b: Self::SCALE }
+    }
+
+    fn sum(self, other: Inner) -> Self {
+        Inner {
+            a: self.a + other.a,
+            b: self.b + other.b,
+        }
+    }
+
+    fn scaled(&self, k: f32) -> f32 {
+        self.b * k * Self::SCALE
+    }
+
+    fn with_a(mut self, a: u32) -> Self {
+        self.a = a;
+        self
+    }
+}
+
+#[shader_item]
+#[derive(Clone, Copy)]
+struct Wrap<T> {
+    v: T,
+}
+
+#[shader_item]
+impl<T: Copy + rsshader::reflection::Add<Output = T>> Wrap<T> {
+    fn new(v: T) -> Self {
+        Self { v }
+    }
+
+    fn twice(self) -> T {
+        self.v + self.v
+    }
+
+    fn get<const N: usize>(self, arr: [T; N]) -> T {
+        arr[N - 1] + self.v
+    }
+}
+
+#[shader_item]
+fn methods(x: f32) -> f32 {
+    let i = Inner::new(3).sum(Inner::ONE).with_a(4);
+    let j = i.sum(Inner { a: 2, b: x });
+    let w = Wrap::<f32>::new(x);
+    let u = Wrap::<u32>::new(2).twice() + j.a;
+    i.scaled(2.0)
+        + j.scaled(x)
+        + w.twice()
+        + Inner::SCALE
+        + w.get([1.0, 2.0])
+        + Wrap::<f32> { v: 3.0 }.get([x])
+        + if u > 8 { 1.0 } else { 0.0 }
+}
+
+fragment!(fs_methods => methods(0.0));
+
+#[test]
+fn calls_inherent_methods() {
+    let shader = Shader::new(wgsl!(fs_methods));
+
+    for x in [0.5, -2.0] {
+        assert_eq!(shader.call(&[x.into()]), methods(x).into());
+    }
+}
+
+#[shader_item]
+pub trait Brdf {
+    const GAIN: f32 = 1.5;
+    const ID: u32;
+
+    fn eval(&self, x: f32) -> f32;
+
+    fn albedo(&self) -> f32 {
+        self.eval(0.5) * Self::GAIN
+    }
+
+    fn unit() -> f32 {
+        1.0
+    }
+}
+
+#[shader_item]
+impl Brdf for Inner {
+    const ID: u32 = 1;
+
+    fn eval(&self, x: f32) -> f32 {
+        self.b * x
+    }
+
+    fn unit() -> f32 {
+        2.0
+    }
+}
+
+#[shader_item]
+impl<T: Copy + rsshader::reflection::Ty + rsshader::reflection::Add<Output = T>> Brdf for Wrap<T> {
+    const GAIN: f32 = 3.0;
+    const ID: u32 = 2;
+
+    fn eval(&self, x: f32) -> f32 {
+        x + Self::GAIN
+    }
+
+    fn albedo(&self) -> f32 {
+        0.25
+    }
+}
+
+#[shader_item]
+fn shade<B: Brdf>(b: B, x: f32) -> f32 {
+    let id = B::ID + 1;
+    let k = if id > 2 { 1.0 } else { 0.0 };
+    b.eval(x) + b.albedo() + B::unit() + B::GAIN + <B as Brdf>::unit() + k
+}
+
+#[shader_item]
+fn traits(x: f32) -> f32 {
+    shade(Inner::new(2), x) + shade(Wrap::<f32>::new(x), 1.0) + Inner::ONE.albedo() + Inner::GAIN
+}
+
+fragment!(fs_traits => traits(0.0));
+
+#[test]
+fn dispatches_trait_methods_statically() {
+    let shader = Shader::new(wgsl!(fs_traits));
+
+    for x in [0.5, -2.0] {
+        assert_eq!(shader.call(&[x.into()]), traits(x).into());
+    }
+}
+
+#[shader_item]
+#[derive(Clone, Copy)]
+pub struct Complex {
+    re: f32,
+    im: f32,
+}
+
+#[shader_item]
+impl core::ops::Add for Complex {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self {
+        Complex {
+            re: self.re + rhs.re,
+            im: self.im + rhs.im,
+        }
+    }
+}
+
+#[shader_item]
+impl core::ops::Mul<f32> for Complex {
+    type Output = Complex;
+
+    fn mul(self, k: f32) -> Complex {
+        Complex {
+            re: self.re * k,
+            im: self.im * k,
+        }
+    }
+}
+
+#[shader_item]
+impl core::ops::Neg for Complex {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        Complex {
+            re: -self.re,
+            im: -self.im,
+        }
+    }
+}
+
+#[shader_item]
+impl core::ops::AddAssign for Complex {
+    fn add_assign(&mut self, rhs: Self) {
+        self.re += rhs.re;
+        self.im += rhs.im;
+    }
+}
+
+#[shader_item]
+impl core::ops::Add for Vec4 {
+    type Output = Self;
+
+    #[builtin(add)]
+    fn add(self, rhs: Self) -> Self {
+        Vec4 {
+            x: self.x + rhs.x,
+            y: self.y + rhs.y,
+            z: self.z + rhs.z,
+            w: self.w + rhs.w,
+        }
+    }
+}
+
+#[shader_item]
+impl core::ops::AddAssign for Vec4 {
+    #[builtin(add)]
+    fn add_assign(&mut self, rhs: Self) {
+        self.x += rhs.x;
+        self.y += rhs.y;
+        self.z += rhs.z;
+        self.w += rhs.w;
+    }
+}
+
+#[shader_item]
+impl core::ops::Neg for Vec4 {
+    type Output = Self;
+
+    #[builtin(neg)]
+    fn neg(self) -> Self {
+        Vec4 {
+            x: -self.x,
+            y: -self.y,
+            z: -self.z,
+            w: -self.w,
+        }
+    }
+}
+
+#[shader_item]
+fn operators(re: f32, v: Vec4) -> f32 {
+    let a = Complex { re, im: 2.0 };
+    let mut c = -(a + a) * 2.0;
+    c = a + c;
+    c += a;
+    let mut w = v + -v;
+    w = v + w;
+    w += v;
+    w.x -= 1.0;
+    let mut k = c.re;
+    k *= 3.0;
+    k + c.im + w.x + w.w
+}
+
+fragment!(fs_operators => operators(0.0, splat(0.0)));
+
+#[test]
+fn dispatches_operators_to_ops_impls() {
+    let wgsl = wgsl!(fs_operators);
+    validate(wgsl);
+
+    let shader = Shader::new(wgsl);
+    let v = Vec4 {
+        x: 1.0,
+        y: 2.0,
+        z: 3.0,
+        w: -4.0,
+    };
+    assert_eq!(
+        shader.call(&[0.5.into(), v.into()]),
+        operators(0.5, v).into()
+    );
+}
+
+#[shader_item(vector)]
+#[derive(Debug, Clone, Copy)]
+struct Simd<const N: usize, T>([T; N]);
+
+type Simd2 = Simd<2, f32>;
+type Simd4 = Simd<4, f32>;
+
+impl From<Simd4> for common::eval::Value {
+    fn from(value: Simd4) -> Self {
+        Self::Vector(value.0.map(Into::into).to_vec())
+    }
+}
+
+#[shader_item]
+impl<const N: usize, T: Copy> Simd<N, T> {
+    #[builtin(new_vec)]
+    fn new(array: [T; N]) -> Self {
+        Self(array)
+    }
+
+    #[builtin(new_vec)]
+    fn splat(value: T) -> Self {
+        Self([value; N])
+    }
+}
+
+#[shader_item]
+impl Simd4 {
+    #[builtin(new_vec)]
+    fn from_parts(x: f32, yz: Simd2, w: f32) -> Self {
+        Self([x, yz.0[0], yz.0[1], w])
+    }
+}
+
+#[shader_item]
+impl<const N: usize, T: core::ops::Add<Output = T>> core::ops::Add for Simd<N, T> {
+    type Output = Self;
+
+    #[builtin(add)]
+    fn add(self, rhs: Self) -> Self {
+        let mut rhs = rhs.0.into_iter();
+        Self(self.0.map(|x| x + rhs.next().unwrap()))
+    }
+}
+
+#[shader_item]
+fn simd(x: f32) -> Simd4 {
+    let a = Simd4::splat(x) + Simd4::new([x, 1.0, 2.0, 3.0]);
+    a + Simd4::from_parts(x, Simd2::splat(0.5), -1.0)
+}
+
+fragment!(fs_simd => simd(0.0));
+
+#[test]
+fn lowers_vector_structs_and_builtin_constructors() {
+    let wgsl = wgsl!(fs_simd);
+    validate(wgsl);
+
+    let shader = Shader::new(wgsl);
+    for x in [0.0, 1.5, -2.0] {
+        assert_eq!(shader.call(&[x.into()]), simd(x).into());
+    }
+}
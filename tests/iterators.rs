@@ -0,0 +1,72 @@
+#![allow(dead_code)]
+
+#[macro_use]
+mod common;
+
+use common::{
+    assert_not_contains,
+    eval::{Shader, Value},
+};
+use rsshader::{shader_item, wgsl};
+
+#[shader_item]
+fn iters(weights: [f32; 4], samples: [f32; 4], k: f32) -> f32 {
+    let dot = weights.iter().zip(samples).map(|(w, s)| w * s).sum::<f32>();
+    let folded = samples
+        .into_iter()
+        .enumerate()
+        .fold(0.0, |acc, (i, s)| acc + s * i as f32 * k);
+    let big = weights.iter().any(|w| *w > k);
+    let pos = samples.iter().all(|&s| s >= 0.0);
+    let filtered: f32 = weights
+        .iter()
+        .enumerate()
+        .filter(|(i, w)| *i % 2 == 0 && **w > 0.5)
+        .zip(samples.iter())
+        .map(|((_, w), s)| w + s)
+        .product();
+    let min = samples.iter().min_by(|a, b| a.total_cmp(b)).unwrap();
+    let max = weights
+        .iter()
+        .map(|w| w * k)
+        .min_by(|a, b| b.partial_cmp(a).unwrap())
+        .unwrap_or(1.0);
+    let mut acc = 0.0;
+    for (i, w) in weights.iter().enumerate() {
+        if i == 3 {
+            break;
+        }
+        acc += w * samples[i];
+    }
+    for s in samples {
+        if s < 0.0 {
+            continue;
+        }
+        acc += s;
+    }
+    let count = [1u32, 2, 3]
+        .iter()
+        .filter(|n| **n > 1)
+        .map(|n| n * 2)
+        .sum::<u32>();
+    dot + folded + filtered + min + max + acc + count as f32 + big as u32 as f32 + pos as u32 as f32
+}
+
+fragment!(fs_iters => iters([0.0, 0.0, 0.0, 0.0], [0.0, 0.0, 0.0, 0.0], 0.0));
+
+#[test]
+fn lowers_iterator_chains_to_loops() {
+    let wgsl = wgsl!(fs_iters);
+    assert_not_contains(wgsl, "iter");
+
+    let shader = Shader::new(wgsl);
+    let array = |values: [f32; 4]| Value::Composite(values.map(Into::into).to_vec());
+
+    let weights = [0.25, 1.0, 0.75, 2.0];
+    for (samples, k) in [([1.0, 2.0, 3.0, 4.0], 0.5), ([-1.0, 0.5, 3.0, -4.0], 3.0)] {
+        assert_eq!(
+            shader.call(&[array(weights), array(samples), k.into()]),
+            iters(weights, samples, k).into()
+        );
+    }
+}
@@ -0,0 +1,79 @@
+#![allow(dead_code)]
+
+#[macro_use]
+mod common;
+
+use common::{Vec2, Vec4, eval::Shader};
+use rsshader::{shader_item, wgsl};
+
+#[shader_item]
+fn macros(x: f32, n: u32) -> f32 {
+    assert!(x > 0.0);
+    debug_assert_ne!(n, 3, "n must not be {}", 3);
+    let xy: Vec2 = vec2!(x, 1.0);
+    let v: Vec4 = vec4!(xy, 0.0, 1.0);
+    let w: Vec4 = vec4!(2.0);
+    let u: Vec4 = vec4!(x, n as f32, 3.0, 4.0);
+    let small = matches!(n, 0..=2 | 7);
+    let guarded = matches!(n, 4 | 5 if x > 1.0,);
+    if small || guarded {
+        v.x + w.y + u.y + xy.x
+    } else {
+        u.x
+    }
+}
+
+fragment!(fs_macros => macros(0.0, 0u32));
+
+#[test]
+fn expands_known_macros() {
+    let shader = Shader::new(wgsl!(fs_macros));
+
+    for (x, n) in [(0.5, 0), (2.0, 4), (0.5, 5), (1.5, 7), (1.5, 9)] {
+        assert_eq!(
+            shader.call(&[x.into(), n.into()]),
+            macros(x, n).into(),
+            "macros({x}, {n})"
+        );
+    }
+}
+
+/// A constructor macro named differently from the known ones.
+macro_rules! point {
+    ($($args:tt)*) => {
+        vec2!($($args)*)
+    };
+}
+
+#[shader_item]
+fn bump(n: &mut u32) -> bool {
+    *n += 1;
+    *n > 1
+}
+
+#[shader_item]
+#[shader_macros(point = vec2)]
+fn registered(x: f32, n: u32) -> f32 {
+    let mut count = n;
+    assert!(bump(&mut count) || n == 0);
+    debug_assert_ne!(bump(&mut count), n == 100, "bumped {} times", 2);
+    let p: Vec2 = point!(x, count as f32);
+    let squares: [f32; 4] = core::array::from_fn(|i| p.x * i as f32 + p.y);
+    let idx: [u32; 3] = std::array::from_fn(|i| i as u32 * count);
+    squares[1] + squares[3] + idx[2] as f32
+}
+
+fragment!(fs_registered => registered(0.0, 0u32));
+
+#[test]
+fn expands_registered_macros_and_array_from_fn() {
+    let shader = Shader::new(wgsl!(fs_registered));
+
+    for (x, n) in [(0.5, 1), (2.0, 4), (-1.5, 7)] {
+        assert_eq!(
+            shader.call(&[x.into(), n.into()]),
+            registered(x, n).into(),
+            "registered({x}, {n})"
+        );
+    }
+}
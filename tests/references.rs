@@ -0,0 +1,141 @@
+#![allow(dead_code)]
+
+#[macro_use]
+mod common;
+
+use common::{Vec4, assert_contains, eval::Shader, splat};
+use rsshader::{shader_item, wgsl};
+
+#[shader_item]
+#[derive(Clone, Copy)]
+struct Inner {
+    a: u32,
+    b: f32,
+}
+
+#[shader_item]
+#[derive(Clone, Copy)]
+struct Light {
+    color: Vec4,
+    inner: Inner,
+    weights: [f32; 3],
+}
+
+#[shader_item]
+impl core::ops::Add for Vec4 {
+    type Output = Self;
+
+    #[builtin(add)]
+    fn add(self, rhs: Self) -> Self {
+        Vec4 {
+            x: self.x + rhs.x,
+            y: self.y + rhs.y,
+            z: self.z + rhs.z,
+            w: self.w + rhs.w,
+        }
+    }
+}
+
+#[shader_item]
+fn accumulate(acc: &mut Vec4, light: &Light) {
+    acc.x += light.color.x * light.inner.b;
+    *acc = *acc + light.color;
+    acc.w = light.weights[1];
+}
+
+#[shader_item]
+fn bump(k: &mut f32, n: &mut u32) {
+    *k *= 2.0;
+    *n += 1;
+}
+
+#[shader_item]
+impl Light {
+    fn brighten(&mut self, k: f32) {
+        self.color.x *= k;
+        bump(&mut self.inner.b, &mut self.inner.a);
+        self.dim();
+    }
+
+    fn dim(&mut self) {
+        self.weights[0] = 0.5 * self.total();
+    }
+
+    fn total(&self) -> f32 {
+        self.color.x + self.inner.b
+    }
+}
+
+#[shader_item]
+#[allow(clippy::explicit_auto_deref)]
+fn refs(x: f32, color: Vec4) -> f32 {
+    let mut acc = color;
+    let mut light = Light {
+        color,
+        inner: Inner { a: 3, b: 1.5 },
+        weights: [x, 1.0, 2.0],
+    };
+    accumulate(&mut acc, &light);
+    light.brighten(x);
+    let mut lights = [light, light];
+    lights[1].brighten(2.0);
+    bump(&mut lights[0].inner.b, &mut light.inner.a);
+    let r = &mut acc;
+    r.y = 3.0;
+    accumulate(r, &lights[1]);
+    accumulate(&mut *r, &light);
+    let l = &light;
+    acc.x + acc.y + l.total() + lights[0].total() + (*l).inner.b + light.inner.a as f32
+}
+
+fragment!(fs_refs => refs(0.0, splat(0.0)));
+
+#[test]
+fn passes_references_as_pointers() {
+    let wgsl = wgsl!(fs_refs);
+    assert_contains(wgsl, "ptr<function,");
+
+    let shader = Shader::new(wgsl);
+    let color = Vec4 {
+        x: 1.0,
+        y: 2.0,
+        z: 3.0,
+        w: 4.0,
+    };
+    for x in [0.5, -2.0] {
+        assert_eq!(
+            shader.call(&[x.into(), color.into()]),
+            refs(x, color).into()
+        );
+    }
+}
+
+#[shader_item]
+fn inc(y: &mut f32) -> f32 {
+    *y += 1.0;
+    *y
+}
+
+#[shader_item]
+#[allow(unused_must_use, unused_parens, clippy::unnecessary_operation)]
+fn side_effects(x: f32) -> f32 {
+    let mut y = x;
+    inc(&mut y) + 1.0;
+    let _ = inc(&mut y) * 2.0;
+    (inc(&mut y));
+    {
+        inc(&mut y) - 1.0
+    };
+    y
+}
+
+fragment!(fs_side_effects => side_effects(0.0));
+
+#[test]
+fn keeps_calls_in_discarded_values() {
+    let shader = Shader::new(wgsl!(fs_side_effects));
+
+    for x in [0.5, -2.0] {
+        assert_eq!(shader.call(&[x.into()]), side_effects(x).into());
+    }
+}
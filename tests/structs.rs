@@ -0,0 +1,130 @@
+#![allow(dead_code)]
+
+#[macro_use]
+mod common;
+
+use common::{Vec3, Vec4, eval::Shader, splat};
+use rsshader::{shader_item, wgsl};
+
+#[shader_item]
+#[derive(Clone, Copy)]
+struct Inner {
+    a: u32,
+    b: f32,
+}
+
+#[shader_item]
+#[derive(Clone, Copy)]
+struct Pair(Inner, bool);
+
+#[shader_item]
+#[allow(clippy::init_numbered_fields)]
+fn structs(value: f32, x: u32) -> f32 {
+    let inner = Inner { b: value, a: 3 };
+    let pair = Pair { 0: inner, 1: x > 2 };
+    let y = pair.0.a + x;
+    let z = Inner { a: y, ..pair.0 };
+    let q = if pair.1 { 1 } else { 2 };
+    if z.a * q > 6 { z.b } else { -z.b }
+}
+
+fragment!(fs_structs => structs(0.0, 0u32));
+
+#[test]
+fn lowers_struct_literals_and_field_reads() {
+    let shader = Shader::new(wgsl!(fs_structs));
+
+    for (value, x) in [(0.5, 0), (-2.0, 3), (1.0, 7)] {
+        assert_eq!(
+            shader.call(&[value.into(), x.into()]),
+            structs(value, x).into()
+        );
+    }
+}
+
+#[shader_item]
+fn constructors(position: Vec4, x: u32) -> f32 {
+    let v = Vec3 {
+        x: position.w,
+        y: 1.0,
+        z: 2.0,
+    };
+    let w = Vec4 { y: v.z, ..position };
+    let pair = Pair(Inner { a: x, b: v.x }, x > 1);
+    let other = Pair(pair.0, !pair.1);
+    let k = if other.1 { 3.0 } else { 4.0 };
+    v.x + v.y + w.y * w.z + other.0.b * k + pair.0.a as f32
+}
+
+fragment!(fs_constructors => constructors(splat(0.0), 0u32));
+
+#[test]
+fn constructs_vectors_and_tuple_structs() {
+    let shader = Shader::new(wgsl!(fs_constructors));
+
+    for (w, x) in [(0.5, 0), (-2.0, 3)] {
+        let position = Vec4 {
+            x: 1.0,
+            y: 2.0,
+            z: 3.0,
+            w,
+        };
+        assert_eq!(
+            shader.call(&[position.into(), x.into()]),
+            constructors(position, x).into()
+        );
+    }
+}
+
+#[shader_item]
+#[allow(clippy::init_numbered_fields)]
+fn places(mut position: Vec4, alpha: f32, k: u32) -> f32 {
+    let mut arr = [1u32, 2, 3, 4];
+    let mut zeros = [0; 3];
+    let mut i = 1;
+    arr[i] += 1;
+    arr[i + 1] *= k;
+    arr[2] = arr[0] << 2;
+    zeros[i] = 4;
+    let mut pair = Pair {
+        0: Inner { a: 1, b: 2.0 },
+        1: false,
+    };
+    pair.0.a += arr[3];
+    pair.0.b /= alpha;
+    pair.1 |= pair.0.a > 3;
+    position.w *= alpha;
+    position.x = position.y + pair.0.b;
+    let mut idx = k;
+    idx <<= 2;
+    idx %= 7;
+    idx ^= zeros[1];
+    i += 1;
+    idx += arr[i];
+    let bonus = if pair.1 { idx } else { idx + 1 };
+    if bonus > 4 {
+        position.x + position.w
+    } else {
+        position.x - position.w
+    }
+}
+
+fragment!(fs_places => places(splat(0.0), 0.0, 0u32));
+
+#[test]
+fn assigns_to_places() {
+    let shader = Shader::new(wgsl!(fs_places));
+
+    for (x, alpha, k) in [(0.5, 0.5, 0), (-2.0, 4.0, 3), (1.0, -1.0, 9)] {
+        let position = Vec4 {
+            x,
+            y: 1.0,
+            z: 2.0,
+            w: 3.0,
+        };
+        assert_eq!(
+            shader.call(&[position.into(), alpha.into(), k.into()]),
+            places(position, alpha, k).into()
+        );
+    }
+}
@@ -0,0 +1,7 @@
+//! Checks the diagnostics of unsupported constructs against the expected
+//! compiler output in `tests/ui`.
+
+#[test]
+fn ui() {
+    trybuild::TestCases::new().compile_fail("tests/ui/*.rs");
+}
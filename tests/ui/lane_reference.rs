@@ -0,0 +1,38 @@
+use rsshader::{
+    ir::{Length, Primitive, TypeIr},
+    reflection::{Field, Ty, VectorTy, name_id},
+    shader_item,
+};
+
+#[derive(Clone, Copy)]
+struct Vec2 {
+    x: f32,
+    y: f32,
+}
+
+impl Ty for Vec2 {
+    const IR: TypeIr = TypeIr::Vector {
+        n: Length::Two,
+        t: Primitive::F32,
+    };
+}
+
+impl VectorTy<2, f32> for Vec2 {}
+
+impl Field<{ name_id("x") }> for Vec2 {
+    type Ty = f32;
+    const IDX: usize = 0;
+}
+
+#[shader_item]
+fn bump(k: &mut f32) {
+    *k += 1.0;
+}
+
+#[shader_item]
+fn lanes(mut v: Vec2) -> Vec2 {
+    bump(&mut v.x);
+    v
+}
+
+fn main() {}
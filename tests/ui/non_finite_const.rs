@@ -0,0 +1,12 @@
+use rsshader::shader_item;
+
+#[shader_item]
+const FAR: f32 = f32::INFINITY;
+
+#[shader_item]
+const LIMITS: [f32; 2] = [0.0, f32::NAN];
+
+#[shader_item]
+const NEAR: f32 = 0.1;
+
+fn main() {}
@@ -0,0 +1,17 @@
+use rsshader::shader_item;
+
+#[shader_item]
+#[derive(Clone, Copy)]
+struct Complex {
+    re: f32,
+    im: f32,
+}
+
+#[shader_item]
+impl PartialEq for Complex {
+    fn eq(&self, other: &Self) -> bool {
+        self.re == other.re && self.im == other.im
+    }
+}
+
+fn main() {}
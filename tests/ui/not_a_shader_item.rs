@@ -0,0 +1,39 @@
+use rsshader::shader_item;
+
+#[derive(Clone, Copy)]
+struct Plain {
+    x: f32,
+}
+
+#[shader_item]
+fn method_call(x: f32) -> f32 {
+    x.sin()
+}
+
+#[shader_item]
+fn plain_param(p: Plain) -> f32 {
+    p.x
+}
+
+#[shader_item]
+fn plain_literal(x: f32) -> f32 {
+    Plain { x }.x
+}
+
+fn host_only(x: f32) -> f32 {
+    x
+}
+
+#[shader_item]
+fn host_call(x: f32) -> f32 {
+    host_only(x)
+}
+
+const SIZE: usize = 3;
+
+#[shader_item]
+fn host_const(x: u32) -> u32 {
+    x + SIZE as u32
+}
+
+fn main() {}
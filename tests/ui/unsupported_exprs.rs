@@ -0,0 +1,26 @@
+use rsshader::shader_item;
+
+#[shader_item]
+fn tuple(x: f32) -> f32 {
+    let pair = (x, x);
+    pair.0
+}
+
+#[shader_item]
+fn text(x: u32) -> u32 {
+    let _ = "text";
+    x
+}
+
+#[shader_item]
+fn sliced(xs: [f32; 4]) -> f32 {
+    let ys = &xs[1..];
+    ys[0]
+}
+
+#[shader_item]
+unsafe fn unsafe_fn(x: f32) -> f32 {
+    x
+}
+
+fn main() {}
@@ -0,0 +1,12 @@
+use rsshader::shader_item;
+
+#[shader_item]
+fn nested_literal(a: Option<u32>) -> u32 {
+    let b = match a {
+        Some(0) => 1,
+        _ => 2,
+    };
+    b
+}
+
+fn main() {}
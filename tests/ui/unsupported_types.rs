@@ -0,0 +1,28 @@
+use rsshader::shader_item;
+
+#[shader_item]
+fn heap(values: Vec<f32>) -> f32 {
+    values[0]
+}
+
+#[shader_item]
+fn slice(values: &[f32]) -> f32 {
+    values[0]
+}
+
+#[shader_item]
+fn tagged<const TAG: char>(x: f32) -> f32 {
+    x
+}
+
+#[shader_item]
+fn scaled<const N: u64>(x: f32) -> f32 {
+    x * N as f32
+}
+
+#[shader_item]
+struct Named {
+    name: String,
+}
+
+fn main() {}
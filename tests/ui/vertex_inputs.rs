@@ -0,0 +1,54 @@
+use rsshader::{
+    ir::{Length, Primitive, TypeIr},
+    reflection::{Ty, VectorTy},
+    shader_item, wgsl,
+};
+
+#[derive(Clone, Copy)]
+struct Vec4 {
+    x: f32,
+    y: f32,
+    z: f32,
+    w: f32,
+}
+
+impl Ty for Vec4 {
+    const IR: TypeIr = TypeIr::Vector {
+        n: Length::Four,
+        t: Primitive::F32,
+    };
+}
+
+impl VectorTy<4, f32> for Vec4 {}
+
+#[shader_item(fragment)]
+#[derive(Clone, Copy)]
+struct Frag {
+    #[position]
+    position: Vec4,
+}
+
+#[shader_item]
+#[derive(Clone, Copy)]
+struct Flagged {
+    position: Vec4,
+    flag: bool,
+}
+
+#[shader_item(vertex)]
+fn vs_array(positions: [Vec4; 2]) -> Frag {
+    Frag {
+        position: positions[0],
+    }
+}
+
+#[shader_item(vertex)]
+fn vs_flagged(v: Flagged) -> Frag {
+    Frag {
+        position: v.position,
+    }
+}
+
+fn main() {
+    let _ = wgsl!(vs_flagged);
+}